@@ -1,4 +1,6 @@
-use banderwagon::{msm::MSMPrecompWnaf, msm_windowed_sign::MSMPrecompWindowSigned, Element, Fr};
+use banderwagon::{
+    msm::MSMPrecompWnaf, msm_windowed_sign::MSMPrecompWindowSigned, multi_scalar_mul, Element, Fr,
+};
 use criterion::{criterion_group, criterion_main, Criterion};
 use rand::RngCore;
 
@@ -20,6 +22,44 @@ pub fn msm_wnaf(c: &mut Criterion) {
     });
 }
 
+// Compares the GLV paths used by `Element` against the generic arkworks ones
+pub fn glv(c: &mut Criterion) {
+    use ark_ec::{CurveGroup, VariableBaseMSM};
+    use ark_ed_on_bls12_381_bandersnatch::{EdwardsAffine, EdwardsProjective};
+
+    use ark_serialize::CanonicalDeserialize;
+
+    let to_projective = |element: &Element| -> EdwardsProjective {
+        EdwardsAffine::deserialize_uncompressed_unchecked(&element.to_bytes_uncompressed()[..])
+            .unwrap()
+            .into()
+    };
+
+    let base = random_point(7, 1)[0];
+    let scalar = random_scalars(1, 32)[0];
+    let base_projective = to_projective(&base);
+    c.bench_function("scalar mul glv", |b| b.iter(|| base * scalar));
+    c.bench_function("scalar mul generic", |b| {
+        b.iter(|| base_projective * scalar)
+    });
+
+    for num_elements in [16, 256] {
+        let bases = random_point(300, num_elements);
+        let scalars = random_scalars(num_elements, 32);
+        let bases_projective: Vec<_> = bases.iter().map(to_projective).collect();
+
+        c.bench_function(&format!("msm glv: {}", num_elements), |b| {
+            b.iter(|| multi_scalar_mul(&bases, &scalars))
+        });
+        c.bench_function(&format!("msm generic: {}", num_elements), |b| {
+            b.iter(|| {
+                let bases = EdwardsProjective::normalize_batch(&bases_projective);
+                EdwardsProjective::msm(&bases, &scalars).unwrap()
+            })
+        });
+    }
+}
+
 pub fn keccak_32bytes(c: &mut Criterion) {
     use rand::Rng;
     use sha3::{Digest, Keccak256};
@@ -34,7 +74,7 @@ pub fn keccak_32bytes(c: &mut Criterion) {
                 (keccak, rand_buffer)
             },
             |(mut keccak, rand_buffer)| {
-                keccak.update(&rand_buffer);
+                keccak.update(rand_buffer);
                 keccak.finalize()
             },
         )
@@ -43,7 +83,7 @@ pub fn keccak_32bytes(c: &mut Criterion) {
 
 fn random_point(seed: u64, num_points: usize) -> Vec<Element> {
     (0..num_points)
-        .map(|i| Element::prime_subgroup_generator() * Fr::from((seed + i as u64 + 1) as u64))
+        .map(|i| Element::prime_subgroup_generator() * Fr::from(seed + i as u64 + 1))
         .collect()
 }
 fn random_scalars(num_points: usize, num_bytes: usize) -> Vec<Fr> {
@@ -58,5 +98,5 @@ fn random_scalars(num_points: usize, num_bytes: usize) -> Vec<Fr> {
         .collect()
 }
 
-criterion_group!(benches, msm_wnaf, keccak_32bytes);
+criterion_group!(benches, msm_wnaf, glv, keccak_32bytes);
 criterion_main!(benches);
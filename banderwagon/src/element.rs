@@ -1,7 +1,8 @@
-use ark_ec::{twisted_edwards::TECurveConfig, Group, ScalarMul, VariableBaseMSM};
+use ark_ec::{twisted_edwards::TECurveConfig, Group};
 use ark_ed_on_bls12_381_bandersnatch::{BandersnatchConfig, EdwardsAffine, EdwardsProjective, Fq};
 use ark_ff::{batch_inversion, Field, One, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rayon::prelude::*;
 
 pub use ark_ed_on_bls12_381_bandersnatch::Fr;
 
@@ -36,22 +37,21 @@ impl PartialEq for Element {
 impl Element {
     pub fn to_bytes(&self) -> [u8; 32] {
         // We assume that internally this point is "correct"
-        //
-        // We serialize a correct point by serializing the x co-ordinate times sign(y)
         let affine = EdwardsAffine::from(self.0);
-        let x = if is_positive(affine.y) {
-            affine.x
-        } else {
-            -affine.x
-        };
-        let mut bytes = [0u8; 32];
-        x.serialize_compressed(&mut bytes[..])
-            .expect("serialization failed");
-
-        // reverse bytes to big endian, for interoperability
-        bytes.reverse();
+        compress_affine(affine.x, affine.y)
+    }
 
-        bytes
+    // Same as calling `to_bytes` on each element, however the inversions needed
+    // to convert to affine are shared between all of the elements
+    pub fn batch_to_bytes(elements: &[Element]) -> Vec<[u8; 32]> {
+        let mut z_inverses: Vec<_> = elements.iter().map(|element| element.0.z).collect();
+        batch_inversion(&mut z_inverses);
+
+        elements
+            .par_iter()
+            .zip(z_inverses)
+            .map(|(element, z_inv)| compress_affine(element.0.x * z_inv, element.0.y * z_inv))
+            .collect()
     }
 
     // Do not compare the results of this function.
@@ -75,11 +75,7 @@ impl Element {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Option<Element> {
-        // Switch from big endian to little endian, as arkworks library uses little endian
-        let mut bytes = bytes.to_vec();
-        bytes.reverse();
-
-        let x: Fq = Fq::deserialize_compressed(&bytes[..]).ok()?;
+        let x = deserialize_x(bytes)?;
 
         let return_positive_y = true;
 
@@ -103,6 +99,38 @@ impl Element {
         Some(element)
     }
 
+    // Same as calling `from_bytes` on each of the byte arrays, returning None if any of them
+    // is not a valid element.
+    //
+    // The divisions needed to recover y are shared between all of the elements,
+    // and the square roots and subgroup checks are done in parallel.
+    pub fn batch_from_bytes(bytes: &[[u8; 32]]) -> Option<Vec<Element>> {
+        let xs: Vec<Fq> = bytes
+            .par_iter()
+            .map(|bytes| deserialize_x(bytes))
+            .collect::<Option<_>>()?;
+
+        // y^2 = (ax^2 - 1) / (dx^2 - 1), where the denominator is never zero since d is not a square
+        let mut denominators: Vec<_> = xs
+            .iter()
+            .map(|x| BandersnatchConfig::COEFF_D * x.square() - Fq::one())
+            .collect();
+        batch_inversion(&mut denominators);
+
+        xs.into_par_iter()
+            .zip(denominators)
+            .map(|(x, denominator_inv)| {
+                let y_squared =
+                    (BandersnatchConfig::COEFF_A * x.square() - Fq::one()) * denominator_inv;
+                let y = y_squared.sqrt()?;
+                let y = if is_positive(y) { y } else { -y };
+
+                let element = Element(EdwardsProjective::new_unchecked(x, y, x * y, Fq::one()));
+                element.subgroup_check().then_some(element)
+            })
+            .collect()
+    }
+
     pub const fn compressed_serialized_size() -> usize {
         32
     }
@@ -181,6 +209,27 @@ impl Element {
     }
 }
 
+// We serialize a correct point by serializing the x co-ordinate times sign(y)
+fn compress_affine(x: Fq, y: Fq) -> [u8; 32] {
+    let x = if is_positive(y) { x } else { -x };
+    let mut bytes = [0u8; 32];
+    x.serialize_compressed(&mut bytes[..])
+        .expect("serialization failed");
+
+    // reverse bytes to big endian, for interoperability
+    bytes.reverse();
+
+    bytes
+}
+
+fn deserialize_x(bytes: &[u8]) -> Option<Fq> {
+    // Switch from big endian to little endian, as arkworks library uses little endian
+    let mut bytes = bytes.to_vec();
+    bytes.reverse();
+
+    Fq::deserialize_compressed(&bytes[..]).ok()
+}
+
 // The lexographically largest value is defined to be the positive value
 fn is_positive(coordinate: Fq) -> bool {
     coordinate > -coordinate
@@ -194,13 +243,7 @@ fn legendre_check_point(x: &Fq) -> bool {
 pub fn multi_scalar_mul(bases: &[Element], scalars: &[Fr]) -> Element {
     let bases_inner: Vec<_> = bases.iter().map(|element| element.0).collect();
 
-    // XXX: Converting all of these to affine hurts performance
-    let bases = EdwardsProjective::batch_convert_to_mul_base(&bases_inner);
-
-    let result = EdwardsProjective::msm(&bases, scalars)
-        .expect("number of bases should equal number of scalars");
-
-    Element(result)
+    Element(crate::glv::msm(&bases_inner, scalars))
 }
 
 #[cfg(test)]
@@ -232,6 +275,57 @@ mod tests {
         assert_eq!(element, generator)
     }
 
+    #[test]
+    fn batch_serialization_matches_single() {
+        let mut points: Vec<_> = (0..20)
+            .map(|i| Element::prime_subgroup_generator() * Fr::from(i * 1234567 + 1))
+            .collect();
+        points.push(Element::zero());
+        // Same element, with a different representative
+        points.push(Element(points[0].0 + test::two_torsion()));
+
+        let bytes = Element::batch_to_bytes(&points);
+        let expected: Vec<_> = points.iter().map(Element::to_bytes).collect();
+        assert_eq!(bytes, expected);
+
+        let got = Element::batch_from_bytes(&bytes).unwrap();
+        let expected: Vec<_> = bytes
+            .iter()
+            .map(|bytes| Element::from_bytes(bytes).unwrap())
+            .collect();
+        assert_eq!(got, expected);
+        for (got, expected) in got.iter().zip(&expected) {
+            assert_eq!(
+                got.to_bytes_uncompressed(),
+                expected.to_bytes_uncompressed()
+            );
+        }
+
+        assert_eq!(Element::batch_from_bytes(&[]), Some(Vec::new()));
+        assert_eq!(Element::batch_to_bytes(&[]), Vec::<[u8; 32]>::new());
+    }
+
+    #[test]
+    fn batch_deserialization_rejects_invalid() {
+        let mut bytes = Element::batch_to_bytes(&[
+            Element::prime_subgroup_generator(),
+            Element::prime_subgroup_generator() * Fr::from(2u64),
+        ]);
+        // Find an x co-ordinate which is on the curve, but not in the subgroup
+        let mut invalid = [0u8; 32];
+        for i in 1u8.. {
+            invalid[31] = i;
+            if Element::from_bytes(&invalid).is_none() {
+                break;
+            }
+        }
+        bytes.push(invalid);
+        assert_eq!(Element::batch_from_bytes(&bytes), None);
+
+        // Non-canonical field elements are rejected
+        assert_eq!(Element::batch_from_bytes(&[[0xff; 32]]), None);
+    }
+
     #[test]
     fn from_batch_map_to_scalar_field() {
         let mut points = Vec::new();
@@ -256,7 +350,7 @@ mod tests {
 mod test {
     use super::*;
     // Two torsion point, *not*  point at infinity {0,-1,0,1}
-    fn two_torsion() -> EdwardsProjective {
+    pub(super) fn two_torsion() -> EdwardsProjective {
         EdwardsProjective::new_unchecked(Fq::zero(), -Fq::one(), Fq::zero(), Fq::one())
     }
     fn points_at_infinity() -> [EdwardsProjective; 2] {
@@ -0,0 +1,356 @@
+// GLV scalar multiplication, using the degree-2 endomorphism of Bandersnatch.
+//
+// The endomorphism ψ acts on the prime order subgroup as multiplication by λ, where λ² = -2 mod r.
+// A scalar k is split into k1 + k2·λ, where k1 and k2 have at most 127 bits, so that
+// k·P = k1·P + k2·ψ(P) needs half as many doublings.
+//
+// The endomorphism and the lattice basis are from section 4 of https://eprint.iacr.org/2021/1152
+//
+// ψ kills the two torsion point, however banderwagon elements are only defined up to
+// the two torsion point, so the results are still the same elements.
+use ark_ec::{CurveGroup, Group};
+use ark_ed_on_bls12_381_bandersnatch::{EdwardsAffine, EdwardsProjective, Fq, Fr};
+use ark_ff::{BigInteger256, Field, PrimeField, Zero};
+use rayon::prelude::*;
+
+// ψ(x, y) = (c(1 - y²)/(xy), b(y² + b)/(y² - b))
+const ENDO_B: Fq = Fq::new(BigInteger256::new([
+    0xee0f014d172510b4,
+    0x2ea712770d9af4d6,
+    0x61f00d3a63511a88,
+    0x52c9f28b828426a5,
+]));
+const ENDO_C: Fq = Fq::new(BigInteger256::new([
+    0x515c806cdf650b3d,
+    0x8456abcfff36f4e9,
+    0xa97c6efd6c17d107,
+    0x6cc624cf865457c3,
+]));
+
+// ψ(P) = λP for every P in the prime order subgroup
+#[cfg(test)]
+const LAMBDA: Fr = Fr::new(BigInteger256::new([
+    0xd13d21408783df05,
+    0xcfc49db970a5056e,
+    0xedf849562b38c72b,
+    0x13b4f3dc4a39a493,
+]));
+
+// A short basis (a1, b1), (a2, b2) for the lattice of (x, y) where x + yλ = 0 mod r.
+// All of the entries are positive, except for b2 = -a1.
+const A1: u128 = 113482231691339203864511368254957623327;
+const B1: u128 = 10741319382058138887739339959866629956;
+const A2: u128 = 21482638764116277775478679919733259912;
+
+// round(2^256 · a1 / r) and round(2^256 · b1 / r), which are used to approximate
+// the coordinates of k in the lattice basis without a division
+const G1: [u64; 3] = [0xdebac77a3f4747c2, 0xf21df5b0541cf632, 0x2];
+const G2: [u64; 3] = [0x993b75e7547768ab, 0x4760f127d8767bde, 0x0];
+
+// The window size used for a single scalar multiplication
+const WNAF_WINDOW_SIZE: usize = 5;
+
+// Computes ψ(P)
+pub(crate) fn endomorphism(point: &EdwardsProjective) -> EdwardsProjective {
+    // The identity and the two torsion point are the kernel of ψ
+    if point.x.is_zero() {
+        return EdwardsProjective::zero();
+    }
+
+    let y2 = point.y.square();
+    let z2 = point.z.square();
+    let b_z2 = ENDO_B * z2;
+
+    let f = ENDO_C * (z2 - y2);
+    let g = ENDO_B * (y2 + b_z2);
+    let h = y2 - b_z2;
+    let xy = point.x * point.y;
+
+    // (f·h : g·xy : h·xy) in projective coordinates, where the extended coordinate is f·g
+    EdwardsProjective::new_unchecked(f * h, g * xy, f * g, h * xy)
+}
+
+// Splits k into (k1, k2) such that k = k1 + k2·λ mod r.
+// Each half is returned as (is_negative, magnitude), where the magnitude is less than 2^127.
+pub(crate) fn decompose(k: &Fr) -> [(bool, u128); 2] {
+    let k_limbs = k.into_bigint().0;
+    let c1 = Fr::from(mul_shift_256(&k_limbs, &G1));
+    let c2 = Fr::from(mul_shift_256(&k_limbs, &G2));
+
+    // (k1, k2) = (k, 0) - c1·(a1, b1) - c2·(a2, b2)
+    let k1 = *k - c1 * Fr::from(A1) - c2 * Fr::from(A2);
+    let k2 = c2 * Fr::from(A1) - c1 * Fr::from(B1);
+
+    [to_signed(k1), to_signed(k2)]
+}
+
+// Computes k·P
+pub(crate) fn mul(point: &EdwardsProjective, scalar: &Fr) -> EdwardsProjective {
+    let [(k1_neg, k1), (k2_neg, k2)] = decompose(scalar);
+
+    let point_1 = if k1_neg { -*point } else { *point };
+    let point_2 = endomorphism(point);
+    let point_2 = if k2_neg { -point_2 } else { point_2 };
+
+    let table_1 = odd_multiples(point_1);
+    let table_2 = odd_multiples(point_2);
+    let digits_1 = wnaf_digits(k1);
+    let digits_2 = wnaf_digits(k2);
+
+    // Both halves share the doublings
+    let mut result = EdwardsProjective::zero();
+    for i in (0..digits_1.len().max(digits_2.len())).rev() {
+        result.double_in_place();
+        for (digits, table) in [(&digits_1, &table_1), (&digits_2, &table_2)] {
+            match digits.get(i).copied().unwrap_or(0) {
+                0 => {}
+                digit if digit > 0 => result += table[(digit as usize) / 2],
+                digit => result -= table[(digit.unsigned_abs() as usize) / 2],
+            }
+        }
+    }
+    result
+}
+
+// Computes the sum of scalars[i]·bases[i], using Pippenger's algorithm on the
+// decomposed scalars and twice the number of bases
+pub(crate) fn msm(bases: &[EdwardsProjective], scalars: &[Fr]) -> EdwardsProjective {
+    assert_eq!(
+        bases.len(),
+        scalars.len(),
+        "number of bases should equal number of scalars"
+    );
+
+    let (points, halves): (Vec<_>, Vec<_>) = bases
+        .par_iter()
+        .zip(scalars)
+        .flat_map_iter(|(base, scalar)| {
+            let [(k1_neg, k1), (k2_neg, k2)] = decompose(scalar);
+            let endo = endomorphism(base);
+            [
+                (if k1_neg { -*base } else { *base }, k1),
+                (if k2_neg { -endo } else { endo }, k2),
+            ]
+        })
+        .unzip();
+    let points = EdwardsProjective::normalize_batch(&points);
+
+    pippenger(&points, &halves)
+}
+
+// Pippenger's bucket method with signed digits, for scalars with at most 128 bits
+fn pippenger(bases: &[EdwardsAffine], scalars: &[u128]) -> EdwardsProjective {
+    let num_bits = 128 - scalars.iter().fold(0, |acc, k| acc | k).leading_zeros() as usize;
+    let window_size = if bases.len() < 32 {
+        3
+    } else {
+        // This is the same heuristic as arkworks
+        (bases.len().ilog2() as usize * 69) / 100 + 2
+    };
+    // The extra window holds the carry of the top digit
+    let num_windows = num_bits / window_size + 1;
+
+    let digits: Vec<_> = scalars
+        .iter()
+        .map(|scalar| signed_digits(*scalar, window_size, num_windows))
+        .collect();
+
+    let window_sums: Vec<_> = (0..num_windows)
+        .into_par_iter()
+        .map(|window| {
+            let mut buckets = vec![EdwardsProjective::zero(); 1 << (window_size - 1)];
+            for (digits, base) in digits.iter().zip(bases) {
+                match digits[window] {
+                    0 => {}
+                    digit if digit > 0 => buckets[digit as usize - 1] += base,
+                    digit => buckets[digit.unsigned_abs() as usize - 1] -= base,
+                }
+            }
+
+            // Computes the sum of (i + 1)·buckets[i]
+            let mut running_sum = EdwardsProjective::zero();
+            let mut window_sum = EdwardsProjective::zero();
+            for bucket in buckets.into_iter().rev() {
+                running_sum += bucket;
+                window_sum += running_sum;
+            }
+            window_sum
+        })
+        .collect();
+
+    let mut result = EdwardsProjective::zero();
+    for window_sum in window_sums.into_iter().rev() {
+        for _ in 0..window_size {
+            result.double_in_place();
+        }
+        result += window_sum;
+    }
+    result
+}
+
+// Writes the scalar in base 2^w, with digits in (-2^(w-1), 2^(w-1)]
+fn signed_digits(scalar: u128, window_size: usize, num_windows: usize) -> Vec<i64> {
+    let radix = 1i64 << window_size;
+    let mut carry = 0;
+    (0..num_windows)
+        .map(|window| {
+            let shift = window * window_size;
+            let bits = if shift < 128 {
+                ((scalar >> shift) as i64) & (radix - 1)
+            } else {
+                0
+            };
+            let mut digit = bits + carry;
+            carry = 0;
+            if digit > radix / 2 {
+                digit -= radix;
+                carry = 1;
+            }
+            digit
+        })
+        .collect()
+}
+
+// Returns the width-w NAF of the scalar, least significant digit first
+fn wnaf_digits(mut scalar: u128) -> Vec<i64> {
+    let radix = 1i64 << WNAF_WINDOW_SIZE;
+    let mut digits = Vec::with_capacity(129);
+    while scalar != 0 {
+        let digit = if scalar & 1 == 1 {
+            let mut digit = (scalar & (radix as u128 - 1)) as i64;
+            if digit >= radix / 2 {
+                digit -= radix;
+            }
+            if digit > 0 {
+                scalar -= digit as u128;
+            } else {
+                scalar += digit.unsigned_abs() as u128;
+            }
+            digit
+        } else {
+            0
+        };
+        digits.push(digit);
+        scalar >>= 1;
+    }
+    digits
+}
+
+// Returns [P, 3P, 5P, ...], which are the multiples needed for the wNAF digits
+fn odd_multiples(point: EdwardsProjective) -> Vec<EdwardsProjective> {
+    let double = point.double();
+    let mut multiples = Vec::with_capacity(1 << (WNAF_WINDOW_SIZE - 2));
+    multiples.push(point);
+    for i in 1..(1 << (WNAF_WINDOW_SIZE - 2)) {
+        multiples.push(multiples[i - 1] + double);
+    }
+    multiples
+}
+
+// Returns the top 128 bits of k·g / 2^256
+fn mul_shift_256(k: &[u64; 4], g: &[u64; 3]) -> u128 {
+    let mut product = [0u64; 7];
+    for (i, k_limb) in k.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, g_limb) in g.iter().enumerate() {
+            let acc = (*k_limb as u128) * (*g_limb as u128) + product[i + j] as u128 + carry;
+            product[i + j] = acc as u64;
+            carry = acc >> 64;
+        }
+        product[i + g.len()] = carry as u64;
+    }
+    debug_assert_eq!(product[6], 0);
+    (product[4] as u128) | ((product[5] as u128) << 64)
+}
+
+// Returns (is_negative, magnitude), where the element is interpreted as lying in (-r/2, r/2)
+fn to_signed(x: Fr) -> (bool, u128) {
+    let (is_negative, magnitude) = if x.into_bigint() > Fr::MODULUS_MINUS_ONE_DIV_TWO {
+        (true, -x)
+    } else {
+        (false, x)
+    };
+    let limbs = magnitude.into_bigint().0;
+    debug_assert!(
+        limbs[2] == 0 && limbs[3] == 0,
+        "decomposed scalar is too large"
+    );
+    (is_negative, (limbs[0] as u128) | ((limbs[1] as u128) << 64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::VariableBaseMSM;
+    use ark_ff::{One, UniformRand};
+
+    fn random_points(n: usize) -> Vec<EdwardsProjective> {
+        (0..n)
+            .map(|i| EdwardsProjective::generator() * Fr::from(i as u64 * 7919 + 3))
+            .collect()
+    }
+
+    #[test]
+    fn endomorphism_is_lambda() {
+        assert_eq!(LAMBDA * LAMBDA, -Fr::from(2u64));
+        for point in random_points(5) {
+            assert_eq!(endomorphism(&point), point * LAMBDA);
+        }
+        assert!(endomorphism(&EdwardsProjective::zero()).is_zero());
+    }
+
+    #[test]
+    fn decomposition() {
+        let mut rng = rand::thread_rng();
+        let edge_cases = [
+            Fr::zero(),
+            Fr::one(),
+            -Fr::one(),
+            LAMBDA,
+            Fr::from(A1),
+            Fr::from(Fr::MODULUS_MINUS_ONE_DIV_TWO),
+        ];
+        let random = (0..1000).map(|_| Fr::rand(&mut rng));
+        for k in edge_cases.into_iter().chain(random) {
+            let [(k1_neg, k1), (k2_neg, k2)] = decompose(&k);
+            assert!(k1 < 1 << 127 && k2 < 1 << 127);
+
+            let signed = |neg: bool, x: u128| if neg { -Fr::from(x) } else { Fr::from(x) };
+            assert_eq!(signed(k1_neg, k1) + signed(k2_neg, k2) * LAMBDA, k);
+        }
+    }
+
+    #[test]
+    fn mul_matches_generic() {
+        let mut rng = rand::thread_rng();
+        let two_torsion =
+            EdwardsProjective::new_unchecked(Fq::zero(), -Fq::one(), Fq::zero(), Fq::one());
+        for point in random_points(4) {
+            for scalar in [Fr::zero(), Fr::one(), -Fr::one(), Fr::rand(&mut rng)] {
+                assert_eq!(mul(&point, &scalar), point * scalar);
+            }
+        }
+        // With the two torsion point, the results agree as banderwagon elements
+        let point = random_points(1)[0] + two_torsion;
+        let scalar = Fr::rand(&mut rng);
+        let (got, expected) = (mul(&point, &scalar), point * scalar);
+        assert_eq!(got.x * expected.y, expected.x * got.y);
+    }
+
+    #[test]
+    fn msm_matches_generic() {
+        let mut rng = rand::thread_rng();
+        for n in [0, 1, 2, 15, 64, 300] {
+            let bases = random_points(n);
+            let mut scalars: Vec<_> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+            if n > 2 {
+                scalars[0] = Fr::zero();
+                scalars[1] = -Fr::one();
+            }
+
+            let affine = EdwardsProjective::normalize_batch(&bases);
+            let expected = EdwardsProjective::msm(&affine, &scalars).unwrap();
+            assert_eq!(msm(&bases, &scalars), expected, "n = {}", n);
+        }
+    }
+}
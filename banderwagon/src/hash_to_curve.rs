@@ -0,0 +1,452 @@
+// Hashing arbitrary bytes to banderwagon elements, following RFC 9380.
+//
+// Messages are hashed to field elements with expand_message_xmd over SHA-256, and each field
+// element is mapped to the curve with Elligator 2 on the Montgomery form of Bandersnatch,
+// following the straight-line description in appendix G.2 of the RFC.
+//
+// The map to the curve does not branch on the field element it is given: selections use
+// `subtle`, comparisons compare the Montgomery limbs in constant time, and `sgn0` does its own
+// Montgomery reduction. Exponents are public constants. The field arithmetic itself comes from
+// arkworks, which ends each multiplication with a conditional subtraction, so this does not
+// rule out timing differences coming from arkworks.
+//
+// The suites are:
+// - banderwagon_XMD:SHA-256_ELL2_RO_ for `Element::hash_to_curve`
+// - banderwagon_XMD:SHA-256_ELL2_NU_ for `Element::encode_to_curve`
+//
+// Applications should pick their own domain separation tag, so that the elements they derive
+// are independent of the ones derived by other applications.
+use crate::Element;
+use ark_ed_on_bls12_381_bandersnatch::{EdwardsProjective, Fq};
+use ark_ff::{BigInteger256, Field, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+// The number of bytes hashed for each field element, which is ceil((ceil(log2(q)) + 128) / 8)
+const HASH_TO_FIELD_LENGTH: usize = 48;
+
+// The non-square used by Elligator 2
+const Z: u64 = 5;
+
+// The Montgomery form of Bandersnatch is K·t² = s³ + J·s² + s, where
+// J = 2(a + d)/(a - d) and K = 4/(a - d)
+const K: Fq = fq_from_u64_limbs([
+    0x926c66eb6fa86d15,
+    0xbd025b636bd74122,
+    0x316b96e5c340cf6a,
+    0x384d1c153c878eea,
+]);
+// J / K
+const J_DIV_K: Fq = fq_from_u64_limbs([
+    0x59b4f97a8c46ac71,
+    0x65b333b8bbf2a7c9,
+    0xe3371dfc35f1db6c,
+    0x31c4e09319e133e5,
+]);
+// 1 / K²
+const INV_K_SQUARED: Fq = fq_from_u64_limbs([
+    0xdfbb904be14f50e1,
+    0x544bddb76a1e7d86,
+    0xa19b6f1789fe957a,
+    0x4e73b361c820997f,
+]);
+
+// Constants for `sqrt_ratio`, where q - 1 = 2^32 · c2
+const TWO_ADICITY: u32 = 32;
+// (c2 - 1) / 2
+const SQRT_RATIO_C3: [u64; 4] = [
+    0x7fff2dff7fffffff,
+    0x04d0ec02a9ded201,
+    0x94cebea4199cec04,
+    0x0000000039f6d3a9,
+];
+// Z^c2
+const SQRT_RATIO_C6: Fq = fq_from_u64_limbs([
+    0x1b788f500b912f1f,
+    0xc4024ff270b3e094,
+    0x0fd56dc8d168d6c0,
+    0x0212d79e5b416b6f,
+]);
+// Z^((c2 + 1) / 2)
+const SQRT_RATIO_C7: Fq = fq_from_u64_limbs([
+    0xfd266ae5766aaa8a,
+    0x1cdf6c8a97508a38,
+    0x468c49d83da8ca4d,
+    0x1f3c6e3831a08403,
+]);
+
+// q - 2, the exponent used to invert without branching
+const MODULUS_MINUS_TWO: [u64; 4] = {
+    let mut limbs = <Fq as PrimeField>::MODULUS.0;
+    limbs[0] -= 2;
+    limbs
+};
+
+// -q⁻¹ mod 2^64, used by `sgn0` to convert out of Montgomery form
+const MODULUS_INV: u64 = {
+    let q0 = <Fq as PrimeField>::MODULUS.0[0];
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 63 {
+        inv = inv.wrapping_mul(inv);
+        inv = inv.wrapping_mul(q0);
+        i += 1;
+    }
+    inv.wrapping_neg()
+};
+
+const fn fq_from_u64_limbs(limbs: [u64; 4]) -> Fq {
+    Fq::new(BigInteger256::new(limbs))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashToCurveError {
+    // RFC 9380 requires a non-empty domain separation tag
+    EmptyDomainSeparationTag,
+}
+
+impl std::fmt::Display for HashToCurveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashToCurveError::EmptyDomainSeparationTag => {
+                write!(f, "the domain separation tag must not be empty")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashToCurveError {}
+
+impl Element {
+    // Hashes a message to an element, such that the output is indistinguishable
+    // from a uniformly random element.
+    //
+    // The domain separation tag must not be empty.
+    pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Result<Element, HashToCurveError> {
+        let [u0, u1] = hash_to_field::<2>(msg, dst)?;
+        let point = map_to_curve(u0) + map_to_curve(u1);
+        Ok(Element(clear_cofactor(point)))
+    }
+
+    // Encodes a message as an element, using a single map to the curve.
+    //
+    // This is cheaper than `hash_to_curve`, however the output is not uniformly distributed,
+    // so it should only be used where that is acceptable.
+    pub fn encode_to_curve(msg: &[u8], dst: &[u8]) -> Result<Element, HashToCurveError> {
+        let [u] = hash_to_field::<1>(msg, dst)?;
+        Ok(Element(clear_cofactor(map_to_curve(u))))
+    }
+}
+
+// The curve is Z/2 × Z/2r, so doubling maps every point into the prime order subgroup
+fn clear_cofactor(point: EdwardsProjective) -> EdwardsProjective {
+    point + point
+}
+
+fn hash_to_field<const N: usize>(msg: &[u8], dst: &[u8]) -> Result<[Fq; N], HashToCurveError> {
+    let uniform_bytes = expand_message_xmd(msg, dst, N * HASH_TO_FIELD_LENGTH)?;
+    Ok(std::array::from_fn(|i| {
+        let bytes = &uniform_bytes[i * HASH_TO_FIELD_LENGTH..(i + 1) * HASH_TO_FIELD_LENGTH];
+        Fq::from_be_bytes_mod_order(bytes)
+    }))
+}
+
+// See section 5.3.1 of RFC 9380
+//
+// The output length is chosen by this module, so it is only checked with an assertion
+fn expand_message_xmd(
+    msg: &[u8],
+    dst: &[u8],
+    len_in_bytes: usize,
+) -> Result<Vec<u8>, HashToCurveError> {
+    const B_IN_BYTES: usize = 32;
+    const S_IN_BYTES: usize = 64;
+
+    if dst.is_empty() {
+        return Err(HashToCurveError::EmptyDomainSeparationTag);
+    }
+    let ell = (len_in_bytes + B_IN_BYTES - 1) / B_IN_BYTES;
+    assert!(ell <= 255 && len_in_bytes <= u16::MAX as usize);
+
+    // Long tags are hashed down, see section 5.3.3
+    let dst = if dst.len() > 255 {
+        Sha256::new()
+            .chain(b"H2C-OVERSIZE-DST-")
+            .chain(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    let dst_prime = [&dst[..], &[dst.len() as u8]].concat();
+
+    let b_0 = Sha256::new()
+        .chain([0u8; S_IN_BYTES])
+        .chain(msg)
+        .chain((len_in_bytes as u16).to_be_bytes())
+        .chain([0u8])
+        .chain(&dst_prime)
+        .finalize();
+
+    let mut uniform_bytes = Vec::with_capacity(ell * B_IN_BYTES);
+    let mut b_i = Sha256::new()
+        .chain(b_0)
+        .chain([1u8])
+        .chain(&dst_prime)
+        .finalize();
+    uniform_bytes.extend_from_slice(&b_i);
+    for i in 2..=ell {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new()
+            .chain(xored)
+            .chain([i as u8])
+            .chain(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+
+    uniform_bytes.truncate(len_in_bytes);
+    Ok(uniform_bytes)
+}
+
+// Elligator 2, see appendix G.2.1 of RFC 9380, followed by the map to twisted Edwards form
+fn map_to_curve(u: Fq) -> EdwardsProjective {
+    let z = Fq::from(Z);
+
+    let mut tv1 = z * u.square();
+    let e1 = ct_eq(&tv1, &-Fq::ONE);
+    tv1 = cmov(tv1, Fq::zero(), e1);
+    let x1 = -J_DIV_K * inv0(tv1 + Fq::ONE);
+    let gx1 = ((x1 + J_DIV_K) * x1 + INV_K_SQUARED) * x1;
+    let x2 = -x1 - J_DIV_K;
+
+    // If gx1 is not square, then y1 = sqrt(Z·gx1) and u·y1 is the square root of gx2 = Z·u²·gx1
+    let (e2, y1) = sqrt_ratio(gx1, Fq::ONE);
+    let x = cmov(x2, x1, e2);
+    let y = cmov(u * y1, y1, e2);
+    let e3 = sgn0(y);
+    let y = cmov(-y, y, !(e2 ^ e3));
+
+    let s = x * K;
+    let t = y * K;
+
+    // (v, w) = (s / t, (s - 1) / (s + 1)), which is the identity if either denominator is zero
+    let denominator = t * (s + Fq::ONE);
+    let inv = inv0(denominator);
+    let v = s * (s + Fq::ONE) * inv;
+    let w = cmov(
+        (s - Fq::ONE) * t * inv,
+        Fq::ONE,
+        ct_eq(&denominator, &Fq::zero()),
+    );
+
+    EdwardsProjective::new_unchecked(v, w, v * w, Fq::ONE)
+}
+
+// Returns (true, sqrt(u / v)) if u / v is square, and (false, sqrt(Z · u / v)) otherwise.
+// See appendix F.2.1.1 of RFC 9380
+fn sqrt_ratio(u: Fq, v: Fq) -> (Choice, Fq) {
+    let c4 = (1u64 << TWO_ADICITY) - 1;
+    let c5 = 1u64 << (TWO_ADICITY - 1);
+
+    let mut tv1 = SQRT_RATIO_C6;
+    let mut tv2 = v.pow([c4]);
+    let mut tv3 = tv2.square() * v;
+    let mut tv5 = (u * tv3).pow(SQRT_RATIO_C3) * tv2;
+    tv2 = tv5 * v;
+    tv3 = tv5 * u;
+    let mut tv4 = tv3 * tv2;
+    tv5 = tv4.pow([c5]);
+    let is_qr = ct_eq(&tv5, &Fq::ONE);
+    tv2 = tv3 * SQRT_RATIO_C7;
+    tv5 = tv4 * tv1;
+    tv3 = cmov(tv2, tv3, is_qr);
+    tv4 = cmov(tv5, tv4, is_qr);
+
+    for i in (2..=TWO_ADICITY).rev() {
+        tv5 = tv4.pow([1u64 << (i - 2)]);
+        let e1 = ct_eq(&tv5, &Fq::ONE);
+        tv2 = tv3 * tv1;
+        tv1 = tv1.square();
+        tv5 = tv4 * tv1;
+        tv3 = cmov(tv2, tv3, e1);
+        tv4 = cmov(tv5, tv4, e1);
+    }
+
+    (is_qr, tv3)
+}
+
+// Returns a if choice is false, and b otherwise
+fn cmov(a: Fq, b: Fq, choice: Choice) -> Fq {
+    let limbs = std::array::from_fn(|i| u64::conditional_select(&a.0 .0[i], &b.0 .0[i], choice));
+    Fq::new_unchecked(BigInteger256::new(limbs))
+}
+
+// Field elements are always fully reduced, so they are equal exactly when their limbs are
+fn ct_eq(a: &Fq, b: &Fq) -> Choice {
+    a.0 .0[..].ct_eq(&b.0 .0[..])
+}
+
+// Returns the inverse of x, or zero if x is zero
+fn inv0(x: Fq) -> Fq {
+    x.pow(MODULUS_MINUS_TWO)
+}
+
+// Returns the parity of x, see section 4.1 of RFC 9380.
+//
+// The parity is that of the canonical integer, so x is taken out of Montgomery form by
+// multiplying it with 1. Since the upper half of the product is zero, the result is already
+// below q, and unlike `into_bigint` no final subtraction is needed.
+fn sgn0(x: Fq) -> Choice {
+    let modulus = <Fq as PrimeField>::MODULUS.0;
+    let mut t = [0u64; 8];
+    t[..4].copy_from_slice(&x.0 .0);
+
+    for i in 0..4 {
+        let k = t[i].wrapping_mul(MODULUS_INV);
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let sum = t[i + j] as u128 + k as u128 * modulus[j] as u128 + carry;
+            t[i + j] = sum as u64;
+            carry = sum >> 64;
+        }
+        for limb in &mut t[i + 4..] {
+            let sum = *limb as u128 + carry;
+            *limb = sum as u64;
+            carry = sum >> 64;
+        }
+    }
+
+    Choice::from((t[4] & 1) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::CurveGroup;
+
+    const DST_RO: &[u8] = b"QUUX-V01-CS02-with-banderwagon_XMD:SHA-256_ELL2_RO_";
+    const DST_NU: &[u8] = b"QUUX-V01-CS02-with-banderwagon_XMD:SHA-256_ELL2_NU_";
+
+    fn messages() -> [Vec<u8>; 4] {
+        [
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdef0123456789".to_vec(),
+            [&b"q128_"[..], &[b'q'; 128][..]].concat(),
+        ]
+    }
+
+    #[test]
+    fn expand_message_xmd_vectors() {
+        // From appendix K.1 of RFC 9380
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        assert_eq!(
+            hex::encode(expand_message_xmd(b"", dst, 0x20).unwrap()),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+        assert_eq!(
+            hex::encode(expand_message_xmd(b"abc", dst, 0x20).unwrap()),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+    }
+
+    // The RFC does not define a suite for banderwagon, so there are no official vectors.
+    // These were generated by this implementation, with the RFC's test DSTs and messages,
+    // and only guard against regressions.
+    #[test]
+    fn hash_to_curve_regression_vectors() {
+        let expected = [
+            "01b44d9918f99f4437e8b67960601ee53ebd60c4e2ea06863e122b739872e271",
+            "1acc0fb997d4fa438f127c891dbc3eca51f0390a69e89551e274016007a0fa84",
+            "1b5df4a678b6accbb4eb96d5769ad31f75d0b21c5a6cf42860363c0db310febf",
+            "2e21b3f71c392779e90cdd929735cfcbe875ca619023fda6c9ca222f77d59341",
+        ];
+        for (msg, expected) in messages().iter().zip(expected) {
+            let element = Element::hash_to_curve(msg, DST_RO).unwrap();
+            assert_eq!(hex::encode(element.to_bytes()), expected);
+            assert_eq!(Element::from_bytes(&element.to_bytes()), Some(element));
+        }
+    }
+
+    // Regression vectors, see `hash_to_curve_regression_vectors`
+    #[test]
+    fn encode_to_curve_regression_vectors() {
+        let expected = [
+            "087b211cea4eacf0e095f903dde0cf2ad9189c33b51cd00c6fa80c7540ef021a",
+            "3db8eaf8f222dad0261eee4e74ee3bed3ce83a3f9cdc67b2e9ac4fa2059eddaf",
+            "1aa1a93b9d5379a71662008badad33de92bf3ea1faa3f1f5536af7c8ee78c788",
+            "271ea79d2645badf0c54d6a7d3971dcbbf7d9d39e5380e01049a7cb00107c1c9",
+        ];
+        for (msg, expected) in messages().iter().zip(expected) {
+            let element = Element::encode_to_curve(msg, DST_NU).unwrap();
+            assert_eq!(hex::encode(element.to_bytes()), expected);
+        }
+    }
+
+    #[test]
+    fn map_to_curve_edge_cases() {
+        // u = 0 gives t = 0, which is mapped to the identity
+        assert!(map_to_curve(Fq::zero()).is_zero());
+
+        // u and -u are mapped to the same point
+        for u in [Fq::from(1u64), Fq::from(5u64), Fq::from(123456789u64)] {
+            let point = map_to_curve(u);
+            assert_eq!(point, map_to_curve(-u));
+            assert!(point.into_affine().is_on_curve());
+        }
+    }
+
+    #[test]
+    fn constant_time_helpers() {
+        use ark_ff::{BigInteger, UniformRand};
+        let mut rng = rand::thread_rng();
+        let values: Vec<Fq> = [Fq::zero(), Fq::ONE, -Fq::ONE]
+            .into_iter()
+            .chain((0..100).map(|_| Fq::rand(&mut rng)))
+            .collect();
+
+        for x in &values {
+            assert_eq!(bool::from(sgn0(*x)), x.into_bigint().is_odd());
+            assert!(bool::from(ct_eq(x, x)));
+            assert!(!bool::from(ct_eq(x, &(*x + Fq::ONE))));
+            assert_eq!(cmov(*x, Fq::ONE, Choice::from(0)), *x);
+            assert_eq!(cmov(*x, Fq::ONE, Choice::from(1)), Fq::ONE);
+        }
+    }
+
+    #[test]
+    fn sqrt_ratio_matches_sqrt() {
+        for u in [1u64, 2, 3, 4, 5, 25, 1000] {
+            let u = Fq::from(u);
+            let (is_qr, root) = sqrt_ratio(u, Fq::from(7u64));
+            let ratio = u / Fq::from(7u64);
+            if bool::from(is_qr) {
+                assert_eq!(root.square(), ratio);
+            } else {
+                assert_eq!(root.square(), Fq::from(Z) * ratio);
+            }
+            assert_eq!(bool::from(is_qr), ratio.legendre().is_qr());
+        }
+    }
+
+    #[test]
+    fn domain_separation() {
+        assert_ne!(
+            Element::hash_to_curve(b"msg", b"app one"),
+            Element::hash_to_curve(b"msg", b"app two")
+        );
+        // Long tags are hashed down, rather than rejected
+        let long_dst = [b'x'; 300];
+        assert!(Element::hash_to_curve(b"msg", &long_dst).is_ok());
+
+        assert_eq!(
+            Element::hash_to_curve(b"msg", b""),
+            Err(HashToCurveError::EmptyDomainSeparationTag)
+        );
+        assert_eq!(
+            Element::encode_to_curve(b"msg", b""),
+            Err(HashToCurveError::EmptyDomainSeparationTag)
+        );
+    }
+}
@@ -1,11 +1,15 @@
+mod glv;
+mod hash_to_curve;
 pub mod msm;
 pub mod msm_windowed_sign;
+mod table_io;
 pub mod trait_impls;
 
 mod element;
 use ark_ed_on_bls12_381_bandersnatch::Fq;
 use ark_ff::BigInteger256;
 pub use element::{multi_scalar_mul, Element, Fr};
+pub use hash_to_curve::HashToCurveError;
 
 // Re-export arkworks traits that one may need to use in order to use
 // specific methods on field elements and for serialization.
@@ -1,8 +1,10 @@
-use ark_ec::scalar_mul::wnaf::WnafContext;
+use ark_ec::{scalar_mul::wnaf::WnafContext, CurveGroup};
 use ark_ed_on_bls12_381_bandersnatch::{EdwardsProjective, Fr};
 use ark_ff::Zero;
 use rayon::prelude::*;
+use std::io::{Read, Write};
 
+use crate::table_io::{read_length, read_points, write_points, write_u64};
 use crate::Element;
 #[derive(Clone, Debug)]
 pub struct MSMPrecompWnaf {
@@ -58,6 +60,64 @@ impl MSMPrecompWnaf {
 
         Element(result)
     }
+
+    // Returns the number of bytes used by the table of a single base, for the given window size
+    pub fn table_size_in_bytes(window_size: usize) -> usize {
+        (1 << (window_size - 1)) * std::mem::size_of::<EdwardsProjective>()
+    }
+
+    // Returns the number of bytes used by all of the tables
+    pub fn memory_usage(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|table| table.len() * std::mem::size_of::<EdwardsProjective>())
+            .sum()
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    // Returns the number of bases that tables were computed for
+    pub fn num_bases(&self) -> usize {
+        self.tables.len()
+    }
+
+    // Returns the base that the table at `index` was computed for
+    pub fn base(&self, index: usize) -> Element {
+        // The first entry in a wnaf table is the base itself
+        Element(self.tables[index][0])
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_u64(&mut writer, self.window_size as u64)?;
+        write_u64(&mut writer, self.tables.len() as u64)?;
+        for table in &self.tables {
+            write_points(&mut writer, &EdwardsProjective::normalize_batch(table))?;
+        }
+        Ok(())
+    }
+
+    // Reads tables which were written with `write`.
+    //
+    // The points are not validated, so the bytes must come from a trusted source
+    // or have their integrity checked by the caller.
+    pub fn read_unchecked<R: Read>(mut reader: R) -> std::io::Result<MSMPrecompWnaf> {
+        let window_size = read_length(&mut reader, 2, 24)?;
+        let num_bases = read_length(&mut reader, 0, 1 << 16)?;
+        let table_size = 1 << (window_size - 1);
+
+        let mut tables = Vec::with_capacity(num_bases);
+        for _ in 0..num_bases {
+            let table = read_points(&mut reader, table_size)?;
+            tables.push(table.into_iter().map(EdwardsProjective::from).collect());
+        }
+
+        Ok(MSMPrecompWnaf {
+            window_size,
+            tables,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -86,4 +146,28 @@ mod tests {
         assert_eq!(result, got_result);
         assert_eq!(result, got_par_result);
     }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let bases: Vec<_> = (1..=4u64)
+            .map(|i| Element::prime_subgroup_generator() * Fr::from(i))
+            .collect();
+        let precomp = MSMPrecompWnaf::new(&bases, 4);
+
+        let mut bytes = Vec::new();
+        precomp.write(&mut bytes).unwrap();
+        let got = MSMPrecompWnaf::read_unchecked(&bytes[..]).unwrap();
+
+        let scalars: Vec<_> = (0..4u64).map(|i| -Fr::from(i + 7)).collect();
+        assert_eq!(got.num_bases(), 4);
+        assert_eq!(got.base(2), bases[2]);
+        assert_eq!(got.mul(&scalars), precomp.mul(&scalars));
+        assert_eq!(
+            got.memory_usage(),
+            4 * MSMPrecompWnaf::table_size_in_bytes(4)
+        );
+
+        // Truncated tables should not be accepted
+        assert!(MSMPrecompWnaf::read_unchecked(&bytes[..bytes.len() - 1]).is_err());
+    }
 }
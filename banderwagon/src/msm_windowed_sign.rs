@@ -3,8 +3,11 @@ use ark_ec::CurveGroup;
 use ark_ed_on_bls12_381_bandersnatch::{EdwardsAffine, EdwardsProjective, Fr};
 use ark_ff::Zero;
 use ark_ff::{BigInteger, BigInteger256};
+use std::io::{Read, Write};
 use std::ops::Neg;
 
+use crate::table_io::{read_length, read_points, write_points, write_u64};
+
 #[derive(Debug, Clone)]
 pub struct MSMPrecompWindowSigned {
     tables: Vec<Vec<EdwardsAffine>>,
@@ -108,6 +111,117 @@ impl MSMPrecompWindowSigned {
 
         Element(result)
     }
+
+    // Same as `mul`, however the scalars are split between threads
+    pub fn mul_par(&self, scalars: &[Fr]) -> Element {
+        use rayon::prelude::*;
+
+        let result: EdwardsProjective = scalars
+            .par_iter()
+            .zip(self.tables.par_iter())
+            .map(|(scalar, sub_table)| {
+                let bigint: BigInteger256 = (*scalar).into();
+                self.mul_table(sub_table, &bigint.to_bytes_le())
+            })
+            .sum();
+
+        Element(result)
+    }
+
+    // Computes the scalar multiplication for a single base, given the tables for that base
+    fn mul_table(&self, sub_table: &[EdwardsAffine], scalar_bytes: &[u8]) -> EdwardsProjective {
+        let mut result = EdwardsProjective::zero();
+        for window_idx in 0..self.num_windows {
+            let point_idx = get_booth_index(window_idx, self.window_size, scalar_bytes);
+            if point_idx == 0 {
+                continue;
+            }
+            let sign = point_idx.is_positive();
+            let point_idx = point_idx.unsigned_abs() as usize - 1;
+
+            let scaled_point_index = window_idx * (1 << (self.window_size - 1)) + point_idx;
+            let point = sub_table[scaled_point_index];
+
+            if sign {
+                result += point;
+            } else {
+                result -= point;
+            }
+        }
+        result
+    }
+}
+
+impl MSMPrecompWindowSigned {
+    // Computes scalar * base, for the base at `index`
+    pub fn mul_index(&self, scalar: Fr, index: usize) -> Element {
+        let bigint: BigInteger256 = scalar.into();
+        let scalar_bytes = bigint.to_bytes_le();
+        Element(self.mul_table(&self.tables[index], &scalar_bytes))
+    }
+
+    // Returns the number of bytes used by the tables of a single base, for the given window size
+    pub fn table_size_in_bytes(window_size: usize) -> usize {
+        use ark_ff::PrimeField;
+
+        let num_windows = Fr::MODULUS_BIT_SIZE as usize / window_size + 1;
+        num_windows * (1 << (window_size - 1)) * std::mem::size_of::<EdwardsAffine>()
+    }
+
+    // Returns the number of bytes used by all of the tables
+    pub fn memory_usage(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|table| table.len() * std::mem::size_of::<EdwardsAffine>())
+            .sum()
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    // Returns the number of bases that tables were computed for
+    pub fn num_bases(&self) -> usize {
+        self.tables.len()
+    }
+
+    // Returns the base that the table at `index` was computed for
+    pub fn base(&self, index: usize) -> Element {
+        // The first entry in each table is the base multiplied by one
+        Element(self.tables[index][0].into())
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_u64(&mut writer, self.window_size as u64)?;
+        write_u64(&mut writer, self.tables.len() as u64)?;
+        for table in &self.tables {
+            write_points(&mut writer, table)?;
+        }
+        Ok(())
+    }
+
+    // Reads tables which were written with `write`.
+    //
+    // The points are not validated, so the bytes must come from a trusted source
+    // or have their integrity checked by the caller.
+    pub fn read_unchecked<R: Read>(mut reader: R) -> std::io::Result<MSMPrecompWindowSigned> {
+        use ark_ff::PrimeField;
+
+        let window_size = read_length(&mut reader, 2, 24)?;
+        let num_bases = read_length(&mut reader, 0, 1 << 16)?;
+        let num_windows = Fr::MODULUS_BIT_SIZE as usize / window_size + 1;
+        let table_size = num_windows * (1 << (window_size - 1));
+
+        let tables = (0..num_bases)
+            .map(|_| read_points(&mut reader, table_size))
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(MSMPrecompWindowSigned {
+            tables,
+            num_windows,
+            window_size,
+        })
+    }
 }
 
 // TODO: Link to halo2 file + docs + comments
@@ -169,6 +283,12 @@ fn smoke_test_interop_strauss() {
 
     let precomp = MSMPrecompWindowSigned::new(&points, 2);
     let result = precomp.mul(&scalars);
+    assert_eq!(result, precomp.mul_par(&scalars));
+    assert_eq!(points[3] * scalars[3], precomp.mul_index(scalars[3], 3));
+    assert_eq!(
+        precomp.memory_usage(),
+        length * MSMPrecompWindowSigned::table_size_in_bytes(2)
+    );
 
     let mut expected = Element::zero();
     for (scalar, point) in scalars.into_iter().zip(points) {
@@ -178,6 +298,23 @@ fn smoke_test_interop_strauss() {
     assert_eq!(expected, result)
 }
 
+#[test]
+fn write_read_roundtrip() {
+    let points: Vec<_> = (1..=3u64)
+        .map(|i| Element::prime_subgroup_generator() * Fr::from(i))
+        .collect();
+    let precomp = MSMPrecompWindowSigned::new(&points, 4);
+
+    let mut bytes = Vec::new();
+    precomp.write(&mut bytes).unwrap();
+    let got = MSMPrecompWindowSigned::read_unchecked(&bytes[..]).unwrap();
+
+    let scalars: Vec<_> = (0..3u64).map(|i| -Fr::from(i + 11)).collect();
+    assert_eq!(got.num_bases(), 3);
+    assert_eq!(got.base(1), points[1]);
+    assert_eq!(got.mul(&scalars), precomp.mul(&scalars));
+}
+
 #[cfg(test)]
 mod booth_tests {
     use std::ops::Neg;
@@ -197,7 +334,7 @@ mod booth_tests {
 
         let got = mul(&s, &gen, 4);
 
-        assert_eq!(Element::from(res), got)
+        assert_eq!(res, got)
     }
 
     fn mul(scalar: &Fr, point: &Element, window: usize) -> Element {
@@ -222,7 +359,7 @@ mod booth_tests {
                 acc_scalar = acc_scalar + acc_scalar;
             }
 
-            let idx = get_booth_index(i as usize, window, u.as_ref());
+            let idx = get_booth_index(i, window, u.as_ref());
 
             if idx.is_negative() {
                 acc += table[idx.unsigned_abs() as usize].neg();
@@ -236,6 +373,6 @@ mod booth_tests {
 
         assert_eq!(acc_scalar, *scalar);
 
-        acc.into()
+        acc
     }
 }
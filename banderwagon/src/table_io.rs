@@ -0,0 +1,108 @@
+// Helpers to read and write precomputed tables.
+//
+// Points are written as affine points, and are read back without any validation,
+// since validating millions of points would take longer than recomputing the tables.
+// Callers are expected to check the integrity of the bytes.
+use ark_ed_on_bls12_381_bandersnatch::{EdwardsAffine, Fq};
+use ark_ff::BigInt;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+// An upper bound on the lengths that we read, so that corrupted bytes
+// cannot make us allocate an unbounded amount of memory
+const MAX_LENGTH: u64 = 1 << 32;
+
+pub(crate) fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// Reads a length, which must be between `min` and `max` inclusive
+pub(crate) fn read_length<R: Read>(reader: &mut R, min: u64, max: u64) -> Result<usize> {
+    let length = read_u64(reader)?;
+    if length < min || length > max.min(MAX_LENGTH) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("table length {} is out of range", length),
+        ));
+    }
+    Ok(length as usize)
+}
+
+// Each coordinate is written as the little endian limbs of its Montgomery form,
+// which avoids converting in and out of Montgomery form
+const POINT_SIZE: usize = 64;
+
+pub(crate) fn write_points<W: Write>(writer: &mut W, points: &[EdwardsAffine]) -> Result<()> {
+    let mut bytes = Vec::with_capacity(points.len() * POINT_SIZE);
+    for point in points {
+        for limb in point.x.0 .0.iter().chain(point.y.0 .0.iter()) {
+            bytes.extend_from_slice(&limb.to_le_bytes());
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+pub(crate) fn read_points<R: Read>(reader: &mut R, length: usize) -> Result<Vec<EdwardsAffine>> {
+    use rayon::prelude::*;
+
+    // The length was read from the same untrusted bytes, so we only allocate
+    // as the bytes arrive, instead of trusting the length up front
+    let num_bytes = length
+        .checked_mul(POINT_SIZE)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "table length is too large"))?;
+    let mut bytes = Vec::new();
+    reader.take(num_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != num_bytes {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!(
+                "expected {} bytes of points, but only {} were left",
+                num_bytes,
+                bytes.len()
+            ),
+        ));
+    }
+
+    let points = bytes
+        .par_chunks_exact(POINT_SIZE)
+        .map(|point_bytes| {
+            let (x_bytes, y_bytes) = point_bytes.split_at(POINT_SIZE / 2);
+            EdwardsAffine::new_unchecked(read_fq(x_bytes), read_fq(y_bytes))
+        })
+        .collect();
+    Ok(points)
+}
+
+fn read_fq(bytes: &[u8]) -> Fq {
+    let mut limbs = [0u64; 4];
+    for (limb, limb_bytes) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(limb_bytes.try_into().unwrap());
+    }
+    Fq::new_unchecked(BigInt::new(limbs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_points, write_points, POINT_SIZE};
+    use crate::Element;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn read_points_checks_the_input_size() {
+        let points = vec![Element::prime_subgroup_generator().0.into(); 3];
+        let mut bytes = Vec::new();
+        write_points(&mut bytes, &points).unwrap();
+        assert_eq!(bytes.len(), 3 * POINT_SIZE);
+
+        assert_eq!(read_points(&mut &bytes[..], 3).unwrap(), points);
+
+        // A length which is far larger than the input should fail, without allocating for it
+        let err = read_points(&mut &bytes[..], 1 << 40).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod from_to_bytes;
 pub mod ops;
+#[cfg(feature = "serde")]
+pub mod serde;
 pub mod serialize;
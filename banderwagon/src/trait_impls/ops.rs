@@ -11,14 +11,14 @@ impl Mul<Fr> for Element {
     type Output = Element;
 
     fn mul(self, rhs: Fr) -> Self::Output {
-        Element(self.0.mul(rhs))
+        Element(crate::glv::mul(&self.0, &rhs))
     }
 }
 impl Mul<&Fr> for &Element {
     type Output = Element;
 
     fn mul(self, rhs: &Fr) -> Self::Output {
-        Element(self.0.mul(rhs))
+        Element(crate::glv::mul(&self.0, rhs))
     }
 }
 impl Add<Element> for Element {
@@ -0,0 +1,178 @@
+// Serde support for `Element` and `Fr`.
+//
+// Both types are encoded using their canonical 32 byte encoding. Human-readable formats,
+// such as JSON, use a 0x-prefixed hex string, and binary formats use the raw bytes.
+//
+// `Fr` is defined in arkworks, so we cannot implement the serde traits for it directly.
+// Use `#[serde(with = "banderwagon::trait_impls::serde::fr")]` on fields of type `Fr` instead.
+//
+// `serialize_bytes` and `deserialize_bytes` are exposed so that types which are built
+// from elements, such as proofs, can use the same encoding for their canonical bytes.
+use crate::{Element, Fr};
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::fmt;
+
+// Serializes the bytes as a 0x-prefixed hex string for human-readable formats
+// and as raw bytes otherwise
+pub fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+// Deserializes bytes which were serialized with `serialize_bytes`.
+//
+// The 0x prefix is optional for human-readable formats.
+pub fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a hex string or a byte array")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+            let value = value.strip_prefix("0x").unwrap_or(value);
+            hex::decode(value).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(value.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(value)
+        }
+
+        // Some binary formats do not have a native byte array type
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(BytesVisitor)
+    } else {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+fn deserialize_array<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+    let bytes = deserialize_bytes(deserializer)?;
+    let len = bytes.len();
+    bytes
+        .try_into()
+        .map_err(|_| de::Error::invalid_length(len, &"32 bytes"))
+}
+
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.to_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Element {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_array(deserializer)?;
+        Element::from_bytes(&bytes)
+            .ok_or_else(|| de::Error::custom("bytes do not encode a banderwagon element"))
+    }
+}
+
+// Serde functions for `Fr`, which use the little-endian canonical encoding
+pub mod fr {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(scalar: &Fr, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = [0u8; 32];
+        scalar
+            .serialize_compressed(&mut bytes[..])
+            .map_err(::serde::ser::Error::custom)?;
+        serialize_bytes(&bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Fr, D::Error> {
+        let bytes = deserialize_array(deserializer)?;
+        Fr::deserialize_compressed(&bytes[..])
+            .map_err(|_| de::Error::custom("bytes do not encode a canonical scalar"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{BigInteger, PrimeField};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper {
+        point: Element,
+        #[serde(with = "fr")]
+        scalar: Fr,
+    }
+
+    fn wrapper() -> Wrapper {
+        Wrapper {
+            point: Element::prime_subgroup_generator() * Fr::from(1234u64),
+            scalar: -Fr::from(5u64),
+        }
+    }
+
+    #[test]
+    fn json_roundtrip_matches_canonical_bytes() {
+        let value = wrapper();
+        let json = serde_json::to_value(&value).unwrap();
+
+        let mut scalar_bytes = Vec::new();
+        value
+            .scalar
+            .serialize_compressed(&mut scalar_bytes)
+            .unwrap();
+        assert_eq!(
+            json["point"],
+            format!("0x{}", hex::encode(value.point.to_bytes()))
+        );
+        assert_eq!(json["scalar"], format!("0x{}", hex::encode(scalar_bytes)));
+
+        let got: Wrapper = serde_json::from_value(json).unwrap();
+        assert_eq!(got, value);
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let value = wrapper();
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&value, &mut bytes).unwrap();
+        let got: Wrapper = ciborium::from_reader(&bytes[..]).unwrap();
+        assert_eq!(got, value);
+    }
+
+    #[test]
+    fn rejects_invalid_encodings() {
+        // The prefix is optional
+        let hex_point = hex::encode(Element::prime_subgroup_generator().to_bytes());
+        let got: Element = serde_json::from_str(&format!("\"{}\"", hex_point)).unwrap();
+        assert_eq!(got, Element::prime_subgroup_generator());
+
+        // Wrong length
+        assert!(serde_json::from_str::<Element>("\"0x0102\"").is_err());
+        // Not a hex string
+        assert!(serde_json::from_str::<Element>(&format!("\"0x{}\"", "zz".repeat(32))).is_err());
+        // Not on the curve
+        assert!(serde_json::from_str::<Element>(&format!("\"0x{}\"", "ff".repeat(32))).is_err());
+
+        // The modulus is not a canonical scalar
+        let modulus = Fr::MODULUS.to_bytes_le();
+        let json = format!("\"0x{}\"", hex::encode(modulus));
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        assert!(fr::deserialize(&mut deserializer).is_err());
+    }
+}
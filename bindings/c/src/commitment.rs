@@ -0,0 +1,434 @@
+//! C functions for creating, updating and hashing commitments.
+//!
+//! Every function returns a `VerkleStatus`, see `lib.rs` for the conventions on outputs.
+//!
+//! Commitments are passed uncompressed as 64 bytes, and must have been created by these
+//! functions. They are converted to and from the 32 byte compressed form with
+//! `serialize_commitment` and `deserialize_commitment`. Scalars are passed as 32 byte
+//! little-endian field elements.
+use crate::status::{
+    check_not_null, guard, input_array, input_arrays, input_slice, write_output, VerkleStatus,
+};
+use ffi_interface::Context;
+
+/// Commits to `len` bytes of little-endian scalars, and writes the 64 byte uncompressed
+/// commitment into `out`.
+///
+/// `len` must be a multiple of 32, and at most 256 scalars can be committed to.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn commit_to_scalars(
+    ctx: *const Context,
+    scalars: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, scalars, out);
+        let (context, scalars) = unsafe { (&*ctx, input_slice(scalars, len)) };
+
+        let commitment = ffi_interface::commit_to_scalars(context, scalars)?;
+        unsafe { write_output(out, out_capacity, &commitment) }
+    })
+}
+
+/// Adds the commitments `lhs` and `rhs`, and writes the 64 byte result into `out`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn add_commitment(
+    lhs: *const u8,
+    rhs: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(lhs, rhs, out);
+        let (lhs, rhs) = unsafe { (input_array(lhs), input_array(rhs)) };
+
+        let sum = ffi_interface::add_commitment(lhs, rhs);
+        unsafe { write_output(out, out_capacity, &sum) }
+    })
+}
+
+/// Updates the scalar at `commitment_index` of `old_commitment` from `old_scalar` to
+/// `new_scalar`, and writes the 64 byte updated commitment into `out`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn update_commitment(
+    ctx: *const Context,
+    old_commitment: *const u8,
+    commitment_index: u8,
+    old_scalar: *const u8,
+    new_scalar: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, old_commitment, old_scalar, new_scalar, out);
+        let context = unsafe { &*ctx };
+        let (old_commitment, old_scalar, new_scalar) = unsafe {
+            (
+                input_array(old_commitment),
+                input_array(old_scalar),
+                input_array(new_scalar),
+            )
+        };
+
+        let commitment = ffi_interface::update_commitment(
+            context,
+            old_commitment,
+            commitment_index,
+            old_scalar,
+            new_scalar,
+        )?;
+        unsafe { write_output(out, out_capacity, &commitment) }
+    })
+}
+
+/// Same as `update_commitment`, however `len` scalars are updated at once.
+///
+/// `commitment_indices` must point to `len` bytes, and `old_scalars` and `new_scalars`
+/// must each point to `len * 32` bytes.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[allow(clippy::too_many_arguments)]
+#[no_mangle]
+pub extern "C" fn update_commitment_sparse(
+    ctx: *const Context,
+    old_commitment: *const u8,
+    commitment_indices: *const u8,
+    old_scalars: *const u8,
+    new_scalars: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(
+            ctx,
+            old_commitment,
+            commitment_indices,
+            old_scalars,
+            new_scalars,
+            out
+        );
+        let context = unsafe { &*ctx };
+        let (old_commitment, commitment_indices, old_scalars, new_scalars) = unsafe {
+            (
+                input_array(old_commitment),
+                input_slice(commitment_indices, len),
+                input_arrays(old_scalars, len)?,
+                input_arrays(new_scalars, len)?,
+            )
+        };
+
+        let commitment = ffi_interface::update_commitment_sparse(
+            context,
+            old_commitment,
+            commitment_indices
+                .iter()
+                .map(|&index| index as usize)
+                .collect(),
+            old_scalars,
+            new_scalars,
+        )?;
+        unsafe { write_output(out, out_capacity, &commitment) }
+    })
+}
+
+/// Hashes `commitment` to a scalar, and writes the 32 byte scalar into `out`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn hash_commitment(
+    commitment: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(commitment, out);
+        let commitment = unsafe { input_array(commitment) };
+
+        let hash = ffi_interface::hash_commitment(commitment);
+        unsafe { write_output(out, out_capacity, &hash) }
+    })
+}
+
+/// Hashes `len` commitments to scalars, and writes the `len * 32` bytes of scalars into `out`.
+///
+/// This is faster than calling `hash_commitment` for each commitment.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn hash_commitments(
+    commitments: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(commitments, out);
+        let commitments = unsafe { input_arrays::<64>(commitments, len)? };
+
+        let hashes = ffi_interface::hash_commitments(&commitments);
+        unsafe { write_output(out, out_capacity, &hashes.concat()) }
+    })
+}
+
+/// Compresses `commitment`, and writes the 32 byte result into `out`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn serialize_commitment(
+    commitment: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(commitment, out);
+        let commitment = unsafe { input_array(commitment) };
+
+        let serialized = ffi_interface::serialize_commitment(commitment);
+        unsafe { write_output(out, out_capacity, &serialized) }
+    })
+}
+
+/// Compresses `len` commitments, and writes the `len * 32` bytes of results into `out`.
+///
+/// This is faster than calling `serialize_commitment` for each commitment.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn serialize_commitments(
+    commitments: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(commitments, out);
+        let commitments = unsafe { input_arrays::<64>(commitments, len)? };
+
+        let serialized = ffi_interface::serialize_commitments(&commitments);
+        unsafe { write_output(out, out_capacity, &serialized.concat()) }
+    })
+}
+
+/// Decompresses a 32 byte commitment, and writes the 64 byte result into `out`.
+///
+/// Returns `CouldNotDeserializeCommitment` if the bytes are not a valid commitment.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn deserialize_commitment(
+    serialized_commitment: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(serialized_commitment, out);
+        let serialized_commitment = unsafe { input_array(serialized_commitment) };
+
+        let commitment = ffi_interface::deserialize_commitment(serialized_commitment)?;
+        unsafe { write_output(out, out_capacity, &commitment) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_context;
+    use banderwagon::{Element, Fr};
+    use ffi_interface::CommitmentBytes;
+    use ipa_multipoint::committer::Committer;
+
+    fn commit(scalars: &[Fr]) -> CommitmentBytes {
+        let bytes: Vec<u8> = scalars
+            .iter()
+            .flat_map(|scalar| ffi_interface::fr_to_le_bytes(*scalar))
+            .collect();
+        let mut out = [0u8; 64];
+        assert_eq!(
+            commit_to_scalars(
+                test_context(),
+                bytes.as_ptr(),
+                bytes.len(),
+                out.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::Ok
+        );
+        out
+    }
+
+    fn element(commitment: CommitmentBytes) -> Element {
+        Element::from_bytes_unchecked_uncompressed(commitment)
+    }
+
+    #[test]
+    fn commit_and_add() {
+        let ctx = unsafe { &*test_context() };
+        let a = commit(&[Fr::from(1u64), Fr::from(2u64)]);
+        let b = commit(&[Fr::from(3u64)]);
+        assert_eq!(
+            element(a),
+            ctx.committer
+                .commit_lagrange(&[Fr::from(1u64), Fr::from(2u64)])
+        );
+
+        let mut sum = [0u8; 64];
+        assert_eq!(
+            add_commitment(a.as_ptr(), b.as_ptr(), sum.as_mut_ptr(), 64),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            element(sum),
+            element(commit(&[Fr::from(4u64), Fr::from(2u64)]))
+        );
+
+        assert_eq!(
+            commit_to_scalars(test_context(), [0u8; 31].as_ptr(), 31, sum.as_mut_ptr(), 64),
+            VerkleStatus::LengthOfScalarsNotMultipleOf32
+        );
+        assert_eq!(
+            add_commitment(a.as_ptr(), b.as_ptr(), sum.as_mut_ptr(), 32),
+            VerkleStatus::OutputTooSmall
+        );
+    }
+
+    #[test]
+    fn update_commitments() {
+        let old = commit(&[Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)]);
+        let zero = ffi_interface::fr_to_le_bytes(Fr::from(0u64));
+        let one = ffi_interface::fr_to_le_bytes(Fr::from(1u64));
+        let three = ffi_interface::fr_to_le_bytes(Fr::from(3u64));
+        let five = ffi_interface::fr_to_le_bytes(Fr::from(5u64));
+
+        let mut updated = [0u8; 64];
+        assert_eq!(
+            update_commitment(
+                test_context(),
+                old.as_ptr(),
+                2,
+                three.as_ptr(),
+                five.as_ptr(),
+                updated.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::Ok
+        );
+        let expected = commit(&[Fr::from(1u64), Fr::from(2u64), Fr::from(5u64)]);
+        assert_eq!(element(updated), element(expected));
+
+        let indices = [0u8, 2];
+        let old_scalars = [one, three].concat();
+        let new_scalars = [zero, five].concat();
+        assert_eq!(
+            update_commitment_sparse(
+                test_context(),
+                old.as_ptr(),
+                indices.as_ptr(),
+                old_scalars.as_ptr(),
+                new_scalars.as_ptr(),
+                2,
+                updated.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::Ok
+        );
+        let expected = commit(&[Fr::from(0u64), Fr::from(2u64), Fr::from(5u64)]);
+        assert_eq!(element(updated), element(expected));
+
+        // Scalars larger than the modulus are not canonical
+        let non_canonical = [0xffu8; 32];
+        assert_eq!(
+            update_commitment(
+                test_context(),
+                old.as_ptr(),
+                0,
+                non_canonical.as_ptr(),
+                five.as_ptr(),
+                updated.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::FailedToDeserializeScalar
+        );
+        assert_eq!(
+            update_commitment_sparse(
+                test_context(),
+                old.as_ptr(),
+                indices.as_ptr(),
+                [non_canonical; 2].concat().as_ptr(),
+                new_scalars.as_ptr(),
+                2,
+                updated.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::FailedToDeserializeScalar
+        );
+    }
+
+    #[test]
+    fn hash_commitments_matches_single() {
+        let commitments = [commit(&[Fr::from(1u64)]), commit(&[Fr::from(2u64)])];
+
+        let mut hashes = [0u8; 64];
+        assert_eq!(
+            hash_commitments(commitments.concat().as_ptr(), 2, hashes.as_mut_ptr(), 64),
+            VerkleStatus::Ok
+        );
+        for (commitment, hash) in commitments.iter().zip(hashes.chunks_exact(32)) {
+            let mut single = [0u8; 32];
+            assert_eq!(
+                hash_commitment(commitment.as_ptr(), single.as_mut_ptr(), 32),
+                VerkleStatus::Ok
+            );
+            assert_eq!(single, hash);
+            assert_eq!(
+                single,
+                ffi_interface::fr_to_le_bytes(element(*commitment).map_to_scalar_field())
+            );
+        }
+
+        assert_eq!(
+            hash_commitments(commitments.concat().as_ptr(), 2, hashes.as_mut_ptr(), 32),
+            VerkleStatus::OutputTooSmall
+        );
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let commitments = [commit(&[Fr::from(1u64)]), commit(&[Fr::from(2u64)])];
+
+        let mut serialized = [0u8; 64];
+        assert_eq!(
+            serialize_commitments(
+                commitments.concat().as_ptr(),
+                2,
+                serialized.as_mut_ptr(),
+                64
+            ),
+            VerkleStatus::Ok
+        );
+        for (commitment, compressed) in commitments.iter().zip(serialized.chunks_exact(32)) {
+            let mut single = [0u8; 32];
+            assert_eq!(
+                serialize_commitment(commitment.as_ptr(), single.as_mut_ptr(), 32),
+                VerkleStatus::Ok
+            );
+            assert_eq!(single, compressed);
+
+            let mut decompressed = [0u8; 64];
+            assert_eq!(
+                deserialize_commitment(single.as_ptr(), decompressed.as_mut_ptr(), 64),
+                VerkleStatus::Ok
+            );
+            assert_eq!(element(decompressed), element(*commitment));
+        }
+
+        let mut decompressed = [0u8; 64];
+        assert_eq!(
+            deserialize_commitment([0xffu8; 32].as_ptr(), decompressed.as_mut_ptr(), 64),
+            VerkleStatus::CouldNotDeserializeCommitment
+        );
+        assert_eq!(
+            deserialize_commitment(std::ptr::null(), decompressed.as_mut_ptr(), 64),
+            VerkleStatus::NullPointer
+        );
+    }
+}
@@ -1,21 +1,92 @@
+pub mod commitment;
+pub mod status;
+pub mod trie;
+
 use ffi_interface::{
-    deserialize_proof_query, deserialize_proof_query_uncompressed, deserialize_verifier_query,
-    deserialize_verifier_query_uncompressed, fr_from_le_bytes, Context,
+    deserialize_proof_query_uncompressed, deserialize_verifier_query_uncompressed, Context,
+};
+use ipa_multipoint::multiproof::{
+    MultiPoint, MultiPointProof, ProverQuery, ProverQueryRef, VerifierQuery,
 };
-use ipa_multipoint::committer::Committer;
-use ipa_multipoint::multiproof::{MultiPoint, MultiPointProof, ProverQuery, VerifierQuery};
 use ipa_multipoint::transcript::Transcript;
+use status::{check_not_null, guard, input_array, input_slice, write_output, VerkleStatus};
+use std::ffi::{c_char, CStr};
 
 #[allow(deprecated)]
 use ffi_interface::get_tree_key_hash;
 
+// Every function returns a `VerkleStatus`, and only writes to its outputs when it returns `Ok`.
+// Outputs are written to a buffer of `out_capacity` bytes, which must be at least as large
+// as the output, otherwise `OutputTooSmall` is returned.
+
+/// The size of a serialized proof created with `create_proof`
+pub const PROOF_SIZE: usize = 576;
+/// The size of a serialized proof created with `create_proof_uncompressed`
+pub const UNCOMPRESSED_PROOF_SIZE: usize = 1120;
+
+/// Creates a context and writes it to `out_ctx`.
+///
+/// The context must be freed with `context_free`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn context_new(out_ctx: *mut *mut Context) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(out_ctx);
+        let ctx = Box::new(Context::default());
+        unsafe { *out_ctx = Box::into_raw(ctx) };
+        Ok(())
+    })
+}
+
+/// Creates a context whose committer tables are read from the `len` bytes at `tables`,
+/// and writes it to `out_ctx`.
+///
+/// This is much faster than `context_new`. The tables are created with
+/// `context_write_precomputed_tables`, and `InvalidPrecomputedTables` is returned if they
+/// cannot be read. The context must be freed with `context_free`.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn context_new() -> *mut Context {
-    let ctx = Box::new(Context::default());
-    Box::into_raw(ctx)
+pub extern "C" fn context_new_from_precomputed_tables(
+    tables: *const u8,
+    len: usize,
+    out_ctx: *mut *mut Context,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(tables, out_ctx);
+        let tables = unsafe { input_slice(tables, len) };
+
+        let ctx = Box::new(Context::from_precomputed_tables(tables)?);
+        unsafe { *out_ctx = Box::into_raw(ctx) };
+        Ok(())
+    })
 }
 
+/// Serializes the committer tables of the context into `out`.
+///
+/// The length of the tables is always written to `out_len`, so if `OutputTooSmall` is
+/// returned, the caller can retry with a buffer of that size.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn context_write_precomputed_tables(
+    ctx: *const Context,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, out, out_len);
+        let context = unsafe { &*ctx };
+
+        let mut tables = Vec::new();
+        context.write_precomputed_tables(&mut tables)?;
+        unsafe {
+            *out_len = tables.len();
+            write_output(out, out_capacity, &tables)
+        }
+    })
+}
+
+/// Frees a context created with `context_new`. Passing null is a no-op.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn context_free(ctx: *mut Context) {
@@ -27,283 +98,600 @@ pub extern "C" fn context_free(ctx: *mut Context) {
     }
 }
 
+/// Writes the 32 byte tree key hash for `address` and `tree_index_le` into `out`.
 #[allow(deprecated)]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn pedersen_hash(
-    ctx: *mut Context,
+    ctx: *const Context,
     address: *const u8,
     tree_index_le: *const u8,
     out: *mut u8,
-) {
-    if ctx.is_null() || address.is_null() || tree_index_le.is_null() || out.is_null() {
-        // TODO: We have ommited the error handling for null pointers at the moment.
-        // TODO: Likely will panic in this case.
-        return;
-    }
-
-    let (tree_index, add, context) = unsafe {
-        let add_slice = std::slice::from_raw_parts(address, 32);
-        let ctx_ref = &*ctx;
-        let tree_index_slice = std::slice::from_raw_parts(tree_index_le, 32);
-
-        (tree_index_slice, add_slice, ctx_ref)
-    };
-
-    let hash = get_tree_key_hash(
-        context,
-        <[u8; 32]>::try_from(add).unwrap(),
-        <[u8; 32]>::try_from(tree_index).unwrap(),
-    );
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, address, tree_index_le, out);
+        let (context, address, tree_index) =
+            unsafe { (&*ctx, input_array(address), input_array(tree_index_le)) };
+
+        let hash = get_tree_key_hash(context, address, tree_index);
+        unsafe { write_output(out, out_capacity, &hash) }
+    })
+}
 
-    unsafe {
-        let commitment_data_slice = std::slice::from_raw_parts_mut(out, 32);
-        commitment_data_slice.copy_from_slice(&hash);
-    }
+/// Writes the 32 byte tree key for `address`, `tree_index_le` and `sub_index` into `out`.
+///
+/// This is the tree key hash from `pedersen_hash`, with its last byte set to `sub_index`.
+#[allow(deprecated)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn get_tree_key(
+    ctx: *const Context,
+    address: *const u8,
+    tree_index_le: *const u8,
+    sub_index: u8,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, address, tree_index_le, out);
+        let (context, address, tree_index) =
+            unsafe { (&*ctx, input_array(address), input_array(tree_index_le)) };
+
+        let key = ffi_interface::get_tree_key(context, address, tree_index, sub_index);
+        unsafe { write_output(out, out_capacity, &key) }
+    })
 }
 
+/// Commits to `len` bytes of little-endian scalars, and writes the 32 byte compressed
+/// commitment into `out`.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn multi_scalar_mul(
-    ctx: *mut Context,
+    ctx: *const Context,
     scalars: *const u8,
     len: usize,
     out: *mut u8,
-) {
-    let (scalar_slice, context) = unsafe {
-        let scalar = std::slice::from_raw_parts(scalars, len);
-        let ctx_ref = &*ctx;
-
-        (scalar, ctx_ref)
-    };
-
-    let mut inputs = Vec::with_capacity(len);
-    for chunk in scalar_slice.chunks_exact(32) {
-        inputs.push(fr_from_le_bytes(chunk).unwrap());
-    }
-
-    let data = context.committer.commit_lagrange(&inputs);
-    let hash = data.to_bytes();
-
-    unsafe {
-        let commitment_data_slice = std::slice::from_raw_parts_mut(out, 32);
-        commitment_data_slice.copy_from_slice(&hash);
-    }
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, scalars, out);
+        let (context, scalars) = unsafe { (&*ctx, input_slice(scalars, len)) };
+
+        let commitment = ffi_interface::commit_to_scalars(context, scalars)?;
+        let commitment = ffi_interface::serialize_commitment(commitment);
+        unsafe { write_output(out, out_capacity, &commitment) }
+    })
 }
 
+/// Creates a multiproof for the serialized prover queries in `input`, and writes the
+/// `PROOF_SIZE` byte proof into `out`.
+///
+/// Returns `CouldNotDeserializeCommitment` or `FailedToDeserializeScalar` if a query in
+/// `input` contains an invalid commitment or scalar.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn create_proof(ctx: *mut Context, input: *const u8, len: usize, out: *mut u8) {
-    const CHUNK_SIZE: usize = 8257; // TODO: get this from ipa-multipoint
-    const PROOF_SIZE: usize = 576; // TODO: get this from ipa-multipoint
-
-    let (scalar_slice, context) = unsafe {
-        let scalar = std::slice::from_raw_parts(input, len);
-        let ctx_ref = &*ctx;
-
-        (scalar, ctx_ref)
-    };
-
-    let num_openings = len / CHUNK_SIZE;
-
-    let proofs_bytes = scalar_slice.chunks_exact(CHUNK_SIZE);
-    assert!(
-        proofs_bytes.remainder().is_empty(),
-        "There should be no left over bytes when chunking the proof"
-    );
-
-    // - Deserialize proof queries
-    //
-    let mut prover_queries: Vec<ProverQuery> = Vec::with_capacity(num_openings);
-
-    for proof_bytes in proofs_bytes {
-        let prover_query = deserialize_proof_query(proof_bytes);
-        prover_queries.push(prover_query);
-    }
-
-    // - Create proofs
-    //
-
-    let mut transcript = Transcript::new(b"verkle");
-
-    let proof = MultiPoint::open(
-        // TODO: This should not need to clone the CRS, but instead take a reference
-        context.crs.clone(),
-        &context.precomputed_weights,
-        &mut transcript,
-        prover_queries,
-    );
-
-    let hash = proof.to_bytes().expect("cannot serialize proof");
-    unsafe {
-        let commitment_data_slice = std::slice::from_raw_parts_mut(out, PROOF_SIZE);
-        commitment_data_slice.copy_from_slice(&hash);
-    }
+pub extern "C" fn create_proof(
+    ctx: *const Context,
+    input: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, input, out);
+        let (context, input) = unsafe { (&*ctx, input_slice(input, len)) };
+
+        let proof = ffi_interface::create_proof(context, input.to_vec())?;
+        unsafe { write_output(out, out_capacity, &proof) }
+    })
 }
 
+/// Same as `create_proof`, however the commitments in `input` and the proof are uncompressed.
+///
+/// The proof is `UNCOMPRESSED_PROOF_SIZE` bytes.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn create_proof_uncompressed(
-    ctx: *mut Context,
+    ctx: *const Context,
     input: *const u8,
     len: usize,
     out: *mut u8,
-) {
+    out_capacity: usize,
+) -> VerkleStatus {
     // 8257 + 32 because first commitment is uncompressed as 64 bytes
     const CHUNK_SIZE: usize = 8289; // TODO: get this from ipa-multipoint
-    const PROOF_SIZE: usize = 1120; // TODO: get this from ipa-multipoint
 
-    let (scalar_slice, context) = unsafe {
-        let scalar = std::slice::from_raw_parts(input, len);
-        let ctx_ref = &*ctx;
+    guard(|| {
+        check_not_null!(ctx, input, out);
+        let (context, input) = unsafe { (&*ctx, input_slice(input, len)) };
 
-        (scalar, ctx_ref)
-    };
+        let proofs_bytes = input.chunks_exact(CHUNK_SIZE);
+        if !proofs_bytes.remainder().is_empty() {
+            return Err(VerkleStatus::LengthIsNotAnExpectedMultiple);
+        }
 
-    let num_openings = len / CHUNK_SIZE;
+        // - Deserialize proof queries
+        //
+        let prover_queries: Vec<ProverQuery> = proofs_bytes
+            .map(deserialize_proof_query_uncompressed)
+            .collect::<Result<_, _>>()?;
 
-    let proofs_bytes = scalar_slice.chunks_exact(CHUNK_SIZE);
-    assert!(
-        proofs_bytes.remainder().is_empty(),
-        "There should be no left over bytes when chunking the proof"
-    );
+        // - Create proofs
+        //
 
-    // - Deserialize proof queries
-    //
-    let mut prover_queries: Vec<ProverQuery> = Vec::with_capacity(num_openings);
+        let mut transcript = Transcript::new(b"verkle");
 
-    for proof_bytes in proofs_bytes {
-        let prover_query = deserialize_proof_query_uncompressed(proof_bytes);
-        prover_queries.push(prover_query);
-    }
-
-    // - Create proofs
-    //
-
-    let mut transcript = Transcript::new(b"verkle");
+        let prover_queries: Vec<_> = prover_queries.iter().map(ProverQueryRef::from).collect();
+        let proof =
+            MultiPoint::open_with_key(&context.prover_key, &mut transcript, &prover_queries);
 
-    let proof = MultiPoint::open(
-        // TODO: This should not need to clone the CRS, but instead take a reference
-        context.crs.clone(),
-        &context.precomputed_weights,
-        &mut transcript,
-        prover_queries,
-    );
-
-    let hash = proof
-        .to_bytes_uncompressed()
-        .expect("cannot serialize proof");
-    unsafe {
-        let commitment_data_slice = std::slice::from_raw_parts_mut(out, PROOF_SIZE);
-        commitment_data_slice.copy_from_slice(&hash);
-    }
+        let proof = proof
+            .to_bytes_uncompressed()
+            .map_err(|_| VerkleStatus::InvalidInput)?;
+        unsafe { write_output(out, out_capacity, &proof) }
+    })
 }
 
+/// Verifies a `PROOF_SIZE` byte proof, followed by the serialized verifier queries.
+///
+/// Returns `ProofVerificationFailed` if the proof is invalid.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn verify_proof(ctx: *mut Context, input: *const u8, len: usize) -> bool {
-    const CHUNK_SIZE: usize = 65; // TODO: get this from ipa-multipoint
-    const PROOF_SIZE: usize = 576; // TODO: get this from ipa-multipoint
-
-    let (proof_slice, verifier_queries_slices, context) = unsafe {
-        let input_slice = std::slice::from_raw_parts(input, len);
-
-        let (proof_slice, verifier_queries_slices) = input_slice.split_at(PROOF_SIZE);
-
-        let ctx_ref = &*ctx;
-
-        (proof_slice, verifier_queries_slices, ctx_ref)
-    };
+pub extern "C" fn verify_proof(ctx: *const Context, input: *const u8, len: usize) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, input);
+        let (context, input) = unsafe { (&*ctx, input_slice(input, len)) };
+
+        if input.len() < PROOF_SIZE {
+            return Err(VerkleStatus::InvalidInput);
+        }
+        ffi_interface::verify_proof(context, input.to_vec()).map_err(VerkleStatus::from)
+    })
+}
 
-    let verifier_queries_bytes = verifier_queries_slices.chunks_exact(CHUNK_SIZE);
-    assert!(
-        verifier_queries_bytes.remainder().is_empty(),
-        "There should be no left over bytes when chunking the verifier queries"
-    );
+/// Same as `verify_proof`, however the proof and the commitments in the verifier queries
+/// are uncompressed.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verify_proof_uncompressed(
+    ctx: *const Context,
+    input: *const u8,
+    len: usize,
+) -> VerkleStatus {
+    // Chunk is now 65 + 32 = 97 because first commitment is uncompressed as 64 bytes
+    const CHUNK_SIZE: usize = 97; // TODO: get this from ipa-multipoint
 
-    let num_openings = verifier_queries_bytes.len() / CHUNK_SIZE;
+    guard(|| {
+        check_not_null!(ctx, input);
+        let (context, input) = unsafe { (&*ctx, input_slice(input, len)) };
 
-    // - Deserialize verifier queries
-    //
+        if input.len() < UNCOMPRESSED_PROOF_SIZE {
+            return Err(VerkleStatus::InvalidInput);
+        }
+        let (proof_slice, verifier_queries_slices) = input.split_at(UNCOMPRESSED_PROOF_SIZE);
 
-    let mut verifier_queries: Vec<VerifierQuery> = Vec::with_capacity(num_openings);
+        let verifier_queries_bytes = verifier_queries_slices.chunks_exact(CHUNK_SIZE);
+        if !verifier_queries_bytes.remainder().is_empty() {
+            return Err(VerkleStatus::LengthIsNotAnExpectedMultiple);
+        }
 
-    for verifier_query_bytes in verifier_queries_bytes {
-        let verifier_query = deserialize_verifier_query(verifier_query_bytes);
-        verifier_queries.push(verifier_query);
-    }
+        // - Deserialize verifier queries
+        //
 
-    // - Check proof
-    //
+        let verifier_queries: Vec<VerifierQuery> = verifier_queries_bytes
+            .map(deserialize_verifier_query_uncompressed)
+            .collect::<Result<_, _>>()?;
 
-    let proof = MultiPointProof::from_bytes(proof_slice, 256).unwrap();
+        // - Check proof
+        //
 
-    let mut transcript = Transcript::new(b"verkle");
+        let proof = MultiPointProof::from_bytes_unchecked_uncompressed(proof_slice, 256)
+            .map_err(|_| VerkleStatus::InvalidInput)?;
 
-    // TODO: This should not need to clone the CRS, but instead take a reference
+        let mut transcript = Transcript::new(b"verkle");
 
-    MultiPointProof::check(
-        &proof,
-        &context.crs.clone(),
-        &context.precomputed_weights,
-        &verifier_queries,
-        &mut transcript,
-    )
+        if proof.check_with_key(&context.verifier_key, &verifier_queries, &mut transcript) {
+            Ok(())
+        } else {
+            Err(VerkleStatus::ProofVerificationFailed)
+        }
+    })
 }
 
+/// Verifies an execution witness, given as a JSON string, against the hex encoded state `root`.
+///
+/// Both strings must be null terminated. Returns `InvalidInput` if they are not valid UTF-8,
+/// and `ProofVerificationFailed` if the witness cannot be parsed or is invalid.
+#[allow(deprecated)]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn verify_proof_uncompressed(
-    ctx: *mut Context,
-    input: *const u8,
-    len: usize,
-) -> bool {
-    // Chunk is now 65 + 32 = 97 because first commitment is uncompressed as 64 bytes
-    const CHUNK_SIZE: usize = 97; // TODO: get this from ipa-multipoint
-    const PROOF_SIZE: usize = 1120; // TODO: get this from ipa-multipoint
-
-    let (proof_slice, verifier_queries_slices, context) = unsafe {
-        let input_slice = std::slice::from_raw_parts(input, len);
-
-        let (proof_slice, verifier_queries_slices) = input_slice.split_at(PROOF_SIZE);
-
-        let ctx_ref = &*ctx;
+pub extern "C" fn verify_execution_witness(
+    root: *const c_char,
+    execution_witness_json: *const c_char,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(root, execution_witness_json);
+        let (root, execution_witness_json) =
+            unsafe { (CStr::from_ptr(root), CStr::from_ptr(execution_witness_json)) };
+        let root = root.to_str().map_err(|_| VerkleStatus::InvalidInput)?;
+        let execution_witness_json = execution_witness_json
+            .to_str()
+            .map_err(|_| VerkleStatus::InvalidInput)?;
+
+        if ffi_interface::verify_execution_witness(root, execution_witness_json) {
+            Ok(())
+        } else {
+            Err(VerkleStatus::ProofVerificationFailed)
+        }
+    })
+}
 
-        (proof_slice, verifier_queries_slices, ctx_ref)
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use banderwagon::{Element, Fr};
+    use ipa_multipoint::committer::Committer;
+    use std::sync::OnceLock;
+
+    // Creating a context is slow, so the tests share one
+    pub(crate) fn test_context() -> *const Context {
+        struct SharedContext(*mut Context);
+        unsafe impl Send for SharedContext {}
+        unsafe impl Sync for SharedContext {}
+
+        static CONTEXT: OnceLock<SharedContext> = OnceLock::new();
+        CONTEXT
+            .get_or_init(|| {
+                let mut ctx = std::ptr::null_mut();
+                assert_eq!(context_new(&mut ctx), VerkleStatus::Ok);
+                SharedContext(ctx)
+            })
+            .0
+    }
 
-    let verifier_queries_bytes = verifier_queries_slices.chunks_exact(CHUNK_SIZE);
-    assert!(
-        verifier_queries_bytes.remainder().is_empty(),
-        "There should be no left over bytes when chunking the verifier queries"
-    );
+    fn scalar_bytes(scalars: &[Fr]) -> Vec<u8> {
+        scalars
+            .iter()
+            .flat_map(|scalar| ffi_interface::fr_to_le_bytes(*scalar))
+            .collect()
+    }
 
-    let num_openings = verifier_queries_bytes.len() / CHUNK_SIZE;
+    // Creates a compressed proof for a polynomial, along with the verifier query for it
+    fn proof_input() -> (Vec<u8>, Vec<u8>) {
+        let ctx = unsafe { &*test_context() };
+        let poly: Vec<Fr> = (0..256u64).map(|i| Fr::from(i * 7 + 1)).collect();
+        let commitment = ctx.committer.commit_lagrange(&poly).to_bytes();
+        let point = 3u8;
+
+        let mut prover_query = commitment.to_vec();
+        prover_query.extend(scalar_bytes(&poly));
+        prover_query.push(point);
+        prover_query.extend(ffi_interface::fr_to_le_bytes(poly[point as usize]));
+
+        let mut verifier_query = commitment.to_vec();
+        verifier_query.push(point);
+        verifier_query.extend(ffi_interface::fr_to_le_bytes(poly[point as usize]));
+        (prover_query, verifier_query)
+    }
 
-    // - Deserialize verifier queries
-    //
+    #[test]
+    fn null_pointers_are_rejected() {
+        let ctx = test_context();
+        let bytes = [0u8; 32];
+        let mut out = [0u8; PROOF_SIZE];
+        let null = std::ptr::null::<u8>();
+
+        assert_eq!(context_new(std::ptr::null_mut()), VerkleStatus::NullPointer);
+        assert_eq!(
+            pedersen_hash(
+                std::ptr::null(),
+                bytes.as_ptr(),
+                bytes.as_ptr(),
+                out.as_mut_ptr(),
+                32
+            ),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            pedersen_hash(ctx, bytes.as_ptr(), null, out.as_mut_ptr(), 32),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            multi_scalar_mul(ctx, null, 0, out.as_mut_ptr(), 32),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            multi_scalar_mul(ctx, bytes.as_ptr(), 32, std::ptr::null_mut(), 32),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            create_proof(ctx, null, 0, out.as_mut_ptr(), PROOF_SIZE),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            verify_proof(std::ptr::null(), bytes.as_ptr(), 32),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            verify_proof_uncompressed(ctx, null, 0),
+            VerkleStatus::NullPointer
+        );
+        context_free(std::ptr::null_mut());
+    }
 
-    let mut verifier_queries: Vec<VerifierQuery> = Vec::with_capacity(num_openings);
+    #[test]
+    fn outputs_must_fit() {
+        let ctx = test_context();
+        let bytes = [0u8; 32];
+        let mut out = [0u8; 32];
+
+        assert_eq!(
+            pedersen_hash(ctx, bytes.as_ptr(), bytes.as_ptr(), out.as_mut_ptr(), 31),
+            VerkleStatus::OutputTooSmall
+        );
+        assert_eq!(out, [0u8; 32], "nothing should be written on failure");
+        assert_eq!(
+            pedersen_hash(ctx, bytes.as_ptr(), bytes.as_ptr(), out.as_mut_ptr(), 32),
+            VerkleStatus::Ok
+        );
+
+        let (prover_query, _) = proof_input();
+        let mut proof = vec![0u8; PROOF_SIZE - 1];
+        assert_eq!(
+            create_proof(
+                ctx,
+                prover_query.as_ptr(),
+                prover_query.len(),
+                proof.as_mut_ptr(),
+                proof.len()
+            ),
+            VerkleStatus::OutputTooSmall
+        );
+    }
 
-    for verifier_query_bytes in verifier_queries_bytes {
-        let verifier_query = deserialize_verifier_query_uncompressed(verifier_query_bytes);
-        verifier_queries.push(verifier_query);
+    #[test]
+    fn multi_scalar_mul_errors() {
+        let ctx = test_context();
+        let mut out = [0u8; 32];
+
+        let scalars = scalar_bytes(&[Fr::from(1u64), Fr::from(2u64)]);
+        assert_eq!(
+            multi_scalar_mul(ctx, scalars.as_ptr(), 33, out.as_mut_ptr(), 32),
+            VerkleStatus::LengthOfScalarsNotMultipleOf32
+        );
+
+        let too_many = vec![0u8; 257 * 32];
+        assert_eq!(
+            multi_scalar_mul(ctx, too_many.as_ptr(), too_many.len(), out.as_mut_ptr(), 32),
+            VerkleStatus::TooManyScalars
+        );
+
+        // Scalars larger than the modulus are not canonical
+        let non_canonical = [0xffu8; 32];
+        assert_eq!(
+            multi_scalar_mul(ctx, non_canonical.as_ptr(), 32, out.as_mut_ptr(), 32),
+            VerkleStatus::FailedToDeserializeScalar
+        );
+
+        assert_eq!(
+            multi_scalar_mul(ctx, scalars.as_ptr(), 64, out.as_mut_ptr(), 32),
+            VerkleStatus::Ok
+        );
+        let expected = unsafe { &*ctx }
+            .committer
+            .commit_lagrange(&[Fr::from(1u64), Fr::from(2u64)]);
+        assert_eq!(Element::from_bytes(&out), Some(expected));
     }
 
-    // - Check proof
-    //
+    #[test]
+    fn proof_roundtrip_and_errors() {
+        let ctx = test_context();
+        let (prover_query, verifier_query) = proof_input();
+
+        let mut proof = vec![0u8; PROOF_SIZE];
+        assert_eq!(
+            create_proof(
+                ctx,
+                prover_query.as_ptr(),
+                prover_query.len(),
+                proof.as_mut_ptr(),
+                proof.len()
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            create_proof(
+                ctx,
+                prover_query.as_ptr(),
+                prover_query.len() - 1,
+                proof.as_mut_ptr(),
+                proof.len()
+            ),
+            VerkleStatus::LengthIsNotAnExpectedMultiple
+        );
+
+        let mut input = proof.clone();
+        input.extend(&verifier_query);
+        assert_eq!(
+            verify_proof(ctx, input.as_ptr(), input.len()),
+            VerkleStatus::Ok
+        );
+
+        // A wrong evaluation
+        let last = input.len() - 1;
+        input[last - 31] ^= 1;
+        assert_eq!(
+            verify_proof(ctx, input.as_ptr(), input.len()),
+            VerkleStatus::ProofVerificationFailed
+        );
+
+        assert_eq!(
+            verify_proof(ctx, input.as_ptr(), PROOF_SIZE - 1),
+            VerkleStatus::InvalidInput
+        );
+        assert_eq!(
+            verify_proof(ctx, input.as_ptr(), input.len() - 1),
+            VerkleStatus::LengthIsNotAnExpectedMultiple
+        );
+
+        let mut bad_commitment = prover_query.clone();
+        bad_commitment[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            create_proof(
+                ctx,
+                bad_commitment.as_ptr(),
+                bad_commitment.len(),
+                proof.as_mut_ptr(),
+                proof.len()
+            ),
+            VerkleStatus::CouldNotDeserializeCommitment
+        );
+    }
 
-    let proof = MultiPointProof::from_bytes_unchecked_uncompressed(proof_slice, 256).unwrap();
+    #[test]
+    fn uncompressed_proof_roundtrip() {
+        let ctx = test_context();
+        let (prover_query, verifier_query) = proof_input();
+        let commitment = Element::from_bytes(&prover_query[..32]).unwrap();
+
+        let mut prover_query_uncompressed = commitment.to_bytes_uncompressed().to_vec();
+        prover_query_uncompressed.extend(&prover_query[32..]);
+        let mut verifier_query_uncompressed = commitment.to_bytes_uncompressed().to_vec();
+        verifier_query_uncompressed.extend(&verifier_query[32..]);
+
+        let mut proof = vec![0u8; UNCOMPRESSED_PROOF_SIZE];
+        assert_eq!(
+            create_proof_uncompressed(
+                ctx,
+                prover_query_uncompressed.as_ptr(),
+                prover_query_uncompressed.len(),
+                proof.as_mut_ptr(),
+                proof.len()
+            ),
+            VerkleStatus::Ok
+        );
+
+        let mut input = proof;
+        input.extend(&verifier_query_uncompressed);
+        assert_eq!(
+            verify_proof_uncompressed(ctx, input.as_ptr(), input.len()),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verify_proof_uncompressed(ctx, input.as_ptr(), input.len() - 1),
+            VerkleStatus::LengthIsNotAnExpectedMultiple
+        );
+    }
 
-    let mut transcript = Transcript::new(b"verkle");
+    #[test]
+    fn get_tree_key_sets_sub_index() {
+        let ctx = test_context();
+        let (address, tree_index) = ([1u8; 32], [2u8; 32]);
+
+        let mut hash = [0u8; 32];
+        assert_eq!(
+            pedersen_hash(
+                ctx,
+                address.as_ptr(),
+                tree_index.as_ptr(),
+                hash.as_mut_ptr(),
+                32
+            ),
+            VerkleStatus::Ok
+        );
+        let mut key = [0u8; 32];
+        assert_eq!(
+            get_tree_key(
+                ctx,
+                address.as_ptr(),
+                tree_index.as_ptr(),
+                7,
+                key.as_mut_ptr(),
+                32
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(key[..31], hash[..31]);
+        assert_eq!(key[31], 7);
+
+        assert_eq!(
+            get_tree_key(
+                ctx,
+                address.as_ptr(),
+                tree_index.as_ptr(),
+                7,
+                key.as_mut_ptr(),
+                31
+            ),
+            VerkleStatus::OutputTooSmall
+        );
+    }
 
-    // TODO: This should not need to clone the CRS, but instead take a reference
+    #[test]
+    fn precomputed_tables_roundtrip() {
+        let ctx = test_context();
+
+        let mut len = 0;
+        let mut tables = vec![0u8; 1];
+        assert_eq!(
+            context_write_precomputed_tables(ctx, tables.as_mut_ptr(), tables.len(), &mut len),
+            VerkleStatus::OutputTooSmall
+        );
+        tables.resize(len, 0);
+        assert_eq!(
+            context_write_precomputed_tables(ctx, tables.as_mut_ptr(), tables.len(), &mut len),
+            VerkleStatus::Ok
+        );
+
+        let mut loaded = std::ptr::null_mut();
+        assert_eq!(
+            context_new_from_precomputed_tables(tables.as_ptr(), tables.len(), &mut loaded),
+            VerkleStatus::Ok
+        );
+        let scalars = scalar_bytes(&[Fr::from(3u64), Fr::from(4u64)]);
+        let (mut expected, mut got) = ([0u8; 32], [0u8; 32]);
+        assert_eq!(
+            multi_scalar_mul(ctx, scalars.as_ptr(), 64, expected.as_mut_ptr(), 32),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            multi_scalar_mul(loaded, scalars.as_ptr(), 64, got.as_mut_ptr(), 32),
+            VerkleStatus::Ok
+        );
+        assert_eq!(got, expected);
+        context_free(loaded);
+
+        let mut invalid = std::ptr::null_mut();
+        assert_eq!(
+            context_new_from_precomputed_tables(tables.as_ptr(), 10, &mut invalid),
+            VerkleStatus::InvalidPrecomputedTables
+        );
+        assert!(invalid.is_null());
+    }
 
-    MultiPointProof::check(
-        &proof,
-        &context.crs.clone(),
-        &context.precomputed_weights,
-        &verifier_queries,
-        &mut transcript,
-    )
+    #[test]
+    fn execution_witness_verification() {
+        use std::ffi::CString;
+        use verkle_trie::proof::golang_proof_format::{
+            EXECUTION_WITNESS_JSON, PREVIOUS_STATE_ROOT,
+        };
+
+        let root = CString::new(PREVIOUS_STATE_ROOT).unwrap();
+        let witness = CString::new(EXECUTION_WITNESS_JSON).unwrap();
+        assert_eq!(
+            verify_execution_witness(root.as_ptr(), witness.as_ptr()),
+            VerkleStatus::Ok
+        );
+
+        let other_root = CString::new(format!("0x{}", "00".repeat(32))).unwrap();
+        assert_eq!(
+            verify_execution_witness(other_root.as_ptr(), witness.as_ptr()),
+            VerkleStatus::ProofVerificationFailed
+        );
+        assert_eq!(
+            verify_execution_witness(root.as_ptr(), [0xffu8, 0].as_ptr() as *const c_char),
+            VerkleStatus::InvalidInput
+        );
+    }
 }
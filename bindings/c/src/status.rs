@@ -0,0 +1,106 @@
+//! The status codes returned by the C functions, and helpers for the FFI boundary.
+
+/// The result of calling a C function.
+///
+/// The variants from `LengthOfScalarsNotMultipleOf32` to `InvalidPrecomputedTables` mirror
+/// `ffi_interface::Error`. The other variants are specific to the C boundary.
+///
+/// If a function returns `Panic`, then any handle which was passed to it should be freed,
+/// and not used again.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerkleStatus {
+    Ok = 0,
+    NullPointer = 1,
+    // The output buffer is smaller than the output
+    OutputTooSmall = 2,
+    LengthOfScalarsNotMultipleOf32 = 3,
+    TooManyScalars = 4,
+    FailedToDeserializeScalar = 5,
+    LengthIsNotAnExpectedMultiple = 6,
+    CouldNotDeserializeCommitment = 7,
+    ProofVerificationFailed = 8,
+    InvalidPrecomputedTables = 9,
+    // The input could not be parsed, for example a malformed proof or a non UTF-8 path
+    InvalidInput = 10,
+    // The function panicked, this is a bug
+    Panic = 11,
+}
+
+impl From<ffi_interface::Error> for VerkleStatus {
+    fn from(error: ffi_interface::Error) -> Self {
+        use ffi_interface::Error;
+        match error {
+            Error::LengthOfScalarsNotMultipleOf32 { .. } => {
+                VerkleStatus::LengthOfScalarsNotMultipleOf32
+            }
+            Error::TooManyScalars { .. } => VerkleStatus::TooManyScalars,
+            Error::FailedToDeserializeScalar { .. } => VerkleStatus::FailedToDeserializeScalar,
+            Error::LengthIsNotAnExpectedMultiple { .. } => {
+                VerkleStatus::LengthIsNotAnExpectedMultiple
+            }
+            Error::CouldNotDeserializeCommitment { .. } => {
+                VerkleStatus::CouldNotDeserializeCommitment
+            }
+            Error::ProofVerificationFailed => VerkleStatus::ProofVerificationFailed,
+            Error::InvalidPrecomputedTables { .. } => VerkleStatus::InvalidPrecomputedTables,
+        }
+    }
+}
+
+// Runs `f`, and converts its error or a panic into a status, so that panics never unwind into C
+pub(crate) fn guard(f: impl FnOnce() -> Result<(), VerkleStatus>) -> VerkleStatus {
+    // The handles that `f` uses should not be reused after a panic, see `VerkleStatus::Panic`
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => VerkleStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => VerkleStatus::Panic,
+    }
+}
+
+// Returns `NullPointer` from the enclosing function if any of the pointers are null
+macro_rules! check_not_null {
+    ($($ptr:expr),+ $(,)?) => {
+        if $($ptr.is_null())||+ {
+            return Err($crate::status::VerkleStatus::NullPointer);
+        }
+    };
+}
+pub(crate) use check_not_null;
+
+// The caller must ensure that `ptr` points to `len` readable bytes
+pub(crate) unsafe fn input_slice<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+    std::slice::from_raw_parts(ptr, len)
+}
+
+// The caller must ensure that `ptr` points to `N` readable bytes
+pub(crate) unsafe fn input_array<const N: usize>(ptr: *const u8) -> [u8; N] {
+    input_slice(ptr, N).try_into().unwrap()
+}
+
+// The caller must ensure that `ptr` points to `len * N` readable bytes
+pub(crate) unsafe fn input_arrays<const N: usize>(
+    ptr: *const u8,
+    len: usize,
+) -> Result<Vec<[u8; N]>, VerkleStatus> {
+    let num_bytes = len.checked_mul(N).ok_or(VerkleStatus::InvalidInput)?;
+    Ok(input_slice(ptr, num_bytes)
+        .chunks_exact(N)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect())
+}
+
+// Copies `bytes` into `out`, if it has room for them.
+//
+// The caller must ensure that `out` points to `out_capacity` writable bytes.
+pub(crate) unsafe fn write_output(
+    out: *mut u8,
+    out_capacity: usize,
+    bytes: &[u8],
+) -> Result<(), VerkleStatus> {
+    if out_capacity < bytes.len() {
+        return Err(VerkleStatus::OutputTooSmall);
+    }
+    std::slice::from_raw_parts_mut(out, bytes.len()).copy_from_slice(bytes);
+    Ok(())
+}
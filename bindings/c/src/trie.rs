@@ -0,0 +1,691 @@
+//! C functions for creating and using a verkle trie.
+//!
+//! Every function returns a `VerkleStatus`, see `lib.rs` for the conventions on outputs.
+//!
+//! Ownership:
+//! - `verkle_trie_new_in_memory` and `verkle_trie_new_on_disk` write a handle which is owned
+//!   by the caller and must be freed exactly once with `verkle_trie_free`.
+//! - The trie shares the committer of the `Context`, which stays alive until both have been
+//!   freed, so the `Context` may be freed before the trie.
+//! - All other pointers are borrowed for the duration of the call.
+//!
+//! Keys and values are passed as contiguous arrays of 32 byte items. Commitments are
+//! passed as 32 byte compressed group elements.
+use crate::status::{
+    check_not_null, guard, input_array, input_arrays, input_slice, write_output, VerkleStatus,
+};
+use ffi_interface::Context;
+use ipa_multipoint::committer::DefaultCommitter;
+use std::ffi::{c_char, CStr};
+use std::sync::Arc;
+use verkle_db::{BareMetalDiskDb, SledDb};
+use verkle_trie::database::{memory_db::MemoryDb, VerkleDb};
+use verkle_trie::proof::{stateless_updater, VerkleProof};
+use verkle_trie::{Config, Element, Trie, TrieTrait};
+
+/// An opaque handle to a verkle trie, whose nodes are either stored in memory or on disk.
+#[allow(clippy::large_enum_variant)]
+pub enum VerkleTrie {
+    Memory(Trie<MemoryDb, Arc<DefaultCommitter>>),
+    Disk(Trie<VerkleDb<SledDb>, Arc<DefaultCommitter>>),
+}
+
+// Calls `$body` with the trie, regardless of where its nodes are stored
+macro_rules! with_trie {
+    ($verkle_trie:expr, $trie:ident => $body:expr) => {
+        match $verkle_trie {
+            VerkleTrie::Memory($trie) => $body,
+            VerkleTrie::Disk($trie) => $body,
+        }
+    };
+}
+
+/// Creates a trie whose nodes are stored in memory, and writes its handle to `out_trie`.
+///
+/// The handle must be freed with `verkle_trie_free`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_new_in_memory(
+    ctx: *const Context,
+    out_trie: *mut *mut VerkleTrie,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, out_trie);
+        let context = unsafe { &*ctx };
+
+        let config = Config {
+            db: MemoryDb::new(),
+            committer: context.committer.clone(),
+        };
+        let trie = Box::new(VerkleTrie::Memory(Trie::new(config)));
+        unsafe { *out_trie = Box::into_raw(trie) };
+        Ok(())
+    })
+}
+
+/// Opens the trie stored in the directory at `path`, creating it if it does not exist,
+/// and writes its handle to `out_trie`.
+///
+/// Inserted values are only persisted once `verkle_trie_flush` is called.
+///
+/// Returns `InvalidInput` if `path` is not valid UTF-8 or the database could not be opened.
+/// The handle must be freed with `verkle_trie_free`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_new_on_disk(
+    ctx: *const Context,
+    path: *const c_char,
+    out_trie: *mut *mut VerkleTrie,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(ctx, path, out_trie);
+        let (context, path) = unsafe { (&*ctx, CStr::from_ptr(path)) };
+        let path = path.to_str().map_err(|_| VerkleStatus::InvalidInput)?;
+
+        // `from_path` panics if sled cannot open the database, for example if another
+        // process holds its lock
+        let db = std::panic::catch_unwind(|| VerkleDb::<SledDb>::from_path(path))
+            .map_err(|_| VerkleStatus::InvalidInput)?;
+        let config = Config {
+            db,
+            committer: context.committer.clone(),
+        };
+        let trie = Box::new(VerkleTrie::Disk(Trie::new(config)));
+        unsafe { *out_trie = Box::into_raw(trie) };
+        Ok(())
+    })
+}
+
+/// Frees a trie created with `verkle_trie_new_in_memory` or `verkle_trie_new_on_disk`.
+/// Passing null is a no-op.
+///
+/// Values which have not been flushed to disk are discarded.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_free(trie: *mut VerkleTrie) {
+    if trie.is_null() {
+        return;
+    }
+    unsafe {
+        let _ = Box::from_raw(trie);
+    }
+}
+
+/// Inserts `len` key-value pairs into the trie.
+///
+/// `keys` and `values` must each point to `len * 32` bytes.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_insert(
+    trie: *mut VerkleTrie,
+    keys: *const u8,
+    values: *const u8,
+    len: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(trie, keys, values);
+        let (trie, keys, values) = unsafe {
+            (
+                &mut *trie,
+                input_arrays(keys, len)?,
+                input_arrays(values, len)?,
+            )
+        };
+
+        with_trie!(trie, trie => trie.insert(keys.into_iter().zip(values)));
+        Ok(())
+    })
+}
+
+/// Writes the 32 byte value for `key` into `out`, and whether the key is in the trie
+/// into `out_found`.
+///
+/// `out` is left untouched if the key is not in the trie.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_get(
+    trie: *const VerkleTrie,
+    key: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_found: *mut bool,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(trie, key, out, out_found);
+        let (trie, key) = unsafe { (&*trie, input_array(key)) };
+
+        let value = with_trie!(trie, trie => trie.get(key));
+        if let Some(value) = value {
+            unsafe { write_output(out, out_capacity, &value)? };
+        }
+        unsafe { *out_found = value.is_some() };
+        Ok(())
+    })
+}
+
+/// Writes the 32 byte compressed root commitment into `out`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_root_commitment(
+    trie: *const VerkleTrie,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(trie, out);
+        let trie = unsafe { &*trie };
+
+        let root = with_trie!(trie, trie => trie.root_commitment());
+        unsafe { write_output(out, out_capacity, &root.to_bytes()) }
+    })
+}
+
+/// Persists the values inserted since the last flush.
+///
+/// This is a no-op for tries which are stored in memory.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_flush(trie: *mut VerkleTrie) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(trie);
+        let trie = unsafe { &mut *trie };
+
+        with_trie!(trie, trie => trie.flush_database());
+        Ok(())
+    })
+}
+
+/// Creates a serialized `VerkleProof` for `len` keys, which may or may not be in the trie,
+/// and writes it into `out`.
+///
+/// The length of the proof is always written to `out_len`, so if `OutputTooSmall` is
+/// returned, the caller can retry with a buffer of that size.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_trie_create_proof(
+    trie: *const VerkleTrie,
+    keys: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(trie, keys, out, out_len);
+        let (trie, keys) = unsafe { (&*trie, input_arrays(keys, len)?) };
+
+        let proof = with_trie!(trie, trie => trie.create_verkle_proof(keys.into_iter()))
+            .map_err(|_| VerkleStatus::InvalidInput)?;
+        let mut bytes = Vec::new();
+        proof
+            .write(&mut bytes)
+            .map_err(|_| VerkleStatus::InvalidInput)?;
+
+        unsafe {
+            *out_len = bytes.len();
+            write_output(out, out_capacity, &bytes)
+        }
+    })
+}
+
+/// Verifies a serialized `VerkleProof` for `len` keys against the compressed `root` commitment.
+///
+/// `keys` and `values` must each point to `len * 32` bytes, and `values_present` to `len` bools.
+/// A key whose entry in `values_present` is false is proven to be absent, and its value is ignored.
+///
+/// Returns `InvalidInput` if the proof cannot be deserialized, and `ProofVerificationFailed`
+/// if it is invalid.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn verkle_proof_verify(
+    proof: *const u8,
+    proof_len: usize,
+    root: *const u8,
+    keys: *const u8,
+    values: *const u8,
+    values_present: *const bool,
+    len: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(proof, root, keys, values, values_present);
+        let (proof, root) = unsafe { read_proof_and_root(proof, proof_len, root)? };
+        let (keys, values) = unsafe {
+            (
+                input_arrays(keys, len)?,
+                read_optional_values(values, values_present, len)?,
+            )
+        };
+
+        if !proof.check(keys, values, root).0 {
+            return Err(VerkleStatus::ProofVerificationFailed);
+        }
+        Ok(())
+    })
+}
+
+/// Verifies a serialized `VerkleProof` like `verkle_proof_verify`, and then writes the
+/// 32 byte compressed root commitment, after the proven keys are set to `new_values`, into `out`.
+///
+/// `new_values` and `new_values_present` have the same layout as `values` and `values_present`.
+/// A key whose entry in `new_values_present` is false is left unchanged.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[allow(clippy::too_many_arguments)]
+#[no_mangle]
+pub extern "C" fn verkle_proof_verify_and_update(
+    ctx: *const Context,
+    proof: *const u8,
+    proof_len: usize,
+    root: *const u8,
+    keys: *const u8,
+    values: *const u8,
+    values_present: *const bool,
+    new_values: *const u8,
+    new_values_present: *const bool,
+    len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+) -> VerkleStatus {
+    guard(|| {
+        check_not_null!(
+            ctx,
+            proof,
+            root,
+            keys,
+            values,
+            values_present,
+            new_values,
+            new_values_present,
+            out
+        );
+        let context = unsafe { &*ctx };
+        let (proof, root) = unsafe { read_proof_and_root(proof, proof_len, root)? };
+        let (keys, values, new_values) = unsafe {
+            (
+                input_arrays(keys, len)?,
+                read_optional_values(values, values_present, len)?,
+                read_optional_values(new_values, new_values_present, len)?,
+            )
+        };
+
+        let new_root = stateless_updater::verify_and_update(
+            proof,
+            root,
+            keys,
+            values,
+            new_values,
+            &context.committer,
+        )
+        .map_err(|_| VerkleStatus::ProofVerificationFailed)?;
+        unsafe { write_output(out, out_capacity, &new_root.to_bytes()) }
+    })
+}
+
+unsafe fn read_optional_values(
+    values: *const u8,
+    present: *const bool,
+    len: usize,
+) -> Result<Vec<Option<[u8; 32]>>, VerkleStatus> {
+    let present = std::slice::from_raw_parts(present, len);
+    Ok(input_arrays(values, len)?
+        .into_iter()
+        .zip(present)
+        .map(|(value, present)| present.then_some(value))
+        .collect())
+}
+
+unsafe fn read_proof_and_root(
+    proof: *const u8,
+    proof_len: usize,
+    root: *const u8,
+) -> Result<(VerkleProof, Element), VerkleStatus> {
+    let proof =
+        VerkleProof::read(input_slice(proof, proof_len)).map_err(|_| VerkleStatus::InvalidInput)?;
+    let root = Element::from_bytes(&input_array::<32>(root))
+        .ok_or(VerkleStatus::CouldNotDeserializeCommitment)?;
+    Ok((proof, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_context;
+
+    fn new_in_memory() -> *mut VerkleTrie {
+        let mut trie = std::ptr::null_mut();
+        assert_eq!(
+            verkle_trie_new_in_memory(test_context(), &mut trie),
+            VerkleStatus::Ok
+        );
+        trie
+    }
+
+    // sled releases its file lock from a background thread after the database is dropped,
+    // so reopening a database right after freeing its trie can briefly fail
+    fn reopen_on_disk(path: &std::ffi::CStr) -> *mut VerkleTrie {
+        let mut trie = std::ptr::null_mut();
+        for _ in 0..50 {
+            if verkle_trie_new_on_disk(test_context(), path.as_ptr(), &mut trie) == VerkleStatus::Ok
+            {
+                return trie;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        panic!("could not reopen the database");
+    }
+
+    fn root(trie: *const VerkleTrie) -> [u8; 32] {
+        let mut root = [0u8; 32];
+        assert_eq!(
+            verkle_trie_root_commitment(trie, root.as_mut_ptr(), 32),
+            VerkleStatus::Ok
+        );
+        root
+    }
+
+    #[test]
+    fn trie_roundtrip() {
+        let trie = new_in_memory();
+
+        let keys: Vec<u8> = (0..3u8).flat_map(|i| [i; 32]).collect();
+        let values: Vec<u8> = (0..3u8).flat_map(|i| [i + 10; 32]).collect();
+        assert_eq!(
+            verkle_trie_insert(trie, keys.as_ptr(), values.as_ptr(), 3),
+            VerkleStatus::Ok
+        );
+        assert_eq!(verkle_trie_flush(trie), VerkleStatus::Ok);
+
+        let mut value = [0u8; 32];
+        let mut found = false;
+        assert_eq!(
+            verkle_trie_get(trie, [1u8; 32].as_ptr(), value.as_mut_ptr(), 32, &mut found),
+            VerkleStatus::Ok
+        );
+        assert!(found);
+        assert_eq!(value, [11u8; 32]);
+        assert_eq!(
+            verkle_trie_get(trie, [9u8; 32].as_ptr(), value.as_mut_ptr(), 32, &mut found),
+            VerkleStatus::Ok
+        );
+        assert!(!found);
+
+        let root_before = root(trie);
+
+        // Prove two of the keys and one absent key
+        let proven_keys: Vec<u8> = [[0u8; 32], [2u8; 32], [9u8; 32]].concat();
+        let proven_values: Vec<u8> = [[10u8; 32], [12u8; 32], [0u8; 32]].concat();
+        let present = [true, true, false];
+
+        // The required length is reported when the buffer is too small
+        let mut proof_len = 0;
+        let mut proof = vec![0u8; 1];
+        assert_eq!(
+            verkle_trie_create_proof(
+                trie,
+                proven_keys.as_ptr(),
+                3,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_len
+            ),
+            VerkleStatus::OutputTooSmall
+        );
+        proof.resize(proof_len, 0);
+        assert_eq!(
+            verkle_trie_create_proof(
+                trie,
+                proven_keys.as_ptr(),
+                3,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_len
+            ),
+            VerkleStatus::Ok
+        );
+
+        assert_eq!(
+            verkle_proof_verify(
+                proof.as_ptr(),
+                proof_len,
+                root_before.as_ptr(),
+                proven_keys.as_ptr(),
+                proven_values.as_ptr(),
+                present.as_ptr(),
+                3
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verkle_proof_verify(
+                proof.as_ptr(),
+                proof_len,
+                root_before.as_ptr(),
+                proven_keys.as_ptr(),
+                proven_values.as_ptr(),
+                [true, true, true].as_ptr(),
+                3
+            ),
+            VerkleStatus::ProofVerificationFailed
+        );
+
+        // The updated root should match the root after inserting the new values.
+        // Only the first key is updated.
+        let new_values: Vec<u8> = [[20u8; 32], [0u8; 32], [0u8; 32]].concat();
+        let mut new_root = [0u8; 32];
+        assert_eq!(
+            verkle_proof_verify_and_update(
+                test_context(),
+                proof.as_ptr(),
+                proof_len,
+                root_before.as_ptr(),
+                proven_keys.as_ptr(),
+                proven_values.as_ptr(),
+                present.as_ptr(),
+                new_values.as_ptr(),
+                [true, false, false].as_ptr(),
+                3,
+                new_root.as_mut_ptr(),
+                32
+            ),
+            VerkleStatus::Ok
+        );
+
+        assert_eq!(
+            verkle_trie_insert(trie, proven_keys.as_ptr(), new_values.as_ptr(), 1),
+            VerkleStatus::Ok
+        );
+        assert_eq!(new_root, root(trie));
+
+        verkle_trie_free(trie);
+    }
+
+    #[test]
+    fn trie_bad_inputs() {
+        let trie = new_in_memory();
+        let bytes = [0u8; 32];
+        let null = std::ptr::null::<u8>();
+
+        assert_eq!(
+            verkle_trie_new_in_memory(std::ptr::null(), &mut std::ptr::null_mut()),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            verkle_trie_insert(trie, bytes.as_ptr(), null, 1),
+            VerkleStatus::NullPointer
+        );
+        assert_eq!(
+            verkle_trie_root_commitment(trie, [0u8; 32].as_mut_ptr(), 31),
+            VerkleStatus::OutputTooSmall
+        );
+        assert_eq!(
+            verkle_trie_flush(std::ptr::null_mut()),
+            VerkleStatus::NullPointer
+        );
+
+        // Malformed proofs and roots
+        let root = root(trie);
+        assert_eq!(
+            verkle_proof_verify(
+                bytes.as_ptr(),
+                bytes.len(),
+                root.as_ptr(),
+                bytes.as_ptr(),
+                bytes.as_ptr(),
+                [true].as_ptr(),
+                1
+            ),
+            VerkleStatus::InvalidInput
+        );
+        let mut proof_len = 0;
+        let mut proof = vec![0u8; 4096];
+        assert_eq!(
+            verkle_trie_create_proof(
+                trie,
+                bytes.as_ptr(),
+                1,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_len
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verkle_proof_verify(
+                proof.as_ptr(),
+                proof_len,
+                [0xffu8; 32].as_ptr(),
+                bytes.as_ptr(),
+                bytes.as_ptr(),
+                [false].as_ptr(),
+                1
+            ),
+            VerkleStatus::CouldNotDeserializeCommitment
+        );
+
+        // Paths must be valid UTF-8
+        let path = [0xffu8, 0];
+        let mut disk_trie = std::ptr::null_mut();
+        assert_eq!(
+            verkle_trie_new_on_disk(
+                test_context(),
+                path.as_ptr() as *const c_char,
+                &mut disk_trie
+            ),
+            VerkleStatus::InvalidInput
+        );
+        assert!(disk_trie.is_null());
+
+        verkle_trie_free(trie);
+    }
+
+    #[test]
+    fn on_disk_trie_persists_after_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = std::ffi::CString::new(dir.path().to_str().unwrap()).unwrap();
+
+        let mut trie = std::ptr::null_mut();
+        assert_eq!(
+            verkle_trie_new_on_disk(test_context(), path.as_ptr(), &mut trie),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verkle_trie_insert(trie, [1u8; 32].as_ptr(), [2u8; 32].as_ptr(), 1),
+            VerkleStatus::Ok
+        );
+        assert_eq!(verkle_trie_flush(trie), VerkleStatus::Ok);
+        let root_before = root(trie);
+        verkle_trie_free(trie);
+
+        let trie = reopen_on_disk(&path);
+        let mut value = [0u8; 32];
+        let mut found = false;
+        assert_eq!(
+            verkle_trie_get(trie, [1u8; 32].as_ptr(), value.as_mut_ptr(), 32, &mut found),
+            VerkleStatus::Ok
+        );
+        assert!(found);
+        assert_eq!(value, [2u8; 32]);
+        assert_eq!(root(trie), root_before);
+
+        verkle_trie_free(trie);
+    }
+
+    // Reopening a trie with many keys reads stems and branches back from disk, including
+    // branches below the root
+    #[test]
+    fn on_disk_trie_with_many_keys_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = std::ffi::CString::new(dir.path().to_str().unwrap()).unwrap();
+
+        // Keys share their first bytes in groups, so that the trie has several levels
+        let keys: Vec<[u8; 32]> = (0..300u16)
+            .map(|i| {
+                let mut key = [0u8; 32];
+                key[0] = (i % 3) as u8;
+                key[1] = (i % 7) as u8;
+                key[2..4].copy_from_slice(&i.to_le_bytes());
+                key[31] = i as u8;
+                key
+            })
+            .collect();
+        let values: Vec<[u8; 32]> = (0..300u16).map(|i| [(i % 251) as u8 + 1; 32]).collect();
+        let extra_key = {
+            let mut key = keys[10];
+            key[31] = key[31].wrapping_add(1);
+            key[4] = 0xff;
+            key
+        };
+
+        let mut trie = std::ptr::null_mut();
+        assert_eq!(
+            verkle_trie_new_on_disk(test_context(), path.as_ptr(), &mut trie),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verkle_trie_insert(
+                trie,
+                keys.concat().as_ptr(),
+                values.concat().as_ptr(),
+                keys.len()
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(verkle_trie_flush(trie), VerkleStatus::Ok);
+        verkle_trie_free(trie);
+
+        let trie = reopen_on_disk(&path);
+        for (key, expected) in keys.iter().zip(&values) {
+            let mut value = [0u8; 32];
+            let mut found = false;
+            assert_eq!(
+                verkle_trie_get(trie, key.as_ptr(), value.as_mut_ptr(), 32, &mut found),
+                VerkleStatus::Ok
+            );
+            assert!(found);
+            assert_eq!(&value, expected);
+        }
+
+        // Updating the reopened trie must give the same root as building it in memory
+        assert_eq!(
+            verkle_trie_insert(trie, extra_key.as_ptr(), [7u8; 32].as_ptr(), 1),
+            VerkleStatus::Ok
+        );
+        let memory = new_in_memory();
+        assert_eq!(
+            verkle_trie_insert(
+                memory,
+                keys.concat().as_ptr(),
+                values.concat().as_ptr(),
+                keys.len()
+            ),
+            VerkleStatus::Ok
+        );
+        assert_eq!(
+            verkle_trie_insert(memory, extra_key.as_ptr(), [7u8; 32].as_ptr(), 1),
+            VerkleStatus::Ok
+        );
+        assert_eq!(root(trie), root(memory));
+
+        verkle_trie_free(trie);
+        verkle_trie_free(memory);
+    }
+}
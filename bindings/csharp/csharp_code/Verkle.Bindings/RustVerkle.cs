@@ -7,7 +7,14 @@ namespace Verkle.Bindings;
 
 public unsafe class RustVerkle: IDisposable
 {
-    private Context* _context = context_new();
+    private Context* _context;
+
+    public RustVerkle()
+    {
+        Context* context;
+        ThrowIfFailed(context_new(&context));
+        _context = context;
+    }
 
     public void Dispose()
     {
@@ -24,7 +31,105 @@ public unsafe class RustVerkle: IDisposable
         fixed (byte* indexPtr = treeIndexLe)
         fixed (byte* hashPtr = outHash)
         {
-            pedersen_hash(_context, addrPtr, indexPtr, hashPtr);
+            ThrowIfFailed(pedersen_hash(_context, addrPtr, indexPtr, hashPtr, (nuint)outHash.Length));
+        }
+    }
+
+    public void GetTreeKey(byte[] address, byte[] treeIndexLe, byte subIndex, byte[] outKey)
+    {
+        fixed (byte* addrPtr = address)
+        fixed (byte* indexPtr = treeIndexLe)
+        fixed (byte* keyPtr = outKey)
+        {
+            ThrowIfFailed(get_tree_key(_context, addrPtr, indexPtr, subIndex, keyPtr, (nuint)outKey.Length));
+        }
+    }
+
+    // Commitments are 64 bytes and uncompressed, scalars are 32 bytes and little-endian
+
+    public void CommitToScalars(byte[] scalars, byte[] outCommitment)
+    {
+        fixed (byte* scalarsPtr = scalars)
+        fixed (byte* commitmentPtr = outCommitment)
+        {
+            ThrowIfFailed(commit_to_scalars(_context, scalarsPtr, (nuint)scalars.Length, commitmentPtr, (nuint)outCommitment.Length));
+        }
+    }
+
+    public static void AddCommitment(byte[] lhs, byte[] rhs, byte[] outCommitment)
+    {
+        fixed (byte* lhsPtr = lhs)
+        fixed (byte* rhsPtr = rhs)
+        fixed (byte* commitmentPtr = outCommitment)
+        {
+            ThrowIfFailed(add_commitment(lhsPtr, rhsPtr, commitmentPtr, (nuint)outCommitment.Length));
+        }
+    }
+
+    public void UpdateCommitment(byte[] oldCommitment, byte index, byte[] oldScalar, byte[] newScalar, byte[] outCommitment)
+    {
+        fixed (byte* oldCommitmentPtr = oldCommitment)
+        fixed (byte* oldScalarPtr = oldScalar)
+        fixed (byte* newScalarPtr = newScalar)
+        fixed (byte* commitmentPtr = outCommitment)
+        {
+            ThrowIfFailed(update_commitment(_context, oldCommitmentPtr, index, oldScalarPtr, newScalarPtr, commitmentPtr, (nuint)outCommitment.Length));
+        }
+    }
+
+    public void UpdateCommitmentSparse(byte[] oldCommitment, byte[] indices, byte[] oldScalars, byte[] newScalars, byte[] outCommitment)
+    {
+        fixed (byte* oldCommitmentPtr = oldCommitment)
+        fixed (byte* indicesPtr = indices)
+        fixed (byte* oldScalarsPtr = oldScalars)
+        fixed (byte* newScalarsPtr = newScalars)
+        fixed (byte* commitmentPtr = outCommitment)
+        {
+            ThrowIfFailed(update_commitment_sparse(_context, oldCommitmentPtr, indicesPtr, oldScalarsPtr, newScalarsPtr, (nuint)indices.Length, commitmentPtr, (nuint)outCommitment.Length));
+        }
+    }
+
+    public static void HashCommitment(byte[] commitment, byte[] outScalar)
+    {
+        fixed (byte* commitmentPtr = commitment)
+        fixed (byte* scalarPtr = outScalar)
+        {
+            ThrowIfFailed(hash_commitment(commitmentPtr, scalarPtr, (nuint)outScalar.Length));
+        }
+    }
+
+    public static void HashCommitments(byte[] commitments, byte[] outScalars)
+    {
+        fixed (byte* commitmentsPtr = commitments)
+        fixed (byte* scalarsPtr = outScalars)
+        {
+            ThrowIfFailed(hash_commitments(commitmentsPtr, (nuint)(commitments.Length / 64), scalarsPtr, (nuint)outScalars.Length));
+        }
+    }
+
+    public static void SerializeCommitment(byte[] commitment, byte[] outSerialized)
+    {
+        fixed (byte* commitmentPtr = commitment)
+        fixed (byte* serializedPtr = outSerialized)
+        {
+            ThrowIfFailed(serialize_commitment(commitmentPtr, serializedPtr, (nuint)outSerialized.Length));
+        }
+    }
+
+    public static void DeserializeCommitment(byte[] serialized, byte[] outCommitment)
+    {
+        fixed (byte* serializedPtr = serialized)
+        fixed (byte* commitmentPtr = outCommitment)
+        {
+            ThrowIfFailed(deserialize_commitment(serializedPtr, commitmentPtr, (nuint)outCommitment.Length));
+        }
+    }
+
+    private static void ThrowIfFailed(VerkleStatus status)
+    {
+        if (status != VerkleStatus.Ok)
+        {
+            throw new InvalidOperationException($"verkle call failed with status {status}");
         }
     }
 }
\ No newline at end of file
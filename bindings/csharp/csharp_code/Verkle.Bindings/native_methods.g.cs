@@ -16,35 +16,292 @@ namespace Verkle.Bindings
 
 
 
+
+
+        /// <summary>
+        ///  Creates a context and writes it to `out_ctx`.
+        ///
+        ///  The context must be freed with `context_free`.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "context_new", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern Context* context_new();
+        internal static extern VerkleStatus context_new(Context** out_ctx);
+
+        /// <summary>
+        ///  Creates a context whose committer tables are read from the `len` bytes at `tables`,
+        ///  and writes it to `out_ctx`.
+        ///
+        ///  This is much faster than `context_new`. The tables are created with
+        ///  `context_write_precomputed_tables`, and `InvalidPrecomputedTables` is returned if they
+        ///  cannot be read. The context must be freed with `context_free`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "context_new_from_precomputed_tables", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus context_new_from_precomputed_tables(byte* tables, System.UIntPtr len, Context** out_ctx);
+
+        /// <summary>
+        ///  Serializes the committer tables of the context into `out`.
+        ///
+        ///  The length of the tables is always written to `out_len`, so if `OutputTooSmall` is
+        ///  returned, the caller can retry with a buffer of that size.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "context_write_precomputed_tables", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus context_write_precomputed_tables(Context* ctx, byte* @out, System.UIntPtr out_capacity, System.UIntPtr* out_len);
 
+        /// <summary>
+        ///  Frees a context created with `context_new`. Passing null is a no-op.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "context_free", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern void context_free(Context* ctx);
 
+        /// <summary>
+        ///  Writes the 32 byte tree key hash for `address` and `tree_index_le` into `out`.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "pedersen_hash", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void pedersen_hash(Context* ctx, byte* address, byte* tree_index_le, byte* @out);
+        internal static extern VerkleStatus pedersen_hash(Context* ctx, byte* address, byte* tree_index_le, byte* @out, System.UIntPtr out_capacity);
 
+        /// <summary>
+        ///  Writes the 32 byte tree key for `address`, `tree_index_le` and `sub_index` into `out`.
+        ///
+        ///  This is the tree key hash from `pedersen_hash`, with its last byte set to `sub_index`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "get_tree_key", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus get_tree_key(Context* ctx, byte* address, byte* tree_index_le, byte sub_index, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Commits to `len` bytes of little-endian scalars, and writes the 32 byte compressed
+        ///  commitment into `out`.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "multi_scalar_mul", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void multi_scalar_mul(Context* ctx, byte* scalars, System.UIntPtr len, byte* @out);
+        internal static extern VerkleStatus multi_scalar_mul(Context* ctx, byte* scalars, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
 
+        /// <summary>
+        ///  Creates a multiproof for the serialized prover queries in `input`, and writes the
+        ///  `PROOF_SIZE` byte proof into `out`.
+        ///
+        ///  Returns `CouldNotDeserializeCommitment` or `FailedToDeserializeScalar` if a query in
+        ///  `input` contains an invalid commitment or scalar.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "create_proof", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void create_proof(Context* ctx, byte* input, System.UIntPtr len, byte* @out);
+        internal static extern VerkleStatus create_proof(Context* ctx, byte* input, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
 
+        /// <summary>
+        ///  Same as `create_proof`, however the commitments in `input` and the proof are uncompressed.
+        ///
+        ///  The proof is `UNCOMPRESSED_PROOF_SIZE` bytes.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "create_proof_uncompressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void create_proof_uncompressed(Context* ctx, byte* input, System.UIntPtr len, byte* @out);
+        internal static extern VerkleStatus create_proof_uncompressed(Context* ctx, byte* input, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
 
+        /// <summary>
+        ///  Verifies a `PROOF_SIZE` byte proof, followed by the serialized verifier queries.
+        ///
+        ///  Returns `ProofVerificationFailed` if the proof is invalid.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "verify_proof", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        internal static extern bool verify_proof(Context* ctx, byte* input, System.UIntPtr len);
+        internal static extern VerkleStatus verify_proof(Context* ctx, byte* input, System.UIntPtr len);
 
+        /// <summary>
+        ///  Same as `verify_proof`, however the proof and the commitments in the verifier queries
+        ///  are uncompressed.
+        /// </summary>
         [DllImport(__DllName, EntryPoint = "verify_proof_uncompressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        internal static extern bool verify_proof_uncompressed(Context* ctx, byte* input, System.UIntPtr len);
+        internal static extern VerkleStatus verify_proof_uncompressed(Context* ctx, byte* input, System.UIntPtr len);
+
+        /// <summary>
+        ///  Verifies an execution witness, given as a JSON string, against the hex encoded state `root`.
+        ///
+        ///  Both strings must be null terminated. Returns `InvalidInput` if they are not valid UTF-8,
+        ///  and `ProofVerificationFailed` if the witness cannot be parsed or is invalid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verify_execution_witness", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verify_execution_witness(byte* root, byte* execution_witness_json);
+
+        /// <summary>
+        ///  Commits to `len` bytes of little-endian scalars, and writes the 64 byte uncompressed
+        ///  commitment into `out`.
+        ///
+        ///  `len` must be a multiple of 32, and at most 256 scalars can be committed to.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "commit_to_scalars", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus commit_to_scalars(Context* ctx, byte* scalars, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Adds the commitments `lhs` and `rhs`, and writes the 64 byte result into `out`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "add_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus add_commitment(byte* lhs, byte* rhs, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Updates the scalar at `commitment_index` of `old_commitment` from `old_scalar` to
+        ///  `new_scalar`, and writes the 64 byte updated commitment into `out`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "update_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus update_commitment(Context* ctx, byte* old_commitment, byte commitment_index, byte* old_scalar, byte* new_scalar, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Same as `update_commitment`, however `len` scalars are updated at once.
+        ///
+        ///  `commitment_indices` must point to `len` bytes, and `old_scalars` and `new_scalars`
+        ///  must each point to `len * 32` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "update_commitment_sparse", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus update_commitment_sparse(Context* ctx, byte* old_commitment, byte* commitment_indices, byte* old_scalars, byte* new_scalars, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Hashes `commitment` to a scalar, and writes the 32 byte scalar into `out`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "hash_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus hash_commitment(byte* commitment, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Hashes `len` commitments to scalars, and writes the `len * 32` bytes of scalars into `out`.
+        ///
+        ///  This is faster than calling `hash_commitment` for each commitment.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "hash_commitments", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus hash_commitments(byte* commitments, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Compresses `commitment`, and writes the 32 byte result into `out`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "serialize_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus serialize_commitment(byte* commitment, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Compresses `len` commitments, and writes the `len * 32` bytes of results into `out`.
+        ///
+        ///  This is faster than calling `serialize_commitment` for each commitment.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "serialize_commitments", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus serialize_commitments(byte* commitments, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Decompresses a 32 byte commitment, and writes the 64 byte result into `out`.
+        ///
+        ///  Returns `CouldNotDeserializeCommitment` if the bytes are not a valid commitment.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "deserialize_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus deserialize_commitment(byte* serialized_commitment, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Creates a trie whose nodes are stored in memory, and writes its handle to `out_trie`.
+        ///
+        ///  The handle must be freed with `verkle_trie_free`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_new_in_memory", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_new_in_memory(Context* ctx, VerkleTrie** out_trie);
+
+        /// <summary>
+        ///  Opens the trie stored in the directory at `path`, creating it if it does not exist,
+        ///  and writes its handle to `out_trie`.
+        ///
+        ///  Inserted values are only persisted once `verkle_trie_flush` is called.
+        ///
+        ///  Returns `InvalidInput` if `path` is not valid UTF-8 or the database could not be opened.
+        ///  The handle must be freed with `verkle_trie_free`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_new_on_disk", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_new_on_disk(Context* ctx, byte* path, VerkleTrie** out_trie);
+
+        /// <summary>
+        ///  Frees a trie created with `verkle_trie_new_in_memory` or `verkle_trie_new_on_disk`.
+        ///  Passing null is a no-op.
+        ///
+        ///  Values which have not been flushed to disk are discarded.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_free", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void verkle_trie_free(VerkleTrie* trie);
+
+        /// <summary>
+        ///  Inserts `len` key-value pairs into the trie.
+        ///
+        ///  `keys` and `values` must each point to `len * 32` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_insert", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_insert(VerkleTrie* trie, byte* keys, byte* values, System.UIntPtr len);
+
+        /// <summary>
+        ///  Writes the 32 byte value for `key` into `out`, and whether the key is in the trie
+        ///  into `out_found`.
+        ///
+        ///  `out` is left untouched if the key is not in the trie.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_get", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_get(VerkleTrie* trie, byte* key, byte* @out, System.UIntPtr out_capacity, bool* out_found);
+
+        /// <summary>
+        ///  Writes the 32 byte compressed root commitment into `out`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_root_commitment", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_root_commitment(VerkleTrie* trie, byte* @out, System.UIntPtr out_capacity);
+
+        /// <summary>
+        ///  Persists the values inserted since the last flush.
+        ///
+        ///  This is a no-op for tries which are stored in memory.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_flush", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_flush(VerkleTrie* trie);
+
+        /// <summary>
+        ///  Creates a serialized `VerkleProof` for `len` keys, which may or may not be in the trie,
+        ///  and writes it into `out`.
+        ///
+        ///  The length of the proof is always written to `out_len`, so if `OutputTooSmall` is
+        ///  returned, the caller can retry with a buffer of that size.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_trie_create_proof", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_trie_create_proof(VerkleTrie* trie, byte* keys, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity, System.UIntPtr* out_len);
+
+        /// <summary>
+        ///  Verifies a serialized `VerkleProof` for `len` keys against the compressed `root` commitment.
+        ///
+        ///  `keys` and `values` must each point to `len * 32` bytes, and `values_present` to `len` bools.
+        ///  A key whose entry in `values_present` is false is proven to be absent, and its value is ignored.
+        ///
+        ///  Returns `InvalidInput` if the proof cannot be deserialized, and `ProofVerificationFailed`
+        ///  if it is invalid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_proof_verify", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_proof_verify(byte* proof, System.UIntPtr proof_len, byte* root, byte* keys, byte* values, bool* values_present, System.UIntPtr len);
+
+        /// <summary>
+        ///  Verifies a serialized `VerkleProof` like `verkle_proof_verify`, and then writes the
+        ///  32 byte compressed root commitment, after the proven keys are set to `new_values`, into `out`.
+        ///
+        ///  `new_values` and `new_values_present` have the same layout as `values` and `values_present`.
+        ///  A key whose entry in `new_values_present` is false is left unchanged.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "verkle_proof_verify_and_update", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern VerkleStatus verkle_proof_verify_and_update(Context* ctx, byte* proof, System.UIntPtr proof_len, byte* root, byte* keys, byte* values, bool* values_present, byte* new_values, bool* new_values_present, System.UIntPtr len, byte* @out, System.UIntPtr out_capacity);
 
 
     }
 
 
+    /// <summary>
+    ///  The result of calling a C function.
+    ///
+    ///  The variants from `LengthOfScalarsNotMultipleOf32` to `InvalidPrecomputedTables` mirror
+    ///  `ffi_interface::Error`. The other variants are specific to the C boundary.
+    ///
+    ///  If a function returns `Panic`, then any handle which was passed to it should be freed,
+    ///  and not used again.
+    /// </summary>
+    internal enum VerkleStatus : uint
+    {
+        Ok = 0,
+        NullPointer = 1,
+        OutputTooSmall = 2,
+        LengthOfScalarsNotMultipleOf32 = 3,
+        TooManyScalars = 4,
+        FailedToDeserializeScalar = 5,
+        LengthIsNotAnExpectedMultiple = 6,
+        CouldNotDeserializeCommitment = 7,
+        ProofVerificationFailed = 8,
+        InvalidPrecomputedTables = 9,
+        InvalidInput = 10,
+        Panic = 11,
+    }
+
 
 }
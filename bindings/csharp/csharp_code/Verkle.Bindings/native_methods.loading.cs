@@ -50,4 +50,9 @@ internal static unsafe partial class NativeMethods
     internal unsafe partial struct Context
     {
     }
+
+    [StructLayout(LayoutKind.Sequential)]
+    internal unsafe partial struct VerkleTrie
+    {
+    }
 }
\ No newline at end of file
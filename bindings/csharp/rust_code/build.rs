@@ -17,9 +17,15 @@ fn main() {
     let path_to_output_file = parent.join(PATH_FOR_CSHARP_BINDINGS_FILE);
 
     let path_to_c_crates_lib_file = path_to_c_crate().join("src/lib.rs");
+    let path_to_c_crates_commitment_file = path_to_c_crate().join("src/commitment.rs");
+    let path_to_c_crates_trie_file = path_to_c_crate().join("src/trie.rs");
+    let path_to_c_crates_status_file = path_to_c_crate().join("src/status.rs");
 
     csbindgen::Builder::default()
         .input_extern_file(path_to_c_crates_lib_file)
+        .input_extern_file(path_to_c_crates_commitment_file)
+        .input_extern_file(path_to_c_crates_trie_file)
+        .input_extern_file(path_to_c_crates_status_file)
         .csharp_namespace("Verkle.Bindings")
         .csharp_dll_name(package_name_of_c_crate)
         .csharp_class_name("NativeMethods")
@@ -42,13 +42,75 @@ public class LibIpaMultipoint {
     ensureLibraryLoaded();
   }
 
+  /**
+   * Creates a context, which holds the precomputed tables used to commit and create proofs.
+   *
+   * <p>Creating a context is expensive, so it should be reused across calls. The methods which do
+   * not take a context use the one returned by {@link #defaultContext()}.
+   *
+   * @return handle of the context, which must be freed with {@link #freeContext(long)}.
+   */
+  public static native long createContext();
+
+  /**
+   * Creates a context from precomputed tables, which is much faster than {@link
+   * #createContext()}.
+   *
+   * @param tables tables created with {@link #writePrecomputedTables(long)}.
+   * @return handle of the context, which must be freed with {@link #freeContext(long)}.
+   * @throws IllegalArgumentException if the tables cannot be deserialized.
+   */
+  public static native long createContextFromPrecomputedTables(byte[] tables);
+
+  /**
+   * Serializes the precomputed tables of a context.
+   *
+   * @param context handle of the context.
+   * @return serialised tables, which can be loaded with {@link
+   *     #createContextFromPrecomputedTables(byte[])}.
+   */
+  public static native byte[] writePrecomputedTables(long context);
+
+  /**
+   * Frees a context. The handle must not be used afterwards.
+   *
+   * <p>Freeing zero or the default context does nothing.
+   *
+   * @param context handle of the context.
+   */
+  public static native void freeContext(long context);
+
+  /**
+   * Returns the context used by the methods which do not take one.
+   *
+   * <p>It is created on first use and lives until the process exits.
+   *
+   * @return handle of the default context.
+   */
+  public static long defaultContext() {
+    return DefaultContext.HANDLE;
+  }
+
+  private static native long nativeDefaultContext();
+
+  // Holder class, so that the default context is only created when it is first used
+  private static final class DefaultContext {
+    private static final long HANDLE = nativeDefaultContext();
+  }
+
   /**
    * Commit to a vector of values.
    *
+   * @param context handle of the context to use, see {@link #createContext()}.
    * @param values vector of serialised scalars to commit to.
    * @return uncompressed serialised commitment.
    */
-  public static native byte[] commit(byte[] values);
+  public static native byte[] commit(long context, byte[] values);
+
+  /** Same as {@link #commit(long, byte[])}, using the default context. */
+  public static byte[] commit(byte[] values) {
+    return commit(defaultContext(), values);
+  }
 
   /**
    * Adds two commitments together.
@@ -62,14 +124,21 @@ public class LibIpaMultipoint {
   /**
    * Commit to a vector of values and compress commitment.
    *
+   * @param context handle of the context to use, see {@link #createContext()}.
    * @param values vector of serialised scalars to commit to.
    * @return compressed serialised commitment.
    */
-  public static native byte[] commitAsCompressed(byte[] values);
+  public static native byte[] commitAsCompressed(long context, byte[] values);
+
+  /** Same as {@link #commitAsCompressed(long, byte[])}, using the default context. */
+  public static byte[] commitAsCompressed(byte[] values) {
+    return commitAsCompressed(defaultContext(), values);
+  }
 
   /**
    * Update a commitment with a sparse vector.
    *
+   * @param context handle of the context to use, see {@link #createContext()}.
    * @param commitment uncompressed serialised commitment.
    * @param indices indices in value vector to update.
    * @param oldValues old serialised scalars to update.
@@ -77,7 +146,16 @@ public class LibIpaMultipoint {
    * @return uncompressed serialised commitment.
    */
   public static native byte[] updateSparse(
-      byte[] commitment, byte[] indices, byte[] oldValues, byte[] newValues);
+      long context, byte[] commitment, byte[] indices, byte[] oldValues, byte[] newValues);
+
+  /**
+   * Same as {@link #updateSparse(long, byte[], byte[], byte[], byte[])}, using the default
+   * context.
+   */
+  public static byte[] updateSparse(
+      byte[] commitment, byte[] indices, byte[] oldValues, byte[] newValues) {
+    return updateSparse(defaultContext(), commitment, indices, oldValues, newValues);
+  }
 
   /**
    * Compresses a commitment.
@@ -148,6 +226,143 @@ public class LibIpaMultipoint {
       byte[] finalEvaluation,
       byte[] prestateRoot);
 
+  /**
+   * Verifies the Verkle proof against the specified pre-state root, and checks that updating the
+   * keys to their new values results in the specified post-state root.
+   *
+   * @param context handle of the context to use, see {@link #createContext()}.
+   * @param keys accessed or modified keys
+   * @param currentValues current values associated with the keys, empty if absent.
+   * @param newValues new values associated with the keys, empty if unchanged.
+   * @param commitmentsByPath commitments along the path in the Verkle trie.
+   * @param cl left commitments in the IPA proof.
+   * @param cr right commitments in the IPA proof.
+   * @param otherStems others stems that are present.
+   * @param d aggregated commitment to the polynomial D in the IPA proof.
+   * @param depthsExtensionPresentStems depths and extension presence for each stem.
+   * @param finalEvaluation final evaluation point in the IPA proof.
+   * @param prestateRoot root of the prestate to be verified against.
+   * @param poststateRoot root of the poststate to be verified against.
+   * @return true if both the prestate and poststate roots are correct, false if the proof is
+   *     invalid.
+   * @throws IllegalArgumentException if the inputs cannot be deserialized, an element of an array
+   *     is null, or a value is neither empty nor 32 bytes.
+   * @throws IllegalStateException if the proof is valid, but the poststate root cannot be computed
+   *     from it.
+   */
+  public static native boolean verifyPostStateRoot(
+      long context,
+      byte[][] keys,
+      byte[][] currentValues,
+      byte[][] newValues,
+      byte[][] commitmentsByPath,
+      byte[][] cl,
+      byte[][] cr,
+      byte[][] otherStems,
+      byte[] d,
+      byte[] depthsExtensionPresentStems,
+      byte[] finalEvaluation,
+      byte[] prestateRoot,
+      byte[] poststateRoot);
+
+  /**
+   * Same as the {@code verifyPostStateRoot} method which takes a context, using the default
+   * context.
+   */
+  public static boolean verifyPostStateRoot(
+      byte[][] keys,
+      byte[][] currentValues,
+      byte[][] newValues,
+      byte[][] commitmentsByPath,
+      byte[][] cl,
+      byte[][] cr,
+      byte[][] otherStems,
+      byte[] d,
+      byte[] depthsExtensionPresentStems,
+      byte[] finalEvaluation,
+      byte[] prestateRoot,
+      byte[] poststateRoot) {
+    return verifyPostStateRoot(
+        defaultContext(),
+        keys,
+        currentValues,
+        newValues,
+        commitmentsByPath,
+        cl,
+        cr,
+        otherStems,
+        d,
+        depthsExtensionPresentStems,
+        finalEvaluation,
+        prestateRoot,
+        poststateRoot);
+  }
+
+  /**
+   * Creates a multiproof for a list of polynomial openings.
+   *
+   * <p>Each opening is a compressed commitment (32 bytes), the polynomial in evaluation form (256
+   * serialised scalars), the index of the evaluation point (1 byte) and the evaluation (32 bytes).
+   *
+   * @param context handle of the context to use, see {@link #createContext()}.
+   * @param input concatenated openings.
+   * @return serialised multiproof.
+   * @throws IllegalArgumentException if the openings cannot be deserialized.
+   */
+  public static native byte[] createProof(long context, byte[] input);
+
+  /** Same as {@link #createProof(long, byte[])}, using the default context. */
+  public static byte[] createProof(byte[] input) {
+    return createProof(defaultContext(), input);
+  }
+
+  /**
+   * Creates a Verkle proof for the given keys, against the state which contains exactly the given
+   * entries.
+   *
+   * <p>Keys which are not in the state are proven to be absent.
+   *
+   * @param context handle of the context to use, see {@link #createContext()}.
+   * @param stateKeys keys of every entry in the state.
+   * @param stateValues values of every entry in the state.
+   * @param stateRoot compressed root commitment of the state.
+   * @param keys keys to prove.
+   * @return serialised Verkle proof, which can be verified against the state root.
+   * @throws IllegalArgumentException if the keys or values are null or not 32 bytes, there is no
+   *     value for a key in the state, or the state does not have the given root.
+   */
+  public static native byte[] createVerkleProof(
+      long context, byte[][] stateKeys, byte[][] stateValues, byte[] stateRoot, byte[][] keys);
+
+  /**
+   * Same as {@link #createVerkleProof(long, byte[][], byte[][], byte[], byte[][])}, using the
+   * default context.
+   */
+  public static byte[] createVerkleProof(
+      byte[][] stateKeys, byte[][] stateValues, byte[] stateRoot, byte[][] keys) {
+    return createVerkleProof(defaultContext(), stateKeys, stateValues, stateRoot, keys);
+  }
+
+  /**
+   * Computes many tree keys at once.
+   *
+   * <p>Keys which share an address and tree index are only hashed once.
+   *
+   * @param context handle of the context to use, see {@link #createContext()}.
+   * @param addresses concatenated 32 byte addresses.
+   * @param treeIndexesLe concatenated 32 byte little-endian tree indexes.
+   * @param subIndexes one sub index for each address.
+   * @return concatenated 32 byte tree keys.
+   * @throws IllegalArgumentException if the inputs do not have matching lengths.
+   */
+  public static native byte[] getTreeKeys(
+      long context, byte[] addresses, byte[] treeIndexesLe, byte[] subIndexes);
+
+  /** Same as {@link #getTreeKeys(long, byte[], byte[], byte[])}, using the default context. */
+  public static byte[] getTreeKeys(byte[] addresses, byte[] treeIndexesLe, byte[] subIndexes) {
+    return getTreeKeys(defaultContext(), addresses, treeIndexesLe, subIndexes);
+  }
+
   // TODO:Replace the code below with jna.Native
 
   private static final String LIBRARY_NAME = "java_verkle_cryptography";
@@ -215,6 +215,146 @@ public class LibIpaMultipointTest {
     assertThat(Bytes.of(key)).isEqualTo(expectedHashForSubIndex0);
   }
 
+  @Test
+  public void testGetTreeKeys() {
+    // Taken from "get_tree_key_add_commitment_equivalence" and "smoke_test_address_zero" tests in
+    // rust ffi_interface code.
+    Bytes address =
+        Bytes.fromHexString("0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20");
+    Bytes treeIndex =
+        Bytes.fromHexString("0x403f3e3d3c3b3a393837363534333231302f2e2d2c2b2a292827262524232221");
+
+    Bytes result =
+        Bytes.wrap(
+            LibIpaMultipoint.getTreeKeys(
+                Bytes.concatenate(address, address, Bytes32.ZERO).toArray(),
+                Bytes.concatenate(treeIndex, treeIndex, Bytes32.ZERO).toArray(),
+                new byte[] {0, 5, 7}));
+    Bytes expected =
+        Bytes.concatenate(
+            Bytes.fromHexString(
+                "0xff7e3916badeb510dfcdad458726273319280742e553d8d229bd676428147300"),
+            Bytes.fromHexString(
+                "0xff7e3916badeb510dfcdad458726273319280742e553d8d229bd676428147305"),
+            Bytes.fromHexString(
+                "0x1a100684fd68185060405f3f160e4bb6e034194336b547bdae323f888d533207"));
+    assertThat(result).isEqualTo(expected);
+  }
+
+  @Test
+  public void testGetTreeKeysRejectsMismatchedLengths() {
+    assertThatThrownBy(() -> LibIpaMultipoint.getTreeKeys(new byte[32], new byte[32], new byte[2]))
+        .isInstanceOf(IllegalArgumentException.class);
+  }
+
+  @Test
+  public void testCreateProof() {
+    Bytes32[] polynomial = new Bytes32[256];
+    for (int i = 0; i < 256; i++) {
+      polynomial[i] = toBytes32LE(BigInteger.valueOf(i * 7 + 1));
+    }
+    Bytes evaluations = Bytes.concatenate(polynomial);
+    Bytes commitment = Bytes.wrap(LibIpaMultipoint.commitAsCompressed(evaluations.toArray()));
+
+    Bytes opening = Bytes.concatenate(commitment, evaluations, Bytes.of(3), polynomial[3]);
+    byte[] proof = LibIpaMultipoint.createProof(opening.toArray());
+    assertThat(proof).hasSize(576);
+
+    assertThatThrownBy(() -> LibIpaMultipoint.createProof(opening.slice(1).toArray()))
+        .isInstanceOf(IllegalArgumentException.class);
+    Bytes invalidCommitment =
+        Bytes.concatenate(Bytes32.repeat((byte) 0xff), evaluations, Bytes.of(3), polynomial[3]);
+    assertThatThrownBy(() -> LibIpaMultipoint.createProof(invalidCommitment.toArray()))
+        .isInstanceOf(IllegalArgumentException.class);
+  }
+
+  @Test
+  public void testCreateVerkleProof() {
+    byte[][] stateKeys = {
+      Bytes32.fromHexString("0x01").toArray(),
+      Bytes32.rightPad(Bytes.of((byte) 0xff)).toArray()
+    };
+    byte[][] stateValues = {
+      Bytes32.repeat((byte) 2).toArray(), Bytes32.repeat((byte) 3).toArray()
+    };
+    byte[] stateRoot =
+        Bytes32.fromHexString("0x0b73b551b68c625957c9f914f632ce88e36028b5c05bb9156cae68843324c3e6")
+            .toArray();
+    byte[][] keys = {stateKeys[0], Bytes32.fromHexString("0x02").toArray()};
+
+    byte[] proof = LibIpaMultipoint.createVerkleProof(stateKeys, stateValues, stateRoot, keys);
+    assertThat(proof).isNotEmpty();
+
+    // The proof must be against the given state root
+    byte[] otherRoot = LibIpaMultipoint.compress(LibIpaMultipoint.commit(new byte[32]));
+    assertThatThrownBy(
+            () -> LibIpaMultipoint.createVerkleProof(stateKeys, stateValues, otherRoot, keys))
+        .isInstanceOf(IllegalArgumentException.class);
+    assertThatThrownBy(
+            () ->
+                LibIpaMultipoint.createVerkleProof(
+                    stateKeys, new byte[][] {stateValues[0]}, stateRoot, keys))
+        .isInstanceOf(IllegalArgumentException.class);
+    assertThatThrownBy(
+            () ->
+                LibIpaMultipoint.createVerkleProof(
+                    stateKeys, stateValues, stateRoot, new byte[][] {null}))
+        .isInstanceOf(IllegalArgumentException.class);
+  }
+
+  @Test
+  public void testVerifyPostStateRootRejectsMalformedValues() {
+    byte[][] keys = {Bytes32.fromHexString("0x01").toArray()};
+    byte[] root = Bytes32.ZERO.toArray();
+    // A value must be empty or 32 bytes, so that it is not mistaken for an absent value
+    byte[][][] malformedValues = {{new byte[5]}, {null}};
+    for (byte[][] values : malformedValues) {
+      assertThatThrownBy(
+              () ->
+                  LibIpaMultipoint.verifyPostStateRoot(
+                      keys,
+                      values,
+                      new byte[][] {new byte[0]},
+                      new byte[0][],
+                      new byte[0][],
+                      new byte[0][],
+                      new byte[0][],
+                      new byte[32],
+                      new byte[0],
+                      new byte[32],
+                      root,
+                      root))
+          .isInstanceOf(IllegalArgumentException.class);
+    }
+  }
+
+  @Test
+  public void testContextHandles() {
+    byte[] values = toBytes32LE(BigInteger.valueOf(5)).toArray();
+    byte[] expected = LibIpaMultipoint.commit(values);
+
+    long context = LibIpaMultipoint.createContext();
+    long loaded =
+        LibIpaMultipoint.createContextFromPrecomputedTables(
+            LibIpaMultipoint.writePrecomputedTables(context));
+    try {
+      assertThat(LibIpaMultipoint.commit(context, values)).isEqualTo(expected);
+      assertThat(LibIpaMultipoint.commit(loaded, values)).isEqualTo(expected);
+    } finally {
+      LibIpaMultipoint.freeContext(context);
+      LibIpaMultipoint.freeContext(loaded);
+    }
+
+    // The default context is never freed
+    LibIpaMultipoint.freeContext(LibIpaMultipoint.defaultContext());
+    assertThat(LibIpaMultipoint.commit(values)).isEqualTo(expected);
+
+    assertThatThrownBy(() -> LibIpaMultipoint.commit(0, values))
+        .isInstanceOf(IllegalArgumentException.class);
+    assertThatThrownBy(() -> LibIpaMultipoint.createContextFromPrecomputedTables(new byte[10]))
+        .isInstanceOf(IllegalArgumentException.class);
+  }
+
   private static Bytes32 toBytes32LE(BigInteger value) {
     byte[] bytes = new byte[32];
     byte[] valueBytes = value.toByteArray();
@@ -26,6 +26,7 @@ import java.util.ArrayList;
 import java.util.List;
 import java.util.stream.Stream;
 import org.apache.tuweni.bytes.Bytes;
+import org.junit.jupiter.api.Test;
 import org.junit.jupiter.params.ParameterizedTest;
 import org.junit.jupiter.params.provider.Arguments;
 import org.junit.jupiter.params.provider.MethodSource;
@@ -121,6 +122,60 @@ public class VerifyProofTest {
         preStateRoot.toArrayUnsafe());
   }
 
+  @Test
+  public void TestVerifyPostStateRoot() throws IOException {
+    final InputStream inputStream =
+        VerifyProofTest.class.getResourceAsStream("/valid_block_72.json");
+    final ExecutionWitnessData executionWitnessData =
+        objectMapper.readValue(inputStream, new TypeReference<>() {});
+    final Bytes preStateRoot =
+        Bytes.fromHexString("0x64e1a647f42e5c2e3c434531ccf529e1b3e93363a40db9fc8eec81f492123510");
+    final Bytes postStateRoot = Bytes.fromHexString(executionWitnessData.header.stateRoot);
+
+    assertThat(verifyPostState(executionWitnessData, preStateRoot, postStateRoot)).isTrue();
+    assertThat(verifyPostState(executionWitnessData, preStateRoot, preStateRoot)).isFalse();
+  }
+
+  private boolean verifyPostState(
+      final ExecutionWitnessData executionWitnessData,
+      final Bytes preStateRoot,
+      final Bytes postStateRoot) {
+    final List<byte[]> allStemsKeys = new ArrayList<>();
+    final List<byte[]> allCurrentValues = new ArrayList<>();
+    final List<byte[]> allNewValues = new ArrayList<>();
+    executionWitnessData.executionWitness.stateDiff.forEach(
+        stateDiff -> {
+          Bytes stem = Bytes.fromHexString(stateDiff.stem);
+          stateDiff.suffixDiffs.forEach(
+              suffixDiff -> {
+                allStemsKeys.add(
+                    Bytes.concatenate(stem, Bytes.of(suffixDiff.suffix)).toArrayUnsafe());
+                allCurrentValues.add(toValue(suffixDiff.currentValue));
+                allNewValues.add(toValue(suffixDiff.newValue));
+              });
+        });
+    final ExecutionWitnessData.VerkleProof verkleProof =
+        executionWitnessData.executionWitness.verkleProof;
+
+    return LibIpaMultipoint.verifyPostStateRoot(
+        allStemsKeys.toArray(byte[][]::new),
+        allCurrentValues.toArray(byte[][]::new),
+        allNewValues.toArray(byte[][]::new),
+        toArray(verkleProof.commitmentsByPath),
+        toArray(verkleProof.ipaProof.cl),
+        toArray(verkleProof.ipaProof.cr),
+        toArray(verkleProof.otherStems),
+        Bytes.fromHexString(verkleProof.d).toArrayUnsafe(),
+        Bytes.fromHexString(verkleProof.depthExtensionPresent).toArrayUnsafe(),
+        Bytes.fromHexString(verkleProof.ipaProof.finalEvaluation).toArrayUnsafe(),
+        preStateRoot.toArrayUnsafe(),
+        postStateRoot.toArrayUnsafe());
+  }
+
+  private byte[] toValue(final String value) {
+    return ((value == null) ? Bytes.EMPTY : Bytes.fromHexString(value)).toArrayUnsafe();
+  }
+
   private byte[][] toArray(final List<String> elt) {
     return elt.stream().map(Bytes::fromHexString).map(Bytes::toArrayUnsafe).toArray(byte[][]::new);
   }
@@ -11,29 +11,122 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 mod parsers;
-use parsers::{parse_commitment, parse_commitments, parse_indices, parse_scalars};
+use parsers::{
+    parse_32_byte_arrays, parse_commitment, parse_commitments, parse_indices, parse_scalars,
+};
 
 mod utils;
-use utils::{
-    byte_to_depth_extension_present, bytes32_to_element, bytes32_to_scalar, jobjectarray_to_vec,
-};
-use utils::{
-    convert_byte_array_to_fixed_array, convert_to_btree_set, get_array, get_optional_array,
-};
+use utils::context_from_handle;
+use utils::{convert_byte_array_to_fixed_array, get_array, get_optional_array, parse_verkle_proof};
+use utils::{jobjectarray_to_optional_vec, jobjectarray_to_vec};
 
+use banderwagon::Element;
 use jni::objects::{JByteArray, JClass, JObjectArray};
+use jni::sys::jlong;
 use jni::JNIEnv;
 use once_cell::sync::Lazy;
 
-use ipa_multipoint::ipa::IPAProof;
-use ipa_multipoint::multiproof::MultiPointProof;
 use std::convert::TryInto;
-use verkle_trie::proof::{ExtPresent, VerificationHint, VerkleProof};
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::errors::VerificationError;
+use verkle_trie::proof::stateless_updater;
+use verkle_trie::{Config, Trie, TrieTrait};
 
-// TODO: Use a pointer here instead. This is only being used so that the interface does not get changed.
-// TODO: and bindings do not need to be modified.
+/// The context used by the Java methods which do not take one, see `defaultContext`.
+///
+/// It lives for the whole process, so `freeContext` ignores it.
 pub static CONFIG: Lazy<ffi_interface::Context> = Lazy::new(ffi_interface::Context::default);
 
+/// Creates a context and returns its handle, which must be freed with `freeContext`.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_createContext(
+    _env: JNIEnv,
+    _class: JClass<'_>,
+) -> jlong {
+    Box::into_raw(Box::new(ffi_interface::Context::default())) as jlong
+}
+
+/// Creates a context whose committer tables are read from `tables`, and returns its handle.
+///
+/// This is much faster than `createContext`. The tables are created with `writePrecomputedTables`.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_createContextFromPrecomputedTables(
+    mut env: JNIEnv,
+    _class: JClass<'_>,
+    tables: JByteArray,
+) -> jlong {
+    let context = env
+        .convert_byte_array(tables)
+        .map_err(|_| "cannot convert byte array to vector".to_string())
+        .and_then(|tables| {
+            ffi_interface::Context::from_precomputed_tables(&tables).map_err(|e| format!("{e:?}"))
+        });
+    match context {
+        Ok(context) => Box::into_raw(Box::new(context)) as jlong,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", e)
+                .expect("Failed to throw exception for createContextFromPrecomputedTables.");
+            0
+        }
+    }
+}
+
+/// Serializes the committer tables of the context, so that they can be loaded with
+/// `createContextFromPrecomputedTables`.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_writePrecomputedTables<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'_>,
+    context: jlong,
+) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
+    let mut tables = Vec::new();
+    if let Err(e) = context.write_precomputed_tables(&mut tables) {
+        env.throw_new("java/lang/IllegalStateException", format!("{e:?}"))
+            .expect("Failed to throw exception for writePrecomputedTables.");
+        return JByteArray::default();
+    }
+    let result = match env.byte_array_from_slice(&tables) {
+        Ok(s) => s,
+        Err(e) => {
+            let error_message = format!("Couldn't return tables: {:?}", e);
+            env.throw_new("java/lang/IllegalArgumentException", &error_message)
+                .expect("Couldn't convert to byte array");
+            return JByteArray::default();
+        }
+    };
+    result
+}
+
+/// Frees a context created with `createContext` or `createContextFromPrecomputedTables`.
+///
+/// Passing zero or the default context is a no-op.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_freeContext(
+    _env: JNIEnv,
+    _class: JClass<'_>,
+    context: jlong,
+) {
+    let context = context as *mut ffi_interface::Context;
+    if context.is_null() || std::ptr::eq(context, &*CONFIG) {
+        return;
+    }
+    unsafe {
+        let _ = Box::from_raw(context);
+    }
+}
+
+/// Returns the handle of `CONFIG`, which is wrapped by `defaultContext` on the Java side.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_nativeDefaultContext(
+    _env: JNIEnv,
+    _class: JClass<'_>,
+) -> jlong {
+    &*CONFIG as *const ffi_interface::Context as jlong
+}
+
 /// Commit receives a list of 32 byte scalars and returns a 32 byte scalar
 ///
 /// Scalar is actually the map_to_field(commitment) because we want to
@@ -42,8 +135,12 @@ pub static CONFIG: Lazy<ffi_interface::Context> = Lazy::new(ffi_interface::Conte
 pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commit<'local>(
     mut env: JNIEnv<'local>,
     _class: JClass<'_>,
+    context: jlong,
     values: JByteArray,
 ) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
     let input = match parse_scalars(&env, values) {
         Ok(v) => v,
         Err(e) => {
@@ -52,7 +149,7 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commit<'local>(
             return JByteArray::default();
         }
     };
-    let commitment = match ffi_interface::commit_to_scalars(&CONFIG, &input) {
+    let commitment = match ffi_interface::commit_to_scalars(context, &input) {
         Ok(v) => v,
         Err(e) => {
             let error_message = format!("Could not commit to scalars: {:?}", e);
@@ -77,8 +174,12 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commit<'local>(
 pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commitAsCompressed<'local>(
     mut env: JNIEnv<'local>,
     _class: JClass<'_>,
+    context: jlong,
     values: JByteArray,
 ) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
     let input = match parse_scalars(&env, values) {
         Ok(v) => v,
         Err(e) => {
@@ -87,7 +188,7 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commitAsCompres
             return JByteArray::default();
         }
     };
-    let commitment = match ffi_interface::commit_to_scalars(&CONFIG, &input) {
+    let commitment = match ffi_interface::commit_to_scalars(context, &input) {
         Ok(v) => v,
         Err(e) => {
             env.throw_new("java/lang/IllegalArgumentException", format!("{e:?}"))
@@ -112,11 +213,15 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_commitAsCompres
 pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_updateSparse<'local>(
     mut env: JNIEnv<'local>,
     _class: JClass<'_>,
+    context: jlong,
     commitment: JByteArray,
     indices: JByteArray,
     old_values: JByteArray,
     new_values: JByteArray,
 ) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
     let commitment = match parse_commitment(&env, commitment) {
         Ok(v) => v,
         Err(e) => {
@@ -166,7 +271,7 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_updateSparse<'l
         })
         .collect();
     let commitment =
-        match ffi_interface::update_commitment_sparse(&CONFIG, commitment, pos, old, new) {
+        match ffi_interface::update_commitment_sparse(context, commitment, pos, old, new) {
             Ok(v) => v,
             Err(e) => {
                 env.throw_new("java/lang/IllegalArgumentException", format!("{e:?}"))
@@ -230,9 +335,13 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_compressMany<'l
             return JByteArray::default();
         }
     };
-    let compressed: Vec<u8> = commitments
+    let commitments: Vec<_> = commitments
         .chunks_exact(64)
-        .flat_map(|x| ffi_interface::serialize_commitment(x.try_into().unwrap()))
+        .map(|x| x.try_into().unwrap())
+        .collect();
+    let compressed: Vec<u8> = ffi_interface::serialize_commitments(&commitments)
+        .into_iter()
+        .flatten()
         .collect();
     let result = match env.byte_array_from_slice(&compressed) {
         Ok(s) => s,
@@ -393,86 +502,325 @@ pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_verifyPreStateR
         }
     }
 
-    let formatted_commitments =
-        match jobjectarray_to_vec(&mut env, &commitments_by_path, bytes32_to_element) {
-            Some(vec) => vec,
-            None => return false,
-        };
-
-    let formatted_cl = match jobjectarray_to_vec(&mut env, &cl, bytes32_to_element) {
-        Some(vec) => vec,
+    let verkle_proof = match parse_verkle_proof(
+        &mut env,
+        &commitments_by_path,
+        &cl,
+        &cr,
+        &other_stems,
+        d,
+        depths_extension_present_stems,
+        final_evaluation,
+    ) {
+        Some(proof) => proof,
         None => return false,
     };
 
-    let formatted_cr = match jobjectarray_to_vec(&mut env, &cr, bytes32_to_element) {
-        Some(vec) => vec,
+    let prestate_root_bytes = match convert_byte_array_to_fixed_array(&env, prestate_root)
+        .and_then(|bytes| Element::from_bytes(&bytes))
+    {
+        Some(element) => element,
         None => return false,
     };
 
-    let formatted_d = match convert_byte_array_to_fixed_array(&env, d) {
-        Some(arr) => arr,
-        None => return false,
-    };
+    let (bool, _update_hint) = verkle_proof.check(
+        formatted_keys,
+        formatted_current_values,
+        prestate_root_bytes,
+    );
+    bool
+}
 
-    let formatted_final_evaluation = match convert_byte_array_to_fixed_array(&env, final_evaluation)
-    {
-        Some(arr) => arr,
-        None => return false,
+/// Creates a multiproof for the serialized polynomial openings in `input`.
+///
+/// See `ffi_interface::create_proof` for the format of the input.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_createProof<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'_>,
+    context: jlong,
+    input: JByteArray,
+) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
     };
-
-    let scalar_final_evaluation = match bytes32_to_scalar(formatted_final_evaluation) {
-        Some(scalar) => scalar,
-        None => return false,
+    let input = match env.convert_byte_array(input) {
+        Ok(v) => v,
+        Err(_) => {
+            env.throw_new(
+                "java/lang/IllegalArgumentException",
+                "cannot convert byte array to vector",
+            )
+            .expect("Failed to throw exception for createProof inputs.");
+            return JByteArray::default();
+        }
     };
+    let proof = match ffi_interface::create_proof(context, input) {
+        Ok(v) => v,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", format!("{e:?}"))
+                .expect("Failed to throw exception for createProof inputs.");
+            return JByteArray::default();
+        }
+    };
+    let result = match env.byte_array_from_slice(&proof) {
+        Ok(s) => s,
+        Err(e) => {
+            let error_message = format!("Couldn't return proof: {:?}", e);
+            env.throw_new("java/lang/IllegalArgumentException", &error_message)
+                .expect("Couldn't convert to byte array");
+            return JByteArray::default();
+        }
+    };
+    result
+}
 
-    let g_x_comm = match bytes32_to_element(formatted_d) {
-        Some(element) => element,
-        None => return false,
+/// Creates a serialized `VerkleProof` for `keys`, against the state which contains exactly the
+/// entries `state_keys` and `state_values`.
+///
+/// The trie built from the state must have the root `state_root`, so that the proof can be
+/// verified against it. Keys which are not in the state are proven to be absent.
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_createVerkleProof<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'_>,
+    context: jlong,
+    state_keys: JObjectArray,
+    state_values: JObjectArray,
+    state_root: JByteArray,
+    keys: JObjectArray,
+) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
+    let inputs =
+        jobjectarray_to_vec(&mut env, &state_keys, "stateKeys", Some).and_then(|state_keys| {
+            let state_values = jobjectarray_to_vec(&mut env, &state_values, "stateValues", Some)?;
+            let keys = jobjectarray_to_vec(&mut env, &keys, "keys", Some)?;
+            if state_keys.len() != state_values.len() {
+                return Err("There should be a value for each key in the state".to_string());
+            }
+            Ok((state_keys, state_values, keys))
+        });
+    let (state_keys, state_values, formatted_keys) = match inputs {
+        Ok(v) => v,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", e)
+                .expect("Failed to throw exception for createVerkleProof inputs.");
+            return JByteArray::default();
+        }
     };
+    let Some(state_root) = convert_byte_array_to_fixed_array(&env, state_root) else {
+        env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "State root should be 32 bytes",
+        )
+        .expect("Failed to throw exception for createVerkleProof state root.");
+        return JByteArray::default();
+    };
+
+    let mut trie = Trie::new(Config {
+        db: MemoryDb::new(),
+        committer: &context.committer,
+    });
+    trie.insert(state_keys.into_iter().zip(state_values));
+    if trie.root_commitment().to_bytes() != state_root {
+        env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "The state does not have the given state root",
+        )
+        .expect("Failed to throw exception for createVerkleProof state root.");
+        return JByteArray::default();
+    }
 
-    let proof = MultiPointProof {
-        open_proof: IPAProof {
-            L_vec: formatted_cl,
-            R_vec: formatted_cr,
-            a: scalar_final_evaluation,
-        },
-        g_x_comm,
+    let proof = match trie.create_verkle_proof(formatted_keys.into_iter()) {
+        Ok(proof) => proof,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalStateException", format!("{e:?}"))
+                .expect("Failed to throw exception for createVerkleProof.");
+            return JByteArray::default();
+        }
     };
+    let mut proof_bytes = Vec::new();
+    proof
+        .write(&mut proof_bytes)
+        .expect("infallible: writing to a vector");
 
-    let depths_bytes = match env.convert_byte_array(depths_extension_present_stems) {
-        Ok(bytes) => bytes,
-        Err(_) => return false,
+    let result = match env.byte_array_from_slice(&proof_bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            let error_message = format!("Couldn't return proof: {:?}", e);
+            env.throw_new("java/lang/IllegalArgumentException", &error_message)
+                .expect("Couldn't convert to byte array");
+            return JByteArray::default();
+        }
     };
-    let (formatted_extension_present, depths): (Vec<ExtPresent>, Vec<u8>) = depths_bytes
-        .iter()
-        .map(|&byte| byte_to_depth_extension_present(byte))
-        .unzip();
+    result
+}
 
-    let formatted_other_stems = match convert_to_btree_set(&mut env, &other_stems) {
-        Some(set) => set,
-        None => return false,
+/// Verifies the proof against `prestate_root` like `verifyPreStateRoot`, and then checks that
+/// setting the keys to `new_values` results in `poststate_root`.
+///
+/// Empty current values are absent, and empty new values leave their key unchanged.
+/// An invalid proof returns `false`; malformed inputs throw an `IllegalArgumentException`, and a
+/// proof which cannot be used to compute the post-state root throws an `IllegalStateException`.
+#[allow(clippy::too_many_arguments)]
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_verifyPostStateRoot(
+    mut env: JNIEnv,
+    _class: JClass<'_>,
+    context: jlong,
+    stems_keys: JObjectArray,
+    current_values: JObjectArray,
+    new_values: JObjectArray,
+    commitments_by_path: JObjectArray,
+    cl: JObjectArray,
+    cr: JObjectArray,
+    other_stems: JObjectArray,
+    d: JByteArray,
+    depths_extension_present_stems: JByteArray,
+    final_evaluation: JByteArray,
+    prestate_root: JByteArray,
+    poststate_root: JByteArray,
+) -> bool {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return false;
+    };
+    let inputs = jobjectarray_to_vec(&mut env, &stems_keys, "keys", Some).and_then(|keys| {
+        let current_values =
+            jobjectarray_to_optional_vec(&mut env, &current_values, "currentValues")?;
+        let new_values = jobjectarray_to_optional_vec(&mut env, &new_values, "newValues")?;
+        if keys.len() != current_values.len() || keys.len() != new_values.len() {
+            return Err("There should be a current and new value for each key".to_string());
+        }
+        Ok((keys, current_values, new_values))
+    });
+    let (formatted_keys, formatted_current_values, formatted_new_values) = match inputs {
+        Ok(v) => v,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", e)
+                .expect("Failed to throw exception for verifyPostStateRoot inputs.");
+            return false;
+        }
     };
 
-    let verkle_proof = VerkleProof {
-        verification_hint: VerificationHint {
-            depths,
-            extension_present: formatted_extension_present,
-            diff_stem_no_proof: formatted_other_stems,
-        },
-        comms_sorted: formatted_commitments,
-        proof,
+    let verkle_proof = match parse_verkle_proof(
+        &mut env,
+        &commitments_by_path,
+        &cl,
+        &cr,
+        &other_stems,
+        d,
+        depths_extension_present_stems,
+        final_evaluation,
+    ) {
+        Some(proof) => proof,
+        None => {
+            env.throw_new(
+                "java/lang/IllegalArgumentException",
+                "Could not deserialize the verkle proof",
+            )
+            .expect("Failed to throw exception for verifyPostStateRoot proof.");
+            return false;
+        }
     };
 
-    let prestate_root_bytes =
-        match convert_byte_array_to_fixed_array(&env, prestate_root).and_then(bytes32_to_element) {
-            Some(element) => element,
-            None => return false,
-        };
+    let roots = convert_byte_array_to_fixed_array(&env, prestate_root)
+        .and_then(|bytes| Element::from_bytes(&bytes))
+        .zip(
+            convert_byte_array_to_fixed_array(&env, poststate_root)
+                .and_then(|bytes| Element::from_bytes(&bytes)),
+        );
+    let (prestate_root, poststate_root) = match roots {
+        Some(roots) => roots,
+        None => {
+            env.throw_new(
+                "java/lang/IllegalArgumentException",
+                "Could not deserialize the state roots",
+            )
+            .expect("Failed to throw exception for verifyPostStateRoot roots.");
+            return false;
+        }
+    };
 
-    let (bool, _update_hint) = verkle_proof.check(
+    match stateless_updater::verify_and_update(
+        verkle_proof,
+        prestate_root,
         formatted_keys,
         formatted_current_values,
-        prestate_root_bytes,
-    );
-    bool
+        formatted_new_values,
+        &context.committer,
+    ) {
+        Ok(root) => root == poststate_root,
+        Err(VerificationError::InvalidProof) => false,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalStateException", format!("{e}"))
+                .expect("Failed to throw exception for verifyPostStateRoot.");
+            false
+        }
+    }
+}
+
+/// Computes the tree key for each address, tree index and sub index.
+///
+/// `addresses` and `tree_indexes_le` are concatenations of 32 byte values, and `sub_indexes`
+/// has one byte for each of them. Returns the concatenation of the 32 byte tree keys.
+#[allow(deprecated)]
+#[no_mangle]
+pub extern "system" fn Java_verkle_cryptography_LibIpaMultipoint_getTreeKeys<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'_>,
+    context: jlong,
+    addresses: JByteArray,
+    tree_indexes_le: JByteArray,
+    sub_indexes: JByteArray,
+) -> JByteArray<'local> {
+    let Some(context) = context_from_handle(&mut env, context) else {
+        return JByteArray::default();
+    };
+    let inputs = parse_32_byte_arrays(&env, addresses, "addresses").and_then(|addresses| {
+        let tree_indexes = parse_32_byte_arrays(&env, tree_indexes_le, "tree indexes")?;
+        let sub_indexes = env
+            .convert_byte_array(sub_indexes)
+            .map_err(|_| "cannot convert sub indexes to vector".to_string())?;
+        if addresses.len() != tree_indexes.len() || addresses.len() != sub_indexes.len() {
+            return Err("There should be a tree index and sub index for each address".to_string());
+        }
+        Ok((addresses, tree_indexes, sub_indexes))
+    });
+    let (addresses, tree_indexes, sub_indexes) = match inputs {
+        Ok(v) => v,
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", e)
+                .expect("Failed to throw exception for getTreeKeys inputs.");
+            return JByteArray::default();
+        }
+    };
+
+    // Keys in the same stem share their hash, which is the expensive part
+    let mut stem_hashes = std::collections::HashMap::new();
+    let mut tree_keys = Vec::with_capacity(addresses.len() * 32);
+    for ((address, tree_index), sub_index) in
+        addresses.into_iter().zip(tree_indexes).zip(sub_indexes)
+    {
+        let mut tree_key = *stem_hashes
+            .entry((address, tree_index))
+            .or_insert_with(|| ffi_interface::get_tree_key_hash(context, address, tree_index));
+        tree_key[31] = sub_index;
+        tree_keys.extend(tree_key);
+    }
+
+    let result = match env.byte_array_from_slice(&tree_keys) {
+        Ok(s) => s,
+        Err(e) => {
+            let error_message = format!(
+                "Invalid tree keys output. Couldn't convert to byte array: {:?}",
+                e
+            );
+            env.throw_new("java/lang/IllegalArgumentException", &error_message)
+                .expect("Couldn't convert to byte array");
+            return JByteArray::default();
+        }
+    };
+    result
 }
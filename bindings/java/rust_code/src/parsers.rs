@@ -47,3 +47,23 @@ pub fn parse_commitments<'a>(
 
     Ok(commitment_bytes)
 }
+
+pub fn parse_32_byte_arrays(
+    env: &JNIEnv,
+    values: JByteArray<'_>,
+    name: &str,
+) -> Result<Vec<[u8; 32]>, String> {
+    let bytes = env
+        .convert_byte_array(values)
+        .map_err(|_| format!("cannot convert {name} to vector"))?;
+
+    if bytes.len() % 32 != 0 {
+        return Err(format!(
+            "Wrong {name} size: should be a multiple of 32 bytes"
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(32)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect())
+}
@@ -1,75 +1,43 @@
-use banderwagon::{CanonicalDeserialize, Element, Fr};
 use jni::objects::{JByteArray, JObjectArray};
+use jni::sys::jlong;
 use jni::JNIEnv;
-use std::collections::BTreeSet;
-use verkle_trie::proof::ExtPresent;
+use verkle_trie::proof::witness::{IpaProofBytes, VerkleProofBytes};
+use verkle_trie::proof::VerkleProof;
 
-/// Converts a 32-byte array into an `Element` object.
+/// Borrows the `Context` behind a handle returned by `createContext`,
+/// `createContextFromPrecomputedTables` or `defaultContext`.
 ///
-/// This function attempts to convert a fixed-size byte array into an `Element`.
-/// returns `Some(Element)`. Otherwise, it returns `None`.
+/// If the handle is zero, an `IllegalArgumentException` is thrown and `None` is returned.
+/// Any other handle must not have been freed yet.
 ///
 /// # Arguments
 ///
-/// * `bytes` - A 32-byte array representing the binary representation of an `Element`.
+/// * `env` - The JNI environment, used to throw the exception.
+/// * `handle` - The context handle passed from Java.
 ///
 /// # Returns
 ///
-/// An `Option<Element>` which is `Some` if the conversion is successful, otherwise `None`.
-pub fn bytes32_to_element(bytes: [u8; 32]) -> Option<Element> {
-    Element::from_bytes(&bytes)
-}
-
-/// Converts a 32-byte array into a scalar value `Fr`.
-///
-/// This function reverses the byte array and attempts to deserialize it into a scalar
-/// value of type `Fr`, which is used in cryptographic operations. The deserialization
-/// uses a compressed format. If successful, it returns `Some(Fr)`. Otherwise, it returns `None`.
-///
-/// # Arguments
-///
-/// * `bytes` - A 32-byte array representing the binary representation of a scalar value.
-///
-/// # Returns
-///
-/// An `Option<Fr>` which is `Some` if the deserialization is successful, otherwise `None`.
-pub fn bytes32_to_scalar(bytes: [u8; 32]) -> Option<Fr> {
-    let mut bytes = bytes;
-    bytes.reverse();
-    CanonicalDeserialize::deserialize_compressed(&bytes[..]).ok()
-}
-
-/// Extracts extension presence and depth information from a byte.
-///
-/// This function interprets the lower 3 bits of the input byte as the extension
-/// presence status and the remaining higher bits as the depth. It returns a tuple
-/// containing the `ExtPresent` status and the depth as an `u8`.
-///
-/// # Arguments
-///
-/// * `value` - A byte containing encoded extension presence and depth information.
-///
-/// # Returns
-///
-/// A tuple `(ExtPresent, u8)` where the first element represents the extension presence
-/// status and the second element represents the depth.
-pub fn byte_to_depth_extension_present(value: u8) -> (ExtPresent, u8) {
-    let ext_status = value & 3;
-    let ext_status = match ext_status {
-        0 => ExtPresent::None,
-        1 => ExtPresent::DifferentStem,
-        2 => ExtPresent::Present,
-        _ => return (ExtPresent::None, 0), // Handle unexpected value gracefully
-    };
-    let depth = value >> 3;
-    (ext_status, depth)
+/// An `Option<&Context>` which is `Some` if the handle is not zero, otherwise `None`.
+pub fn context_from_handle<'a>(
+    env: &mut JNIEnv,
+    handle: jlong,
+) -> Option<&'a ffi_interface::Context> {
+    if handle == 0 {
+        env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "context handle is null",
+        )
+        .expect("Failed to throw exception for context handle.");
+        return None;
+    }
+    Some(unsafe { &*(handle as *const ffi_interface::Context) })
 }
 
 /// Converts a `jobjectArray` into a `Vec<T>` by applying a conversion function to each element.
 ///
 /// This function iterates over each element of the input `jobjectArray`, applies a conversion
 /// function to convert each element into type `T`, and collects the results into a `Vec<T>`.
-/// If any conversion fails, it returns `None`.
+/// If an element is `null`, is not 32 bytes, or cannot be converted, it returns an error.
 ///
 /// # Type Parameters
 ///
@@ -80,26 +48,32 @@ pub fn byte_to_depth_extension_present(value: u8) -> (ExtPresent, u8) {
 ///
 /// * `env` - The JNI environment.
 /// * `array` - The input `jobjectArray` containing the elements to be converted.
+/// * `name` - The name of the array, used in the error message.
 /// * `converter` - A function that converts a `[u8; 32]` array into `Option<T>`.
 ///
 /// # Returns
 ///
-/// An `Option<Vec<T>>` which is `Some` containing the converted elements if all conversions
-/// are successful, otherwise `None`.
+/// A `Result<Vec<T>, String>` which is `Ok` containing the converted elements if all conversions
+/// are successful, otherwise an error message.
 pub fn jobjectarray_to_vec<T, F>(
     env: &mut JNIEnv,
     array: &JObjectArray<'_>,
+    name: &str,
     mut converter: F,
-) -> Option<Vec<T>>
+) -> Result<Vec<T>, String>
 where
     F: FnMut([u8; 32]) -> Option<T>,
 {
-    let vec_vec = jobject_array_to_2d_byte_array(env, array);
-
-    // Convert vector into fixed size 32 byte arrays
-    let vec_arr: Option<Vec<[u8; 32]>> = vec_vec.into_iter().map(|v| v.try_into().ok()).collect();
-
-    vec_arr?.into_iter().map(&mut converter).collect()
+    jobject_array_to_2d_byte_array(env, array, name)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, bytes)| {
+            let bytes: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| format!("{name}[{i}] should be 32 bytes"))?;
+            converter(bytes).ok_or_else(|| format!("{name}[{i}] is not valid"))
+        })
+        .collect()
 }
 
 /// Converts a `jbyteArray` into a fixed-size `[u8; 32]` array.
@@ -146,7 +120,7 @@ pub fn convert_byte_array_to_fixed_array(
 ///
 /// An `Option<[u8; 32]>` which is `Some` containing the byte array if successful, otherwise `None`.
 pub fn get_array(env: &mut JNIEnv, array: &JObjectArray<'_>, index: i32) -> Option<[u8; 32]> {
-    let vec_vec = jobject_array_to_2d_byte_array(env, array);
+    let vec_vec = jobject_array_to_2d_byte_array(env, array, "array").ok()?;
     let bytes = vec_vec.get(index as usize).cloned()?;
     if bytes.len() != 32 {
         return None;
@@ -188,7 +162,7 @@ pub fn get_optional_array(
     array: &JObjectArray<'_>,
     index: i32,
 ) -> Option<Option<[u8; 32]>> {
-    let vec_of_vec = jobject_array_to_2d_byte_array(env, array);
+    let vec_of_vec = jobject_array_to_2d_byte_array(env, array, "array").ok()?;
     vec_of_vec
         .get(index as usize)
         .cloned()
@@ -208,11 +182,9 @@ pub fn get_optional_array(
     // }
 }
 
-/// Converts a `jobjectArray` into a `BTreeSet<[u8; 31]>`.
+/// Converts a `jobjectArray` into a `Vec<[u8; N]>`.
 ///
-/// This function iterates over each element of the input `jobjectArray`, attempts to convert
-/// each element into a `[u8; 31]` array, and inserts the result into a `BTreeSet`. If any
-/// conversion fails or if the size does not match, it returns `None`.
+/// If any element is not exactly `N` bytes, it returns `None`.
 ///
 /// # Arguments
 ///
@@ -221,50 +193,88 @@ pub fn get_optional_array(
 ///
 /// # Returns
 ///
-/// An `Option<BTreeSet<[u8; 31]>>` which is `Some` containing the converted elements as a set
+/// An `Option<Vec<[u8; N]>>` which is `Some` containing the converted elements
 /// if all conversions are successful, otherwise `None`.
-pub fn convert_to_btree_set(
+pub fn jobjectarray_to_fixed_arrays<const N: usize>(
     env: &mut JNIEnv,
     array: &JObjectArray<'_>,
-) -> Option<BTreeSet<[u8; 31]>> {
-    // jobject_array_to_2d_byte_array(env, array)
-    //     .into_iter()
-    //     .map(|arr| arr.try_into().ok())
-    //     .collect()
-    let vec_of_vec = jobject_array_to_2d_byte_array(env, array);
+) -> Option<Vec<[u8; N]>> {
+    jobject_array_to_2d_byte_array(env, array, "array")
+        .ok()?
+        .into_iter()
+        .map(|bytes| bytes.try_into().ok())
+        .collect()
+}
 
-    let mut set = BTreeSet::new();
-    // Check if any of the inner elements are not 31 bytes and return None if so
-    // or add them to BTreeSet if they are
-    for arr in vec_of_vec {
-        if arr.len() != 31 {
-            return None;
-        }
-        set.insert(arr.try_into().expect("infallible: array is 31 bytes"));
-    }
-    Some(set)
+/// Converts a `jobjectArray` into a `Vec<Option<[u8; 32]>>`.
+///
+/// Absent values are passed as empty arrays and are converted to `None`.
+/// Any other element which is not 32 bytes is an error, so that a malformed value
+/// is not mistaken for an absent one.
+///
+/// # Arguments
+///
+/// * `env` - The JNI environment.
+/// * `array` - The `jobjectArray` containing the optional values.
+/// * `name` - The name of the array, used in the error message.
+///
+/// # Returns
+///
+/// A `Result<Vec<Option<[u8; 32]>>, String>` with one entry for each element of the array,
+/// or an error message.
+pub fn jobjectarray_to_optional_vec(
+    env: &mut JNIEnv,
+    array: &JObjectArray<'_>,
+    name: &str,
+) -> Result<Vec<Option<[u8; 32]>>, String> {
+    jobject_array_to_2d_byte_array(env, array, name)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, bytes)| match bytes.len() {
+            0 => Ok(None),
+            32 => Ok(Some(
+                bytes.try_into().expect("infallible: checked the length"),
+            )),
+            len => Err(format!(
+                "{name}[{i}] should be empty or 32 bytes, got {len} bytes"
+            )),
+        })
+        .collect()
 }
 
+/// Copies each element of a `byte[][]` into a `Vec<u8>`.
+///
+/// Returns an error if an element is `null`, or if a JNI call fails.
 pub(crate) fn jobject_array_to_2d_byte_array(
     env: &mut JNIEnv,
     array: &JObjectArray,
-) -> Vec<Vec<u8>> {
+    name: &str,
+) -> Result<Vec<Vec<u8>>, String> {
+    let jni_error = |e: jni::errors::Error| format!("could not read {name}: {e}");
+
+    if array.is_null() {
+        return Err(format!("{name} should not be null"));
+    }
     // Get the length of the outer array
-    let outer_len = env.get_array_length(array).unwrap();
+    let outer_len = env.get_array_length(array).map_err(jni_error)?;
 
     let mut result = Vec::with_capacity(outer_len as usize);
 
     for i in 0..outer_len {
         // Get each inner array (JByteArray)
-        let inner_array_obj = env.get_object_array_element(array, i).unwrap();
+        let inner_array_obj = env.get_object_array_element(array, i).map_err(jni_error)?;
+        if inner_array_obj.is_null() {
+            return Err(format!("{name}[{i}] should not be null"));
+        }
         let inner_array: JByteArray = JByteArray::from(inner_array_obj);
 
         // Get the length of the inner array
-        let inner_len = env.get_array_length(&inner_array).unwrap();
+        let inner_len = env.get_array_length(&inner_array).map_err(jni_error)?;
 
         // Get the elements of the inner array
         let mut buf = vec![0; inner_len as usize];
-        env.get_byte_array_region(inner_array, 0, &mut buf).unwrap();
+        env.get_byte_array_region(inner_array, 0, &mut buf)
+            .map_err(jni_error)?;
 
         // Convert i8 to u8
         let buf = buf.into_iter().map(|x| x as u8).collect();
@@ -272,5 +282,36 @@ pub(crate) fn jobject_array_to_2d_byte_array(
         result.push(buf);
     }
 
-    result
+    Ok(result)
+}
+
+/// Assembles a `VerkleProof` from its components, as they appear in an execution witness.
+///
+/// The components are validated by `VerkleProofBytes::to_verkle_proof`.
+/// Returns `None` if any of the components could not be converted.
+#[allow(clippy::too_many_arguments)]
+pub fn parse_verkle_proof(
+    env: &mut JNIEnv,
+    commitments_by_path: &JObjectArray<'_>,
+    cl: &JObjectArray<'_>,
+    cr: &JObjectArray<'_>,
+    other_stems: &JObjectArray<'_>,
+    d: JByteArray<'_>,
+    depths_extension_present_stems: JByteArray<'_>,
+    final_evaluation: JByteArray<'_>,
+) -> Option<VerkleProof> {
+    let proof = VerkleProofBytes {
+        other_stems: jobjectarray_to_fixed_arrays(env, other_stems)?,
+        depth_extension_present: env
+            .convert_byte_array(depths_extension_present_stems)
+            .ok()?,
+        commitments_by_path: jobjectarray_to_fixed_arrays(env, commitments_by_path)?,
+        d: convert_byte_array_to_fixed_array(env, d)?,
+        ipa_proof: IpaProofBytes {
+            cl: jobjectarray_to_fixed_arrays(env, cl)?,
+            cr: jobjectarray_to_fixed_arrays(env, cr)?,
+            final_evaluation: convert_byte_array_to_fixed_array(env, final_evaluation)?,
+        },
+    };
+    proof.to_verkle_proof().ok()
 }
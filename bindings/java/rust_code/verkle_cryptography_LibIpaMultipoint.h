@@ -7,13 +7,53 @@
 #ifdef __cplusplus
 extern "C" {
 #endif
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    createContext
+ * Signature: ()J
+ */
+JNIEXPORT jlong JNICALL Java_verkle_cryptography_LibIpaMultipoint_createContext
+  (JNIEnv *, jclass);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    createContextFromPrecomputedTables
+ * Signature: ([B)J
+ */
+JNIEXPORT jlong JNICALL Java_verkle_cryptography_LibIpaMultipoint_createContextFromPrecomputedTables
+  (JNIEnv *, jclass, jbyteArray);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    writePrecomputedTables
+ * Signature: (J)[B
+ */
+JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_writePrecomputedTables
+  (JNIEnv *, jclass, jlong);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    freeContext
+ * Signature: (J)V
+ */
+JNIEXPORT void JNICALL Java_verkle_cryptography_LibIpaMultipoint_freeContext
+  (JNIEnv *, jclass, jlong);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    nativeDefaultContext
+ * Signature: ()J
+ */
+JNIEXPORT jlong JNICALL Java_verkle_cryptography_LibIpaMultipoint_nativeDefaultContext
+  (JNIEnv *, jclass);
+
 /*
  * Class:     verkle_cryptography_LibIpaMultipoint
  * Method:    commit
- * Signature: ([B)[B
+ * Signature: (J[B)[B
  */
 JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_commit
-  (JNIEnv *, jclass, jbyteArray);
+  (JNIEnv *, jclass, jlong, jbyteArray);
 
 /*
  * Class:     verkle_cryptography_LibIpaMultipoint
@@ -26,18 +66,18 @@ JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_addCommit
 /*
  * Class:     verkle_cryptography_LibIpaMultipoint
  * Method:    commitAsCompressed
- * Signature: ([B)[B
+ * Signature: (J[B)[B
  */
 JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_commitAsCompressed
-  (JNIEnv *, jclass, jbyteArray);
+  (JNIEnv *, jclass, jlong, jbyteArray);
 
 /*
  * Class:     verkle_cryptography_LibIpaMultipoint
  * Method:    updateSparse
- * Signature: ([B[B[B[B)[B
+ * Signature: (J[B[B[B[B)[B
  */
 JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_updateSparse
-  (JNIEnv *, jclass, jbyteArray, jbyteArray, jbyteArray, jbyteArray);
+  (JNIEnv *, jclass, jlong, jbyteArray, jbyteArray, jbyteArray, jbyteArray);
 
 /*
  * Class:     verkle_cryptography_LibIpaMultipoint
@@ -79,6 +119,38 @@ JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_hashMany
 JNIEXPORT jboolean JNICALL Java_verkle_cryptography_LibIpaMultipoint_verifyPreStateRoot
   (JNIEnv *, jclass, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jbyteArray, jbyteArray, jbyteArray, jbyteArray);
 
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    verifyPostStateRoot
+ * Signature: (J[[B[[B[[B[[B[[B[[B[[B[B[B[B[B[B)Z
+ */
+JNIEXPORT jboolean JNICALL Java_verkle_cryptography_LibIpaMultipoint_verifyPostStateRoot
+  (JNIEnv *, jclass, jlong, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jobjectArray, jbyteArray, jbyteArray, jbyteArray, jbyteArray, jbyteArray);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    createProof
+ * Signature: (J[B)[B
+ */
+JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_createProof
+  (JNIEnv *, jclass, jlong, jbyteArray);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    createVerkleProof
+ * Signature: (J[[B[[B[B[[B)[B
+ */
+JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_createVerkleProof
+  (JNIEnv *, jclass, jlong, jobjectArray, jobjectArray, jbyteArray, jobjectArray);
+
+/*
+ * Class:     verkle_cryptography_LibIpaMultipoint
+ * Method:    getTreeKeys
+ * Signature: (J[B[B[B)[B
+ */
+JNIEXPORT jbyteArray JNICALL Java_verkle_cryptography_LibIpaMultipoint_getTreeKeys
+  (JNIEnv *, jclass, jlong, jbyteArray, jbyteArray, jbyteArray);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,161 @@
+//! The `Committer` primitives used to compute and update commitments outside of a trie.
+//!
+//! Commitments are passed as 64 byte uncompressed group elements, and scalars as 32 byte
+//! little endian field elements. Use `serialize_commitment` to get the 32 byte compressed form.
+use crate::{to_array, to_arrays, to_py_err, CONFIG};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(commit_to_scalars, m)?)?;
+    m.add_function(wrap_pyfunction!(add_commitment, m)?)?;
+    m.add_function(wrap_pyfunction!(update_commitment, m)?)?;
+    m.add_function(wrap_pyfunction!(update_commitment_sparse, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_commitment, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_commitments, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_commitment, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_commitments, m)?)?;
+    m.add_function(wrap_pyfunction!(deserialize_commitment, m)?)?;
+    Ok(())
+}
+
+/// Commits to at most 256 scalars, concatenated into `scalars`.
+#[pyfunction]
+pub fn commit_to_scalars(scalars: &[u8]) -> PyResult<[u8; 64]> {
+    ffi_interface::commit_to_scalars(&CONFIG, scalars).map_err(to_py_err)
+}
+
+#[pyfunction]
+pub fn add_commitment(lhs: &[u8], rhs: &[u8]) -> PyResult<[u8; 64]> {
+    Ok(ffi_interface::add_commitment(
+        to_array(lhs, "lhs")?,
+        to_array(rhs, "rhs")?,
+    ))
+}
+
+/// Updates the scalar at `index` from `old_scalar` to `new_scalar`.
+#[pyfunction]
+pub fn update_commitment(
+    commitment: &[u8],
+    index: u8,
+    old_scalar: &[u8],
+    new_scalar: &[u8],
+) -> PyResult<[u8; 64]> {
+    ffi_interface::update_commitment(
+        &CONFIG,
+        to_array(commitment, "commitment")?,
+        index,
+        to_array(old_scalar, "old_scalar")?,
+        to_array(new_scalar, "new_scalar")?,
+    )
+    .map_err(to_py_err)
+}
+
+/// Updates the scalars at `indices` at once, which is faster than calling
+/// `update_commitment` for each of them.
+#[pyfunction]
+pub fn update_commitment_sparse(
+    commitment: &[u8],
+    indices: Vec<u8>,
+    old_scalars: Vec<Vec<u8>>,
+    new_scalars: Vec<Vec<u8>>,
+) -> PyResult<[u8; 64]> {
+    if indices.len() != old_scalars.len() || indices.len() != new_scalars.len() {
+        return Err(PyValueError::new_err(
+            "indices, old_scalars and new_scalars must have the same length",
+        ));
+    }
+    ffi_interface::update_commitment_sparse(
+        &CONFIG,
+        to_array(commitment, "commitment")?,
+        indices.into_iter().map(usize::from).collect(),
+        to_arrays(old_scalars, "old_scalar")?,
+        to_arrays(new_scalars, "new_scalar")?,
+    )
+    .map_err(to_py_err)
+}
+
+/// Maps a commitment to a scalar, which is how a child commitment is stored in its parent.
+#[pyfunction]
+pub fn hash_commitment(commitment: &[u8]) -> PyResult<[u8; 32]> {
+    Ok(ffi_interface::hash_commitment(to_array(
+        commitment,
+        "commitment",
+    )?))
+}
+
+/// Same as `hash_commitment` for many commitments, but faster.
+#[pyfunction]
+pub fn hash_commitments(commitments: Vec<Vec<u8>>) -> PyResult<Vec<[u8; 32]>> {
+    Ok(ffi_interface::hash_commitments(&to_arrays(
+        commitments,
+        "commitment",
+    )?))
+}
+
+#[pyfunction]
+pub fn serialize_commitment(commitment: &[u8]) -> PyResult<[u8; 32]> {
+    Ok(ffi_interface::serialize_commitment(to_array(
+        commitment,
+        "commitment",
+    )?))
+}
+
+#[pyfunction]
+pub fn serialize_commitments(commitments: Vec<Vec<u8>>) -> PyResult<Vec<[u8; 32]>> {
+    Ok(ffi_interface::serialize_commitments(&to_arrays(
+        commitments,
+        "commitment",
+    )?))
+}
+
+#[pyfunction]
+pub fn deserialize_commitment(commitment: &[u8]) -> PyResult<[u8; 64]> {
+    ffi_interface::deserialize_commitment(to_array(commitment, "commitment")?).map_err(to_py_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(value: u8) -> [u8; 32] {
+        let mut scalar = [0u8; 32];
+        scalar[0] = value;
+        scalar
+    }
+
+    #[test]
+    fn update_matches_commit() {
+        let mut scalars = [0u8; 64];
+        scalars[..32].copy_from_slice(&scalar(1));
+        let commitment = commit_to_scalars(&scalars).unwrap();
+
+        scalars[32..].copy_from_slice(&scalar(2));
+        let expected = commit_to_scalars(&scalars).unwrap();
+
+        let updated = update_commitment(&commitment, 1, &scalar(0), &scalar(2)).unwrap();
+        assert_eq!(updated, expected);
+
+        let updated = update_commitment_sparse(
+            &commitment,
+            vec![1],
+            vec![scalar(0).to_vec()],
+            vec![scalar(2).to_vec()],
+        )
+        .unwrap();
+        assert_eq!(updated, expected);
+        assert!(update_commitment_sparse(&commitment, vec![1, 2], vec![], vec![]).is_err());
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let commitment = commit_to_scalars(&scalar(7)).unwrap();
+        let serialized = serialize_commitment(&commitment).unwrap();
+        assert_eq!(deserialize_commitment(&serialized).unwrap(), commitment);
+        assert_eq!(
+            hash_commitments(vec![commitment.to_vec()]).unwrap(),
+            vec![hash_commitment(&commitment).unwrap()]
+        );
+        assert!(commit_to_scalars(&[0u8; 31]).is_err());
+    }
+}
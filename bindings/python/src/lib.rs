@@ -0,0 +1,192 @@
+//! Python bindings for research and test-vector tooling.
+//!
+//! The extension module is called `verkle` and exposes:
+//! - Tree key derivation from `verkle-spec`
+//! - An in-memory `Trie`, which can create `VerkleProof`s, see `trie.rs`
+//! - The `Committer` primitives from `ffi_interface`, see `commitment.rs`
+//!
+//! Keys, values, commitments and proofs are passed as `bytes`. Tree indices, code chunk ids
+//! and storage slots are passed as `int`s. Malformed inputs raise `ValueError`.
+mod commitment;
+mod trie;
+
+use ffi_interface::Context;
+use num_bigint::BigUint;
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use verkle_spec::{addr20_to_addr32, Address20, Address32, Code, Hasher, Storage, H256, U256};
+
+/// The context shared by every function in the module, since it is expensive to create.
+pub static CONFIG: Lazy<Context> = Lazy::new(Context::default);
+
+/// Hashes with the committer in `CONFIG`, instead of creating a committer for every key
+/// like the default `Hasher` does.
+pub struct ConfigHasher;
+
+impl Hasher for ConfigHasher {
+    fn hash64(bytes64: [u8; 64]) -> H256 {
+        verkle_spec::hash64(&CONFIG.committer, bytes64)
+    }
+}
+
+pub(crate) fn to_array<const N: usize>(bytes: &[u8], name: &str) -> PyResult<[u8; N]> {
+    bytes.try_into().map_err(|_| {
+        PyValueError::new_err(format!("{name} must be {N} bytes, got {}", bytes.len()))
+    })
+}
+
+pub(crate) fn to_arrays<const N: usize>(items: Vec<Vec<u8>>, name: &str) -> PyResult<Vec<[u8; N]>> {
+    items.iter().map(|item| to_array(item, name)).collect()
+}
+
+pub(crate) fn to_py_err(error: ffi_interface::Error) -> PyErr {
+    PyValueError::new_err(format!("{error:?}"))
+}
+
+// Addresses may be given in the old 20 byte format, in which case they are left padded
+fn to_address32(address: &[u8]) -> PyResult<Address32> {
+    match address.len() {
+        20 => Ok(addr20_to_addr32(Address20::from_slice(address))),
+        32 => Ok(Address32::from_slice(address)),
+        len => Err(PyValueError::new_err(format!(
+            "address must be 20 or 32 bytes, got {len}"
+        ))),
+    }
+}
+
+fn to_u256(integer: BigUint, name: &str) -> PyResult<U256> {
+    let bytes = integer.to_bytes_le();
+    if bytes.len() > 32 {
+        return Err(PyValueError::new_err(format!(
+            "{name} does not fit in 256 bits"
+        )));
+    }
+    Ok(U256::from_little_endian(&bytes))
+}
+
+/// Returns the tree key for `sub_index` in the stem of (`address`, `tree_index`).
+#[pyfunction]
+fn get_tree_key(address: &[u8], tree_index: BigUint, sub_index: u8) -> PyResult<[u8; 32]> {
+    let address = to_address32(address)?;
+    let tree_index = to_u256(tree_index, "tree_index")?;
+
+    let mut input = [0u8; 64];
+    input[..32].copy_from_slice(address.as_bytes());
+    tree_index.to_little_endian(&mut input[32..]);
+
+    let mut key = ConfigHasher::hash64(input).to_fixed_bytes();
+    key[31] = sub_index;
+    Ok(key)
+}
+
+/// The tree keys of the account header of an address.
+#[pyclass(frozen)]
+struct Header {
+    inner: verkle_spec::Header,
+}
+
+#[pymethods]
+impl Header {
+    #[new]
+    fn new(address: &[u8]) -> PyResult<Self> {
+        let inner = verkle_spec::Header::new::<ConfigHasher>(to_address32(address)?);
+        Ok(Header { inner })
+    }
+
+    fn version(&self) -> [u8; 32] {
+        self.inner.version().to_fixed_bytes()
+    }
+
+    fn balance(&self) -> [u8; 32] {
+        self.inner.balance().to_fixed_bytes()
+    }
+
+    fn nonce(&self) -> [u8; 32] {
+        self.inner.nonce().to_fixed_bytes()
+    }
+
+    fn code_keccak(&self) -> [u8; 32] {
+        self.inner.code_keccak().to_fixed_bytes()
+    }
+
+    fn code_size(&self) -> [u8; 32] {
+        self.inner.code_size().to_fixed_bytes()
+    }
+}
+
+/// Returns the tree key of the code chunk `chunk_id` of an address.
+#[pyfunction]
+fn code_chunk_key(address: &[u8], chunk_id: BigUint) -> PyResult<[u8; 32]> {
+    let code = Code::new::<ConfigHasher>(to_address32(address)?, to_u256(chunk_id, "chunk_id")?);
+    Ok(code.code_chunk().to_fixed_bytes())
+}
+
+/// Returns the tree key of the storage slot `slot` of an address.
+#[pyfunction]
+fn storage_slot_key(address: &[u8], slot: BigUint) -> PyResult<[u8; 32]> {
+    let storage = Storage::new::<ConfigHasher>(to_address32(address)?, to_u256(slot, "slot")?);
+    Ok(storage.storage_slot().to_fixed_bytes())
+}
+
+/// Splits contract code into the 32 byte chunks which are stored in the trie.
+#[pyfunction]
+fn chunkify_code(code: Vec<u8>) -> Vec<[u8; 32]> {
+    verkle_spec::code::chunkify_code(code)
+}
+
+/// Returns the keccak256 hash of `data`, which is stored under the code keccak key.
+#[pyfunction]
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    keccak_hash::keccak(data).to_fixed_bytes()
+}
+
+#[pymodule]
+fn verkle(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_tree_key, m)?)?;
+    m.add_function(wrap_pyfunction!(code_chunk_key, m)?)?;
+    m.add_function(wrap_pyfunction!(storage_slot_key, m)?)?;
+    m.add_function(wrap_pyfunction!(chunkify_code, m)?)?;
+    m.add_function(wrap_pyfunction!(keccak256, m)?)?;
+    m.add_class::<Header>()?;
+
+    trie::register(m)?;
+    commitment::register(m)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_keys_match_ffi_interface() {
+        let address = [0xffu8; 32];
+        let mut tree_index_le = [0u8; 32];
+        tree_index_le[0] = 5;
+
+        #[allow(deprecated)]
+        let expected = ffi_interface::get_tree_key(&CONFIG, address, tree_index_le, 7);
+        let key = get_tree_key(&address, BigUint::from(5u8), 7).unwrap();
+        assert_eq!(key, expected);
+
+        // The version key is the first key in the stem at tree index zero
+        let header = Header::new(&address).unwrap();
+        let version = get_tree_key(&address, BigUint::from(0u8), 0).unwrap();
+        assert_eq!(header.version(), version);
+    }
+
+    #[test]
+    fn addresses_are_left_padded() {
+        let address20 = [0x11u8; 20];
+        let mut address32 = [0u8; 32];
+        address32[12..].copy_from_slice(&address20);
+
+        assert_eq!(
+            storage_slot_key(&address20, BigUint::from(3u8)).unwrap(),
+            storage_slot_key(&address32, BigUint::from(3u8)).unwrap()
+        );
+        assert!(code_chunk_key(&[0u8; 31], BigUint::from(0u8)).is_err());
+        assert!(get_tree_key(&address32, BigUint::from(1u8) << 256, 0).is_err());
+    }
+}
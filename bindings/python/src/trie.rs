@@ -0,0 +1,166 @@
+//! The in-memory `Trie` and the `VerkleProof`s it creates.
+//!
+//! Commitments are passed as 32 byte compressed group elements, like the state root
+//! in a block header.
+use crate::{to_array, to_arrays, CONFIG};
+use ipa_multipoint::committer::DefaultCommitter;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::sync::Arc;
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::{Config, Element, TrieTrait};
+
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Trie>()?;
+    m.add_class::<VerkleProof>()?;
+    Ok(())
+}
+
+/// A verkle trie whose nodes are stored in memory.
+#[pyclass]
+pub struct Trie {
+    inner: verkle_trie::Trie<MemoryDb, Arc<DefaultCommitter>>,
+}
+
+#[pymethods]
+impl Trie {
+    #[new]
+    pub fn new() -> Self {
+        let config = Config {
+            db: MemoryDb::new(),
+            committer: CONFIG.committer.clone(),
+        };
+        Trie {
+            inner: verkle_trie::Trie::new(config),
+        }
+    }
+
+    /// Inserts a list of (key, value) pairs.
+    pub fn insert(&mut self, items: Vec<(Vec<u8>, Vec<u8>)>) -> PyResult<()> {
+        let items = items
+            .iter()
+            .map(|(key, value)| Ok((to_array(key, "key")?, to_array(value, "value")?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        self.inner.insert(items.into_iter());
+        Ok(())
+    }
+
+    pub fn insert_single(&mut self, key: &[u8], value: &[u8]) -> PyResult<()> {
+        self.inner
+            .insert_single(to_array(key, "key")?, to_array(value, "value")?);
+        Ok(())
+    }
+
+    /// Returns the value stored under `key`, or `None` if the key is not in the trie.
+    pub fn get(&self, key: &[u8]) -> PyResult<Option<[u8; 32]>> {
+        Ok(self.inner.get(to_array(key, "key")?))
+    }
+
+    /// Returns the compressed root commitment, which is the state root.
+    pub fn root_commitment(&self) -> [u8; 32] {
+        self.inner.root_commitment().to_bytes()
+    }
+
+    /// Returns the root commitment mapped to a scalar, serialized in little endian.
+    pub fn root_hash(&self) -> [u8; 32] {
+        ffi_interface::serialization::fr_to_le_bytes(self.inner.root_hash())
+    }
+
+    /// Creates a proof for `keys`, which may or may not be in the trie.
+    pub fn create_proof(&self, keys: Vec<Vec<u8>>) -> PyResult<VerkleProof> {
+        let keys = to_arrays(keys, "key")?;
+        let inner = self
+            .inner
+            .create_verkle_proof(keys.into_iter())
+            .map_err(|error| PyValueError::new_err(format!("{error:?}")))?;
+        Ok(VerkleProof { inner })
+    }
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A proof that a list of keys have the given values, or are absent, in a trie.
+#[pyclass(frozen)]
+pub struct VerkleProof {
+    inner: verkle_trie::proof::VerkleProof,
+}
+
+#[pymethods]
+impl VerkleProof {
+    #[staticmethod]
+    pub fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        let inner = verkle_trie::proof::VerkleProof::read(bytes)
+            .map_err(|error| PyValueError::new_err(format!("{error:?}")))?;
+        Ok(VerkleProof { inner })
+    }
+
+    pub fn to_bytes(&self) -> PyResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.inner
+            .write(&mut bytes)
+            .map_err(|error| PyValueError::new_err(format!("{error:?}")))?;
+        Ok(bytes)
+    }
+
+    /// Verifies the proof against the compressed `root` commitment.
+    ///
+    /// `values` has one entry per key, where `None` means the key is proven to be absent.
+    pub fn verify(
+        &self,
+        root: &[u8],
+        keys: Vec<Vec<u8>>,
+        values: Vec<Option<Vec<u8>>>,
+    ) -> PyResult<bool> {
+        if keys.len() != values.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected one value per key, got {} keys and {} values",
+                keys.len(),
+                values.len()
+            )));
+        }
+        let root = Element::from_bytes(&to_array::<32>(root, "root")?)
+            .ok_or_else(|| PyValueError::new_err("root is not a valid commitment"))?;
+        let keys = to_arrays(keys, "key")?;
+        let values = values
+            .iter()
+            .map(|value| value.as_deref().map(|v| to_array(v, "value")).transpose())
+            .collect::<PyResult<Vec<_>>>()?;
+
+        Ok(self.inner.clone().check(keys, values, root).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proof_roundtrip() {
+        let mut trie = Trie::new();
+        let present = [1u8; 32];
+        let absent = [2u8; 32];
+        trie.insert(vec![(present.to_vec(), [3u8; 32].to_vec())])
+            .unwrap();
+        assert_eq!(trie.get(&present).unwrap(), Some([3u8; 32]));
+        assert_eq!(trie.get(&absent).unwrap(), None);
+
+        let root = trie.root_commitment();
+        let proof = trie
+            .create_proof(vec![present.to_vec(), absent.to_vec()])
+            .unwrap();
+        let proof = VerkleProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+
+        let keys = vec![present.to_vec(), absent.to_vec()];
+        assert!(proof
+            .verify(&root, keys.clone(), vec![Some(vec![3u8; 32]), None])
+            .unwrap());
+        assert!(!proof
+            .verify(&root, keys.clone(), vec![Some(vec![4u8; 32]), None])
+            .unwrap());
+        assert!(proof.verify(&root, keys, vec![None]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+import json
+import unittest
+from pathlib import Path
+
+import verkle
+
+GENESIS_FILEPATH = (
+    Path(__file__).resolve().parents[3] / "verkle-spec" / "assets" / "devnet6_genesis.json"
+)
+STATE_ROOT = bytes.fromhex("1fbf85345a3cbba9a6d44f991b721e55620a22397c2a93ee8d5011136ac300ee")
+KECCAK_EMPTY = bytes.fromhex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+
+
+def to_trie_value(integer):
+    return integer.to_bytes(32, "little")
+
+
+def genesis_trie():
+    with open(GENESIS_FILEPATH) as file:
+        alloc = json.load(file)["alloc"]
+
+    trie = verkle.Trie()
+    for address, account in alloc.items():
+        address = bytes.fromhex(address.removeprefix("0x"))
+        header = verkle.Header(address)
+
+        trie.insert(
+            [
+                (header.version(), to_trie_value(0)),
+                (header.balance(), to_trie_value(int(account["balance"]))),
+                (header.nonce(), to_trie_value(int(account.get("nonce", "0")))),
+            ]
+        )
+
+        code = account.get("code")
+        if code is None:
+            trie.insert_single(header.code_keccak(), KECCAK_EMPTY)
+        else:
+            code = bytes.fromhex(code.removeprefix("0x"))
+            trie.insert(
+                [
+                    (header.code_keccak(), verkle.keccak256(code)),
+                    (header.code_size(), to_trie_value(len(code))),
+                ]
+            )
+            trie.insert(
+                [
+                    (verkle.code_chunk_key(address, chunk_id), chunk)
+                    for chunk_id, chunk in enumerate(verkle.chunkify_code(code))
+                ]
+            )
+
+        for slot, value in account.get("storage", {}).items():
+            trie.insert_single(
+                verkle.storage_slot_key(address, int(slot, 16)),
+                bytes.fromhex(value.removeprefix("0x")).rjust(32, b"\0"),
+            )
+
+    return trie
+
+
+class TestGenesis(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.trie = genesis_trie()
+
+    def test_state_root(self):
+        self.assertEqual(self.trie.root_commitment(), STATE_ROOT)
+
+    def test_proof_against_state_root(self):
+        address = bytes(20)
+        header = verkle.Header(address)
+        keys = [header.version(), header.code_keccak(), bytes(32)]
+        values = [self.trie.get(key) for key in keys]
+
+        proof = self.trie.create_proof(keys)
+        proof = verkle.VerkleProof.from_bytes(proof.to_bytes())
+        self.assertTrue(proof.verify(STATE_ROOT, keys, values))
+
+        values[1] = bytes(32)
+        self.assertFalse(proof.verify(STATE_ROOT, keys, values))
+
+
+class TestTreeKeys(unittest.TestCase):
+    def test_header_keys_share_a_stem(self):
+        header = verkle.Header(bytes(range(20)))
+        keys = [header.version(), header.balance(), header.nonce(), header.code_keccak(), header.code_size()]
+        self.assertEqual({key[:31] for key in keys}, {keys[0][:31]})
+        self.assertEqual([key[31] for key in keys], [0, 1, 2, 3, 4])
+        self.assertEqual(verkle.get_tree_key(bytes(range(20)), 0, 2), header.nonce())
+
+    def test_invalid_address(self):
+        with self.assertRaises(ValueError):
+            verkle.Header(bytes(31))
+
+
+class TestCommitter(unittest.TestCase):
+    def test_update_commitment(self):
+        one = to_trie_value(1)
+        commitment = verkle.commit_to_scalars(one)
+        updated = verkle.update_commitment(commitment, 1, bytes(32), one)
+        self.assertEqual(updated, verkle.commit_to_scalars(one + one))
+        self.assertEqual(updated, verkle.add_commitment(commitment, verkle.commit_to_scalars(bytes(32) + one)))
+
+    def test_serialization(self):
+        commitment = verkle.commit_to_scalars(to_trie_value(5))
+        serialized = verkle.serialize_commitment(commitment)
+        self.assertEqual(len(serialized), 32)
+        self.assertEqual(verkle.deserialize_commitment(serialized), commitment)
+        self.assertEqual(verkle.hash_commitments([commitment]), [verkle.hash_commitment(commitment)])
+
+    def test_invalid_scalars(self):
+        with self.assertRaises(ValueError):
+            verkle.commit_to_scalars(bytes(31))
+
+
+if __name__ == "__main__":
+    unittest.main()
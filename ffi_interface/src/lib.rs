@@ -5,17 +5,22 @@ pub mod serialization;
 // - deserialize_update_commitment_sparse should not be exported and is an abstraction leak
 pub use serialization::{
     deserialize_commitment, deserialize_update_commitment_sparse, serialize_commitment,
+    serialize_commitments,
 };
 
 use banderwagon::Element;
 use banderwagon::Fr;
 use ipa_multipoint::committer::{Committer, DefaultCommitter};
 use ipa_multipoint::crs::CRS;
+use ipa_multipoint::keys::{ProverKey, VerifierKey};
 use ipa_multipoint::lagrange_basis::PrecomputedWeights;
-use ipa_multipoint::multiproof::{MultiPoint, MultiPointProof, ProverQuery, VerifierQuery};
+use ipa_multipoint::multiproof::{
+    MultiPoint, MultiPointProof, ProverQuery, ProverQueryRef, VerifierQuery,
+};
 use ipa_multipoint::transcript::Transcript;
 pub use serialization::{fr_from_le_bytes, fr_to_le_bytes};
-use verkle_trie::proof::golang_proof_format::{bytes32_to_element, hex_to_bytes32, VerkleProofGo};
+use std::sync::Arc;
+use verkle_trie::proof::witness::ExecutionWitness;
 
 pub use crate::serialization::{
     deserialize_proof_query, deserialize_proof_query_uncompressed, deserialize_verifier_query,
@@ -31,10 +36,12 @@ pub use crate::serialization::{
 /// function calls. More so because the Context is relatively expensive to create
 /// compared to making a function call.
 pub struct Context {
-    pub crs: CRS,
-    pub committer: DefaultCommitter,
+    // This is shared, so that tries created from the context do not need to copy the tables
+    pub committer: Arc<DefaultCommitter>,
 
-    pub precomputed_weights: PrecomputedWeights,
+    pub prover_key: ProverKey,
+    // The fixed-base tables of the verifier key are only built when the first proof is verified
+    pub verifier_key: VerifierKey,
 }
 
 impl Default for Context {
@@ -47,12 +54,67 @@ impl Context {
     pub fn new() -> Self {
         let crs = CRS::default();
         let committer = DefaultCommitter::new(&crs.G);
+        Self::with_committer(crs, committer)
+    }
+
+    /// Creates a context whose committer tables are read from bytes produced by
+    /// `write_precomputed_tables`, instead of being recomputed.
+    ///
+    /// This is much faster than `Context::new` for short-lived processes.
+    /// Nothing else is precomputed here: the verifier key builds its tables
+    /// the first time a proof is verified.
+    pub fn from_precomputed_tables(bytes: &[u8]) -> Result<Self, Error> {
+        let crs = CRS::default();
+        let committer = DefaultCommitter::read_precomputation(bytes, &crs.G).map_err(|err| {
+            Error::InvalidPrecomputedTables {
+                reason: err.to_string(),
+            }
+        })?;
+        Ok(Self::with_committer(crs, committer))
+    }
+
+    /// Same as `from_precomputed_tables`, however the tables are read from a file
+    pub fn from_precomputed_tables_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let crs = CRS::default();
+        let committer =
+            DefaultCommitter::read_precomputation_file(path, &crs.G).map_err(|err| {
+                Error::InvalidPrecomputedTables {
+                    reason: err.to_string(),
+                }
+            })?;
+        Ok(Self::with_committer(crs, committer))
+    }
+
+    /// Serializes the committer tables, so that they can be loaded with `from_precomputed_tables`
+    pub fn write_precomputed_tables<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        self.committer
+            .write_precomputation(writer)
+            .map_err(|err| Error::InvalidPrecomputedTables {
+                reason: err.to_string(),
+            })
+    }
+
+    /// Same as `write_precomputed_tables`, however the tables are written to a file
+    pub fn write_precomputed_tables_file<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Error> {
+        self.committer
+            .write_precomputation_file(path)
+            .map_err(|err| Error::InvalidPrecomputedTables {
+                reason: err.to_string(),
+            })
+    }
+
+    fn with_committer(crs: CRS, committer: DefaultCommitter) -> Self {
         let precomputed_weights = PrecomputedWeights::new(256);
+        let prover_key = ProverKey::new(crs, precomputed_weights);
+        let verifier_key = prover_key.verifier_key();
 
         Self {
-            crs,
-            committer,
-            precomputed_weights,
+            committer: Arc::new(committer),
+            prover_key,
+            verifier_key,
         }
     }
 }
@@ -90,6 +152,9 @@ pub enum Error {
         bytes: Vec<u8>,
     },
     ProofVerificationFailed,
+    InvalidPrecomputedTables {
+        reason: String,
+    },
 }
 
 #[allow(deprecated)]
@@ -161,7 +226,7 @@ fn _commit_to_scalars(context: &Context, scalars: &[u8]) -> Result<Element, Erro
     if num_scalars > 256 {
         return Err(Error::TooManyScalars {
             got: num_scalars,
-            expected: context.crs.max_number_of_elements(),
+            expected: context.prover_key.crs.max_number_of_elements(),
         });
     }
 
@@ -208,13 +273,11 @@ pub fn update_commitment(
     let old_scalar = fr_from_le_bytes(&old_scalar_bytes)?;
     let new_scalar = fr_from_le_bytes(&new_scalar_bytes)?;
 
-    // w-v
-    let delta = new_scalar - old_scalar;
-
     // (w-v)G
-    let delta_commitment = context
-        .committer
-        .scalar_mul(delta, commitment_index as usize);
+    let delta_commitment =
+        context
+            .committer
+            .commit_delta(&[(commitment_index as usize, old_scalar, new_scalar)]);
 
     // vG + (w-v)G
     Ok((delta_commitment + old_commitment).to_bytes_uncompressed())
@@ -231,18 +294,16 @@ pub fn update_commitment_sparse(
 ) -> Result<CommitmentBytes, Error> {
     let old_commitment = Element::from_bytes_unchecked_uncompressed(old_commitment_bytes);
 
-    let mut delta_values: Vec<(Fr, usize)> = Vec::new();
+    let mut updates: Vec<(usize, Fr, Fr)> = Vec::with_capacity(commitment_index_vec.len());
 
-    // For each index in commitment_index, we compute the delta value.
     for index in 0..commitment_index_vec.len() {
-        let old_scalar = fr_from_le_bytes(&old_scalar_bytes_vec[index]).unwrap();
-        let new_scalar = fr_from_le_bytes(&new_scalar_bytes_vec[index]).unwrap();
+        let old_scalar = fr_from_le_bytes(&old_scalar_bytes_vec[index])?;
+        let new_scalar = fr_from_le_bytes(&new_scalar_bytes_vec[index])?;
 
-        let tuple = (new_scalar - old_scalar, commitment_index_vec[index]);
-        delta_values.push(tuple);
+        updates.push((commitment_index_vec[index], old_scalar, new_scalar));
     }
 
-    let delta_commitment = context.committer.commit_sparse(delta_values);
+    let delta_commitment = context.committer.commit_delta(&updates);
 
     Ok((delta_commitment + old_commitment).to_bytes_uncompressed())
 }
@@ -313,7 +374,7 @@ pub fn create_proof(context: &Context, input: Vec<u8>) -> Result<Vec<u8>, Error>
     let mut prover_queries: Vec<ProverQuery> = Vec::with_capacity(num_openings);
 
     for proof_bytes in proofs_bytes {
-        let prover_query = deserialize_proof_query(proof_bytes);
+        let prover_query = deserialize_proof_query(proof_bytes)?;
         prover_queries.push(prover_query);
     }
 
@@ -322,13 +383,8 @@ pub fn create_proof(context: &Context, input: Vec<u8>) -> Result<Vec<u8>, Error>
 
     let mut transcript = Transcript::new(b"verkle");
 
-    let proof = MultiPoint::open(
-        // TODO: This should not need to clone the CRS, but instead take a reference
-        context.crs.clone(),
-        &context.precomputed_weights,
-        &mut transcript,
-        prover_queries,
-    );
+    let prover_queries: Vec<_> = prover_queries.iter().map(ProverQueryRef::from).collect();
+    let proof = MultiPoint::open_with_key(&context.prover_key, &mut transcript, &prover_queries);
     Ok(proof.to_bytes().expect("cannot serialize proof"))
 }
 
@@ -370,18 +426,13 @@ pub fn verify_proof(context: &Context, input: Vec<u8>) -> Result<(), Error> {
     let mut verifier_queries: Vec<VerifierQuery> = Vec::with_capacity(num_openings);
 
     for verifier_query_bytes in chunked_verifier_queries {
-        let verifier_query = deserialize_verifier_query(verifier_query_bytes);
+        let verifier_query = deserialize_verifier_query(verifier_query_bytes)?;
         verifier_queries.push(verifier_query);
     }
 
     let mut transcript = Transcript::new(b"verkle");
 
-    if proof.check(
-        &context.crs,
-        &context.precomputed_weights,
-        &verifier_queries,
-        &mut transcript,
-    ) {
+    if proof.check_with_key(&context.verifier_key, &verifier_queries, &mut transcript) {
         Ok(())
     } else {
         Err(Error::ProofVerificationFailed)
@@ -395,14 +446,19 @@ pub fn verify_proof(context: &Context, input: Vec<u8>) -> Result<(), Error> {
 ///
 /// For an example of the format, see: https://github.com/ethereumjs/ethereumjs-monorepo/blob/master/packages/statemanager/test/testdata/verkleKaustinenBlock.json#L1-L2626
 pub fn verify_execution_witness(root: &str, execution_witness_json_str: &str) -> bool {
-    let (verkle_proof, keys_values) = match VerkleProofGo::from_json_str(execution_witness_json_str)
-        .from_verkle_proof_go_to_verkle_proof()
-    {
-        Some((verkle_proof, keys_values)) => (verkle_proof, keys_values),
-        None => return false,
-    };
-
-    let root = match bytes32_to_element(hex_to_bytes32(root)) {
+    let (verkle_proof, keys_values) =
+        match ExecutionWitness::from_json_str(execution_witness_json_str)
+            .and_then(|witness| witness.to_verkle_proof())
+        {
+            Ok((verkle_proof, keys_values)) => (verkle_proof, keys_values),
+            Err(_) => return false,
+        };
+
+    let root = hex::decode(root.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| Element::from_bytes(&bytes));
+    let root = match root {
         Some(root) => root,
         None => return false,
     };
@@ -411,6 +467,7 @@ pub fn verify_execution_witness(root: &str, execution_witness_json_str: &str) ->
     ok
 }
 
+#[allow(deprecated)]
 #[cfg(test)]
 mod tests {
     use crate::{verify_execution_witness, Context};
@@ -426,6 +483,22 @@ mod tests {
         assert!(result);
     }
 
+    #[test]
+    fn context_from_precomputed_tables() {
+        let context = Context::default();
+        let mut bytes = Vec::new();
+        context.write_precomputed_tables(&mut bytes).unwrap();
+
+        let loaded = Context::from_precomputed_tables(&bytes).unwrap();
+        let values: Vec<_> = (0..256u64).map(Fr::from).collect();
+        assert_eq!(
+            loaded.committer.commit_lagrange(&values),
+            context.committer.commit_lagrange(&values)
+        );
+
+        assert!(Context::from_precomputed_tables(&bytes[..bytes.len() / 2]).is_err());
+    }
+
     #[test]
     fn commitment_update() {
         let context = Context::default();
@@ -520,9 +593,6 @@ fn check_identity_constant() {
 #[cfg(test)]
 mod pedersen_hash_tests {
 
-    use banderwagon::Fr;
-    use ipa_multipoint::committer::Committer;
-
     use crate::{
         add_commitment, commit_to_scalars, get_tree_key, get_tree_key_hash, hash_commitment,
         Context,
@@ -599,7 +669,7 @@ mod pedersen_hash_tests {
         let committed_point = add_commitment(address_point_cache, tree_index_commit);
         let mut key = hash_commitment(committed_point);
         key[31] = 0; // modify the last byte since get_tree_key above uses sub_index=0
-        assert_eq!(hex::encode(&key), expected_hash)
+        assert_eq!(hex::encode(key), expected_hash)
     }
 
     #[test]
@@ -745,4 +815,28 @@ mod prover_verifier_test {
 
         assert!(verified);
     }
+
+    #[test]
+    fn test_create_proof_invalid_queries() {
+        let context = Context::new();
+
+        let mut create_prover_bytes = vec![0u8; 8257];
+        create_prover_bytes[..32].copy_from_slice(&[0xff; 32]);
+        let result = super::create_proof(&context, create_prover_bytes.clone());
+        assert!(matches!(
+            result,
+            Err(super::Error::CouldNotDeserializeCommitment { .. })
+        ));
+
+        let commitment = context
+            .committer
+            .commit_lagrange(&[banderwagon::Fr::from(1u128)]);
+        create_prover_bytes[..32].copy_from_slice(&commitment.to_bytes());
+        create_prover_bytes[32..64].copy_from_slice(&[0xff; 32]);
+        let result = super::create_proof(&context, create_prover_bytes);
+        assert!(matches!(
+            result,
+            Err(super::Error::FailedToDeserializeScalar { .. })
+        ));
+    }
 }
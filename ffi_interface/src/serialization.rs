@@ -69,6 +69,16 @@ pub fn deserialize_update_commitment_sparse(
 pub fn serialize_commitment(commitment: CommitmentBytes) -> [u8; 32] {
     Element::from_bytes_unchecked_uncompressed(commitment).to_bytes()
 }
+/// Serializes a list of commitments to byte arrays
+///
+/// Note: This is faster than calling `serialize_commitment` for each commitment.
+pub fn serialize_commitments(commitments: &[CommitmentBytes]) -> Vec<[u8; 32]> {
+    let elements: Vec<_> = commitments
+        .iter()
+        .map(|commitment| Element::from_bytes_unchecked_uncompressed(*commitment))
+        .collect();
+    Element::batch_to_bytes(&elements)
+}
 /// Deserialize a serialized commitment
 ///
 /// Note: This is used so that we can deserialize the root node.
@@ -81,16 +91,15 @@ pub fn deserialize_commitment(serialized_commitment: [u8; 32]) -> Result<Commitm
     Ok(element.to_bytes_uncompressed())
 }
 
-#[must_use]
-pub fn deserialize_proof_query(bytes: &[u8]) -> ProverQuery {
+pub fn deserialize_proof_query(bytes: &[u8]) -> Result<ProverQuery, Error> {
     // Commitment
-    let (commitment, mut bytes) = take_group_element(bytes);
+    let (commitment, mut bytes) = take_group_element(bytes)?;
 
     // f_x is a polynomial of degree 255, so we have 256 Fr elements
     const NUMBER_OF_EVALUATIONS: usize = 256;
     let mut collect_lagrange_basis: Vec<Fr> = Vec::with_capacity(NUMBER_OF_EVALUATIONS);
     for _ in 0..NUMBER_OF_EVALUATIONS {
-        let (scalar, offsetted_bytes) = take_scalar(bytes);
+        let (scalar, offsetted_bytes) = take_scalar(bytes)?;
         collect_lagrange_basis.push(scalar);
         bytes = offsetted_bytes;
     }
@@ -99,20 +108,19 @@ pub fn deserialize_proof_query(bytes: &[u8]) -> ProverQuery {
     let (z_i, bytes) = take_byte(bytes);
 
     // The evaluation is a single scalar
-    let (y_i, bytes) = take_scalar(bytes);
+    let (y_i, bytes) = take_scalar(bytes)?;
 
     assert!(bytes.is_empty(), "we should have consumed all the bytes");
 
-    ProverQuery {
+    Ok(ProverQuery {
         commitment,
         poly: LagrangeBasis::new(collect_lagrange_basis),
         point: z_i,
         result: y_i,
-    }
+    })
 }
 
-#[must_use]
-pub fn deserialize_proof_query_uncompressed(bytes: &[u8]) -> ProverQuery {
+pub fn deserialize_proof_query_uncompressed(bytes: &[u8]) -> Result<ProverQuery, Error> {
     // Commitment
     let (commitment, mut bytes) = take_uncompressed_group_element(bytes);
 
@@ -120,7 +128,7 @@ pub fn deserialize_proof_query_uncompressed(bytes: &[u8]) -> ProverQuery {
     const NUMBER_OF_EVALUATIONS: usize = 256;
     let mut collect_lagrange_basis: Vec<Fr> = Vec::with_capacity(NUMBER_OF_EVALUATIONS);
     for _ in 0..NUMBER_OF_EVALUATIONS {
-        let (scalar, offsetted_bytes) = take_scalar(bytes);
+        let (scalar, offsetted_bytes) = take_scalar(bytes)?;
         collect_lagrange_basis.push(scalar);
         bytes = offsetted_bytes;
     }
@@ -129,40 +137,38 @@ pub fn deserialize_proof_query_uncompressed(bytes: &[u8]) -> ProverQuery {
     let (z_i, bytes) = take_byte(bytes);
 
     // The evaluation is a single scalar
-    let (y_i, bytes) = take_scalar(bytes);
+    let (y_i, bytes) = take_scalar(bytes)?;
 
     assert!(bytes.is_empty(), "we should have consumed all the bytes");
 
-    ProverQuery {
+    Ok(ProverQuery {
         commitment,
         poly: LagrangeBasis::new(collect_lagrange_basis),
         point: z_i,
         result: y_i,
-    }
+    })
 }
 
-#[must_use]
-pub fn deserialize_verifier_query(bytes: &[u8]) -> VerifierQuery {
+pub fn deserialize_verifier_query(bytes: &[u8]) -> Result<VerifierQuery, Error> {
     // Commitment
-    let (commitment, bytes) = take_group_element(bytes);
+    let (commitment, bytes) = take_group_element(bytes)?;
 
     // The input point is a single byte
     let (z_i, bytes) = take_byte(bytes);
 
     // The evaluation is a single scalar
-    let (y_i, bytes) = take_scalar(bytes);
+    let (y_i, bytes) = take_scalar(bytes)?;
 
     assert!(bytes.is_empty(), "we should have consumed all the bytes");
 
-    VerifierQuery {
+    Ok(VerifierQuery {
         commitment,
         point: Fr::from(z_i as u128),
         result: y_i,
-    }
+    })
 }
 
-#[must_use]
-pub fn deserialize_verifier_query_uncompressed(bytes: &[u8]) -> VerifierQuery {
+pub fn deserialize_verifier_query_uncompressed(bytes: &[u8]) -> Result<VerifierQuery, Error> {
     // Commitment
     let (commitment, bytes) = take_uncompressed_group_element(bytes);
 
@@ -170,15 +176,15 @@ pub fn deserialize_verifier_query_uncompressed(bytes: &[u8]) -> VerifierQuery {
     let (z_i, bytes) = take_byte(bytes);
 
     // The evaluation is a single scalar
-    let (y_i, bytes) = take_scalar(bytes);
+    let (y_i, bytes) = take_scalar(bytes)?;
 
     assert!(bytes.is_empty(), "we should have consumed all the bytes");
 
-    VerifierQuery {
+    Ok(VerifierQuery {
         commitment,
         point: Fr::from(z_i as u128),
         result: y_i,
-    }
+    })
 }
 
 #[must_use]
@@ -192,11 +198,13 @@ pub(crate) fn take_uncompressed_group_element(bytes: &[u8]) -> (Element, &[u8])
     (element, &bytes[64..])
 }
 
-#[must_use]
-pub(crate) fn take_group_element(bytes: &[u8]) -> (Element, &[u8]) {
-    let element = Element::from_bytes(&bytes[0..32]).expect("could not deserialize element");
+pub(crate) fn take_group_element(bytes: &[u8]) -> Result<(Element, &[u8]), Error> {
+    let element =
+        Element::from_bytes(&bytes[0..32]).ok_or_else(|| Error::CouldNotDeserializeCommitment {
+            bytes: bytes[0..32].to_vec(),
+        })?;
     // Increment the slice by 32 bytes
-    (element, &bytes[32..])
+    Ok((element, &bytes[32..]))
 }
 
 #[must_use]
@@ -205,11 +213,10 @@ pub(crate) fn take_byte(bytes: &[u8]) -> (usize, &[u8]) {
     // Increment the slice by 32 bytes
     (z_i, &bytes[1..])
 }
-#[must_use]
-pub(crate) fn take_scalar(bytes: &[u8]) -> (Fr, &[u8]) {
-    let y_i = fr_from_le_bytes(&bytes[0..32]).expect("could not deserialize y_i");
+pub(crate) fn take_scalar(bytes: &[u8]) -> Result<(Fr, &[u8]), Error> {
+    let y_i = fr_from_le_bytes(&bytes[0..32])?;
     // Increment the slice by 32 bytes
-    (y_i, &bytes[32..])
+    Ok((y_i, &bytes[32..]))
 }
 
 pub fn fr_to_le_bytes(fr: banderwagon::Fr) -> [u8; 32] {
@@ -309,4 +316,17 @@ mod tests {
 
         assert_eq!(gen, got_commitment);
     }
+
+    #[test]
+    fn serialize_commitments_matches_single() {
+        let commitments: Vec<_> = (0..5u64)
+            .map(|i| (Element::prime_subgroup_generator() * Fr::from(i)).to_bytes_uncompressed())
+            .collect();
+
+        let expected: Vec<_> = commitments
+            .iter()
+            .map(|commitment| serialize_commitment(*commitment))
+            .collect();
+        assert_eq!(super::serialize_commitments(&commitments), expected);
+    }
 }
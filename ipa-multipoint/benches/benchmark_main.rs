@@ -6,4 +6,5 @@ criterion_main! {
     benchmarks::ipa_verify::benches,
     benchmarks::multipoint_verify::benches,
     benchmarks::multipoint_prove::benches,
+    benchmarks::committer::benches,
 }
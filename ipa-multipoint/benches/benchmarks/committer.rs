@@ -0,0 +1,79 @@
+use ark_std::rand::SeedableRng;
+use ark_std::UniformRand;
+use banderwagon::Fr;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ipa_multipoint::committer::layout::{CommitterBuilder, TableKind, TableLayout};
+use ipa_multipoint::committer::Committer;
+use ipa_multipoint::crs::CRS;
+use rand_chacha::ChaCha20Rng;
+
+// Compares the table layouts that `CommitterBuilder` chooses between
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let n = 256;
+    let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+    let values: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+
+    let crs = CRS::new(n, "eth_verkle_oct_2021".as_bytes());
+
+    let layouts = [
+        ("default", CommitterBuilder::new(&crs.G).chosen_layout()),
+        (
+            "key hashing signed w12",
+            TableLayout::key_hashing(
+                n,
+                TableKind::WindowSigned { window_size: 12 },
+                TableKind::Wnaf { window_size: 12 },
+            ),
+        ),
+        (
+            "wnaf w12",
+            TableLayout::uniform(n, TableKind::Wnaf { window_size: 12 }),
+        ),
+        (
+            "wnaf w8",
+            TableLayout::uniform(n, TableKind::Wnaf { window_size: 8 }),
+        ),
+        (
+            "signed w8",
+            TableLayout::uniform(n, TableKind::WindowSigned { window_size: 8 }),
+        ),
+        (
+            "signed w6",
+            TableLayout::uniform(n, TableKind::WindowSigned { window_size: 6 }),
+        ),
+        (
+            "signed w4",
+            TableLayout::uniform(n, TableKind::WindowSigned { window_size: 4 }),
+        ),
+        ("points", TableLayout::uniform(n, TableKind::Points)),
+    ];
+
+    let mut group = c.benchmark_group("committer");
+    group.sample_size(20);
+    for (name, layout) in layouts {
+        group.bench_function(BenchmarkId::new("build", name), |b| {
+            b.iter(|| black_box(CommitterBuilder::new(&crs.G).layout(layout.clone()).build()))
+        });
+        let committer = CommitterBuilder::new(&crs.G).layout(layout).build();
+
+        group.bench_with_input(
+            BenchmarkId::new("commit (256)", name),
+            &values,
+            |b, values| b.iter(|| black_box(committer.commit_lagrange(values))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("commit (5)", name),
+            &values[..5],
+            |b, values| b.iter(|| black_box(committer.commit_lagrange(values))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("scalar mul", name),
+            &values[7],
+            |b, value| b.iter(|| black_box(committer.scalar_mul(*value, 7))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -1,3 +1,4 @@
+pub mod committer;
 pub mod ipa_prove;
 pub mod ipa_verify;
 pub mod multipoint_prove;
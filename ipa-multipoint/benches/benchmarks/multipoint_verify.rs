@@ -3,6 +3,7 @@ use banderwagon::Fr;
 use criterion::BenchmarkId;
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use ipa_multipoint::crs::CRS;
+use ipa_multipoint::keys::VerifierKey;
 use ipa_multipoint::lagrange_basis::*;
 use ipa_multipoint::multiproof::*;
 use ipa_multipoint::transcript::Transcript;
@@ -82,5 +83,64 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+pub fn criterion_benchmark_with_key(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multipoint - verify with key (256)");
+
+    use ark_std::test_rng;
+
+    let n = 256;
+    let crs = CRS::new(n, b"random seed");
+    let precomp = PrecomputedWeights::new(n);
+    let verifier_key = VerifierKey::new(crs.clone(), precomp.clone());
+    // The tables are built lazily, keep them out of the measurements
+    verifier_key.precompute_tables();
+
+    let mut rng = test_rng();
+    let poly = LagrangeBasis::new((0..n).map(|_| Fr::rand(&mut rng)).collect());
+    let poly_comm = crs.commit_lagrange_poly(&poly);
+
+    for num_polynomials in [1, 1_000, 16_000] {
+        let prover_queries: Vec<_> = (0..num_polynomials)
+            .map(|_| ProverQuery {
+                commitment: poly_comm,
+                poly: poly.clone(),
+                point: 1,
+                result: poly.evaluate_in_domain(1),
+            })
+            .collect();
+
+        let mut transcript = Transcript::new(b"foo");
+        let multiproof = MultiPoint::open(
+            crs.clone(),
+            &precomp,
+            &mut transcript,
+            prover_queries.clone(),
+        );
+
+        let verifier_queries: Vec<VerifierQuery> =
+            prover_queries.into_iter().map(Into::into).collect();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_polynomials),
+            &num_polynomials,
+            |b, _| {
+                b.iter_batched(
+                    || Transcript::new(b"foo"),
+                    |mut transcript| {
+                        black_box(multiproof.check_with_key(
+                            &verifier_key,
+                            &verifier_queries,
+                            &mut transcript,
+                        ))
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, criterion_benchmark_with_key);
 criterion_main!(benches);
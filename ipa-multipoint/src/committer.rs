@@ -1,4 +1,12 @@
+pub mod layout;
+
+use crate::{IOError, IOErrorKind, IOResult};
 use banderwagon::{msm::MSMPrecompWnaf, msm_windowed_sign::MSMPrecompWindowSigned, Element, Fr};
+use layout::{CommitterBuilder, TableKind, TableLayout, TableRange};
+use sha2::{Digest, Sha256};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
 
 // This is the functionality that commits to the branch nodes and computes the delta optimization
 // For consistency with the Pcs, ensure that this component uses the same CRS as the Pcs
@@ -10,7 +18,6 @@ pub trait Committer {
     // compute value * G for a specific generator in the SRS
     fn scalar_mul(&self, value: Fr, lagrange_index: usize) -> Element;
 
-    // TODO: For large vectors, we could probably do this in parallel
     fn commit_sparse(&self, val_indices: Vec<(Fr, usize)>) -> Element {
         let mut result = Element::zero();
 
@@ -20,51 +27,597 @@ pub trait Committer {
 
         result
     }
+
+    // Computes the change in a commitment, when the value at each index changes from old to new.
+    // Each update is given as (index, old value, new value), and an index may appear more than once.
+    fn commit_delta(&self, updates: &[(usize, Fr, Fr)]) -> Element {
+        // A single update does not need to allocate
+        if let [(index, old, new)] = updates {
+            return self.scalar_mul(*new - old, *index);
+        }
+        self.commit_sparse(
+            updates
+                .iter()
+                .filter(|(_, old, new)| old != new)
+                .map(|(index, old, new)| (*new - old, *index))
+                .collect(),
+        )
+    }
+}
+
+// Allows a committer to be borrowed by methods that take one by value,
+// since the precomputed tables are expensive to clone
+impl<C: Committer + ?Sized> Committer for &C {
+    fn commit_lagrange(&self, evaluations: &[Fr]) -> Element {
+        (**self).commit_lagrange(evaluations)
+    }
+
+    fn scalar_mul(&self, value: Fr, lagrange_index: usize) -> Element {
+        (**self).scalar_mul(value, lagrange_index)
+    }
+
+    fn commit_sparse(&self, val_indices: Vec<(Fr, usize)>) -> Element {
+        (**self).commit_sparse(val_indices)
+    }
+
+    fn commit_delta(&self, updates: &[(usize, Fr, Fr)]) -> Element {
+        (**self).commit_delta(updates)
+    }
+}
+
+// Allows the same precomputed tables to be shared by values that need to own a committer,
+// such as tries which may outlive the context they were created from
+impl<C: Committer + ?Sized> Committer for Arc<C> {
+    fn commit_lagrange(&self, evaluations: &[Fr]) -> Element {
+        (**self).commit_lagrange(evaluations)
+    }
+
+    fn scalar_mul(&self, value: Fr, lagrange_index: usize) -> Element {
+        (**self).scalar_mul(value, lagrange_index)
+    }
+
+    fn commit_sparse(&self, val_indices: Vec<(Fr, usize)>) -> Element {
+        (**self).commit_sparse(val_indices)
+    }
+
+    fn commit_delta(&self, updates: &[(usize, Fr, Fr)]) -> Element {
+        (**self).commit_delta(updates)
+    }
 }
 
+// Commits using precomputed tables, where each range of generators can use a different kind of table.
+// See `CommitterBuilder` for how the tables are chosen.
 #[derive(Clone, Debug)]
 pub struct DefaultCommitter {
-    precomp_first_five: MSMPrecompWindowSigned,
-    precomp: MSMPrecompWnaf,
+    // Sorted by `start`, and every generator is covered by exactly one table
+    tables: Vec<GeneratorTable>,
+}
+
+#[derive(Clone, Debug)]
+struct GeneratorTable {
+    // The index of the first generator that this table covers
+    start: usize,
+    table: PrecompTable,
+}
+
+#[derive(Clone, Debug)]
+enum PrecompTable {
+    WindowSigned(MSMPrecompWindowSigned),
+    Wnaf(MSMPrecompWnaf),
+    Points(Vec<Element>),
+}
+
+impl PrecompTable {
+    fn num_bases(&self) -> usize {
+        match self {
+            PrecompTable::WindowSigned(table) => table.num_bases(),
+            PrecompTable::Wnaf(table) => table.num_bases(),
+            PrecompTable::Points(points) => points.len(),
+        }
+    }
+
+    fn base(&self, index: usize) -> Element {
+        match self {
+            PrecompTable::WindowSigned(table) => table.base(index),
+            PrecompTable::Wnaf(table) => table.base(index),
+            PrecompTable::Points(points) => points[index],
+        }
+    }
+
+    fn kind(&self) -> TableKind {
+        match self {
+            PrecompTable::WindowSigned(table) => TableKind::WindowSigned {
+                window_size: table.window_size(),
+            },
+            PrecompTable::Wnaf(table) => TableKind::Wnaf {
+                window_size: table.window_size(),
+            },
+            PrecompTable::Points(_) => TableKind::Points,
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        match self {
+            PrecompTable::WindowSigned(table) => table.memory_usage(),
+            PrecompTable::Wnaf(table) => table.memory_usage(),
+            PrecompTable::Points(points) => std::mem::size_of_val(points.as_slice()),
+        }
+    }
+
+    fn mul(&self, scalars: &[Fr]) -> Element {
+        // Preliminary benchmarks indicate that the parallel version is faster
+        // for vectors of length 64 or more
+        let parallel = scalars.len() >= 64;
+        match self {
+            PrecompTable::WindowSigned(table) if parallel => table.mul_par(scalars),
+            PrecompTable::WindowSigned(table) => table.mul(scalars),
+            PrecompTable::Wnaf(table) if parallel => table.mul_par(scalars),
+            PrecompTable::Wnaf(table) => table.mul(scalars),
+            PrecompTable::Points(points) => {
+                banderwagon::multi_scalar_mul(&points[..scalars.len()], scalars)
+            }
+        }
+    }
+
+    fn mul_index(&self, scalar: Fr, index: usize) -> Element {
+        match self {
+            PrecompTable::WindowSigned(table) => table.mul_index(scalar, index),
+            PrecompTable::Wnaf(table) => table.mul_index(scalar, index),
+            PrecompTable::Points(points) => points[index] * scalar,
+        }
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> IOResult<()> {
+        match self {
+            PrecompTable::WindowSigned(table) => {
+                writer.write_all(&[TABLE_TAG_WINDOW_SIGNED])?;
+                table.write(writer)
+            }
+            PrecompTable::Wnaf(table) => {
+                writer.write_all(&[TABLE_TAG_WNAF])?;
+                table.write(writer)
+            }
+            PrecompTable::Points(points) => {
+                writer.write_all(&[TABLE_TAG_POINTS])?;
+                writer.write_all(&(points.len() as u64).to_le_bytes())?;
+                for point in points {
+                    writer.write_all(&point.to_bytes_uncompressed())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // `max_bases` bounds the number of points that we allocate for a `Points` table
+    fn read_unchecked<R: Read>(mut reader: R, max_bases: usize) -> IOResult<Self> {
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            TABLE_TAG_WINDOW_SIGNED => Ok(PrecompTable::WindowSigned(
+                MSMPrecompWindowSigned::read_unchecked(reader)?,
+            )),
+            TABLE_TAG_WNAF => Ok(PrecompTable::Wnaf(MSMPrecompWnaf::read_unchecked(reader)?)),
+            TABLE_TAG_POINTS => {
+                let num_bases = read_u64(&mut reader)?;
+                if num_bases > max_bases as u64 {
+                    return Err(invalid_data("too many points in the precomputed tables"));
+                }
+                let mut points = Vec::with_capacity(num_bases as usize);
+                for _ in 0..num_bases {
+                    let mut bytes = [0u8; 64];
+                    reader.read_exact(&mut bytes)?;
+                    points.push(Element::from_bytes_unchecked_uncompressed(bytes));
+                }
+                Ok(PrecompTable::Points(points))
+            }
+            tag => Err(invalid_data(format!("unknown table tag {}", tag))),
+        }
+    }
 }
 
 impl DefaultCommitter {
+    // Uses the default layout, which favours computing storage keys.
+    // Use `CommitterBuilder` to pick a layout for a different workload or memory budget.
     pub fn new(points: &[Element]) -> Self {
-        // Take the first five elements and use a more aggressive optimization strategy
-        // since they are used for computing storage keys.
+        CommitterBuilder::new(points).build()
+    }
 
-        let (points_five, _) = points.split_at(5);
-        let precomp_first_five = MSMPrecompWindowSigned::new(points_five, 16);
-        let precomp = MSMPrecompWnaf::new(points, 12);
+    // Returns the number of bytes used by the precomputed tables
+    pub fn memory_usage(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|table| table.table.memory_usage())
+            .sum()
+    }
 
-        Self {
-            precomp,
-            precomp_first_five,
+    // Returns the layout of the precomputed tables
+    pub fn layout(&self) -> TableLayout {
+        TableLayout {
+            ranges: self
+                .tables
+                .iter()
+                .map(|table| TableRange {
+                    num_generators: table.table.num_bases(),
+                    kind: table.table.kind(),
+                })
+                .collect(),
         }
     }
+
+    fn num_generators(&self) -> usize {
+        self.tables
+            .last()
+            .map(|table| table.start + table.table.num_bases())
+            .unwrap_or(0)
+    }
+
+    fn base(&self, index: usize) -> Element {
+        let (table, index) = self.find_table(index);
+        table.base(index)
+    }
+
+    // Returns the table that covers the generator at `index`, along with the index into that table
+    fn find_table(&self, index: usize) -> (&PrecompTable, usize) {
+        let table = &self.tables[self.find_table_position(index)];
+        (&table.table, index - table.start)
+    }
+
+    fn find_table_position(&self, index: usize) -> usize {
+        self.tables
+            .partition_point(|table| table.start + table.table.num_bases() <= index)
+    }
+}
+
+// The number of indices from which `commit_sparse` splits the work between threads
+const PARALLEL_SPARSE_THRESHOLD: usize = 16;
+
+// Identifies a file of precomputed committer tables, see `DefaultCommitter::write_precomputation`
+const PRECOMPUTATION_MAGIC: &[u8; 8] = b"VKCMTTBL";
+// This should be bumped whenever the layout of the tables changes
+const PRECOMPUTATION_VERSION: u32 = 2;
+
+// Identifies the kind of each table in a file of precomputed tables
+const TABLE_TAG_WINDOW_SIGNED: u8 = 0;
+const TABLE_TAG_WNAF: u8 = 1;
+const TABLE_TAG_POINTS: u8 = 2;
+
+impl DefaultCommitter {
+    // Serializes the precomputed tables, so that later committers can be created
+    // with `read_precomputation` instead of recomputing them.
+    //
+    // The output starts with a digest of the CRS that the tables were computed for,
+    // and ends with a checksum of everything that came before it.
+    pub fn write_precomputation<W: Write>(&self, writer: W) -> IOResult<()> {
+        let bases: Vec<_> = (0..self.num_generators())
+            .map(|index| self.base(index))
+            .collect();
+
+        let mut writer = HashingWriter::new(writer);
+        writer.write_all(PRECOMPUTATION_MAGIC)?;
+        writer.write_all(&PRECOMPUTATION_VERSION.to_le_bytes())?;
+        writer.write_all(&crs_digest(&bases))?;
+        writer.write_all(&(self.tables.len() as u64).to_le_bytes())?;
+        for table in &self.tables {
+            table.table.write(&mut writer)?;
+        }
+
+        let (mut writer, checksum) = writer.finish();
+        writer.write_all(&checksum)?;
+        writer.flush()
+    }
+
+    // Reads tables which were written with `write_precomputation`.
+    //
+    // `points` are the CRS points that the tables are expected to be computed for.
+    // An error is returned if the tables were computed for different points, or if
+    // the bytes were corrupted.
+    pub fn read_precomputation<R: Read>(reader: R, points: &[Element]) -> IOResult<Self> {
+        let mut reader = HashingReader::new(reader);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != PRECOMPUTATION_MAGIC {
+            return Err(invalid_data("not a file of precomputed committer tables"));
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != PRECOMPUTATION_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported precomputation version {}, expected {}",
+                version, PRECOMPUTATION_VERSION
+            )));
+        }
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+        if digest != crs_digest(points) {
+            return Err(invalid_data("tables were precomputed for a different CRS"));
+        }
+
+        let num_tables = read_u64(&mut reader)?;
+        if num_tables > points.len() as u64 {
+            return Err(invalid_data("too many precomputed tables"));
+        }
+        let mut tables = Vec::with_capacity(num_tables as usize);
+        let mut start = 0;
+        for _ in 0..num_tables {
+            let table = PrecompTable::read_unchecked(&mut reader, points.len())?;
+            let num_bases = table.num_bases();
+            tables.push(GeneratorTable { start, table });
+            start += num_bases;
+        }
+
+        let (mut reader, expected_checksum) = reader.finish();
+        let mut checksum = [0u8; 32];
+        reader.read_exact(&mut checksum)?;
+        if checksum != expected_checksum {
+            return Err(invalid_data(
+                "checksum of the precomputed tables does not match",
+            ));
+        }
+
+        // The digest ties the file to the CRS, this ties the tables themselves to it
+        let tables_match = start == points.len()
+            && tables.iter().all(|table| {
+                table.table.num_bases() > 0
+                    && (0..table.table.num_bases())
+                        .all(|i| table.table.base(i) == points[table.start + i])
+            });
+        if !tables_match {
+            return Err(invalid_data("tables do not match the CRS"));
+        }
+
+        Ok(Self { tables })
+    }
+
+    pub fn write_precomputation_file<P: AsRef<Path>>(&self, path: P) -> IOResult<()> {
+        let file = std::fs::File::create(path)?;
+        self.write_precomputation(BufWriter::new(file))
+    }
+
+    pub fn read_precomputation_file<P: AsRef<Path>>(path: P, points: &[Element]) -> IOResult<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::read_precomputation(BufReader::new(file), points)
+    }
+}
+
+// Hashes the CRS points, so that tables can be matched to the CRS they were computed for
+fn crs_digest(points: &[Element]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((points.len() as u64).to_le_bytes());
+    for point in points {
+        hasher.update(point.to_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn read_u64<R: Read>(mut reader: R) -> IOResult<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> IOError {
+    IOError::new(IOErrorKind::InvalidData, error)
+}
+
+// Computes the checksum of everything that is written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> (W, [u8; 32]) {
+        (self.inner, self.hasher.finalize().into())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        self.inner.flush()
+    }
+}
+
+// Computes the checksum of everything that is read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> (R, [u8; 32]) {
+        (self.inner, self.hasher.finalize().into())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
 }
 
 impl Committer for DefaultCommitter {
     fn commit_lagrange(&self, evaluations: &[Fr]) -> Element {
-        if evaluations.len() <= 5 {
-            return self.precomp_first_five.mul(evaluations);
-        }
-        // Preliminary benchmarks indicate that the parallel version is faster
-        // for vectors of length 64 or more
-        if evaluations.len() >= 64 {
-            self.precomp.mul_par(evaluations)
-        } else {
-            self.precomp.mul(evaluations)
+        let mut result = Element::zero();
+        for table in &self.tables {
+            if table.start >= evaluations.len() {
+                break;
+            }
+            let end = evaluations.len().min(table.start + table.table.num_bases());
+            result += table.table.mul(&evaluations[table.start..end]);
         }
+        result
     }
 
     fn scalar_mul(&self, value: Fr, lagrange_index: usize) -> Element {
-        if lagrange_index < 5 {
-            let mut arr = [Fr::from(0u64); 5];
-            arr[lagrange_index] = value;
-            self.precomp_first_five.mul(&arr)
-        } else {
-            self.precomp.mul_index(value, lagrange_index)
+        let (table, index) = self.find_table(lagrange_index);
+        table.mul_index(value, index)
+    }
+
+    fn commit_sparse(&self, val_indices: Vec<(Fr, usize)>) -> Element {
+        use rayon::prelude::*;
+
+        // Spawning tasks is not worth it for the handful of indices a leaf update touches
+        if val_indices.len() < PARALLEL_SPARSE_THRESHOLD {
+            return val_indices
+                .into_iter()
+                .fold(Element::zero(), |result, (value, lagrange_index)| {
+                    result + self.scalar_mul(value, lagrange_index)
+                });
+        }
+
+        let mut values_by_table = vec![Vec::new(); self.tables.len()];
+        for (value, lagrange_index) in val_indices {
+            let position = self.find_table_position(lagrange_index);
+            let index = lagrange_index - self.tables[position].start;
+            values_by_table[position].push((value, index));
+        }
+
+        self.tables
+            .par_iter()
+            .zip(values_by_table)
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(table, values)| match &table.table {
+                // Without tables, a single multi-scalar multiplication is faster
+                // than a scalar multiplication for each index
+                PrecompTable::Points(points) => {
+                    let (scalars, bases): (Vec<_>, Vec<_>) = values
+                        .into_iter()
+                        .map(|(value, index)| (value, points[index]))
+                        .unzip();
+                    banderwagon::multi_scalar_mul(&bases, &scalars)
+                }
+                table => values
+                    .into_par_iter()
+                    .map(|(value, index)| table.mul_index(value, index))
+                    .reduce(Element::zero, |a, b| a + b),
+            })
+            .reduce(Element::zero, |a, b| a + b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Committer, CommitterBuilder, DefaultCommitter, TableKind, TableLayout, TableRange,
+    };
+    use crate::crs::CRS;
+    use banderwagon::Fr;
+
+    #[test]
+    fn precomputation_roundtrip() {
+        let crs = CRS::new(8, b"random seed");
+        let committer = DefaultCommitter::new(&crs.G);
+
+        let mut bytes = Vec::new();
+        committer.write_precomputation(&mut bytes).unwrap();
+
+        let got = DefaultCommitter::read_precomputation(&bytes[..], &crs.G).unwrap();
+        let values: Vec<_> = (0..8u64).map(|i| Fr::from(i * 31 + 1)).collect();
+        assert_eq!(
+            got.commit_lagrange(&values),
+            committer.commit_lagrange(&values)
+        );
+        assert_eq!(
+            got.scalar_mul(values[2], 2),
+            committer.scalar_mul(values[2], 2)
+        );
+
+        // Tables must be loaded with the CRS that they were computed for
+        let other_crs = CRS::new(8, b"other seed");
+        assert!(DefaultCommitter::read_precomputation(&bytes[..], &other_crs.G).is_err());
+
+        // Every kind of table should survive the roundtrip
+        let layout = TableLayout {
+            ranges: vec![
+                TableRange {
+                    num_generators: 2,
+                    kind: TableKind::Points,
+                },
+                TableRange {
+                    num_generators: 3,
+                    kind: TableKind::Wnaf { window_size: 3 },
+                },
+                TableRange {
+                    num_generators: 3,
+                    kind: TableKind::WindowSigned { window_size: 4 },
+                },
+            ],
+        };
+        let mixed = CommitterBuilder::new(&crs.G).layout(layout.clone()).build();
+        let mut mixed_bytes = Vec::new();
+        mixed.write_precomputation(&mut mixed_bytes).unwrap();
+        let got = DefaultCommitter::read_precomputation(&mixed_bytes[..], &crs.G).unwrap();
+        assert_eq!(got.layout(), layout);
+        assert_eq!(
+            got.commit_lagrange(&values),
+            committer.commit_lagrange(&values)
+        );
+
+        // Corrupted tables should be caught by the checksum
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        assert!(DefaultCommitter::read_precomputation(&bytes[..], &crs.G).is_err());
+    }
+
+    #[test]
+    fn commit_delta_matches_recommitting() {
+        let crs = CRS::new(64, b"random seed");
+        let old: Vec<_> = (0..64u64).map(|i| Fr::from(i * 13 + 7)).collect();
+        let mut new = old.clone();
+
+        // Enough updates to take the parallel path, with an index that is updated twice
+        let mut updates = Vec::new();
+        for index in (0..64).step_by(3) {
+            let value = Fr::from(index as u64 * 1000) - Fr::from(1u64);
+            updates.push((index, new[index], value));
+            new[index] = value;
+        }
+        updates.push((3, new[3], Fr::from(42u64)));
+        new[3] = Fr::from(42u64);
+        // Unchanged values should not affect the result
+        updates.push((5, old[5], old[5]));
+
+        for layout in [
+            TableLayout::key_hashing(
+                64,
+                TableKind::WindowSigned { window_size: 4 },
+                TableKind::Wnaf { window_size: 3 },
+            ),
+            TableLayout::uniform(64, TableKind::Points),
+        ] {
+            let committer = CommitterBuilder::new(&crs.G).layout(layout).build();
+            let expected = committer.commit_lagrange(&new) - committer.commit_lagrange(&old);
+
+            assert_eq!(committer.commit_delta(&updates), expected);
+            // A couple of updates take the sequential path
+            assert_eq!(
+                committer.commit_delta(&updates[..2]),
+                crs.G[0] * (updates[0].2 - updates[0].1) + crs.G[3] * (updates[1].2 - updates[1].1)
+            );
         }
     }
 }
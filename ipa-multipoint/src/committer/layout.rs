@@ -0,0 +1,352 @@
+// Describes which precomputed tables the `DefaultCommitter` uses for each range of generators,
+// and picks a layout from a memory budget and the expected workload.
+//
+// `benches/benchmarks/committer.rs` gave the following on a single core, with a CRS of
+// size 256. Scalar mul is a single scalar multiplication by the generator at index 7.
+//
+// | layout                 | memory | commit (256) | commit (5) | scalar mul | build  |
+// |------------------------|--------|--------------|------------|------------|--------|
+// | default                | ~234MB | 25.9ms       | 30.8µs     | 69.8µs     | 2.31s  |
+// | key hashing signed w12 | ~80MB  | 24.7ms       | 44.3µs     | 105µs      | 436ms  |
+// | wnaf w12               | ~67MB  | 31.3ms       | 501µs      | 98.3µs     | 296ms  |
+// | wnaf w8                | ~4.2MB | 29.9ms       | 477µs      | 104µs      | 15.9ms |
+// | signed w8              | ~67MB  | 4.32ms       | 65.4µs     | 12.0µs     | 1.13s  |
+// | signed w6              | ~23MB  | 5.74ms       | 99.6µs     | 13.1µs     | 706ms  |
+// | signed w4              | ~8.4MB | 7.54ms       | 120µs      | 19.5µs     | 762ms  |
+// | points                 | 32KB   | 7.97ms       | 335µs      | 51.5µs     | 1.08µs |
+//
+// The default is signed w16 for the five key hashing generators and wnaf w12 for the rest,
+// and "key hashing signed w12" is the same with a window size of 12 for the first five.
+//
+// Signed-window tables with a window size of 8 use the same memory as wnaf tables with a
+// window size of 12, but they are ~7x faster for full commitments and ~8x faster for a
+// single scalar multiplication, since they do not need any doublings. They take ~4x longer
+// to compute.
+//
+// The default layout is tuned for `Workload::KeyHashing`: it is the fastest layout for
+// committing to five values, which is how tree keys are computed, and the signed w16 tables
+// that most of its memory goes to (~168MB) make this ~30% faster than a window size of 12.
+// It is however ~6x slower than signed w8 for full commitments and scalar multiplications,
+// so tries, which mostly update and commit to nodes, should use `Workload::SparseUpdates`
+// or `Workload::FullCommits`, which pick signed-window tables. Memory constrained users
+// should set `memory_budget`, which falls back to the smaller layouts below.
+use super::{DefaultCommitter, GeneratorTable, PrecompTable};
+use banderwagon::{msm::MSMPrecompWnaf, msm_windowed_sign::MSMPrecompWindowSigned, Element};
+
+// The number of generators which are used to compute storage keys
+const KEY_HASHING_GENERATORS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    // Signed-window tables, which need no doublings, however their size grows
+    // exponentially with the window size
+    WindowSigned { window_size: usize },
+    // wNAF tables, which are smaller but need a doubling for every bit of the scalar
+    Wnaf { window_size: usize },
+    // No tables, only the generators are stored and a variable-base MSM is used
+    Points,
+}
+
+impl TableKind {
+    // Returns the number of bytes needed for a single generator
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            TableKind::WindowSigned { window_size } => {
+                MSMPrecompWindowSigned::table_size_in_bytes(*window_size)
+            }
+            TableKind::Wnaf { window_size } => MSMPrecompWnaf::table_size_in_bytes(*window_size),
+            TableKind::Points => std::mem::size_of::<Element>(),
+        }
+    }
+}
+
+// A consecutive range of generators, which all use the same kind of table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableRange {
+    pub num_generators: usize,
+    pub kind: TableKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    // The ranges cover the generators in order, starting from the first generator
+    pub ranges: Vec<TableRange>,
+}
+
+impl TableLayout {
+    // Uses the same kind of table for all of the generators
+    pub fn uniform(num_generators: usize, kind: TableKind) -> TableLayout {
+        TableLayout {
+            ranges: vec![TableRange {
+                num_generators,
+                kind,
+            }],
+        }
+    }
+
+    // Uses `key_hashing_kind` for the generators used to compute storage keys,
+    // and `rest_kind` for the rest of the generators
+    pub fn key_hashing(
+        num_generators: usize,
+        key_hashing_kind: TableKind,
+        rest_kind: TableKind,
+    ) -> TableLayout {
+        let prefix = num_generators.min(KEY_HASHING_GENERATORS);
+        let ranges = [
+            (prefix, key_hashing_kind),
+            (num_generators - prefix, rest_kind),
+        ]
+        .into_iter()
+        .filter(|(num_generators, _)| *num_generators > 0)
+        .map(|(num_generators, kind)| TableRange {
+            num_generators,
+            kind,
+        })
+        .collect();
+        TableLayout { ranges }
+    }
+
+    pub fn num_generators(&self) -> usize {
+        self.ranges.iter().map(|range| range.num_generators).sum()
+    }
+
+    // Returns the number of bytes that the tables for this layout will use
+    pub fn memory_usage(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| range.num_generators * range.kind.size_in_bytes())
+            .sum()
+    }
+}
+
+// The operations that the committer is expected to spend most of its time on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Workload {
+    // Committing to at most five values, which is how storage keys are computed.
+    // This is the workload for clients which mostly compute tree keys.
+    #[default]
+    KeyHashing,
+    // Updating a commitment at a few indices, which is how the trie updates its nodes
+    SparseUpdates,
+    // Committing to all of the values of a node at once
+    FullCommits,
+}
+
+impl Workload {
+    // The layouts for this workload, from the fastest to the one which uses the least memory
+    fn candidate_layouts(&self, n: usize) -> Vec<TableLayout> {
+        use TableKind::*;
+
+        let signed = |window_size| WindowSigned { window_size };
+        let wnaf = |window_size| Wnaf { window_size };
+        match self {
+            Workload::KeyHashing => vec![
+                TableLayout::key_hashing(n, signed(16), wnaf(12)),
+                TableLayout::key_hashing(n, signed(16), wnaf(8)),
+                TableLayout::key_hashing(n, signed(12), wnaf(8)),
+                TableLayout::key_hashing(n, signed(8), wnaf(8)),
+                TableLayout::key_hashing(n, signed(4), wnaf(4)),
+                TableLayout::uniform(n, Points),
+            ],
+            Workload::SparseUpdates => vec![
+                TableLayout::key_hashing(n, signed(16), signed(8)),
+                TableLayout::uniform(n, signed(8)),
+                TableLayout::uniform(n, signed(6)),
+                TableLayout::uniform(n, signed(4)),
+                TableLayout::uniform(n, Points),
+            ],
+            Workload::FullCommits => vec![
+                TableLayout::uniform(n, signed(10)),
+                TableLayout::uniform(n, signed(8)),
+                TableLayout::uniform(n, signed(6)),
+                TableLayout::uniform(n, signed(4)),
+                // A variable-base MSM is faster than any table that is smaller than this
+                TableLayout::uniform(n, Points),
+            ],
+        }
+    }
+}
+
+// Builds a `DefaultCommitter`, whose tables are chosen from the expected workload
+// and an optional memory budget.
+//
+// If no layout fits in the budget, then the layout which uses the least memory is chosen.
+pub struct CommitterBuilder<'a> {
+    points: &'a [Element],
+    workload: Workload,
+    memory_budget: Option<usize>,
+    layout: Option<TableLayout>,
+}
+
+impl<'a> CommitterBuilder<'a> {
+    pub fn new(points: &'a [Element]) -> Self {
+        CommitterBuilder {
+            points,
+            workload: Workload::default(),
+            memory_budget: None,
+            layout: None,
+        }
+    }
+
+    pub fn workload(mut self, workload: Workload) -> Self {
+        self.workload = workload;
+        self
+    }
+
+    // The maximum number of bytes that the tables should use
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    // Uses the given layout, instead of choosing one from the workload and the budget
+    pub fn layout(mut self, layout: TableLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    // Returns the layout that `build` will use
+    pub fn chosen_layout(&self) -> TableLayout {
+        if let Some(layout) = &self.layout {
+            return layout.clone();
+        }
+
+        let mut candidates = self.workload.candidate_layouts(self.points.len());
+        let budget = self.memory_budget.unwrap_or(usize::MAX);
+        match candidates
+            .iter()
+            .position(|layout| layout.memory_usage() <= budget)
+        {
+            Some(index) => candidates.swap_remove(index),
+            None => candidates
+                .into_iter()
+                .min_by_key(TableLayout::memory_usage)
+                .expect("every workload has at least one layout"),
+        }
+    }
+
+    pub fn build(self) -> DefaultCommitter {
+        let layout = self.chosen_layout();
+        assert_eq!(
+            layout.num_generators(),
+            self.points.len(),
+            "the layout must cover every generator"
+        );
+
+        let mut tables = Vec::with_capacity(layout.ranges.len());
+        let mut start = 0;
+        for range in layout.ranges {
+            let points = &self.points[start..start + range.num_generators];
+            let table = match range.kind {
+                TableKind::WindowSigned { window_size } => {
+                    PrecompTable::WindowSigned(MSMPrecompWindowSigned::new(points, window_size))
+                }
+                TableKind::Wnaf { window_size } => {
+                    PrecompTable::Wnaf(MSMPrecompWnaf::new(points, window_size))
+                }
+                TableKind::Points => PrecompTable::Points(points.to_vec()),
+            };
+            tables.push(GeneratorTable { start, table });
+            start += range.num_generators;
+        }
+
+        DefaultCommitter { tables }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommitterBuilder, TableKind, TableLayout, Workload};
+    use crate::committer::{Committer, DefaultCommitter};
+    use crate::crs::CRS;
+    use banderwagon::{multi_scalar_mul, Fr};
+
+    #[test]
+    fn layout_respects_memory_budget() {
+        let crs = CRS::new(256, b"random seed");
+
+        for workload in [
+            Workload::KeyHashing,
+            Workload::SparseUpdates,
+            Workload::FullCommits,
+        ] {
+            let unbounded = CommitterBuilder::new(&crs.G)
+                .workload(workload)
+                .chosen_layout();
+            let budget = unbounded.memory_usage() - 1;
+            let bounded = CommitterBuilder::new(&crs.G)
+                .workload(workload)
+                .memory_budget(budget)
+                .chosen_layout();
+            assert!(bounded.memory_usage() <= budget);
+            assert_eq!(bounded.num_generators(), 256);
+
+            // When nothing fits, we should use the smallest layout
+            let smallest = CommitterBuilder::new(&crs.G)
+                .workload(workload)
+                .memory_budget(0)
+                .chosen_layout();
+            assert!(smallest.memory_usage() <= bounded.memory_usage());
+        }
+    }
+
+    #[test]
+    fn layouts_agree_with_msm() {
+        let crs = CRS::new(16, b"random seed");
+        let values: Vec<_> = (0..16u64).map(|i| -Fr::from(i * 97 + 5)).collect();
+        let expected = multi_scalar_mul(&crs.G, &values);
+
+        let layouts = [
+            TableLayout::key_hashing(
+                16,
+                TableKind::WindowSigned { window_size: 4 },
+                TableKind::Wnaf { window_size: 3 },
+            ),
+            TableLayout::uniform(16, TableKind::Points),
+            TableLayout {
+                ranges: vec![
+                    super::TableRange {
+                        num_generators: 3,
+                        kind: TableKind::Wnaf { window_size: 2 },
+                    },
+                    super::TableRange {
+                        num_generators: 13,
+                        kind: TableKind::WindowSigned { window_size: 3 },
+                    },
+                ],
+            },
+        ];
+        for layout in layouts {
+            let committer = CommitterBuilder::new(&crs.G).layout(layout.clone()).build();
+            assert_eq!(committer.layout(), layout);
+            assert_eq!(committer.memory_usage(), layout.memory_usage());
+
+            assert_eq!(committer.commit_lagrange(&values), expected);
+            assert_eq!(
+                committer.commit_lagrange(&values[..4]),
+                multi_scalar_mul(&crs.G[..4], &values[..4])
+            );
+            for index in [0, 2, 3, 4, 5, 15] {
+                assert_eq!(
+                    committer.scalar_mul(values[index], index),
+                    crs.G[index] * values[index]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn default_layout() {
+        let crs = CRS::new(8, b"random seed");
+        let layout = CommitterBuilder::new(&crs.G).chosen_layout();
+        assert_eq!(
+            layout,
+            TableLayout::key_hashing(
+                8,
+                TableKind::WindowSigned { window_size: 16 },
+                TableKind::Wnaf { window_size: 12 }
+            )
+        );
+        assert_eq!(DefaultCommitter::new(&crs.G).layout(), layout);
+    }
+}
@@ -1,10 +1,11 @@
 #![allow(non_snake_case)]
 use crate::crs::CRS;
+use crate::keys::{ProverKey, VerifierKey};
 use crate::math_utils::inner_product;
 use crate::transcript::{Transcript, TranscriptProtocol};
 
 use banderwagon::{multi_scalar_mul, trait_defs::*, Element, Fr};
-use itertools::Itertools;
+use rayon::prelude::*;
 
 use crate::{IOError, IOErrorKind, IOResult};
 
@@ -35,25 +36,23 @@ impl IPAProof {
         let mut L_vec = Vec::with_capacity(num_points as usize);
         let mut R_vec = Vec::with_capacity(num_points as usize);
 
-        assert_eq!(((num_points * 2) + 1) * 32, bytes.len() as u32);
-        assert!(bytes.len() % 32 == 0);
+        if ((num_points as usize * 2) + 1) * 32 != bytes.len() {
+            return Err(IOError::from(IOErrorKind::InvalidData));
+        }
 
         // Chunk the byte slice into 32 bytes
         let mut chunks = bytes.chunks_exact(32);
 
-        for _ in 0..num_points {
-            let chunk = chunks.next().unwrap();
-            let point: Element =
-                Element::from_bytes(chunk).ok_or(IOError::from(IOErrorKind::InvalidData))?;
-            L_vec.push(point)
-        }
-
-        for _ in 0..num_points {
-            let chunk = chunks.next().unwrap();
-            let point: Element =
-                Element::from_bytes(chunk).ok_or(IOError::from(IOErrorKind::InvalidData))?;
-            R_vec.push(point)
-        }
+        // The L and R points are decompressed together, since it is cheaper to do so in a batch
+        let point_bytes: Vec<[u8; 32]> = chunks
+            .by_ref()
+            .take(2 * num_points as usize)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+        let mut points = Element::batch_from_bytes(&point_bytes)
+            .ok_or(IOError::from(IOErrorKind::InvalidData))?;
+        R_vec.extend(points.split_off(num_points as usize));
+        L_vec.extend(points);
 
         let last_32_bytes = chunks.next().unwrap();
 
@@ -103,12 +102,9 @@ impl IPAProof {
         // We do not serialize the length. We assume that the deserializer knows this.
         let mut bytes = Vec::with_capacity(self.serialized_size());
 
-        for L in &self.L_vec {
-            bytes.extend(L.to_bytes());
-        }
-
-        for R in &self.R_vec {
-            bytes.extend(R.to_bytes());
+        let points: Vec<_> = self.L_vec.iter().chain(&self.R_vec).copied().collect();
+        for point_bytes in Element::batch_to_bytes(&points) {
+            bytes.extend(point_bytes);
         }
 
         self.a
@@ -134,9 +130,66 @@ impl IPAProof {
     }
 }
 
+// The proof is serialized using its canonical encoding, see `to_bytes`.
+//
+// The polynomial degree is not serialized, so it is recovered from the number of points.
+#[cfg(feature = "serde")]
+impl serde::Serialize for IPAProof {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.to_bytes().map_err(serde::ser::Error::custom)?;
+        banderwagon::trait_impls::serde::serialize_bytes(&bytes, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IPAProof {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = banderwagon::trait_impls::serde::deserialize_bytes(deserializer)?;
+        let poly_degree = poly_degree_from_serialized_len(bytes.len())
+            .ok_or_else(|| serde::de::Error::custom("invalid proof length"))?;
+        IPAProof::from_bytes(&bytes, poly_degree).map_err(serde::de::Error::custom)
+    }
+}
+
+// Returns the polynomial degree for a serialized proof of the given length
+#[cfg(feature = "serde")]
+pub(crate) fn poly_degree_from_serialized_len(len: usize) -> Option<usize> {
+    // There are two points for every round, and then the final scalar
+    if len % 64 != 32 {
+        return None;
+    }
+    let num_rounds = u32::try_from(len / 64).ok()?;
+    1usize.checked_shl(num_rounds)
+}
+
 pub fn create(
     transcript: &mut Transcript,
-    mut crs: CRS,
+    crs: CRS,
+    a_vec: Vec<Fr>,
+    a_comm: Element,
+    b_vec: Vec<Fr>,
+    // This is the z in f(z)
+    input_point: Fr,
+) -> IPAProof {
+    prove(transcript, &crs, a_vec, a_comm, b_vec, input_point)
+}
+
+// Same as `create`, however the CRS is borrowed from the key instead of being moved in
+pub fn create_with_key(
+    transcript: &mut Transcript,
+    key: &ProverKey,
+    a_vec: Vec<Fr>,
+    a_comm: Element,
+    b_vec: Vec<Fr>,
+    // This is the z in f(z)
+    input_point: Fr,
+) -> IPAProof {
+    prove(transcript, &key.crs, a_vec, a_comm, b_vec, input_point)
+}
+
+pub(crate) fn prove(
+    transcript: &mut Transcript,
+    crs: &CRS,
     mut a_vec: Vec<Fr>,
     a_comm: Element,
     mut b_vec: Vec<Fr>,
@@ -147,7 +200,10 @@ pub fn create(
 
     let mut a = &mut a_vec[..];
     let mut b = &mut b_vec[..];
-    let mut G = &mut crs.G[..];
+    // The generators are folded out of place, so that the CRS can be borrowed.
+    // After the first round, this holds the folded generators.
+    let mut folded_G: Vec<Element>;
+    let mut G = &crs.G[..];
 
     let n = G.len();
 
@@ -176,18 +232,23 @@ pub fn create(
     for _k in 0..num_rounds {
         let (a_L, a_R) = halve(a);
         let (b_L, b_R) = halve(b);
-        let (G_L, G_R) = halve(G);
-
-        let z_L = inner_product(a_R, b_L);
-        let z_R = inner_product(a_L, b_R);
-
-        let L = slow_vartime_multiscalar_mul(
-            a_R.iter().chain(iter::once(&z_L)),
-            G_L.iter().chain(iter::once(&Q)),
-        );
-        let R = slow_vartime_multiscalar_mul(
-            a_L.iter().chain(iter::once(&z_R)),
-            G_R.iter().chain(iter::once(&Q)),
+        let (G_L, G_R) = G.split_at(G.len() / 2);
+
+        let (L, R) = rayon::join(
+            || {
+                let z_L = inner_product(a_R, b_L);
+                slow_vartime_multiscalar_mul(
+                    a_R.iter().chain(iter::once(&z_L)),
+                    G_L.iter().chain(iter::once(&Q)),
+                )
+            },
+            || {
+                let z_R = inner_product(a_L, b_R);
+                slow_vartime_multiscalar_mul(
+                    a_L.iter().chain(iter::once(&z_R)),
+                    G_R.iter().chain(iter::once(&Q)),
+                )
+            },
         );
 
         L_vec.push(L);
@@ -198,15 +259,22 @@ pub fn create(
 
         let x = transcript.challenge_scalar(b"x");
         let x_inv = x.inverse().unwrap();
-        for i in 0..a_L.len() {
-            a_L[i] += x * a_R[i];
-            b_L[i] += x_inv * b_R[i];
-            G_L[i] += G_R[i] * x_inv;
-        }
+
+        a_L.par_iter_mut()
+            .zip(a_R.par_iter())
+            .for_each(|(a_L, a_R)| *a_L += x * a_R);
+        b_L.par_iter_mut()
+            .zip(b_R.par_iter())
+            .for_each(|(b_L, b_R)| *b_L += x_inv * b_R);
+        folded_G = G_L
+            .par_iter()
+            .zip(G_R.par_iter())
+            .map(|(G_L, G_R)| *G_L + *G_R * x_inv)
+            .collect();
 
         a = a_L;
         b = b_L;
-        G = G_L;
+        G = &folded_G;
     }
 
     IPAProof {
@@ -300,6 +368,49 @@ impl IPAProof {
         input_point: Fr,
         output_point: Fr,
     ) -> bool {
+        match self.multiexp_terms(transcript, crs, b_vec, a_comm, input_point, output_point) {
+            Some(terms) => terms.evaluate(crs).is_zero(),
+            None => false,
+        }
+    }
+
+    // Same as `verify_multiexp`, however the part of the multi-exponentiation over the CRS
+    // uses the fixed-base tables in the key
+    pub fn verify_with_key(
+        &self,
+        transcript: &mut Transcript,
+        key: &VerifierKey,
+        b_vec: Vec<Fr>,
+        a_comm: Element,
+        input_point: Fr,
+        output_point: Fr,
+    ) -> bool {
+        match self.multiexp_terms(
+            transcript,
+            &key.crs,
+            b_vec,
+            a_comm,
+            input_point,
+            output_point,
+        ) {
+            Some(terms) => terms.evaluate_with_key(key).is_zero(),
+            None => false,
+        }
+    }
+
+    // Computes the scalars for the single multi-exponentiation that `verify_multiexp` checks
+    // against zero. The proof is valid if and only if the multi-exponentiation is zero.
+    //
+    // Returns None if the proof was not computed over a vector of size n.
+    pub(crate) fn multiexp_terms(
+        &self,
+        transcript: &mut Transcript,
+        crs: &CRS,
+        b_vec: Vec<Fr>,
+        a_comm: Element,
+        input_point: Fr,
+        output_point: Fr,
+    ) -> Option<MultiExpTerms> {
         transcript.domain_sep(b"ipa");
 
         let logn = self.L_vec.len();
@@ -307,7 +418,7 @@ impl IPAProof {
         // Check that the prover computed an inner proof
         // over a vector of size n
         if n != (1 << logn) {
-            return false;
+            return None;
         }
 
         // transcript.append_u64(b"n", n as u64);
@@ -326,43 +437,39 @@ impl IPAProof {
 
         // Generate the coefficients for the `G` vector and the `b` vector
         // {-g_i}{-b_i}
-        let mut g_i: Vec<Fr> = Vec::with_capacity(1 << logn);
-        let mut b_i: Vec<Fr> = Vec::with_capacity(1 << logn);
-
-        for index in 0..n {
-            let mut b = -Fr::one();
-            for (bit, x_inv) in to_bits(index, logn).zip_eq(&challenges_inv) {
-                if bit == 1 {
-                    b *= x_inv;
-                }
-            }
-            b_i.push(b);
-            g_i.push(self.a * b);
-        }
+        let b_i: Vec<Fr> = generator_scalars(&challenges_inv)
+            .into_iter()
+            .map(|b| -b)
+            .collect();
+        let g_i: Vec<Fr> = b_i.iter().map(|b| self.a * b).collect();
 
         let b_0 = inner_product(&b_vec, &b_i);
         let q_i = w * (output_point + self.a * b_0);
 
-        slow_vartime_multiscalar_mul(
-            challenges
-                .iter()
-                .chain(challenges_inv.iter())
-                .chain(iter::once(&Fr::one()))
-                .chain(iter::once(&q_i))
-                .chain(g_i.iter()),
-            self.L_vec
-                .iter()
-                .chain(self.R_vec.iter())
-                .chain(iter::once(&a_comm))
-                .chain(iter::once(&crs.Q))
-                // XXX: note that we can do a Halo style optimization here also
-                // but instead of being (m log(d)) it will be O(mn) which is still good
-                // because the verifier will be doing m*n field operations instead of m size n multi-exponentiations
-                // This is done by interpreting g_i as coefficients in monomial basis
-                // TODO: Optimise the majority of the time is spent on this vector, precompute
-                .chain(crs.G.iter()),
-        )
-        .is_zero()
+        let scalars = challenges
+            .into_iter()
+            .chain(challenges_inv)
+            .chain(iter::once(Fr::one()))
+            .collect();
+        let points = self
+            .L_vec
+            .iter()
+            .chain(self.R_vec.iter())
+            .chain(iter::once(&a_comm))
+            .copied()
+            .collect();
+
+        Some(MultiExpTerms {
+            scalars,
+            points,
+            q_scalar: q_i,
+            // XXX: note that we can do a Halo style optimization here also
+            // but instead of being (m log(d)) it will be O(mn) which is still good
+            // because the verifier will be doing m*n field operations instead of m size n multi-exponentiations
+            // This is done by interpreting g_i as coefficients in monomial basis
+            // TODO: Optimise the majority of the time is spent on this vector, precompute
+            g_scalars: g_i,
+        })
     }
     // It's only semi unrolled.
     // This is being committed incase someone goes through the git history
@@ -413,17 +520,7 @@ impl IPAProof {
         );
 
         // {g_i}
-        let mut g_i: Vec<Fr> = Vec::with_capacity(1 << logn);
-
-        for index in 0..n {
-            let mut g = Fr::one();
-            for (bit, x_inv) in to_bits(index, logn).zip_eq(&challenges_inv) {
-                if bit == 1 {
-                    g *= x_inv;
-                }
-            }
-            g_i.push(g);
-        }
+        let g_i = generator_scalars(&challenges_inv);
 
         let b_0 = inner_product(&b_Vec, &g_i);
         let G_0 = slow_vartime_multiscalar_mul(g_i.iter(), crs.G.iter()); // TODO: Optimise; the majority of the time is spent on this vector, precompute
@@ -434,8 +531,54 @@ impl IPAProof {
     }
 }
 
-fn to_bits(n: usize, bits_needed: usize) -> impl Iterator<Item = u8> {
-    (0..bits_needed).map(move |i| ((n >> i) & 1) as u8).rev()
+// The terms of the IPA verification equation.
+//
+// The points which are specific to a proof are kept separately from the CRS points,
+// so that the terms of many proofs can be merged into a single multi-exponentiation.
+pub(crate) struct MultiExpTerms {
+    // Scalars for the L, R and commitment points of the proof
+    pub(crate) scalars: Vec<Fr>,
+    pub(crate) points: Vec<Element>,
+    // Scalar for the `Q` point in the CRS
+    pub(crate) q_scalar: Fr,
+    // Scalars for the `G` points in the CRS
+    pub(crate) g_scalars: Vec<Fr>,
+}
+
+impl MultiExpTerms {
+    pub(crate) fn evaluate(&self, crs: &CRS) -> Element {
+        slow_vartime_multiscalar_mul(
+            self.scalars
+                .iter()
+                .chain(iter::once(&self.q_scalar))
+                .chain(self.g_scalars.iter()),
+            self.points
+                .iter()
+                .chain(iter::once(&crs.Q))
+                .chain(crs.G.iter()),
+        )
+    }
+
+    pub(crate) fn evaluate_with_key(&self, key: &VerifierKey) -> Element {
+        multi_scalar_mul(&self.points, &self.scalars) + key.mul_crs(&self.g_scalars, self.q_scalar)
+    }
+}
+
+// Computes the product of the inverse challenges which each generator is scaled by,
+// after all of the rounds of folding.
+//
+// The i'th scalar is the product of the x_inv's, whose corresponding bit in `i` is set,
+// where the first challenge corresponds to the most significant bit.
+fn generator_scalars(challenges_inv: &[Fr]) -> Vec<Fr> {
+    let mut scalars = Vec::with_capacity(1 << challenges_inv.len());
+    scalars.push(Fr::one());
+    for x_inv in challenges_inv {
+        scalars = scalars
+            .into_iter()
+            .flat_map(|scalar| [scalar, scalar * x_inv])
+            .collect();
+    }
+    scalars
 }
 
 pub fn slow_vartime_multiscalar_mul<'a>(
@@ -505,4 +648,58 @@ mod tests {
             output_point
         ));
     }
+
+    #[test]
+    fn verify_with_key_agrees_with_multiexp() {
+        use crate::keys::VerifierKey;
+        use crate::lagrange_basis::PrecomputedWeights;
+
+        let n = 16;
+        let crs = CRS::new(n, b"random seed");
+        let key = VerifierKey::new(crs.clone(), PrecomputedWeights::new(n));
+
+        let mut rng = ChaCha20Rng::from_seed([1u8; 32]);
+        let a: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let input_point = Fr::rand(&mut rng);
+
+        let b = powers_of(input_point, n);
+        let output_point = inner_product(&a, &b);
+        let P = slow_vartime_multiscalar_mul(a.iter(), crs.G.iter());
+
+        let mut prover_transcript = Transcript::new(b"ip_no_zk");
+        let proof = prove(&mut prover_transcript, &crs, a, P, b.clone(), input_point);
+
+        for (output_point, expected) in [(output_point, true), (output_point + Fr::one(), false)] {
+            let mut transcript = Transcript::new(b"ip_no_zk");
+            let multiexp = proof.verify_multiexp(
+                &mut transcript,
+                &crs,
+                b.clone(),
+                P,
+                input_point,
+                output_point,
+            );
+            let mut transcript = Transcript::new(b"ip_no_zk");
+            let semi_multiexp = proof.verify_semi_multiexp(
+                &mut transcript,
+                &crs,
+                b.clone(),
+                P,
+                input_point,
+                output_point,
+            );
+            let mut transcript = Transcript::new(b"ip_no_zk");
+            let with_key = proof.verify_with_key(
+                &mut transcript,
+                &key,
+                b.clone(),
+                P,
+                input_point,
+                output_point,
+            );
+            assert_eq!(multiexp, expected);
+            assert_eq!(semi_multiexp, expected);
+            assert_eq!(with_key, expected);
+        }
+    }
 }
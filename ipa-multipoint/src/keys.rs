@@ -0,0 +1,93 @@
+// Keys which bundle everything needed to create or verify proofs.
+//
+// They are built once, and then borrowed by every proof, so that the CRS
+// does not need to be cloned for each proof.
+use crate::crs::CRS;
+use crate::lagrange_basis::PrecomputedWeights;
+use banderwagon::{msm_windowed_sign::MSMPrecompWindowSigned, Element, Fr};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone)]
+pub struct ProverKey {
+    pub crs: CRS,
+    pub precomp: PrecomputedWeights,
+}
+
+impl ProverKey {
+    pub fn new(crs: CRS, precomp: PrecomputedWeights) -> ProverKey {
+        assert_eq!(
+            crs.n,
+            precomp.domain_size(),
+            "the crs and the precomputed weights must be over the same domain"
+        );
+        ProverKey { crs, precomp }
+    }
+
+    // Returns the key needed to verify the proofs created with this key
+    pub fn verifier_key(&self) -> VerifierKey {
+        VerifierKey::new(self.crs.clone(), self.precomp.clone())
+    }
+}
+
+// The window size used for the fixed-base tables of the verifier key.
+//
+// With a window size of 8, the tables for a CRS of size 256 take up roughly 67MB
+// and each scalar multiplication needs 32 point additions.
+const VERIFIER_WINDOW_SIZE: usize = 8;
+
+// The verifier key is deliberately not `Clone`, since the fixed-base tables are large.
+// Share it by reference instead.
+#[derive(Debug)]
+pub struct VerifierKey {
+    pub crs: CRS,
+    pub precomp: PrecomputedWeights,
+    // Fixed-base tables for the `G` vector of the CRS, with `Q` as the last point.
+    //
+    // These are only built the first time a proof is verified with this key,
+    // so that processes which never verify do not pay for them.
+    crs_tables: OnceLock<MSMPrecompWindowSigned>,
+}
+
+impl VerifierKey {
+    pub fn new(crs: CRS, precomp: PrecomputedWeights) -> VerifierKey {
+        assert_eq!(
+            crs.n,
+            precomp.domain_size(),
+            "the crs and the precomputed weights must be over the same domain"
+        );
+        VerifierKey {
+            crs,
+            precomp,
+            crs_tables: OnceLock::new(),
+        }
+    }
+
+    // Builds the fixed-base tables now, instead of when the first proof is verified
+    pub fn precompute_tables(&self) {
+        self.crs_tables();
+    }
+
+    fn crs_tables(&self) -> &MSMPrecompWindowSigned {
+        self.crs_tables.get_or_init(|| {
+            let bases: Vec<_> = self
+                .crs
+                .G
+                .iter()
+                .chain(std::iter::once(&self.crs.Q))
+                .copied()
+                .collect();
+            MSMPrecompWindowSigned::new(&bases, VERIFIER_WINDOW_SIZE)
+        })
+    }
+
+    // Computes sum g_scalars[i] * G_i + q_scalar * Q using the precomputed tables
+    pub(crate) fn mul_crs(&self, g_scalars: &[Fr], q_scalar: Fr) -> Element {
+        assert_eq!(g_scalars.len(), self.crs.n);
+        let scalars: Vec<_> = g_scalars
+            .iter()
+            .copied()
+            .chain(std::iter::once(q_scalar))
+            .collect();
+        self.crs_tables().mul_par(&scalars)
+    }
+}
@@ -63,6 +63,7 @@ impl Sub<&LagrangeBasis> for &LagrangeBasis {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct PrecomputedWeights {
     // This stores A'(x_i) and 1/A'(x_i)
     barycentric_weights: Vec<Fr>,
@@ -97,6 +98,11 @@ impl PrecomputedWeights {
         }
     }
 
+    // Returns the size of the domain that the weights were computed for
+    pub fn domain_size(&self) -> usize {
+        self.barycentric_weights.len() / 2
+    }
+
     pub fn get_inverted_element(&self, domain_element: usize, is_negative: bool) -> Fr {
         let mut index = domain_element - 1;
         if is_negative {
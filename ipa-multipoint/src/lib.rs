@@ -2,6 +2,7 @@ pub mod committer;
 pub mod crs;
 mod default_crs;
 pub mod ipa; // follows the BCMS20 scheme
+pub mod keys;
 pub mod math_utils;
 pub mod multiproof;
 pub mod transcript;
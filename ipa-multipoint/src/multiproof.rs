@@ -2,13 +2,15 @@
 #![allow(non_snake_case)]
 
 use crate::crs::CRS;
-use crate::ipa::{slow_vartime_multiscalar_mul, IPAProof};
+use crate::ipa::{slow_vartime_multiscalar_mul, IPAProof, MultiExpTerms};
+use crate::keys::{ProverKey, VerifierKey};
 use crate::lagrange_basis::{LagrangeBasis, PrecomputedWeights};
 
 use crate::math_utils::powers_of;
 use crate::transcript::Transcript;
 use crate::transcript::TranscriptProtocol;
 
+use rayon::prelude::*;
 use std::collections::HashMap;
 
 use banderwagon::{trait_defs::*, Element, Fr};
@@ -17,12 +19,33 @@ pub struct MultiPoint;
 #[derive(Clone, Debug)]
 pub struct ProverQuery {
     pub commitment: Element,
-    pub poly: LagrangeBasis, // TODO: Make this a reference so that upstream libraries do not need to clone
+    pub poly: LagrangeBasis,
     // Given a function f, we use z_i to denote the input point and y_i to denote the output, ie f(z_i) = y_i
     pub point: usize,
     pub result: Fr,
 }
 
+// Same as `ProverQuery`, however the polynomial is borrowed,
+// so that upstream libraries do not need to clone it
+#[derive(Clone, Copy, Debug)]
+pub struct ProverQueryRef<'a> {
+    pub commitment: Element,
+    pub poly: &'a LagrangeBasis,
+    pub point: usize,
+    pub result: Fr,
+}
+
+impl<'a> From<&'a ProverQuery> for ProverQueryRef<'a> {
+    fn from(pq: &'a ProverQuery) -> Self {
+        ProverQueryRef {
+            commitment: pq.commitment,
+            poly: &pq.poly,
+            point: pq.point,
+            result: pq.result,
+        }
+    }
+}
+
 impl From<ProverQuery> for VerifierQuery {
     fn from(pq: ProverQuery) -> Self {
         VerifierQuery {
@@ -39,10 +62,10 @@ pub struct VerifierQuery {
 }
 
 //XXX: change to group_prover_queries_by_point
-fn group_prover_queries<'a>(
-    prover_queries: &'a [ProverQuery],
+fn group_prover_queries<'a, 'b>(
+    prover_queries: &'a [ProverQueryRef<'b>],
     challenges: &'a [Fr],
-) -> HashMap<usize, Vec<(&'a ProverQuery, &'a Fr)>> {
+) -> HashMap<usize, Vec<(&'a ProverQueryRef<'b>, &'a Fr)>> {
     // We want to group all of the polynomials which are evaluated at the same point together
     use itertools::Itertools;
     prover_queries
@@ -57,6 +80,26 @@ impl MultiPoint {
         precomp: &PrecomputedWeights,
         transcript: &mut Transcript,
         queries: Vec<ProverQuery>,
+    ) -> MultiPointProof {
+        let queries: Vec<_> = queries.iter().map(ProverQueryRef::from).collect();
+        MultiPoint::open_queries(&crs, precomp, transcript, &queries)
+    }
+
+    // Same as `open`, however the CRS and the polynomials are borrowed,
+    // so the key can be reused across many proofs
+    pub fn open_with_key(
+        key: &ProverKey,
+        transcript: &mut Transcript,
+        queries: &[ProverQueryRef],
+    ) -> MultiPointProof {
+        MultiPoint::open_queries(&key.crs, &key.precomp, transcript, queries)
+    }
+
+    fn open_queries(
+        crs: &CRS,
+        precomp: &PrecomputedWeights,
+        transcript: &mut Transcript,
+        queries: &[ProverQueryRef],
     ) -> MultiPointProof {
         transcript.domain_sep(b"multiproof");
         // 1. Compute `r`
@@ -74,11 +117,13 @@ impl MultiPoint {
         let r = transcript.challenge_scalar(b"r");
         let powers_of_r = powers_of(r, queries.len());
 
-        let grouped_queries = group_prover_queries(&queries, &powers_of_r);
+        let grouped_queries: Vec<_> = group_prover_queries(queries, &powers_of_r)
+            .into_iter()
+            .collect();
 
         // aggregate all of the queries evaluated at the same point
         let aggregated_queries: Vec<_> = grouped_queries
-            .into_iter()
+            .into_par_iter()
             .map(|(point, queries_challenges)| {
                 let mut aggregated_polynomial = vec![Fr::zero(); crs.n];
 
@@ -103,12 +148,9 @@ impl MultiPoint {
         // Compute g(X)
         //
         let g_x: LagrangeBasis = aggregated_queries
-            .iter()
+            .par_iter()
             .map(|(point, agg_f_x)| (agg_f_x).divide_by_linear_vanishing(precomp, *point))
-            .fold(LagrangeBasis::zero(), |mut res, val| {
-                res = res + val;
-                res
-            });
+            .reduce(LagrangeBasis::zero, |res, val| res + val);
 
         let g_x_comm = crs.commit_lagrange_poly(&g_x);
         transcript.append_point(b"D", &g_x_comm);
@@ -127,7 +169,7 @@ impl MultiPoint {
         batch_inversion(&mut g1_den);
 
         let g1_x = aggregated_queries
-            .into_iter()
+            .into_par_iter()
             .zip(g1_den)
             .map(|((_, agg_f_x), den_inv)| {
                 let term: Vec<_> = agg_f_x
@@ -138,10 +180,7 @@ impl MultiPoint {
 
                 LagrangeBasis::new(term)
             })
-            .fold(LagrangeBasis::zero(), |mut res, val| {
-                res = res + val;
-                res
-            });
+            .reduce(LagrangeBasis::zero, |res, val| res + val);
 
         let g1_comm = crs.commit_lagrange_poly(&g1_x);
         transcript.append_point(b"E", &g1_comm);
@@ -174,6 +213,9 @@ impl MultiPointProof {
     pub fn from_bytes(bytes: &[u8], poly_degree: usize) -> crate::IOResult<MultiPointProof> {
         use crate::{IOError, IOErrorKind};
 
+        if bytes.len() < 32 {
+            return Err(IOError::from(IOErrorKind::InvalidData));
+        }
         let g_x_comm_bytes = &bytes[0..32];
         let ipa_bytes = &bytes[32..]; // TODO: we should return a Result here incase the user gives us bad bytes
         let point: Element =
@@ -220,7 +262,44 @@ impl MultiPointProof {
     }
 }
 
+// The proof is serialized using its canonical encoding, see `to_bytes`
+#[cfg(feature = "serde")]
+impl serde::Serialize for MultiPointProof {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.to_bytes().map_err(serde::ser::Error::custom)?;
+        banderwagon::trait_impls::serde::serialize_bytes(&bytes, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MultiPointProof {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = banderwagon::trait_impls::serde::deserialize_bytes(deserializer)?;
+        // The first 32 bytes are the commitment to g(X)
+        let poly_degree = bytes
+            .len()
+            .checked_sub(32)
+            .and_then(crate::ipa::poly_degree_from_serialized_len)
+            .ok_or_else(|| serde::de::Error::custom("invalid proof length"))?;
+        MultiPointProof::from_bytes(&bytes, poly_degree).map_err(serde::de::Error::custom)
+    }
+}
+
 impl MultiPointProof {
+    // Same as `check`, however the CRS and the precomputed weights are taken from the key,
+    // and the multi-exponentiation over the CRS uses the fixed-base tables in the key
+    pub fn check_with_key(
+        &self,
+        key: &VerifierKey,
+        queries: &[VerifierQuery],
+        transcript: &mut Transcript,
+    ) -> bool {
+        match self.multiexp_terms(&key.crs, &key.precomp, queries, transcript) {
+            Some(terms) => terms.evaluate_with_key(key).is_zero(),
+            None => false,
+        }
+    }
+
     pub fn check(
         &self,
         crs: &CRS,
@@ -228,6 +307,93 @@ impl MultiPointProof {
         queries: &[VerifierQuery],
         transcript: &mut Transcript,
     ) -> bool {
+        match self.multiexp_terms(crs, precomp, queries, transcript) {
+            Some(terms) => terms.evaluate(crs).is_zero(),
+            None => false,
+        }
+    }
+
+    // Checks many proofs at once, each with its own set of queries and its own transcript.
+    //
+    // The IPA checks of all proofs are combined with random weights into a single
+    // multi-exponentiation, so the multi-exponentiation over the CRS is only done once.
+    // If the batch fails, then each proof is checked on its own, and the result
+    // for each proof is returned in the same order as the input.
+    //
+    // Each proof needs its own transcript, if the number of transcripts does not match
+    // the number of proofs, then every proof is reported as invalid.
+    pub fn batch_check(
+        crs: &CRS,
+        precomp: &PrecomputedWeights,
+        proofs: &[(&MultiPointProof, &[VerifierQuery])],
+        transcripts: Vec<Transcript>,
+    ) -> Result<(), Vec<bool>> {
+        if proofs.len() != transcripts.len() {
+            return Err(vec![false; proofs.len()]);
+        }
+
+        // The individual checks need the transcripts in their original state
+        let fresh_transcripts = transcripts.clone();
+
+        // The weights are derived from every transcript, after it has absorbed the whole proof,
+        // so that a prover cannot choose proofs whose errors cancel each other out.
+        let mut batch_transcript = Transcript::new(b"multiproof_batch");
+        let mut all_terms = Vec::with_capacity(proofs.len());
+        for ((proof, queries), mut transcript) in proofs.iter().zip(transcripts) {
+            match proof.multiexp_terms(crs, precomp, queries, &mut transcript) {
+                Some(terms) => {
+                    let binding = transcript.challenge_scalar(b"batch");
+                    batch_transcript.append_scalar(b"proof", &binding);
+                    all_terms.push(terms);
+                }
+                None => break,
+            }
+        }
+
+        if all_terms.len() == proofs.len() {
+            let mut scalars = Vec::new();
+            let mut points = Vec::new();
+            let mut q_scalar = Fr::zero();
+            let mut g_scalars = vec![Fr::zero(); crs.n];
+            for terms in all_terms {
+                let weight = batch_transcript.challenge_scalar(b"weight");
+
+                scalars.extend(terms.scalars.iter().map(|scalar| *scalar * weight));
+                points.extend(terms.points);
+                q_scalar += terms.q_scalar * weight;
+                for (g_scalar, term) in g_scalars.iter_mut().zip(terms.g_scalars) {
+                    *g_scalar += term * weight;
+                }
+            }
+
+            let batched = MultiExpTerms {
+                scalars,
+                points,
+                q_scalar,
+                g_scalars,
+            };
+            if batched.evaluate(crs).is_zero() {
+                return Ok(());
+            }
+        }
+
+        Err(proofs
+            .iter()
+            .zip(fresh_transcripts)
+            .map(|((proof, queries), mut transcript)| {
+                proof.check(crs, precomp, queries, &mut transcript)
+            })
+            .collect())
+    }
+
+    // Runs the multiproof protocol, and returns the terms of the final IPA check
+    fn multiexp_terms(
+        &self,
+        crs: &CRS,
+        precomp: &PrecomputedWeights,
+        queries: &[VerifierQuery],
+        transcript: &mut Transcript,
+    ) -> Option<MultiExpTerms> {
         transcript.domain_sep(b"multiproof");
         // 1. Compute `r`
         //
@@ -275,7 +441,7 @@ impl MultiPointProof {
         let b = LagrangeBasis::evaluate_lagrange_coefficients(precomp, crs.n, t); // TODO: we could put this as a method on PrecomputedWeights
 
         self.open_proof
-            .verify_multiexp(transcript, crs, b, g3_comm, t, g2_t)
+            .multiexp_terms(transcript, crs, b, g3_comm, t, g2_t)
     }
 }
 
@@ -283,7 +449,7 @@ impl MultiPointProof {
 // TODO method
 // TODO: check that the point is actually not in the domain
 pub(crate) fn open_point_outside_of_domain(
-    crs: CRS,
+    crs: &CRS,
     precomp: &PrecomputedWeights,
     transcript: &mut Transcript,
     polynomial: LagrangeBasis,
@@ -292,7 +458,7 @@ pub(crate) fn open_point_outside_of_domain(
 ) -> IPAProof {
     let a = polynomial.values().to_vec();
     let b = LagrangeBasis::evaluate_lagrange_coefficients(precomp, crs.n, z_i);
-    crate::ipa::create(transcript, crs, a, commitment, b, z_i)
+    crate::ipa::prove(transcript, crs, a, commitment, b, z_i)
 }
 
 #[test]
@@ -408,7 +574,7 @@ fn test_ipa_consistency() {
     let mut prover_transcript = Transcript::new(b"test");
 
     let proof = open_point_outside_of_domain(
-        crs.clone(),
+        &crs,
         &precomp,
         &mut prover_transcript,
         polynomial,
@@ -532,3 +698,162 @@ fn multiproof_consistency() {
     let expected = "4f53588244efaf07a370ee3f9c467f933eed360d4fbf7a19dfc8bc49b67df4711bf1d0a720717cd6a8c75f1a668cb7cbdd63b48c676b89a7aee4298e71bd7f4013d7657146aa9736817da47051ed6a45fc7b5a61d00eb23e5df82a7f285cc10e67d444e91618465ca68d8ae4f2c916d1942201b7e2aae491ef0f809867d00e83468fb7f9af9b42ede76c1e90d89dd789ff22eb09e8b1d062d8a58b6f88b3cbe80136fc68331178cd45a1df9496ded092d976911b5244b85bc3de41e844ec194256b39aeee4ea55538a36139211e9910ad6b7a74e75d45b869d0a67aa4bf600930a5f760dfb8e4df9938d1f47b743d71c78ba8585e3b80aba26d24b1f50b36fa1458e79d54c05f58049245392bc3e2b5c5f9a1b99d43ed112ca82b201fb143d401741713188e47f1d6682b0bf496a5d4182836121efff0fd3b030fc6bfb5e21d6314a200963fe75cb856d444a813426b2084dfdc49dca2e649cb9da8bcb47859a4c629e97898e3547c591e39764110a224150d579c33fb74fa5eb96427036899c04154feab5344873d36a53a5baefd78c132be419f3f3a8dd8f60f72eb78dd5f43c53226f5ceb68947da3e19a750d760fb31fa8d4c7f53bfef11c4b89158aa56b1f4395430e16a3128f88e234ce1df7ef865f2d2c4975e8c82225f578310c31fd41d265fd530cbfa2b8895b228a510b806c31dff3b1fa5c08bffad443d567ed0e628febdd22775776e0cc9cebcaea9c6df9279a5d91dd0ee5e7a0434e989a160005321c97026cb559f71db23360105460d959bcdf74bee22c4ad8805a1d497507";
     assert_eq!(got, expected)
 }
+
+#[test]
+fn open_with_key_matches_open() {
+    use crate::keys::ProverKey;
+
+    let n = 8;
+    let crs = CRS::new(n, b"random seed");
+    let key = ProverKey::new(crs.clone(), PrecomputedWeights::new(n));
+
+    let queries: Vec<_> = (0..4u128)
+        .map(|i| {
+            let poly = LagrangeBasis::new((0..n as u128).map(|j| Fr::from(i * j + 1)).collect());
+            let point = (i as usize * 3) % n;
+            ProverQuery {
+                commitment: crs.commit_lagrange_poly(&poly),
+                result: poly.evaluate_in_domain(point),
+                poly,
+                point,
+            }
+        })
+        .collect();
+
+    let mut transcript = Transcript::new(b"foo");
+    let proof = MultiPoint::open(crs.clone(), &key.precomp, &mut transcript, queries.clone());
+
+    let query_refs: Vec<_> = queries.iter().map(ProverQueryRef::from).collect();
+    let mut transcript = Transcript::new(b"foo");
+    let proof_with_key = MultiPoint::open_with_key(&key, &mut transcript, &query_refs);
+    assert_eq!(proof, proof_with_key);
+
+    let verifier_queries: Vec<VerifierQuery> = queries.into_iter().map(Into::into).collect();
+    let mut transcript = Transcript::new(b"foo");
+    assert!(proof_with_key.check_with_key(&key.verifier_key(), &verifier_queries, &mut transcript));
+}
+
+#[test]
+fn batch_check_multiproofs() {
+    let n = 8;
+    let crs = CRS::new(n, b"random seed");
+    let precomp = PrecomputedWeights::new(n);
+
+    // Each proof opens two polynomials, at points which depend on the proof
+    let mut proofs = Vec::new();
+    let mut queries = Vec::new();
+    for i in 0..3u128 {
+        let polys = [
+            LagrangeBasis::new((0..n as u128).map(|j| Fr::from(i * 100 + j)).collect()),
+            LagrangeBasis::new((0..n as u128).map(|j| Fr::from(i + j * j)).collect()),
+        ];
+        let prover_queries: Vec<_> = polys
+            .into_iter()
+            .enumerate()
+            .map(|(k, poly)| {
+                let point = (i as usize + k) % n;
+                ProverQuery {
+                    commitment: crs.commit_lagrange_poly(&poly),
+                    result: poly.evaluate_in_domain(point),
+                    poly,
+                    point,
+                }
+            })
+            .collect();
+
+        let mut transcript = Transcript::new(b"foo");
+        proofs.push(MultiPoint::open(
+            crs.clone(),
+            &precomp,
+            &mut transcript,
+            prover_queries.clone(),
+        ));
+        queries.push(
+            prover_queries
+                .into_iter()
+                .map(VerifierQuery::from)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let transcripts = || (0..3).map(|_| Transcript::new(b"foo")).collect();
+    let batch: Vec<_> = proofs
+        .iter()
+        .zip(queries.iter().map(Vec::as_slice))
+        .collect();
+    assert_eq!(
+        MultiPointProof::batch_check(&crs, &precomp, &batch, transcripts()),
+        Ok(())
+    );
+
+    // A wrong evaluation in one proof should only invalidate that proof
+    queries[1][0].result += Fr::from(1u128);
+    let batch: Vec<_> = proofs
+        .iter()
+        .zip(queries.iter().map(Vec::as_slice))
+        .collect();
+    assert_eq!(
+        MultiPointProof::batch_check(&crs, &precomp, &batch, transcripts()),
+        Err(vec![true, false, true])
+    );
+
+    // Every proof is invalid, if a transcript is missing
+    assert_eq!(
+        MultiPointProof::batch_check(&crs, &precomp, &batch, transcripts().split_off(1)),
+        Err(vec![false, false, false])
+    );
+
+    // An empty batch is trivially valid
+    assert_eq!(
+        MultiPointProof::batch_check(&crs, &precomp, &[], Vec::new()),
+        Ok(())
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_matches_canonical_bytes() {
+    let n = 8;
+    let crs = CRS::new(n, b"random seed");
+    let precomp = PrecomputedWeights::new(n);
+
+    let poly = LagrangeBasis::new((0..n as u128).map(|i| Fr::from(i * i + 3)).collect());
+    let prover_query = ProverQuery {
+        commitment: crs.commit_lagrange_poly(&poly),
+        result: poly.evaluate_in_domain(5),
+        poly,
+        point: 5,
+    };
+    let mut transcript = Transcript::new(b"foo");
+    let proof = MultiPoint::open(crs, &precomp, &mut transcript, vec![prover_query]);
+
+    let json = serde_json::to_value(&proof).unwrap();
+    let expected = format!("0x{}", hex::encode(proof.to_bytes().unwrap()));
+    assert_eq!(json, expected);
+    assert_eq!(
+        serde_json::from_value::<MultiPointProof>(json).unwrap(),
+        proof
+    );
+
+    let json = serde_json::to_value(&proof.open_proof).unwrap();
+    let expected = format!("0x{}", hex::encode(proof.open_proof.to_bytes().unwrap()));
+    assert_eq!(json, expected);
+    assert_eq!(
+        serde_json::from_value::<IPAProof>(json).unwrap(),
+        proof.open_proof
+    );
+
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&proof, &mut bytes).unwrap();
+    assert_eq!(
+        ciborium::from_reader::<MultiPointProof, _>(&bytes[..]).unwrap(),
+        proof
+    );
+
+    // Proofs whose length does not match any polynomial degree are rejected
+    let mut truncated = proof.to_bytes().unwrap();
+    truncated.truncate(truncated.len() - 32);
+    let json = format!("\"0x{}\"", hex::encode(&truncated));
+    assert!(serde_json::from_str::<MultiPointProof>(&json).is_err());
+    assert!(serde_json::from_str::<MultiPointProof>("\"0x01\"").is_err());
+}
@@ -8,6 +8,7 @@ pub trait TranscriptProtocol {
 }
 
 use sha2::{Digest, Sha256};
+#[derive(Clone)]
 pub struct Transcript {
     state: Vec<u8>,
 }
@@ -0,0 +1,180 @@
+//! Parsing of command line arguments and of the files a trie is built from.
+use crate::keys::CliHasher;
+use anyhow::{bail, Context, Result};
+use keccak_hash::{keccak, KECCAK_EMPTY};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+use verkle_spec::{
+    addr20_to_addr32, code::chunkify_code, Address20, Address32, Code, Header, Storage, H256, U256,
+};
+use verkle_trie::{Key, Value};
+
+pub(crate) fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.trim();
+    Ok(hex::decode(hex.strip_prefix("0x").unwrap_or(hex))?)
+}
+
+pub(crate) fn parse_hex_array<const N: usize>(hex: &str) -> Result<[u8; N]> {
+    let bytes = parse_hex(hex)?;
+    bytes
+        .as_slice()
+        .try_into()
+        .with_context(|| format!("expected {N} bytes, got {}", bytes.len()))
+}
+
+// Accepts both the 20 byte addresses used today and the 32 byte addresses of the EIP
+pub(crate) fn parse_address(hex: &str) -> Result<Address32> {
+    let bytes = parse_hex(hex)?;
+    match bytes.len() {
+        20 => Ok(addr20_to_addr32(Address20::from_slice(&bytes))),
+        32 => Ok(Address32::from_slice(&bytes)),
+        len => bail!("expected a 20 or 32 byte address, got {len} bytes"),
+    }
+}
+
+pub(crate) fn parse_u256(number: &str) -> Result<U256> {
+    let number = number.trim();
+    match number.strip_prefix("0x") {
+        Some(hex) => Ok(U256::from_str(hex)?),
+        None => Ok(U256::from_dec_str(number)?),
+    }
+}
+
+// Reads a file with one `<key> <value>` pair per line. Empty lines and lines starting
+// with `#` are skipped
+pub(crate) fn key_value_file(path: &Path) -> Result<Vec<(Key, Value)>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(line_number, line)| {
+            let mut parts = line.split_whitespace();
+            let (Some(key), Some(value), None) = (parts.next(), parts.next(), parts.next()) else {
+                bail!("line {}: expected `<key> <value>`", line_number + 1);
+            };
+            Ok((
+                parse_hex_array(key).with_context(|| format!("line {}", line_number + 1))?,
+                parse_hex_array(value).with_context(|| format!("line {}", line_number + 1))?,
+            ))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct GenesisAccountState {
+    balance: String,
+    nonce: Option<String>,
+    code: Option<String>,
+    storage: Option<HashMap<U256, H256>>,
+}
+
+#[derive(Deserialize)]
+struct GenesisConfig {
+    alloc: HashMap<Address20, GenesisAccountState>,
+}
+
+fn to_trie_value(u256: U256) -> Value {
+    let mut value = Value::default();
+    u256.to_little_endian(value.as_mut_slice());
+    value
+}
+
+// Returns the leaves of the accounts in the `alloc` of a genesis file
+pub(crate) fn genesis_key_values(path: &Path) -> Result<Vec<(Key, Value)>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let genesis: GenesisConfig = serde_json::from_str(&contents)?;
+
+    let mut key_values = Vec::new();
+    for (address, account_state) in genesis.alloc {
+        let address = addr20_to_addr32(address);
+        let header = Header::new::<CliHasher>(address);
+
+        let balance = parse_u256(&account_state.balance)
+            .with_context(|| format!("balance of {address:?}"))?;
+        let nonce = parse_u256(account_state.nonce.as_deref().unwrap_or("0"))
+            .with_context(|| format!("nonce of {address:?}"))?;
+        key_values.extend([
+            (header.version().0, to_trie_value(U256::zero())),
+            (header.balance().0, to_trie_value(balance)),
+            (header.nonce().0, to_trie_value(nonce)),
+        ]);
+
+        match account_state.code {
+            None => key_values.push((header.code_keccak().0, KECCAK_EMPTY.0)),
+            Some(code) => {
+                let code = parse_hex(&code).with_context(|| format!("code of {address:?}"))?;
+                key_values.extend([
+                    (header.code_keccak().0, keccak(&code).0),
+                    (header.code_size().0, to_trie_value(U256::from(code.len()))),
+                ]);
+                for (chunk_id, code_chunk) in chunkify_code(code).into_iter().enumerate() {
+                    let tree_key = Code::new::<CliHasher>(address, U256::from(chunk_id));
+                    key_values.push((tree_key.code_chunk().0, code_chunk));
+                }
+            }
+        }
+
+        for (storage_slot, storage_value) in account_state.storage.unwrap_or_default() {
+            let tree_key = Storage::new::<CliHasher>(address, storage_slot);
+            key_values.push((tree_key.storage_slot().0, storage_value.0));
+        }
+    }
+    Ok(key_values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element_to_hex, COMMITTER};
+    use std::io::Write;
+    use verkle_trie::{database::memory_db::MemoryDb, Config, Trie, TrieTrait};
+
+    #[test]
+    fn devnet6_genesis_root() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../verkle-spec/assets/devnet6_genesis.json");
+        let mut trie = Trie::new(Config {
+            db: MemoryDb::new(),
+            committer: &*COMMITTER,
+        });
+        trie.insert(genesis_key_values(&path).unwrap().into_iter());
+        assert_eq!(
+            element_to_hex(trie.root_commitment()),
+            "0x1fbf85345a3cbba9a6d44f991b721e55620a22397c2a93ee8d5011136ac300ee"
+        );
+    }
+
+    #[test]
+    fn key_value_file_parsing() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "# comment\n\n0x{} {}",
+            "01".repeat(32),
+            "02".repeat(32)
+        )
+        .unwrap();
+        assert_eq!(
+            key_value_file(file.path()).unwrap(),
+            vec![([1u8; 32], [2u8; 32])]
+        );
+
+        writeln!(file, "{}", "01".repeat(32)).unwrap();
+        let error = key_value_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("line 4"));
+    }
+
+    #[test]
+    fn numbers_and_addresses() {
+        assert_eq!(parse_u256("0x10").unwrap(), U256::from(16));
+        assert_eq!(parse_u256("10").unwrap(), U256::from(10));
+        assert_eq!(
+            parse_address(&"11".repeat(20)).unwrap(),
+            addr20_to_addr32(Address20::repeat_byte(0x11))
+        );
+        assert!(parse_address(&"11".repeat(31)).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Tree keys, computed with verkle-spec.
+use crate::input::{parse_address, parse_u256};
+use crate::COMMITTER;
+use anyhow::Result;
+use clap::Subcommand;
+use verkle_spec::{Code, Hasher, Header, Storage, H256};
+
+// The default `Hasher` creates a committer for every hash, this one uses the shared committer
+pub(crate) struct CliHasher;
+
+impl Hasher for CliHasher {
+    fn hash64(bytes64: [u8; 64]) -> H256 {
+        verkle_spec::hash64(&COMMITTER, bytes64)
+    }
+}
+
+#[derive(Subcommand)]
+pub(crate) enum TreeKeyCommand {
+    /// Prints the version, balance, nonce, code hash and code size keys of an account
+    Header {
+        /// A 20 or 32 byte address in hex
+        address: String,
+    },
+    /// Prints the key of a storage slot
+    Storage {
+        address: String,
+        /// The slot, in decimal or 0x prefixed hex
+        slot: String,
+    },
+    /// Prints the key of a 31 byte code chunk
+    Code {
+        address: String,
+        /// The chunk number, in decimal or 0x prefixed hex
+        chunk: String,
+    },
+}
+
+impl TreeKeyCommand {
+    pub(crate) fn run(self) -> Result<()> {
+        match self {
+            TreeKeyCommand::Header { address } => {
+                let header = Header::new::<CliHasher>(parse_address(&address)?);
+                println!("version:     {:?}", header.version());
+                println!("balance:     {:?}", header.balance());
+                println!("nonce:       {:?}", header.nonce());
+                println!("code_keccak: {:?}", header.code_keccak());
+                println!("code_size:   {:?}", header.code_size());
+            }
+            TreeKeyCommand::Storage { address, slot } => {
+                let storage =
+                    Storage::new::<CliHasher>(parse_address(&address)?, parse_u256(&slot)?);
+                println!("{:?}", storage.storage_slot());
+            }
+            TreeKeyCommand::Code { address, chunk } => {
+                let code = Code::new::<CliHasher>(parse_address(&address)?, parse_u256(&chunk)?);
+                println!("{:?}", code.code_chunk());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use verkle_spec::U256;
+
+    #[test]
+    fn shared_committer_matches_default_hasher() {
+        struct DefaultHasher;
+        impl Hasher for DefaultHasher {}
+
+        let address = parse_address("0x0102030405060708090a0b0c0d0e0f1011121314").unwrap();
+        assert_eq!(
+            Header::new::<CliHasher>(address).balance(),
+            Header::new::<DefaultHasher>(address).balance()
+        );
+        assert_eq!(
+            Storage::new::<CliHasher>(address, U256::from(64)).storage_slot(),
+            Storage::new::<DefaultHasher>(address, U256::from(64)).storage_slot()
+        );
+    }
+}
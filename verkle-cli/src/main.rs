@@ -0,0 +1,239 @@
+//! `verkle`, a command line tool to debug devnets: compute tree keys, build tries and
+//! their roots, create proofs from an on-disk trie and check execution witnesses.
+mod input;
+mod keys;
+mod witness;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ipa_multipoint::committer::DefaultCommitter;
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use verkle_db::BareMetalDiskDb;
+use verkle_trie::constants::CRS;
+use verkle_trie::database::VerkleDb;
+use verkle_trie::{Config, Element, Trie, TrieTrait};
+
+#[cfg(feature = "rocks_db")]
+type DiskDb = verkle_db::RocksDb;
+#[cfg(all(feature = "sled_db", not(feature = "rocks_db")))]
+type DiskDb = verkle_db::SledDb;
+#[cfg(not(any(feature = "sled_db", feature = "rocks_db")))]
+compile_error!("either the `sled_db` or the `rocks_db` feature needs to be enabled");
+
+pub(crate) type DiskTrie = Trie<VerkleDb<DiskDb>, &'static DefaultCommitter>;
+
+// Computing the committer tables takes a while, so they are shared by every command
+pub(crate) static COMMITTER: Lazy<DefaultCommitter> = Lazy::new(|| DefaultCommitter::new(&CRS.G));
+
+#[derive(Parser)]
+#[command(name = "verkle", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Computes the tree keys of an account header, storage slot or code chunk
+    #[command(subcommand)]
+    TreeKey(keys::TreeKeyCommand),
+    /// Builds a trie and prints its root commitment
+    Root {
+        #[command(flatten)]
+        source: Source,
+        /// Also writes the trie to this database
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+    /// Creates an execution witness for `keys` from an on-disk trie
+    Prove {
+        /// The database written by `verkle root --db`
+        #[arg(long)]
+        db: PathBuf,
+        /// 32 byte tree keys in hex
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+    /// Checks or decodes an execution witness in the JSON format used by geth
+    #[command(subcommand)]
+    Witness(witness::WitnessCommand),
+    /// Prints the number of nodes and the depth of the stems of an on-disk trie
+    Stats {
+        #[arg(long)]
+        db: PathBuf,
+    },
+    /// Prints the nodes and commitments below a path of an on-disk trie
+    Subtree {
+        #[arg(long)]
+        db: PathBuf,
+        /// The child indices from the root in hex, the root by default
+        #[arg(long, default_value = "")]
+        path: String,
+        /// The number of levels below the path to print
+        #[arg(long, default_value_t = 1)]
+        depth: usize,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    /// Graphviz DOT
+    Dot,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Source {
+    /// A genesis file, whose `alloc` is inserted into the trie
+    #[arg(long)]
+    genesis: Option<PathBuf>,
+    /// A file with one `<key> <value>` pair in hex per line
+    #[arg(long)]
+    kv: Option<PathBuf>,
+}
+
+impl Source {
+    fn key_values(&self) -> Result<Vec<([u8; 32], [u8; 32])>> {
+        match (&self.genesis, &self.kv) {
+            (Some(path), _) => input::genesis_key_values(path),
+            (_, Some(path)) => input::key_value_file(path),
+            (None, None) => bail!("either --genesis or --kv is required"),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::TreeKey(command) => command.run(),
+        Command::Root { source, db } => {
+            let key_values = source.key_values()?;
+            let root = match db {
+                Some(path) => {
+                    let mut trie = open_disk_trie(&path);
+                    trie.insert(key_values.into_iter());
+                    trie.flush_database();
+                    trie.root_commitment()
+                }
+                None => {
+                    let mut trie = Trie::new(Config {
+                        db: verkle_trie::database::memory_db::MemoryDb::new(),
+                        committer: &*COMMITTER,
+                    });
+                    trie.insert(key_values.into_iter());
+                    trie.root_commitment()
+                }
+            };
+            println!("{}", element_to_hex(root));
+            Ok(())
+        }
+        Command::Prove { db, keys } => {
+            let mut trie = open_existing_disk_trie(&db)?;
+            let keys = keys
+                .iter()
+                .map(|key| input::parse_hex_array(key).with_context(|| format!("key {key}")))
+                .collect::<Result<Vec<_>>>()?;
+            trie.prefetch(&keys)?;
+            println!("{}", witness::create(&trie, keys)?.to_json_string());
+            Ok(())
+        }
+        Command::Witness(command) => command.run(),
+        Command::Stats { db } => {
+            print!("{}", open_existing_disk_trie(&db)?.stats());
+            Ok(())
+        }
+        Command::Subtree {
+            db,
+            path,
+            depth,
+            format,
+        } => {
+            let trie = open_existing_disk_trie(&db)?;
+            let path = input::parse_hex(&path).context("--path")?;
+            let Some(subtree) = trie.subtree(&path, depth)? else {
+                bail!("there is no node at path 0x{}", hex::encode(&path));
+            };
+            match format {
+                Format::Json => println!("{}", serde_json::to_string_pretty(&subtree.to_json())?),
+                Format::Dot => print!("{}", subtree.to_dot()),
+            }
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn open_disk_trie(path: &Path) -> DiskTrie {
+    Trie::new(Config {
+        db: VerkleDb::<DiskDb>::from_path(path),
+        committer: &*COMMITTER,
+    })
+}
+
+// Opening a path which does not exist would create an empty database, which is never what is meant
+fn open_existing_disk_trie(path: &Path) -> Result<DiskTrie> {
+    if !path.exists() {
+        bail!("no database at {}", path.display());
+    }
+    Ok(open_disk_trie(path))
+}
+
+pub(crate) fn element_to_hex(element: Element) -> String {
+    format!("0x{}", hex::encode(element.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use verkle_trie::inspect::{SubtreeNode, TrieStats};
+
+    fn key(first_byte: u8, second_byte: u8, suffix: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[0] = first_byte;
+        key[1] = second_byte;
+        key[31] = suffix;
+        key
+    }
+
+    #[test]
+    fn reopened_disk_trie() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = {
+            let mut trie = open_disk_trie(dir.path());
+            trie.insert(
+                [
+                    (key(0, 0, 0), [1u8; 32]),
+                    (key(0, 0, 1), [2u8; 32]),
+                    (key(0, 1, 0), [3u8; 32]),
+                    (key(1, 0, 0), [4u8; 32]),
+                ]
+                .into_iter(),
+            );
+            trie.flush_database();
+            trie.root_commitment()
+        };
+
+        let trie = open_disk_trie(dir.path());
+        assert_eq!(trie.root_commitment(), root);
+        let witness = witness::create(&trie, vec![key(0, 0, 1), key(2, 0, 0)]).unwrap();
+        witness::verify(&witness, root, None).unwrap();
+
+        assert_eq!(
+            trie.stats(),
+            TrieStats {
+                branches: 2,
+                stems: 3,
+                leaves: 4,
+                stems_by_depth: BTreeMap::from([(1, 1), (2, 2)]),
+                deepest_paths: vec![vec![0, 0], vec![0, 1]],
+            }
+        );
+        let Some(SubtreeNode::Stem { leaves, .. }) = trie.subtree(&[0, 0], 0).unwrap() else {
+            panic!("[0, 0] should be a stem")
+        };
+        assert_eq!(leaves, vec![(0, [1u8; 32]), (1, [2u8; 32])]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Creating, verifying and decoding execution witnesses.
+use crate::input::parse_hex_array;
+use crate::{element_to_hex, COMMITTER};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Subcommand;
+use serde_json::json;
+use std::{fs, path::PathBuf};
+use verkle_trie::database::ReadWriteHigherDb;
+use verkle_trie::proof::stateless_updater::verify_and_update;
+use verkle_trie::proof::witness::{unpack_depth_extension_present, ExecutionWitness, KeysValues};
+use verkle_trie::{Element, Key, Trie, TrieTrait};
+
+#[derive(Subcommand)]
+pub(crate) enum WitnessCommand {
+    /// Verifies the witness against the pre-state root, and optionally
+    /// checks that applying its new values gives the post-state root
+    Verify {
+        file: PathBuf,
+        #[arg(long)]
+        pre_root: String,
+        #[arg(long)]
+        post_root: Option<String>,
+    },
+    /// Prints the accessed keys and the shape of the proof as JSON
+    Decode { file: PathBuf },
+}
+
+impl WitnessCommand {
+    pub(crate) fn run(self) -> Result<()> {
+        match self {
+            WitnessCommand::Verify {
+                file,
+                pre_root,
+                post_root,
+            } => {
+                let witness = read_witness(&file)?;
+                let pre_root = parse_root(&pre_root).context("--pre-root")?;
+                let post_root = post_root
+                    .map(|root| parse_root(&root).context("--post-root"))
+                    .transpose()?;
+                verify(&witness, pre_root, post_root)?;
+                println!("ok");
+                Ok(())
+            }
+            WitnessCommand::Decode { file } => {
+                let witness = read_witness(&file)?;
+                println!("{}", serde_json::to_string_pretty(&decode(&witness)?)?);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Creates a witness for `keys` with their current values in the trie, and no new values
+pub(crate) fn create<S: ReadWriteHigherDb, C: ipa_multipoint::committer::Committer>(
+    trie: &Trie<S, C>,
+    keys: Vec<Key>,
+) -> Result<ExecutionWitness> {
+    let keys_values = KeysValues {
+        current_values: keys.iter().map(|key| trie.get(*key)).collect(),
+        new_values: vec![None; keys.len()],
+        keys,
+    };
+    let proof = trie
+        .create_verkle_proof(keys_values.keys.iter().copied())
+        .map_err(|error| anyhow!("cannot create the proof: {error:?}"))?;
+    Ok(ExecutionWitness::new(&proof, &keys_values)?)
+}
+
+pub(crate) fn verify(
+    witness: &ExecutionWitness,
+    pre_root: Element,
+    post_root: Option<Element>,
+) -> Result<()> {
+    let (proof, keys_values) = witness.to_verkle_proof()?;
+
+    let computed_post_root = verify_and_update(
+        proof,
+        pre_root,
+        keys_values.keys,
+        keys_values.current_values,
+        keys_values.new_values,
+        &*COMMITTER,
+    )
+    .map_err(|error| anyhow!("invalid proof: {error:?}"))?;
+
+    if let Some(post_root) = post_root {
+        if computed_post_root != post_root {
+            bail!(
+                "the post-state root is {}, expected {}",
+                element_to_hex(computed_post_root),
+                element_to_hex(post_root)
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn decode(witness: &ExecutionWitness) -> Result<serde_json::Value> {
+    let hex_value =
+        |value: &Option<[u8; 32]>| value.map(|value| format!("0x{}", hex::encode(value)));
+
+    let state_diff: Vec<_> = witness
+        .state_diff
+        .iter()
+        .map(|stem_state_diff| {
+            let suffix_diffs: Vec<_> = stem_state_diff
+                .suffix_diffs
+                .iter()
+                .map(|suffix_diff| {
+                    json!({
+                        "suffix": suffix_diff.suffix,
+                        "currentValue": hex_value(&suffix_diff.current_value),
+                        "newValue": hex_value(&suffix_diff.new_value),
+                    })
+                })
+                .collect();
+            json!({
+                "stem": format!("0x{}", hex::encode(stem_state_diff.stem)),
+                "suffixDiffs": suffix_diffs,
+            })
+        })
+        .collect();
+
+    let proof = &witness.verkle_proof;
+    let stems = proof
+        .depth_extension_present
+        .iter()
+        .map(|byte| {
+            let (ext_status, depth) = unpack_depth_extension_present(*byte)?;
+            Ok(json!({ "depth": depth, "extensionStatus": format!("{ext_status:?}") }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(json!({
+        "stateDiff": state_diff,
+        "proof": {
+            "stems": stems,
+            "otherStems": proof.other_stems.len(),
+            "commitmentsByPath": proof.commitments_by_path.len(),
+            "ipaRounds": proof.ipa_proof.cl.len(),
+        },
+    }))
+}
+
+fn read_witness(file: &PathBuf) -> Result<ExecutionWitness> {
+    let json =
+        fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))?;
+    Ok(ExecutionWitness::from_json_str(&json)?)
+}
+
+fn parse_root(hex: &str) -> Result<Element> {
+    Element::from_bytes(&parse_hex_array::<32>(hex)?)
+        .ok_or_else(|| anyhow!("{hex} is not a valid commitment"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use verkle_trie::database::memory_db::MemoryDb;
+    use verkle_trie::proof::golang_proof_format::{EXECUTION_WITNESS_JSON, PREVIOUS_STATE_ROOT};
+    use verkle_trie::Config;
+
+    #[test]
+    fn verify_post_state_root() {
+        let mut trie = Trie::new(Config {
+            db: MemoryDb::new(),
+            committer: &*COMMITTER,
+        });
+        trie.insert([([1u8; 32], [1u8; 32]), ([2u8; 32], [2u8; 32])].into_iter());
+        let pre_root = trie.root_commitment();
+
+        let mut witness = create(&trie, vec![[1u8; 32], [3u8; 32]]).unwrap();
+        witness.state_diff[0].suffix_diffs[0].new_value = Some([4u8; 32]);
+        trie.insert_single([1u8; 32], [4u8; 32]);
+
+        verify(&witness, pre_root, Some(trie.root_commitment())).unwrap();
+        assert!(verify(&witness, pre_root, Some(pre_root)).is_err());
+    }
+
+    #[test]
+    fn verify_geth_witness() {
+        let witness = ExecutionWitness::from_json_str(EXECUTION_WITNESS_JSON).unwrap();
+        let pre_root = parse_root(PREVIOUS_STATE_ROOT).unwrap();
+        verify(&witness, pre_root, None).unwrap();
+
+        assert!(verify(&witness, Element::prime_subgroup_generator(), None).is_err());
+        assert!(verify(
+            &witness,
+            pre_root,
+            Some(Element::prime_subgroup_generator())
+        )
+        .is_err());
+
+        let decoded = decode(&witness).unwrap();
+        assert_eq!(
+            decoded["stateDiff"].as_array().unwrap().len(),
+            witness.state_diff.len()
+        );
+    }
+}
@@ -11,11 +11,30 @@ mod rocksdb_impl;
 #[cfg(feature = "rocks_db")]
 pub use rocksdb_impl::DB as RocksDb;
 
+// An error reported by the database while reading, such as an I/O error or corrupted data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchError(pub String);
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
 // Bare metal database assumes the most basic functionality for a key value database
 pub trait BareMetalKVDb {
     // Get the value stored at this key
     fn fetch(&self, key: &[u8]) -> Option<Vec<u8>>;
 
+    // Get the values stored at many keys, in the same order as the keys.
+    // Databases which support multi-gets should override this, and return the errors of
+    // the individual reads instead of panicking
+    fn fetch_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, FetchError> {
+        Ok(keys.iter().map(|key| self.fetch(key)).collect())
+    }
+
     // Create a database given the default path
     // This cannot be implemented here since Self is not sized.
     fn new() -> Self;
@@ -1,4 +1,4 @@
-use crate::{BareMetalDiskDb, BareMetalKVDb};
+use crate::{BareMetalDiskDb, BareMetalKVDb, FetchError};
 pub use rocksdb::DB;
 
 impl BareMetalDiskDb for DB {
@@ -17,6 +17,13 @@ impl BareMetalKVDb for DB {
     fn fetch(&self, key: &[u8]) -> Option<Vec<u8>> {
         self.get(key).unwrap()
     }
+
+    fn fetch_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, FetchError> {
+        self.multi_get(keys)
+            .into_iter()
+            .map(|value| value.map_err(|err| FetchError(err.into_string())))
+            .collect()
+    }
     // Create a database given the default path
     fn new() -> Self {
         Self::from_path(Self::DEFAULT_PATH)
@@ -43,3 +50,23 @@ impl BatchDB for DB {
         self.write(batch).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch_many_matches_fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = DB::from_path(dir.path());
+        let mut batch = <WriteBatch as BatchWriter>::new();
+        batch.batch_put(&[1], &[10]);
+        batch.batch_put(&[3], &[30]);
+        BatchDB::flush(&mut db, batch);
+
+        let keys = vec![vec![1], vec![2], vec![3]];
+        let expected: Vec<_> = keys.iter().map(|key| db.fetch(key)).collect();
+        assert_eq!(expected, vec![Some(vec![10]), None, Some(vec![30])]);
+        assert_eq!(db.fetch_many(&keys), Ok(expected));
+    }
+}
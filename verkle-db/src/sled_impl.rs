@@ -19,3 +19,26 @@ impl BareMetalKVDb for sled::Db {
         Self::from_path(Self::DEFAULT_PATH)
     }
 }
+
+use crate::{BatchDB, BatchWriter};
+use sled::Batch;
+
+impl BatchWriter for Batch {
+    fn new() -> Self {
+        Batch::default()
+    }
+
+    fn batch_put(&mut self, key: &[u8], val: &[u8]) {
+        self.insert(key, val)
+    }
+}
+
+impl BatchDB for sled::Db {
+    type BatchWrite = Batch;
+
+    fn flush(&mut self, batch: Self::BatchWrite) {
+        self.apply_batch(batch).unwrap();
+        // `apply_batch` is atomic, however it is only durable once the db has been flushed
+        sled::Tree::flush(self).unwrap();
+    }
+}
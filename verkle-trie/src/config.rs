@@ -1,5 +1,7 @@
-use crate::constants::new_crs;
+use crate::constants::{new_crs, CRS};
+use crate::errors::ConfigError;
 use ipa_multipoint::committer::DefaultCommitter;
+use std::path::Path;
 
 // TODO: We may not need to have this be generic, now that we have gotten rid of
 // TODO the config with precomputed points
@@ -19,4 +21,60 @@ impl<Storage> DefaultConfig<Storage> {
     }
 }
 
+impl<Storage> DefaultConfig<Storage> {
+    // Creates a config, whose committer tables are read from a file written
+    // with `write_precomputed_points`, instead of being recomputed
+    pub fn from_precomputed_points<P: AsRef<Path>>(
+        db: Storage,
+        path: P,
+    ) -> Result<Self, ConfigError> {
+        if !path.as_ref().exists() {
+            return Err(ConfigError::PrecomputedPointsNotFound);
+        }
+        let committer = DefaultCommitter::read_precomputation_file(path, &CRS.G)
+            .map_err(ConfigError::FileError)?;
+        Ok(Config { db, committer })
+    }
+
+    // Writes the committer tables to a file, which will not be overwritten if it exists
+    pub fn write_precomputed_points<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        if path.as_ref().exists() {
+            return Err(ConfigError::PrecomputedPointsFileExists);
+        }
+        self.committer
+            .write_precomputation_file(path)
+            .map_err(ConfigError::FileError)
+    }
+}
+
 pub type VerkleConfig<Storage> = DefaultConfig<Storage>;
+
+#[cfg(test)]
+mod tests {
+    use super::VerkleConfig;
+    use crate::{database::memory_db::MemoryDb, errors::ConfigError};
+
+    #[test]
+    fn precomputed_points_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("precomputed_points");
+
+        let result = VerkleConfig::from_precomputed_points(MemoryDb::new(), &path);
+        assert!(matches!(
+            result,
+            Err(ConfigError::PrecomputedPointsNotFound)
+        ));
+
+        // An existing file should never be overwritten
+        std::fs::write(&path, b"not a table").unwrap();
+        let config = VerkleConfig::new(MemoryDb::new());
+        let result = config.write_precomputed_points(&path);
+        assert!(matches!(
+            result,
+            Err(ConfigError::PrecomputedPointsFileExists)
+        ));
+
+        let result = VerkleConfig::from_precomputed_points(MemoryDb::new(), &path);
+        assert!(matches!(result, Err(ConfigError::FileError(_))));
+    }
+}
@@ -1,6 +1,10 @@
 use banderwagon::fr_from_u64_limbs;
 pub use banderwagon::Fr;
-use ipa_multipoint::{crs::CRS, lagrange_basis::PrecomputedWeights};
+use ipa_multipoint::{
+    crs::CRS,
+    keys::{ProverKey, VerifierKey},
+    lagrange_basis::PrecomputedWeights,
+};
 use once_cell::sync::Lazy;
 
 pub const FLUSH_BATCH: u32 = 20_000;
@@ -19,6 +23,9 @@ pub fn new_crs() -> CRS {
 }
 pub static PRECOMPUTED_WEIGHTS: Lazy<PrecomputedWeights> =
     Lazy::new(|| PrecomputedWeights::new(VERKLE_NODE_WIDTH));
+pub static PROVER_KEY: Lazy<ProverKey> =
+    Lazy::new(|| ProverKey::new(CRS.clone(), PRECOMPUTED_WEIGHTS.clone()));
+pub static VERIFIER_KEY: Lazy<VerifierKey> = Lazy::new(|| PROVER_KEY.verifier_key());
 
 #[cfg(test)]
 mod tests {
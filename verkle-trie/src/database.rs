@@ -2,9 +2,11 @@ pub mod default;
 mod generic;
 pub mod memory_db;
 pub mod meta;
+pub mod prefetch;
 
 pub use default::VerkleDb;
 pub use meta::{BranchChild, BranchMeta, Meta, StemMeta};
+pub use prefetch::PrefetchCache;
 pub trait ReadWriteHigherDb: ReadOnlyHigherDb + WriteOnlyHigherDb {}
 impl<T: ReadOnlyHigherDb + WriteOnlyHigherDb> ReadWriteHigherDb for T {}
 // There are two ways to use your database with this trie implementation:
@@ -66,4 +68,13 @@ pub trait ReadOnlyHigherDb {
         let root = vec![];
         self.get_branch_meta(&root).is_none()
     }
+
+    // Returns every child of a branch. Unlike `get_branch_children`, this does not assume that
+    // the top of the trie has been loaded into a cache, so it can be used on a database that
+    // was just opened. Databases which can read many keys at once should override this
+    fn fetch_branch_children(&self, branch_id: &[u8]) -> Vec<(u8, BranchChild)> {
+        (0..=255u8)
+            .filter_map(|index| Some((index, self.get_branch_child(branch_id, index)?)))
+            .collect()
+    }
 }
@@ -1,9 +1,9 @@
 use super::{
-    generic::GenericBatchDB, memory_db::MemoryDb, BranchChild, BranchMeta, Flush, ReadOnlyHigherDb,
-    StemMeta, WriteOnlyHigherDb,
+    generic::GenericBatchDB, memory_db::MemoryDb, BranchChild, BranchMeta, Flush, PrefetchCache,
+    ReadOnlyHigherDb, StemMeta, WriteOnlyHigherDb,
 };
 use crate::database::generic::GenericBatchWriter;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter};
 
 // A convenient structure that allows the end user to just implement BatchDb and BareMetalDiskDb
@@ -24,6 +24,10 @@ pub struct VerkleDb<Storage> {
     // This stores the top 3 layers of the trie, since these are the most accessed
     // in the trie on average
     pub cache: MemoryDb,
+    // This stores the nodes read by `prefetch`, and is checked before the storage.
+    // It is cleared on flush, since until then the batch, which is checked first, has any newer writes.
+    // Its size is bounded by `MAX_PREFETCHED_ITEMS`
+    pub prefetched: PrefetchCache,
 }
 
 impl<S: BareMetalDiskDb> BareMetalDiskDb for VerkleDb<S> {
@@ -33,6 +37,7 @@ impl<S: BareMetalDiskDb> BareMetalDiskDb for VerkleDb<S> {
 
             batch: MemoryDb::new(),
             cache: MemoryDb::new(),
+            prefetched: PrefetchCache::default(),
         }
     }
 
@@ -77,6 +82,7 @@ impl<S: BatchDB> Flush for VerkleDb<S> {
         self.storage.flush(w.inner);
 
         self.batch.clear();
+        self.prefetched.clear();
     }
 }
 
@@ -90,6 +96,10 @@ impl<S: BareMetalKVDb> ReadOnlyHigherDb for VerkleDb<S> {
         if let Some(val) = self.batch.get_leaf(key) {
             return Some(val);
         }
+        // Then the prefetched nodes
+        if let Some(val) = self.prefetched.leaves.get(&key) {
+            return *val;
+        }
         // Now try the disk
         self.storage.get_leaf(key)
     }
@@ -103,6 +113,10 @@ impl<S: BareMetalKVDb> ReadOnlyHigherDb for VerkleDb<S> {
         if let Some(val) = self.batch.get_stem_meta(stem_key) {
             return Some(val);
         }
+        // Then the prefetched nodes
+        if let Some(val) = self.prefetched.stems.get(&stem_key) {
+            return *val;
+        }
         // Now try the disk
         self.storage.get_stem_meta(stem_key)
     }
@@ -116,6 +130,10 @@ impl<S: BareMetalKVDb> ReadOnlyHigherDb for VerkleDb<S> {
         if let Some(val) = self.batch.get_branch_meta(key) {
             return Some(val);
         }
+        // Then the prefetched nodes
+        if let Some(val) = self.prefetched.branch_children.get(key) {
+            return val.and_then(|child| child.branch());
+        }
         // Now try the disk
         self.storage.get_branch_meta(key)
     }
@@ -129,6 +147,12 @@ impl<S: BareMetalKVDb> ReadOnlyHigherDb for VerkleDb<S> {
         if let Some(val) = self.batch.get_branch_child(branch_id, index) {
             return Some(val);
         }
+        // Then the prefetched nodes
+        let mut child_path = branch_id.to_vec();
+        child_path.push(index);
+        if let Some(val) = self.prefetched.branch_children.get(&child_path) {
+            return *val;
+        }
         // Now try the disk
         self.storage.get_branch_child(branch_id, index)
     }
@@ -157,6 +181,19 @@ impl<S: BareMetalKVDb> ReadOnlyHigherDb for VerkleDb<S> {
         children.into_iter().collect()
     }
 
+    // Reads all of the children from the storage in one go, and then overrides them with the
+    // fresher children in the batch and the cache, in the same order as `get_branch_child`
+    fn fetch_branch_children(&self, branch_id: &[u8]) -> Vec<(u8, BranchChild)> {
+        let mut children: BTreeMap<_, _> = self
+            .storage
+            .get_branch_children(branch_id)
+            .into_iter()
+            .collect();
+        children.extend(self.batch.get_branch_children(branch_id));
+        children.extend(self.cache.get_branch_children(branch_id));
+        children.into_iter().collect()
+    }
+
     fn get_stem_children(&self, stem_key: [u8; 31]) -> Vec<(u8, [u8; 32])> {
         // Stems don't have a depth, however the children for all stem will always be on the same depth
         // If we get any children for the stem in the cache storage, then this means we have collected all of them
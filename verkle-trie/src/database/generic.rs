@@ -121,12 +121,15 @@ impl<T: BareMetalKVDb> ReadOnlyHigherDb for GenericBatchDB<T> {
         labelled_key.push(BRANCH_TABLE_MARKER);
         labelled_key.extend_from_slice(branch_id);
 
-        for i in 0u8..=255 {
-            let mut child = labelled_key.clone();
-            child.push(i);
-
-            let child_value = self.inner.fetch(&child);
-
+        let child_keys: Vec<_> = (0u8..=255)
+            .map(|i| {
+                let mut child = labelled_key.clone();
+                child.push(i);
+                child
+            })
+            .collect();
+
+        for (i, child_value) in (0u8..=255).zip(self.inner.fetch_many(&child_keys).unwrap()) {
             if let Some(x) = child_value {
                 children.push((i, BranchChild::from_bytes(x).unwrap()))
             }
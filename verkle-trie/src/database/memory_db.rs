@@ -42,10 +42,7 @@ impl ReadOnlyHigherDb for MemoryDb {
     }
 
     fn get_branch_meta(&self, key: &[u8]) -> Option<BranchMeta> {
-        let branch_child = match self.branch_table.get(key) {
-            Some(b_child) => b_child,
-            None => return None,
-        };
+        let branch_child = self.branch_table.get(key)?;
 
         match branch_child {
             BranchChild::Stem(stem_id) => panic!(
@@ -81,6 +78,11 @@ impl ReadOnlyHigherDb for MemoryDb {
         children
     }
 
+    // The branch table holds every node, so there is nothing to fetch
+    fn fetch_branch_children(&self, branch_id: &[u8]) -> Vec<(u8, BranchChild)> {
+        self.get_branch_children(branch_id)
+    }
+
     fn get_stem_children(&self, stem_key: [u8; 31]) -> Vec<(u8, [u8; 32])> {
         let mut children = Vec::with_capacity(256);
 
@@ -114,10 +116,7 @@ impl WriteOnlyHigherDb for MemoryDb {
     }
 
     fn insert_branch(&mut self, key: Vec<u8>, meta: BranchMeta, _depth: u8) -> Option<BranchMeta> {
-        let b_child = match self.branch_table.insert(key, BranchChild::Branch(meta)) {
-            Some(b_child) => b_child,
-            None => return None,
-        };
+        let b_child = self.branch_table.insert(key, BranchChild::Branch(meta))?;
         match b_child {
             BranchChild::Stem(_) => None, // If its a stem, we return None, this only happens in ChainInsert
             BranchChild::Branch(b_meta) => Some(b_meta),
@@ -13,6 +13,10 @@ pub struct StemMeta {
 
     pub stem_commitment: Element,
     pub hash_stem_commitment: Fr,
+
+    // The epoch that this stem was last written to (EIP-7736).
+    // This is always zero, unless the trie has state expiry enabled.
+    pub last_epoch: u64,
 }
 impl std::fmt::Debug for StemMeta {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -41,6 +45,7 @@ impl std::fmt::Debug for StemMeta {
                 "hash_stem_commitment",
                 &hex::encode(scalar_to_array(&self.hash_stem_commitment).unwrap()),
             )
+            .field("last_epoch", &self.last_epoch)
             .finish()
     }
 }
@@ -51,6 +56,15 @@ fn point_to_array(p: &Element) -> Result<[u8; 64], SerializationError> {
 
     Ok(bytes)
 }
+// Nodes are only ever written by the trie, so the points are not validated. Arkworks would
+// reject about half of them, since a banderwagon element is not always represented by a
+// point in the prime order subgroup of bandersnatch
+fn point_from_array(bytes: &[u8]) -> Result<Element, SerializationError> {
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| SerializationError::InvalidData)?;
+    Ok(Element::from_bytes_unchecked_uncompressed(bytes))
+}
 fn compress_point_to_array(p: &Element) -> Result<[u8; 32], SerializationError> {
     let mut bytes = [0u8; 32];
     p.serialize_compressed(&mut bytes[..])?;
@@ -70,15 +84,16 @@ impl FromBytes<Vec<u8>> for StemMeta {
     fn from_bytes(bytes: Vec<u8>) -> Result<StemMeta, SerializationError> {
         let len = bytes.len();
         // TODO: Explain where this number comes from
-        if len != 64 * 3 + 32 * 3 {
+        // The last epoch is only serialized when state expiry is being used
+        if len != 64 * 3 + 32 * 3 && len != 64 * 3 + 32 * 3 + 8 {
             return Err(SerializationError::InvalidData); // TODO not the most accurate error msg for now
         }
 
         let point_bytes = &bytes[0..64 * 3];
         #[allow(clippy::erasing_op)]
-        let c_1 = Element::deserialize_uncompressed(&point_bytes[0 * 64..1 * 64])?;
-        let c_2 = Element::deserialize_uncompressed(&point_bytes[1 * 64..2 * 64])?;
-        let stem_commitment = Element::deserialize_uncompressed(&point_bytes[2 * 64..3 * 64])?;
+        let c_1 = point_from_array(&point_bytes[0 * 64..1 * 64])?;
+        let c_2 = point_from_array(&point_bytes[1 * 64..2 * 64])?;
+        let stem_commitment = point_from_array(&point_bytes[2 * 64..3 * 64])?;
 
         let scalar_bytes = &bytes[64 * 3..];
         #[allow(clippy::erasing_op)]
@@ -86,6 +101,13 @@ impl FromBytes<Vec<u8>> for StemMeta {
         let hash_c2 = Fr::deserialize_uncompressed(&scalar_bytes[1 * 32..2 * 32])?;
         let hash_stem_commitment = Fr::deserialize_uncompressed(&scalar_bytes[2 * 32..3 * 32])?;
 
+        let epoch_bytes = &scalar_bytes[3 * 32..];
+        let last_epoch = if epoch_bytes.is_empty() {
+            0
+        } else {
+            u64::from_le_bytes(epoch_bytes.try_into().unwrap())
+        };
+
         Ok(StemMeta {
             c_1,
             hash_c1,
@@ -93,6 +115,7 @@ impl FromBytes<Vec<u8>> for StemMeta {
             hash_c2,
             stem_commitment,
             hash_stem_commitment,
+            last_epoch,
         })
     }
 }
@@ -109,6 +132,10 @@ impl ToBytes<Vec<u8>> for StemMeta {
         bytes.extend(scalar_to_array(&self.hash_c2)?);
         bytes.extend(scalar_to_array(&self.hash_stem_commitment)?);
 
+        if self.last_epoch != 0 {
+            bytes.extend(self.last_epoch.to_le_bytes());
+        }
+
         Ok(bytes)
     }
 }
@@ -147,14 +174,14 @@ use crate::from_to_bytes::{FromBytes, ToBytes};
 impl FromBytes<Vec<u8>> for BranchMeta {
     fn from_bytes(bytes: Vec<u8>) -> Result<BranchMeta, SerializationError> {
         let len = bytes.len();
-        if !len == 32 + 64 {
+        if len != 32 + 64 {
             return Err(SerializationError::InvalidData);
         }
 
         let point_bytes = &bytes[0..64];
         let scalar_bytes = &bytes[64..64 + 32];
 
-        let commitment = Element::deserialize_uncompressed(point_bytes)?;
+        let commitment = point_from_array(point_bytes)?;
         let hash_commitment = Fr::deserialize_uncompressed(scalar_bytes)?;
 
         Ok(BranchMeta {
@@ -261,3 +288,23 @@ impl BranchChild {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_meta_roundtrip() {
+        // The CRS points, like most commitments, are not all represented by points in the
+        // prime order subgroup
+        for &commitment in &crate::constants::CRS.G[..8] {
+            let meta = BranchMeta {
+                commitment,
+                hash_commitment: commitment.map_to_scalar_field(),
+            };
+            let decoded = BranchMeta::from_bytes(meta.to_bytes().unwrap()).unwrap();
+            assert_eq!(decoded.commitment, meta.commitment);
+            assert_eq!(decoded.hash_commitment, meta.hash_commitment);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use super::{
+    default::VerkleDb,
+    generic::{BRANCH_TABLE_MARKER, LEAF_TABLE_MARKER, STEM_TABLE_MARKER},
+    BranchChild, ReadOnlyHigherDb, StemMeta,
+};
+use crate::errors::PrefetchError;
+use crate::from_to_bytes::FromBytes;
+use crate::{Key, Stem, Trie};
+use ipa_multipoint::committer::Committer;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use verkle_db::{BareMetalKVDb, FetchError};
+
+// The number of keys in a single multi-get. The chunks are fetched in parallel
+const FETCH_CHUNK_SIZE: usize = 1024;
+
+// The number of nodes the prefetch cache holds at most.
+// An entry takes 528 bytes for a stem, 192 bytes for a branch child and 65 bytes for a leaf,
+// before the spare capacity of the hash maps. If every node was a stem, a full cache would
+// take over half a gigabyte, and up to twice that with the spare capacity. Most of the nodes
+// are branch children and leaves, so in practice it stays well below that
+const MAX_PREFETCHED_ITEMS: usize = 1 << 20;
+
+// Nodes that were read from storage ahead of time by `VerkleDb::prefetch`.
+// Absent nodes are stored as `None`, so that they are not looked up again.
+#[derive(Debug, Clone, Default)]
+pub struct PrefetchCache {
+    pub leaves: HashMap<[u8; 32], Option<[u8; 32]>>,
+    pub stems: HashMap<[u8; 31], Option<StemMeta>>,
+    // Maps the path of a node to the node, like the branch table of `MemoryDb`
+    pub branch_children: HashMap<Vec<u8>, Option<BranchChild>>,
+}
+
+impl PrefetchCache {
+    pub fn num_items(&self) -> usize {
+        self.leaves.len() + self.stems.len() + self.branch_children.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.leaves.clear();
+        self.stems.clear();
+        self.branch_children.clear();
+    }
+}
+
+fn labelled_key(marker: u8, key: &[u8]) -> Vec<u8> {
+    let mut labelled_key = Vec::with_capacity(key.len() + 1);
+    labelled_key.push(marker);
+    labelled_key.extend_from_slice(key);
+    labelled_key
+}
+
+impl<S: BareMetalKVDb + Sync> VerkleDb<S> {
+    // Loads everything that creating a proof for `keys` reads into the prefetch cache:
+    // the branches along the paths of the keys along with all of their children, the stems
+    // and the half of the stem's leaves that each key is in.
+    //
+    // The trie is walked one level at a time, and each level is read with multi-gets, so a
+    // single round trip to storage is needed per level, instead of one per node.
+    // Nodes that are already in the cache or the batch are not read again.
+    //
+    // The prefetch cache is cleared when the database is flushed, or before prefetching if it
+    // is full. It never holds more than `MAX_PREFETCHED_ITEMS` nodes: once it is full, the
+    // remaining nodes are not cached, and are read from storage when they are needed
+    pub fn prefetch(&mut self, keys: &[Key]) -> Result<(), PrefetchError> {
+        if self.prefetched.num_items() >= MAX_PREFETCHED_ITEMS {
+            self.prefetched.clear();
+        }
+
+        let mut stems = BTreeSet::new();
+        let mut leaves = BTreeSet::new();
+
+        // Maps the branches at the current depth to the keys that go through them
+        let mut branches: BTreeMap<Vec<u8>, Vec<Key>> = BTreeMap::new();
+        if !keys.is_empty() {
+            branches.insert(vec![], keys.to_vec());
+        }
+        self.prefetch_branch_children(vec![vec![]])?;
+
+        let mut depth = 0;
+        while !branches.is_empty() {
+            // A branch is opened by committing to all of its children
+            let child_paths: Vec<_> = branches
+                .keys()
+                .flat_map(|path| {
+                    (0..=255u8).map(move |index| {
+                        let mut child_path = path.clone();
+                        child_path.push(index);
+                        child_path
+                    })
+                })
+                .collect();
+            self.prefetch_branch_children(child_paths)?;
+
+            let mut next_branches: BTreeMap<Vec<u8>, Vec<Key>> = BTreeMap::new();
+            for (path, keys) in branches {
+                // The stem commitments of the stem children are needed to open the branch
+                for index in 0..=255u8 {
+                    if let Some(BranchChild::Stem(stem)) = self.get_branch_child(&path, index) {
+                        stems.insert(stem);
+                    }
+                }
+
+                for key in keys {
+                    let mut child_path = path.clone();
+                    child_path.push(key[depth]);
+                    match self.get_branch_child(&path, key[depth]) {
+                        Some(BranchChild::Branch(_)) => {
+                            next_branches.entry(child_path).or_default().push(key)
+                        }
+                        Some(BranchChild::Stem(stem)) => {
+                            leaves.insert(key);
+                            if stem == key[..31] {
+                                let start = if key[31] < 128 { 0 } else { 128 };
+                                leaves.extend((start..=start + 127).map(|suffix| {
+                                    let mut leaf_key = key;
+                                    leaf_key[31] = suffix;
+                                    leaf_key
+                                }));
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+            branches = next_branches;
+            depth += 1;
+        }
+
+        self.prefetch_stems(stems.into_iter().collect())?;
+        self.prefetch_leaves(leaves.into_iter().collect())
+    }
+
+    fn is_full(&self) -> bool {
+        self.prefetched.num_items() >= MAX_PREFETCHED_ITEMS
+    }
+
+    fn fetch_parallel(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, FetchError> {
+        let chunks = keys
+            .par_chunks(FETCH_CHUNK_SIZE)
+            .map(|chunk| self.storage.fetch_many(chunk))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    fn prefetch_branch_children(&mut self, paths: Vec<Vec<u8>>) -> Result<(), PrefetchError> {
+        let paths: Vec<_> = paths
+            .into_iter()
+            .filter(|path| {
+                let in_memory = match path.split_last() {
+                    Some((index, parent)) => {
+                        self.cache.get_branch_child(parent, *index).is_some()
+                            || self.batch.get_branch_child(parent, *index).is_some()
+                    }
+                    None => {
+                        self.cache.get_branch_meta(path).is_some()
+                            || self.batch.get_branch_meta(path).is_some()
+                    }
+                };
+                !in_memory && !self.prefetched.branch_children.contains_key(path)
+            })
+            .collect();
+        if self.is_full() {
+            return Ok(());
+        }
+        let labelled_keys: Vec<_> = paths
+            .iter()
+            .map(|path| labelled_key(BRANCH_TABLE_MARKER, path))
+            .collect();
+        let values = self.fetch_parallel(&labelled_keys)?;
+
+        for (path, value) in paths.into_iter().zip(values) {
+            if self.is_full() {
+                break;
+            }
+            let child = match value {
+                Some(bytes) => Some(
+                    BranchChild::from_bytes(bytes)
+                        .map_err(|_| PrefetchError::InvalidBranchChild { path: path.clone() })?,
+                ),
+                None => None,
+            };
+            self.prefetched.branch_children.insert(path, child);
+        }
+        Ok(())
+    }
+
+    fn prefetch_stems(&mut self, stems: Vec<Stem>) -> Result<(), PrefetchError> {
+        let stems: Vec<_> = stems
+            .into_iter()
+            .filter(|stem| {
+                !self.prefetched.stems.contains_key(stem)
+                    && self.cache.get_stem_meta(*stem).is_none()
+                    && self.batch.get_stem_meta(*stem).is_none()
+            })
+            .collect();
+        if self.is_full() {
+            return Ok(());
+        }
+        let labelled_keys: Vec<_> = stems
+            .iter()
+            .map(|stem| labelled_key(STEM_TABLE_MARKER, stem))
+            .collect();
+        let values = self.fetch_parallel(&labelled_keys)?;
+
+        for (stem, value) in stems.into_iter().zip(values) {
+            if self.is_full() {
+                break;
+            }
+            let stem_meta = match value {
+                Some(bytes) => Some(
+                    StemMeta::from_bytes(bytes).map_err(|_| PrefetchError::InvalidStem { stem })?,
+                ),
+                None => None,
+            };
+            self.prefetched.stems.insert(stem, stem_meta);
+        }
+        Ok(())
+    }
+
+    fn prefetch_leaves(&mut self, keys: Vec<Key>) -> Result<(), PrefetchError> {
+        let keys: Vec<_> = keys
+            .into_iter()
+            .filter(|key| {
+                !self.prefetched.leaves.contains_key(key)
+                    && self.cache.get_leaf(*key).is_none()
+                    && self.batch.get_leaf(*key).is_none()
+            })
+            .collect();
+        if self.is_full() {
+            return Ok(());
+        }
+        let labelled_keys: Vec<_> = keys
+            .iter()
+            .map(|key| labelled_key(LEAF_TABLE_MARKER, key))
+            .collect();
+        let values = self.fetch_parallel(&labelled_keys)?;
+
+        for (key, value) in keys.into_iter().zip(values) {
+            if self.is_full() {
+                break;
+            }
+            let value =
+                match value {
+                    Some(bytes) => Some(bytes.as_slice().try_into().map_err(|_| {
+                        PrefetchError::InvalidLeaf {
+                            key,
+                            got: bytes.len(),
+                        }
+                    })?),
+                    None => None,
+                };
+            self.prefetched.leaves.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+impl<S: BareMetalKVDb + Sync, PolyCommit: Committer> Trie<VerkleDb<S>, PolyCommit> {
+    // See `VerkleDb::prefetch`
+    pub fn prefetch(&mut self, keys: &[Key]) -> Result<(), PrefetchError> {
+        self.storage.prefetch(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TrieTrait, VerkleConfig};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use verkle_db::{BareMetalDiskDb, BatchDB, BatchWriter};
+
+    // A key value database which counts its reads, and whose multi-gets can be made to fail
+    #[derive(Default)]
+    struct CountingDb {
+        kv: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+        fetches: AtomicUsize,
+        multi_gets: AtomicUsize,
+        fail_multi_gets: AtomicBool,
+    }
+
+    impl BareMetalKVDb for CountingDb {
+        fn fetch(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.kv.lock().unwrap().get(key).cloned()
+        }
+
+        fn fetch_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, FetchError> {
+            self.multi_gets.fetch_add(1, Ordering::SeqCst);
+            if self.fail_multi_gets.load(Ordering::SeqCst) {
+                return Err(FetchError("disk is on fire".to_string()));
+            }
+            let kv = self.kv.lock().unwrap();
+            Ok(keys.iter().map(|key| kv.get(key).cloned()).collect())
+        }
+
+        fn new() -> Self {
+            CountingDb::default()
+        }
+    }
+
+    impl BareMetalDiskDb for CountingDb {
+        fn from_path<P: AsRef<std::path::Path>>(_: P) -> Self {
+            CountingDb::default()
+        }
+
+        const DEFAULT_PATH: &'static str = "";
+    }
+
+    struct Batch(Vec<(Vec<u8>, Vec<u8>)>);
+
+    impl BatchWriter for Batch {
+        fn new() -> Self {
+            Batch(Vec::new())
+        }
+
+        fn batch_put(&mut self, key: &[u8], val: &[u8]) {
+            self.0.push((key.to_vec(), val.to_vec()))
+        }
+    }
+
+    impl BatchDB for CountingDb {
+        type BatchWrite = Batch;
+
+        fn flush(&mut self, batch: Self::BatchWrite) {
+            self.kv.lock().unwrap().extend(batch.0)
+        }
+    }
+
+    fn key(first_byte: u8, second_byte: u8, suffix: u8) -> Key {
+        let mut key = [suffix; 32];
+        key[0] = first_byte;
+        key[1] = second_byte;
+        key
+    }
+
+    #[test]
+    fn prefetched_proof_does_not_read_storage() {
+        let mut trie = Trie::new(VerkleConfig::new(VerkleDb::<CountingDb>::from_path("")));
+        trie.insert((0..200u8).map(|i| (key(i % 16, i / 16, i), [i; 32])));
+        trie.flush_database();
+        // The top of the trie is kept in memory, drop it to start cold, like after a restart
+        trie.storage.cache.clear();
+
+        let keys = vec![
+            // Present
+            key(3, 0, 3),
+            // Absent, with its stem present, in the other half of the stem
+            key(3, 0, 200),
+            // Absent, with a different stem where it would be
+            key(0, 0, 9),
+            // Absent, in an empty slot
+            key(15, 200, 0),
+        ];
+        let expected = trie.create_verkle_proof(keys.clone().into_iter()).unwrap();
+
+        trie.prefetch(&keys).unwrap();
+        let storage = &trie.storage.storage;
+        let fetches = storage.fetches.load(Ordering::SeqCst);
+        // The root, two levels of branches, the stems and the leaves
+        assert_eq!(storage.multi_gets.load(Ordering::SeqCst), 5);
+
+        let proof = trie.create_verkle_proof(keys.clone().into_iter()).unwrap();
+        assert_eq!(trie.storage.storage.fetches.load(Ordering::SeqCst), fetches);
+        let (mut proof_bytes, mut expected_bytes) = (Vec::new(), Vec::new());
+        proof.write(&mut proof_bytes).unwrap();
+        expected.write(&mut expected_bytes).unwrap();
+        assert_eq!(proof_bytes, expected_bytes);
+
+        // Prefetching again only reads what is missing
+        trie.prefetch(&keys).unwrap();
+        assert_eq!(trie.storage.storage.multi_gets.load(Ordering::SeqCst), 5);
+
+        trie.insert_single(key(3, 0, 3), [0u8; 32]);
+        trie.flush_database();
+        assert_eq!(trie.storage.prefetched.num_items(), 0);
+        assert_eq!(trie.get(key(3, 0, 3)), Some([0u8; 32]));
+    }
+
+    #[test]
+    fn prefetch_cache_is_bounded() {
+        let mut trie = Trie::new(VerkleConfig::new(VerkleDb::<CountingDb>::from_path("")));
+        trie.insert((0..200u8).map(|i| (key(i % 16, i / 16, i), [i; 32])));
+        trie.flush_database();
+        trie.storage.cache.clear();
+
+        let leaves = &mut trie.storage.prefetched.leaves;
+        leaves.extend((0..=MAX_PREFETCHED_ITEMS as u32).map(|i| {
+            let mut key = [0xffu8; 32];
+            key[..4].copy_from_slice(&i.to_be_bytes());
+            (key, None)
+        }));
+
+        let keys = vec![key(3, 0, 3)];
+        trie.prefetch(&keys).unwrap();
+        let num_items = trie.storage.prefetched.num_items();
+        assert!(num_items > 0 && num_items < MAX_PREFETCHED_ITEMS);
+        assert_eq!(trie.get(key(3, 0, 3)), Some([3u8; 32]));
+
+        // A single call stops caching nodes once the cache is full
+        trie.storage.prefetched.clear();
+        let leaves = &mut trie.storage.prefetched.leaves;
+        leaves.extend((0..MAX_PREFETCHED_ITEMS as u32 - 10).map(|i| {
+            let mut key = [0xffu8; 32];
+            key[..4].copy_from_slice(&i.to_be_bytes());
+            (key, None)
+        }));
+        let expected = trie.create_verkle_proof(keys.clone().into_iter()).unwrap();
+        trie.prefetch(&keys).unwrap();
+        assert_eq!(trie.storage.prefetched.num_items(), MAX_PREFETCHED_ITEMS);
+        let proof = trie.create_verkle_proof(keys.into_iter()).unwrap();
+        let (mut proof_bytes, mut expected_bytes) = (Vec::new(), Vec::new());
+        proof.write(&mut proof_bytes).unwrap();
+        expected.write(&mut expected_bytes).unwrap();
+        assert_eq!(proof_bytes, expected_bytes);
+    }
+
+    #[test]
+    fn prefetch_errors_are_returned() {
+        let mut trie = Trie::new(VerkleConfig::new(VerkleDb::<CountingDb>::from_path("")));
+        trie.insert((0..200u8).map(|i| (key(i % 16, i / 16, i), [i; 32])));
+        trie.flush_database();
+        trie.storage.cache.clear();
+        let keys = vec![key(3, 0, 3)];
+
+        trie.storage
+            .storage
+            .fail_multi_gets
+            .store(true, Ordering::SeqCst);
+        assert_eq!(
+            trie.prefetch(&keys),
+            Err(PrefetchError::Fetch(FetchError(
+                "disk is on fire".to_string()
+            )))
+        );
+        trie.storage
+            .storage
+            .fail_multi_gets
+            .store(false, Ordering::SeqCst);
+
+        let leaf = labelled_key(LEAF_TABLE_MARKER, &key(3, 0, 3));
+        trie.storage
+            .storage
+            .kv
+            .lock()
+            .unwrap()
+            .insert(leaf, vec![3; 31]);
+        assert_eq!(
+            trie.prefetch(&keys),
+            Err(PrefetchError::InvalidLeaf {
+                key: key(3, 0, 3),
+                got: 31
+            })
+        );
+
+        let stem = labelled_key(STEM_TABLE_MARKER, &key(3, 0, 3)[..31]);
+        trie.storage
+            .storage
+            .kv
+            .lock()
+            .unwrap()
+            .insert(stem, vec![0; 10]);
+        trie.storage.prefetched.clear();
+        assert_eq!(
+            trie.prefetch(&keys),
+            Err(PrefetchError::InvalidStem {
+                stem: key(3, 0, 3)[..31].try_into().unwrap()
+            })
+        );
+
+        let root_child = labelled_key(BRANCH_TABLE_MARKER, &[3]);
+        trie.storage
+            .storage
+            .kv
+            .lock()
+            .unwrap()
+            .insert(root_child, vec![0; 10]);
+        trie.storage.prefetched.clear();
+        assert_eq!(
+            trie.prefetch(&keys),
+            Err(PrefetchError::InvalidBranchChild { path: vec![3] })
+        );
+    }
+}
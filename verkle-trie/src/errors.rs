@@ -1,3 +1,4 @@
+use crate::proof::ExtPresent;
 use banderwagon::trait_defs::*;
 use thiserror::Error;
 
@@ -24,6 +25,152 @@ pub enum VerificationError {
     OldValueIsPopulated,
     #[error("Prefix Cannot be Empty")]
     EmptyPrefix,
+    #[error("The update hint does not contain stem {}", hex::encode(.0))]
+    MissingStem([u8; 31]),
+    #[error("The update hint does not contain the commitment at path {}", hex::encode(.0))]
+    MissingCommitment(Vec<u8>),
+    #[error("The update hint does not contain the other stem at path {}", hex::encode(.0))]
+    MissingOtherStem(Vec<u8>),
+}
+
+// Describes the first inconsistency that was found while verifying a `VerkleProof`.
+// This is returned by `VerkleProof::check_with_diagnostics` so that one can see
+// why a witness was rejected, instead of just getting `false`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ProofVerificationError {
+    #[error("Expected one value per key, got {values} values for {keys} keys")]
+    MismatchedValuesLength { keys: usize, values: usize },
+    #[error("Verification hint has {depths} depths and {extension_statuses} extension statuses, but there are {stems} unique stems")]
+    HintLengthMismatch {
+        stems: usize,
+        depths: usize,
+        extension_statuses: usize,
+    },
+    #[error("Stem {} has extension status {ext_status:?}, which is not possible at depth {depth}", hex::encode(.stem))]
+    InvalidDepthForExtStatus {
+        stem: [u8; 31],
+        depth: u8,
+        ext_status: ExtPresent,
+    },
+    #[error("Key {} was supplied with conflicting values", hex::encode(.key))]
+    ConflictingValues { key: [u8; 32] },
+    #[error("Key {} has a value, but the proof says that its stem is not in the trie ({ext_status:?})", hex::encode(.key))]
+    ValueForAbsentStem {
+        key: [u8; 32],
+        ext_status: ExtPresent,
+    },
+    #[error("Stems {} and {} both have an extension under the same prefix at depth {depth}", hex::encode(.stem), hex::encode(.other_stem))]
+    MultipleStemsAtPrefix {
+        stem: [u8; 31],
+        other_stem: [u8; 31],
+        depth: u8,
+    },
+    #[error("Stem {} points to a different stem at depth {depth}, but no such stem is in `diff_stem_no_proof`", hex::encode(.stem))]
+    MissingDiffStemNoProof { stem: [u8; 31], depth: u8 },
+    #[error("Stem {} is in `diff_stem_no_proof`, but is never used", hex::encode(.stem))]
+    UnusedDiffStemNoProof { stem: [u8; 31] },
+    #[error("Expected {expected} commitments, but only {got} were supplied")]
+    MissingCommitments { expected: usize, got: usize },
+    #[error("Expected {expected} commitments, but {got} were supplied; the commitment at index {expected} is unused")]
+    UnusedCommitments { expected: usize, got: usize },
+    #[error("The multipoint opening proof is invalid")]
+    InvalidMultiPointProof,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum RangeProofError {
+    #[error("The start of the stem range is after the end")]
+    InvalidRange,
+    #[error("Stem {} is not in the requested range", hex::encode(.stem))]
+    StemOutOfRange { stem: [u8; 31] },
+    #[error("Stem {} was supplied more than once", hex::encode(.stem))]
+    DuplicateStem { stem: [u8; 31] },
+    #[error("The leaves for stem {} are not sorted by suffix", hex::encode(.stem))]
+    UnsortedLeaves { stem: [u8; 31] },
+    #[error("Stem {} is in the range, but was left out", hex::encode(.stem))]
+    MissingStem { stem: [u8; 31] },
+    #[error("Stem {} was supplied, but it is not in the trie", hex::encode(.stem))]
+    StemNotInTrie { stem: [u8; 31] },
+    #[error("The proof does not cover stem {} in the range", hex::encode(.stem))]
+    UncoveredStem { stem: [u8; 31] },
+    #[error("The leaves for stem {} do not match its commitment", hex::encode(.stem))]
+    LeavesMismatch { stem: [u8; 31] },
+    #[error(transparent)]
+    InvalidProof(#[from] ProofVerificationError),
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum StateExpiryError {
+    #[error("State expiry is not enabled for this trie")]
+    NotEnabled,
+    #[error("Stems must stay active for at least one epoch")]
+    ZeroActiveEpochs,
+    #[error("Cannot move from epoch {current} back to epoch {new}")]
+    EpochWentBackwards { current: u64, new: u64 },
+    #[error("Stem {} expired, it was last written to in epoch {last_epoch}", hex::encode(.stem))]
+    StemExpired { stem: [u8; 31], last_epoch: u64 },
+    #[error("Stem {} is not in the trie", hex::encode(.stem))]
+    StemNotFound { stem: [u8; 31] },
+    #[error("Stem {} has not expired", hex::encode(.stem))]
+    StemNotExpired { stem: [u8; 31] },
+    #[error("The leaves for stem {} do not match its commitment", hex::encode(.stem))]
+    InvalidResurrectionProof { stem: [u8; 31] },
+}
+
+// Returned when a `ConversionConfig` is built with a limit that would stop the conversion
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionConfigError {
+    #[error("At least one account must be converted per block")]
+    ZeroAccountsPerBlock,
+    #[error("At least one storage slot must be converted per block")]
+    ZeroSlotsPerBlock,
+}
+
+// Returned when an execution witness cannot be converted, see `proof::witness`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    #[error("Could not parse the execution witness JSON: {0}")]
+    InvalidJson(String),
+    #[error("{field} is not valid hex")]
+    InvalidHex { field: &'static str },
+    #[error("{field} should be {expected} bytes, got {got}")]
+    InvalidLength {
+        field: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Byte {byte:#04x} does not encode a valid extension status")]
+    InvalidExtensionStatus { byte: u8 },
+    #[error("{field} is not a valid commitment")]
+    InvalidCommitment { field: &'static str },
+    #[error("The final evaluation is not a canonical scalar")]
+    InvalidFinalEvaluation,
+    #[error("Got {keys} keys, {current_values} current values and {new_values} new values")]
+    MismatchedValuesLength {
+        keys: usize,
+        current_values: usize,
+        new_values: usize,
+    },
+}
+
+// Returned when the database is inconsistent while it is inspected, see `inspect`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum InspectError {
+    #[error("Stem {} is a child of a branch, but it is not in the database", hex::encode(.stem))]
+    MissingStem { stem: [u8; 31] },
+}
+
+// Returned when `VerkleDb::prefetch` cannot read the nodes it needs from storage
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PrefetchError {
+    #[error("Could not read from the database: {0}")]
+    Fetch(#[from] verkle_db::FetchError),
+    #[error("The branch child at path {} could not be deserialized", hex::encode(.path))]
+    InvalidBranchChild { path: Vec<u8> },
+    #[error("The stem {} could not be deserialized", hex::encode(.stem))]
+    InvalidStem { stem: [u8; 31] },
+    #[error("The leaf at key {} should be 32 bytes, got {got}", hex::encode(.key))]
+    InvalidLeaf { key: [u8; 32], got: usize },
 }
 
 #[derive(Debug, Error)]
@@ -44,4 +191,6 @@ pub enum ProofCreationError {
     EmptyKeySet,
     #[error("Expected to have atleast one query, which will be against the root")]
     ExpectedOneQueryAgainstRoot,
+    #[error("The start of the stem range is after the end")]
+    InvalidStemRange,
 }
@@ -0,0 +1,383 @@
+// Leaf-level state expiry, as described in EIP-7736.
+//
+// When state expiry is enabled, every stem's extension commitment also commits to
+// the epoch that the stem was last written to, at index 4:
+//
+// 1 * G_0 + stem * G_1 + hash(C1) * G_2 + hash(C2) * G_3 + last_epoch * G_4
+//
+// A stem that has not been written to for `num_active_epochs` epochs is expired.
+// Expired stems cannot be read from or written to, until they are resurrected by
+// supplying all of their leaves, which must match the extension commitment.
+use crate::{
+    database::{BranchMeta, Meta, ReadWriteHigherDb, StemMeta},
+    errors::StateExpiryError,
+    group_to_field,
+    proof::{key_path_finder::KeyPathFinder, range::StemLeaves},
+    trie::{epoch_delta, stem_meta_from_leaves},
+    Config, Key, Stem, Trie, Value,
+};
+use ipa_multipoint::committer::Committer;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateExpiry {
+    // The number of epochs that a stem stays active for, after it was last written to
+    pub(crate) num_active_epochs: u64,
+    pub(crate) current_epoch: u64,
+}
+
+impl StateExpiry {
+    fn is_expired(&self, stem_meta: &StemMeta) -> bool {
+        self.current_epoch >= stem_meta.last_epoch.saturating_add(self.num_active_epochs)
+    }
+}
+
+// The data needed to bring an expired stem back into the active state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResurrectionProof {
+    pub stem: Stem,
+    // The epoch that the stem was last written to, before it expired
+    pub last_epoch: u64,
+    // All of the leaves of the stem, sorted by suffix
+    pub leaves: StemLeaves,
+}
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit> {
+    // Creates a new Trie, which tracks the last epoch that each stem was written to.
+    // `num_active_epochs` must be at least one, otherwise every stem expires as soon as it is written
+    pub fn new_with_state_expiry(
+        config: Config<Storage, PolyCommit>,
+        num_active_epochs: u64,
+    ) -> Result<Self, StateExpiryError> {
+        if num_active_epochs == 0 {
+            return Err(StateExpiryError::ZeroActiveEpochs);
+        }
+        let mut trie = Trie::new(config);
+        trie.expiry = Some(StateExpiry {
+            num_active_epochs,
+            current_epoch: 0,
+        });
+        Ok(trie)
+    }
+
+    // Returns the current epoch, if state expiry is enabled
+    pub fn current_epoch(&self) -> Option<u64> {
+        self.expiry.map(|expiry| expiry.current_epoch)
+    }
+
+    // Moves the trie to a new epoch, which may cause stems to expire
+    pub fn set_current_epoch(&mut self, epoch: u64) -> Result<(), StateExpiryError> {
+        let expiry = self.expiry.as_mut().ok_or(StateExpiryError::NotEnabled)?;
+        if epoch < expiry.current_epoch {
+            return Err(StateExpiryError::EpochWentBackwards {
+                current: expiry.current_epoch,
+                new: epoch,
+            });
+        }
+        expiry.current_epoch = epoch;
+        Ok(())
+    }
+
+    // Returns true if the stem is in the trie and it has expired
+    pub fn is_expired(&self, stem: Stem) -> bool {
+        match (self.expiry, self.storage.get_stem_meta(stem)) {
+            (Some(expiry), Some(stem_meta)) => expiry.is_expired(&stem_meta),
+            _ => false,
+        }
+    }
+
+    fn check_not_expired(&self, stem: Stem) -> Result<(), StateExpiryError> {
+        if let (Some(expiry), Some(stem_meta)) = (self.expiry, self.storage.get_stem_meta(stem)) {
+            if expiry.is_expired(&stem_meta) {
+                return Err(StateExpiryError::StemExpired {
+                    stem,
+                    last_epoch: stem_meta.last_epoch,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Gets the value at the `Key`, returning an error if its stem has expired
+    pub fn try_get(&self, key: Key) -> Result<Option<Value>, StateExpiryError> {
+        self.check_not_expired(key[0..31].try_into().unwrap())?;
+        Ok(self.storage.get_leaf(key))
+    }
+
+    // Inserts multiple values into the trie.
+    // If any of the values are for an expired stem, then nothing is inserted
+    pub fn try_insert(
+        &mut self,
+        kv: impl Iterator<Item = (Key, Value)>,
+    ) -> Result<(), StateExpiryError> {
+        let kv: Vec<_> = kv.collect();
+
+        let stems: BTreeSet<Stem> = kv
+            .iter()
+            .map(|(key, _)| key[0..31].try_into().unwrap())
+            .collect();
+        for stem in stems {
+            self.check_not_expired(stem)?;
+        }
+
+        for (key, value) in kv {
+            self.insert_unchecked(key, value);
+        }
+        Ok(())
+    }
+
+    // Brings an expired stem back into the active state.
+    //
+    // The leaves in the proof are checked against the extension commitment of the expired stem,
+    // and the stem is then marked as written to in the current epoch.
+    pub fn resurrect(&mut self, proof: ResurrectionProof) -> Result<(), StateExpiryError> {
+        let expiry = self.expiry.ok_or(StateExpiryError::NotEnabled)?;
+        let stem = proof.stem;
+
+        let stem_meta = self
+            .storage
+            .get_stem_meta(stem)
+            .ok_or(StateExpiryError::StemNotFound { stem })?;
+        if !expiry.is_expired(&stem_meta) {
+            return Err(StateExpiryError::StemNotExpired { stem });
+        }
+
+        let sorted = proof.leaves.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        let expected =
+            stem_meta_from_leaves(stem, &proof.leaves, proof.last_epoch, &self.committer);
+        if !sorted || expected.stem_commitment != stem_meta.stem_commitment {
+            return Err(StateExpiryError::InvalidResurrectionProof { stem });
+        }
+
+        let mut key = [0u8; 32];
+        key[0..31].copy_from_slice(&stem);
+        let key_path = KeyPathFinder::find_key_path(&self.storage, key);
+        let stem_depth = key_path.depth - 1;
+
+        // The leaves may have been pruned from storage, while the stem was expired
+        for (suffix, value) in &proof.leaves {
+            key[31] = *suffix;
+            self.storage.insert_leaf(key, *value, stem_depth);
+        }
+
+        // Update the epoch in the extension commitment
+        let updated_stem_comm = stem_meta.stem_commitment
+            + epoch_delta(&self.committer, stem_meta.last_epoch, expiry.current_epoch);
+        let updated_stem_meta = StemMeta {
+            stem_commitment: updated_stem_comm,
+            hash_stem_commitment: group_to_field(&updated_stem_comm),
+            last_epoch: expiry.current_epoch,
+            ..stem_meta
+        };
+        self.storage
+            .insert_stem(stem, updated_stem_meta, stem_depth);
+
+        // Propagate the change in the stem commitment up to the root
+        let mut old_child_hash = stem_meta.hash_stem_commitment;
+        let mut new_child_hash = updated_stem_meta.hash_stem_commitment;
+        for (branch_id, child_index, meta) in key_path.nodes.into_iter().rev() {
+            let old_branch_meta = match meta {
+                Meta::Branch(_) => self.storage.get_branch_meta(&branch_id).unwrap(),
+                Meta::Stem(_) => continue,
+            };
+            let updated_comm = old_branch_meta.commitment
+                + self.committer.commit_delta(&[(
+                    child_index as usize,
+                    old_child_hash,
+                    new_child_hash,
+                )]);
+            let updated_branch_meta = BranchMeta {
+                commitment: updated_comm,
+                hash_commitment: group_to_field(&updated_comm),
+            };
+            let depth = branch_id.len() as u8;
+            self.storage
+                .insert_branch(branch_id, updated_branch_meta, depth);
+
+            old_child_hash = old_branch_meta.hash_commitment;
+            new_child_hash = updated_branch_meta.hash_commitment;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ResurrectionProof;
+    use crate::database::{memory_db::MemoryDb, ReadOnlyHigherDb, StemMeta};
+    use crate::errors::StateExpiryError;
+    use crate::from_to_bytes::{FromBytes, ToBytes};
+    use crate::trie::stem_meta_from_leaves;
+    use crate::{trie::Trie, DefaultConfig, TrieTrait};
+    use banderwagon::{Element, Fr};
+    use ipa_multipoint::committer::DefaultCommitter;
+
+    fn key(stem_byte: u8, suffix: u8) -> [u8; 32] {
+        let mut key = [stem_byte; 32];
+        key[31] = suffix;
+        key
+    }
+
+    fn expiring_trie() -> Trie<MemoryDb, DefaultCommitter> {
+        Trie::new_with_state_expiry(DefaultConfig::new(MemoryDb::new()), 2).unwrap()
+    }
+
+    #[test]
+    fn zero_active_epochs_is_rejected() {
+        let trie = Trie::new_with_state_expiry(DefaultConfig::new(MemoryDb::new()), 0);
+        assert_eq!(trie.err(), Some(StateExpiryError::ZeroActiveEpochs));
+    }
+
+    #[test]
+    fn epoch_zero_matches_trie_without_expiry() {
+        let mut trie = Trie::new(DefaultConfig::new(MemoryDb::new()));
+        let mut expiring = expiring_trie();
+
+        for i in 0..4 {
+            trie.insert_single(key(i, i), [i; 32]);
+            expiring.insert_single(key(i, i), [i; 32]);
+        }
+
+        assert_eq!(trie.root_commitment(), expiring.root_commitment());
+    }
+
+    #[test]
+    fn writes_update_the_epoch() {
+        let mut trie = expiring_trie();
+        trie.insert_single(key(1, 0), [1; 32]);
+
+        trie.set_current_epoch(1).unwrap();
+        trie.insert_single(key(1, 200), [2; 32]);
+
+        let stem = [1u8; 31];
+        let stem_meta = trie.storage.get_stem_meta(stem).unwrap();
+        assert_eq!(stem_meta.last_epoch, 1);
+
+        let leaves = vec![(0, [1; 32]), (200, [2; 32])];
+        let expected = stem_meta_from_leaves(stem, &leaves, 1, &trie.committer);
+        assert_eq!(stem_meta, expected);
+
+        // Proofs can still be created and verified
+        let root = trie.root_commitment();
+        let proof = trie
+            .create_verkle_proof(vec![key(1, 200)].into_iter())
+            .unwrap();
+        let (ok, _) = proof.check(vec![key(1, 200)], vec![Some([2; 32])], root);
+        assert!(ok);
+
+        assert_eq!(
+            trie.set_current_epoch(0),
+            Err(StateExpiryError::EpochWentBackwards { current: 1, new: 0 })
+        );
+    }
+
+    #[test]
+    fn expired_stems_are_inaccessible() {
+        let mut trie = expiring_trie();
+        trie.insert_single(key(1, 0), [1; 32]);
+
+        trie.set_current_epoch(1).unwrap();
+        trie.insert_single(key(2, 0), [2; 32]);
+
+        trie.set_current_epoch(2).unwrap();
+        assert!(trie.is_expired([1; 31]));
+        assert!(!trie.is_expired([2; 31]));
+
+        assert_eq!(trie.get(key(1, 0)), None);
+        assert_eq!(
+            trie.try_get(key(1, 0)),
+            Err(StateExpiryError::StemExpired {
+                stem: [1; 31],
+                last_epoch: 0
+            })
+        );
+        assert_eq!(trie.get(key(2, 0)), Some([2; 32]));
+
+        // Nothing is written, if one of the stems has expired
+        let root = trie.root_commitment();
+        let result = trie.try_insert(vec![(key(2, 1), [3; 32]), (key(1, 1), [3; 32])].into_iter());
+        assert_eq!(
+            result,
+            Err(StateExpiryError::StemExpired {
+                stem: [1; 31],
+                last_epoch: 0
+            })
+        );
+        assert_eq!(trie.root_commitment(), root);
+        assert_eq!(trie.get(key(2, 1)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "use `try_insert`")]
+    fn insert_into_expired_stem_panics() {
+        let mut trie = expiring_trie();
+        trie.insert_single(key(1, 0), [1; 32]);
+
+        trie.set_current_epoch(2).unwrap();
+        trie.insert(vec![(key(2, 1), [3; 32]), (key(1, 1), [3; 32])].into_iter());
+    }
+
+    #[test]
+    fn resurrection() {
+        let mut trie = expiring_trie();
+        trie.insert_single(key(1, 0), [1; 32]);
+        trie.insert_single(key(1, 5), [5; 32]);
+        trie.insert_single(key(2, 0), [2; 32]);
+
+        trie.set_current_epoch(3).unwrap();
+        let stem = [1u8; 31];
+
+        let bad_proof = ResurrectionProof {
+            stem,
+            last_epoch: 0,
+            leaves: vec![(0, [1; 32]), (5, [6; 32])],
+        };
+        assert_eq!(
+            trie.resurrect(bad_proof),
+            Err(StateExpiryError::InvalidResurrectionProof { stem })
+        );
+
+        let proof = ResurrectionProof {
+            stem,
+            last_epoch: 0,
+            leaves: vec![(0, [1; 32]), (5, [5; 32])],
+        };
+        trie.resurrect(proof.clone()).unwrap();
+        assert_eq!(trie.get(key(1, 5)), Some([5; 32]));
+        assert_eq!(
+            trie.resurrect(proof.clone()),
+            Err(StateExpiryError::StemNotExpired { stem })
+        );
+
+        // The trie should be the same as if the stem was written to in the current epoch
+        let mut expected = expiring_trie();
+        expected.insert_single(key(2, 0), [2; 32]);
+        expected.set_current_epoch(3).unwrap();
+        expected.insert(
+            proof
+                .leaves
+                .iter()
+                .map(|(suffix, value)| (key(1, *suffix), *value)),
+        );
+        assert_eq!(trie.root_commitment(), expected.root_commitment());
+    }
+
+    #[test]
+    fn stem_meta_serialization() {
+        for last_epoch in [0, 7] {
+            let stem_meta = StemMeta {
+                c_1: Element::zero(),
+                hash_c1: Fr::from(1u64),
+                c_2: Element::zero(),
+                hash_c2: Fr::from(2u64),
+                stem_commitment: Element::zero(),
+                hash_stem_commitment: Fr::from(3u64),
+                last_epoch,
+            };
+            let bytes = stem_meta.to_bytes().unwrap();
+            assert_eq!(bytes.len() == 64 * 3 + 32 * 3, last_epoch == 0);
+            assert_eq!(StemMeta::from_bytes(bytes).unwrap(), stem_meta);
+        }
+    }
+}
@@ -0,0 +1,411 @@
+// Read-only views of the shape of a trie: node counts, the depth of the stems,
+// and the nodes and commitments below a path, which can be exported as JSON or Graphviz DOT.
+//
+// A path is the list of child indices from the root, so the root is at the empty path
+// and a stem is at the shortest prefix of it that no other stem shares.
+use crate::{
+    database::{BranchChild, ReadOnlyHigherDb, ReadWriteHigherDb},
+    errors::InspectError,
+    Element, Stem, Trie, Value,
+};
+use ipa_multipoint::committer::Committer;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+// The number of paths kept in `TrieStats::deepest_paths`
+pub const MAX_DEEPEST_PATHS: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrieStats {
+    // The number of internal nodes, including the root
+    pub branches: usize,
+    pub stems: usize,
+    pub leaves: usize,
+    // Maps the depth of a stem, which is the length of its path, to the number of stems at that depth
+    pub stems_by_depth: BTreeMap<usize, usize>,
+    // The paths of the deepest stems, at most `MAX_DEEPEST_PATHS` of them, in lexicographic order
+    pub deepest_paths: Vec<Vec<u8>>,
+}
+
+impl TrieStats {
+    // Walks the whole trie, which reads every node from the storage
+    pub fn collect<S: ReadOnlyHigherDb>(storage: &S) -> TrieStats {
+        let mut stats = TrieStats::default();
+        if storage.root_is_missing() {
+            return stats;
+        }
+
+        let mut branches = vec![vec![]];
+        while let Some(path) = branches.pop() {
+            stats.branches += 1;
+            let mut child_branches = Vec::new();
+            for (index, child) in storage.fetch_branch_children(&path) {
+                let mut child_path = path.clone();
+                child_path.push(index);
+                match child {
+                    BranchChild::Branch(_) => child_branches.push(child_path),
+                    BranchChild::Stem(stem) => {
+                        stats.leaves += storage.get_stem_children(stem).len();
+                        stats.add_stem(child_path);
+                    }
+                }
+            }
+            // Pushed in reverse, so that stems at the same depth are visited in lexicographic order
+            branches.extend(child_branches.into_iter().rev());
+        }
+        stats
+    }
+
+    fn add_stem(&mut self, path: Vec<u8>) {
+        self.stems += 1;
+        *self.stems_by_depth.entry(path.len()).or_default() += 1;
+
+        match self.deepest_paths.first().map(Vec::len) {
+            Some(max_depth) if path.len() < max_depth => {}
+            Some(max_depth) if path.len() == max_depth => {
+                if self.deepest_paths.len() < MAX_DEEPEST_PATHS {
+                    self.deepest_paths.push(path)
+                }
+            }
+            _ => self.deepest_paths = vec![path],
+        }
+    }
+
+    // The depth of the deepest stem, or zero for an empty trie
+    pub fn max_depth(&self) -> usize {
+        self.stems_by_depth.keys().last().copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for TrieStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "branches: {}", self.branches)?;
+        writeln!(f, "stems:    {}", self.stems)?;
+        writeln!(f, "leaves:   {}", self.leaves)?;
+        writeln!(f, "stems by depth:")?;
+        for (depth, count) in &self.stems_by_depth {
+            writeln!(f, "  {depth}: {count}")?;
+        }
+        writeln!(f, "deepest paths:")?;
+        for path in &self.deepest_paths {
+            writeln!(f, "  {}", hex::encode(path))?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubtreeNode {
+    Branch {
+        path: Vec<u8>,
+        commitment: Element,
+        // `None` when the subtree was cut off above this node's children
+        children: Option<Vec<(u8, SubtreeNode)>>,
+    },
+    Stem {
+        path: Vec<u8>,
+        stem: Stem,
+        commitment: Element,
+        c_1: Element,
+        c_2: Element,
+        leaves: Vec<(u8, Value)>,
+    },
+}
+
+// Returns the node at `path` and its descendants, down to `depth` levels below it.
+// Returns `None` if there is no node at `path`, and an error if the database is inconsistent
+pub fn subtree<S: ReadOnlyHigherDb>(
+    storage: &S,
+    path: &[u8],
+    depth: usize,
+) -> Result<Option<SubtreeNode>, InspectError> {
+    let child = match path.split_last() {
+        None => storage.get_branch_meta(&[]).map(BranchChild::Branch),
+        Some((index, parent)) => storage.get_branch_child(parent, *index),
+    };
+    child
+        .map(|child| subtree_node(storage, path.to_vec(), child, depth))
+        .transpose()
+}
+
+fn subtree_node<S: ReadOnlyHigherDb>(
+    storage: &S,
+    path: Vec<u8>,
+    node: BranchChild,
+    depth: usize,
+) -> Result<SubtreeNode, InspectError> {
+    match node {
+        BranchChild::Branch(branch_meta) => {
+            let children = if depth > 0 {
+                let children = storage
+                    .fetch_branch_children(&path)
+                    .into_iter()
+                    .map(|(index, child)| {
+                        let mut child_path = path.clone();
+                        child_path.push(index);
+                        Ok((index, subtree_node(storage, child_path, child, depth - 1)?))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Some(children)
+            } else {
+                None
+            };
+            Ok(SubtreeNode::Branch {
+                path,
+                commitment: branch_meta.commitment,
+                children,
+            })
+        }
+        BranchChild::Stem(stem) => {
+            let stem_meta = storage
+                .get_stem_meta(stem)
+                .ok_or(InspectError::MissingStem { stem })?;
+            let mut leaves = storage.get_stem_children(stem);
+            leaves.sort_by_key(|(suffix, _)| *suffix);
+            Ok(SubtreeNode::Stem {
+                path,
+                stem,
+                commitment: stem_meta.stem_commitment,
+                c_1: stem_meta.c_1,
+                c_2: stem_meta.c_2,
+                leaves,
+            })
+        }
+    }
+}
+
+fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+impl SubtreeNode {
+    pub fn path(&self) -> &[u8] {
+        match self {
+            SubtreeNode::Branch { path, .. } | SubtreeNode::Stem { path, .. } => path,
+        }
+    }
+
+    pub fn commitment(&self) -> Element {
+        match self {
+            SubtreeNode::Branch { commitment, .. } | SubtreeNode::Stem { commitment, .. } => {
+                *commitment
+            }
+        }
+    }
+
+    // Commitments are compressed and everything is serialized as 0x prefixed hex
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            SubtreeNode::Branch {
+                path,
+                commitment,
+                children,
+            } => json!({
+                "type": "branch",
+                "path": to_hex(path),
+                "commitment": to_hex(commitment.to_bytes()),
+                "children": children.as_ref().map(|children| {
+                    children
+                        .iter()
+                        .map(|(_, child)| child.to_json())
+                        .collect::<Vec<_>>()
+                }),
+            }),
+            SubtreeNode::Stem {
+                path,
+                stem,
+                commitment,
+                c_1,
+                c_2,
+                leaves,
+            } => json!({
+                "type": "stem",
+                "path": to_hex(path),
+                "stem": to_hex(stem),
+                "commitment": to_hex(commitment.to_bytes()),
+                "c1": to_hex(c_1.to_bytes()),
+                "c2": to_hex(c_2.to_bytes()),
+                "leaves": leaves
+                    .iter()
+                    .map(|(suffix, value)| json!({ "suffix": suffix, "value": to_hex(value) }))
+                    .collect::<Vec<_>>(),
+            }),
+        }
+    }
+
+    // Nodes are labelled with their path and the first bytes of their commitment,
+    // and edges with the child index
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph verkle {\n    node [shape=box, fontname=monospace];\n");
+        self.write_dot(&mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn write_dot(&self, dot: &mut String) {
+        let id = dot_id(self.path());
+        let commitment = &hex::encode(self.commitment().to_bytes())[..8];
+        match self {
+            SubtreeNode::Branch { path, children, .. } => {
+                let _ = writeln!(
+                    dot,
+                    "    {id} [label=\"branch {}\\n{commitment}\"];",
+                    to_hex(path)
+                );
+                match children {
+                    Some(children) => {
+                        for (index, child) in children {
+                            child.write_dot(dot);
+                            let _ = writeln!(
+                                dot,
+                                "    {id} -> {} [label=\"{index}\"];",
+                                dot_id(child.path())
+                            );
+                        }
+                    }
+                    None => {
+                        let _ = writeln!(dot, "    {id}_more [label=\"...\", shape=plaintext];");
+                        let _ = writeln!(dot, "    {id} -> {id}_more;");
+                    }
+                }
+            }
+            SubtreeNode::Stem { stem, leaves, .. } => {
+                let _ = writeln!(
+                    dot,
+                    "    {id} [label=\"stem {}\\n{commitment}\\n{} leaves\", shape=ellipse];",
+                    to_hex(stem),
+                    leaves.len()
+                );
+            }
+        }
+    }
+}
+
+fn dot_id(path: &[u8]) -> String {
+    format!("n{}", hex::encode(path))
+}
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit> {
+    pub fn stats(&self) -> TrieStats {
+        TrieStats::collect(&self.storage)
+    }
+
+    // See `inspect::subtree`
+    pub fn subtree(&self, path: &[u8], depth: usize) -> Result<Option<SubtreeNode>, InspectError> {
+        subtree(&self.storage, path, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{memory_db::MemoryDb, WriteOnlyHigherDb};
+    use crate::{TrieTrait, VerkleConfig};
+
+    fn key(prefix: &[u8], suffix: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[..prefix.len()].copy_from_slice(prefix);
+        key[31] = suffix;
+        key
+    }
+
+    fn trie() -> Trie<MemoryDb, ipa_multipoint::committer::DefaultCommitter> {
+        let mut trie = Trie::new(VerkleConfig::new(MemoryDb::new()));
+        trie.insert(
+            [
+                (key(&[0, 0, 0], 0), [1u8; 32]),
+                (key(&[0, 0, 0], 1), [2u8; 32]),
+                (key(&[0, 0, 1], 0), [3u8; 32]),
+                (key(&[0, 1], 0), [4u8; 32]),
+                (key(&[1], 0), [5u8; 32]),
+            ]
+            .into_iter(),
+        );
+        trie
+    }
+
+    #[test]
+    fn stats() {
+        let trie = trie();
+        assert_eq!(
+            trie.stats(),
+            TrieStats {
+                branches: 3,
+                stems: 4,
+                leaves: 5,
+                stems_by_depth: BTreeMap::from([(1, 1), (2, 1), (3, 2)]),
+                deepest_paths: vec![vec![0, 0, 0], vec![0, 0, 1]],
+            }
+        );
+        assert_eq!(trie.stats().max_depth(), 3);
+
+        let empty = Trie::new(VerkleConfig::new(MemoryDb::new()));
+        assert_eq!(empty.stats().branches, 1);
+        assert_eq!(empty.stats().max_depth(), 0);
+    }
+
+    #[test]
+    fn subtree_by_path() {
+        let trie = trie();
+
+        let root = trie.subtree(&[], 1).unwrap().unwrap();
+        assert_eq!(root.commitment(), trie.root_commitment());
+        let SubtreeNode::Branch {
+            children: Some(children),
+            ..
+        } = &root
+        else {
+            panic!("the root should be an expanded branch")
+        };
+        assert_eq!(children.len(), 2);
+        assert!(matches!(
+            children[0].1,
+            SubtreeNode::Branch { children: None, .. }
+        ));
+
+        let SubtreeNode::Stem {
+            stem, path, leaves, ..
+        } = trie.subtree(&[0, 0, 0], 0).unwrap().unwrap()
+        else {
+            panic!("[0, 0, 0] should be a stem")
+        };
+        assert_eq!(path, vec![0, 0, 0]);
+        assert_eq!(stem, key(&[0, 0, 0], 0)[..31]);
+        assert_eq!(leaves, vec![(0, [1u8; 32]), (1, [2u8; 32])]);
+
+        assert!(trie.subtree(&[2], 0).unwrap().is_none());
+        assert!(trie.subtree(&[1, 0], 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn subtree_with_missing_stem_is_an_error() {
+        let mut trie = trie();
+        let stem = [7u8; 31];
+        trie.storage.add_stem_as_branch_child(vec![2], stem, 1);
+
+        assert_eq!(
+            trie.subtree(&[], 1),
+            Err(InspectError::MissingStem { stem })
+        );
+        assert!(trie.subtree(&[0], 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn subtree_export() {
+        let trie = trie();
+        let subtree = trie.subtree(&[0], 8).unwrap().unwrap();
+
+        let json = subtree.to_json();
+        assert_eq!(json["type"], "branch");
+        assert_eq!(json["path"], "0x00");
+        assert_eq!(json["children"][0]["children"][1]["path"], "0x000001");
+        assert_eq!(json["children"][1]["leaves"][0]["suffix"], 0);
+
+        let dot = subtree.to_dot();
+        assert!(dot.starts_with("digraph verkle {"));
+        assert!(dot.contains("n0000 -> n000001 [label=\"1\"];"));
+        assert_eq!(dot.matches("shape=ellipse").count(), 3);
+    }
+}
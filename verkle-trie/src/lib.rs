@@ -4,8 +4,12 @@ pub mod config;
 pub mod constants;
 pub mod database;
 pub mod errors;
+pub mod expiry;
 pub mod from_to_bytes;
+pub mod inspect;
+pub mod overlay;
 pub mod proof;
+pub mod reference;
 pub mod trie;
 
 pub use config::*;
@@ -23,6 +27,9 @@ pub trait TrieTrait {
     /// If the number of items is below FLUSH_BATCH, they will be persisted
     /// atomically
     /// This method will implicitly compute the new root
+    ///
+    /// If state expiry is enabled, this panics when one of the stems has expired,
+    /// and nothing is written. Use `Trie::try_insert` to handle expired stems
     fn insert(&mut self, kv: impl Iterator<Item = (Key, Value)>);
 
     /// Inserts a single value
@@ -34,6 +41,9 @@ pub trait TrieTrait {
     /// Gets the value at the `Key` if it exists
     /// Returns an error if it does not exist
     /// TODO: Find out if this method is ever needed
+    ///
+    /// If state expiry is enabled, values for expired stems are also returned as `None`,
+    /// see `Trie::try_get` to tell these apart from keys that are not in the trie
     fn get(&self, key: Key) -> Option<Value>;
 
     /// Returns the root of the trie
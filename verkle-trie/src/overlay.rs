@@ -0,0 +1,475 @@
+// Overlay tree for the Merkle-Patricia to verkle state conversion, as described in EIP-7612.
+//
+// The Merkle-Patricia state is frozen and becomes a read-only base state.
+// All new writes go into the verkle trie, which sits on top of the base state as an overlay.
+// Reads check the overlay first and fall back to the base state.
+//
+// In every block, a fixed number of accounts and storage slots are moved from the base state
+// into the overlay, in a deterministic order. Once every account has been moved, the base
+// state is no longer needed.
+use crate::{
+    database::ReadWriteHigherDb,
+    errors::{ConversionConfigError, ProofCreationError},
+    proof::VerkleProof,
+    trie::Trie,
+    Key, TrieTrait, Value,
+};
+use banderwagon::{Element, Fr};
+use ipa_multipoint::committer::Committer;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+// Identifies an account in the base state.
+// Accounts are converted in ascending order of their identifier, which for a
+// Merkle-Patricia base state is the hash of the address.
+pub type AccountId = [u8; 32];
+
+// The verkle leaves for an account
+pub type AccountLeaves = Vec<(Key, Value)>;
+
+// The state that the verkle trie is being converted from.
+//
+// The base state is expected to already map its data to verkle keys and values,
+// for example by using the tree key functions in verkle-spec.
+pub trait BaseState {
+    // Returns the value for a verkle key in the base state
+    fn get(&self, key: Key) -> Option<Value>;
+
+    // Returns the first account after `after`, or the first account if `after` is None,
+    // along with the leaves for its account header and code
+    fn next_account(&self, after: Option<AccountId>) -> Option<(AccountId, AccountLeaves)>;
+
+    // Returns at most `max` storage slots for the account, starting from the slot at index `start`.
+    // The slots must always be returned in the same order.
+    fn storage_slots(&self, account: AccountId, start: usize, max: usize) -> Vec<(Key, Value)>;
+}
+
+// The number of accounts and storage slots that are moved into the verkle trie in each block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionConfig {
+    accounts_per_block: usize,
+    slots_per_block: usize,
+}
+
+impl ConversionConfig {
+    // Both limits must be at least one, otherwise the conversion would never finish
+    pub fn new(
+        accounts_per_block: usize,
+        slots_per_block: usize,
+    ) -> Result<Self, ConversionConfigError> {
+        if accounts_per_block == 0 {
+            return Err(ConversionConfigError::ZeroAccountsPerBlock);
+        }
+        if slots_per_block == 0 {
+            return Err(ConversionConfigError::ZeroSlotsPerBlock);
+        }
+        Ok(ConversionConfig {
+            accounts_per_block,
+            slots_per_block,
+        })
+    }
+
+    pub fn accounts_per_block(&self) -> usize {
+        self.accounts_per_block
+    }
+
+    pub fn slots_per_block(&self) -> usize {
+        self.slots_per_block
+    }
+}
+
+// Records how far the conversion has gotten, so that it can be resumed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionProgress {
+    // The last account whose leaves and storage slots were all moved
+    pub last_converted_account: Option<AccountId>,
+    // Whether the header and code leaves for the account after
+    // `last_converted_account` have been moved
+    pub account_leaves_converted: bool,
+    // The index of the next storage slot to move for the account after `last_converted_account`
+    pub next_storage_slot: usize,
+    pub accounts_converted: u64,
+    pub slots_converted: u64,
+    pub finished: bool,
+}
+
+// Collects the next batch of leaves to move from the base state, updating the progress.
+//
+// The leaves of an account count towards the account limit, and each storage slot
+// counts towards the slot limit. An empty batch is returned once the conversion is finished.
+pub fn next_conversion_batch<B: BaseState>(
+    base: &B,
+    config: &ConversionConfig,
+    progress: &mut ConversionProgress,
+) -> Vec<(Key, Value)> {
+    let mut accounts_left = config.accounts_per_block;
+    let mut slots_left = config.slots_per_block;
+    let mut batch = Vec::new();
+
+    while !progress.finished {
+        let (account, account_leaves) = match base.next_account(progress.last_converted_account) {
+            Some(account) => account,
+            None => {
+                progress.finished = true;
+                break;
+            }
+        };
+
+        if !progress.account_leaves_converted {
+            if accounts_left == 0 {
+                break;
+            }
+            batch.extend(account_leaves);
+            progress.account_leaves_converted = true;
+            progress.accounts_converted += 1;
+            accounts_left -= 1;
+        }
+
+        // Fetch one more slot than we need, to check whether the account has any slots left
+        let slots = base.storage_slots(
+            account,
+            progress.next_storage_slot,
+            slots_left.saturating_add(1),
+        );
+        let has_more_slots = slots.len() > slots_left;
+        let num_slots = slots.len().min(slots_left);
+
+        batch.extend(slots.into_iter().take(num_slots));
+        progress.next_storage_slot += num_slots;
+        progress.slots_converted += num_slots as u64;
+        slots_left -= num_slots;
+
+        if has_more_slots {
+            break;
+        }
+
+        progress.last_converted_account = Some(account);
+        progress.account_leaves_converted = false;
+        progress.next_storage_slot = 0;
+    }
+
+    batch
+}
+
+// Iterates the conversion batches for each block, until the base state has been fully moved
+pub struct ConversionIter<'a, B: BaseState> {
+    base: &'a B,
+    config: ConversionConfig,
+    progress: ConversionProgress,
+}
+
+impl<'a, B: BaseState> ConversionIter<'a, B> {
+    pub fn new(base: &'a B, config: ConversionConfig, progress: ConversionProgress) -> Self {
+        ConversionIter {
+            base,
+            config,
+            progress,
+        }
+    }
+
+    pub fn progress(&self) -> &ConversionProgress {
+        &self.progress
+    }
+}
+
+impl<B: BaseState> Iterator for ConversionIter<'_, B> {
+    type Item = Vec<(Key, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.progress.finished {
+            return None;
+        }
+        let batch = next_conversion_batch(self.base, &self.config, &mut self.progress);
+        if batch.is_empty() && self.progress.finished {
+            return None;
+        }
+        Some(batch)
+    }
+}
+
+// A verkle trie that sits on top of a read-only base state
+pub struct OverlayTrie<Storage, PolyCommit: Committer, Base> {
+    trie: Trie<Storage, PolyCommit>,
+    base: Base,
+    config: ConversionConfig,
+    progress: ConversionProgress,
+}
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit> {
+    // Uses this trie as the overlay for `base`, starting a fresh conversion
+    pub fn with_base_state<Base: BaseState>(
+        self,
+        base: Base,
+        config: ConversionConfig,
+    ) -> OverlayTrie<Storage, PolyCommit, Base> {
+        OverlayTrie::with_progress(self, base, config, ConversionProgress::default())
+    }
+}
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer, Base: BaseState>
+    OverlayTrie<Storage, PolyCommit, Base>
+{
+    // Resumes a conversion, that was stopped at `progress`
+    pub fn with_progress(
+        trie: Trie<Storage, PolyCommit>,
+        base: Base,
+        config: ConversionConfig,
+        progress: ConversionProgress,
+    ) -> Self {
+        OverlayTrie {
+            trie,
+            base,
+            config,
+            progress,
+        }
+    }
+
+    // Moves the next batch of leaves from the base state into the verkle trie.
+    // Leaves which have already been written to the overlay are not overwritten.
+    //
+    // Returns the number of leaves that were moved
+    pub fn convert_block(&mut self) -> usize {
+        let batch = next_conversion_batch(&self.base, &self.config, &mut self.progress);
+
+        let leaves: Vec<_> = batch
+            .into_iter()
+            .filter(|(key, _)| self.trie.get(*key).is_none())
+            .collect();
+        let num_leaves = leaves.len();
+        self.trie.insert(leaves.into_iter());
+
+        num_leaves
+    }
+
+    pub fn progress(&self) -> &ConversionProgress {
+        &self.progress
+    }
+
+    pub fn is_converted(&self) -> bool {
+        self.progress.finished
+    }
+
+    pub fn trie(&self) -> &Trie<Storage, PolyCommit> {
+        &self.trie
+    }
+
+    // Returns the verkle trie and the base state.
+    // Once the conversion has finished, the base state can be dropped.
+    pub fn into_parts(self) -> (Trie<Storage, PolyCommit>, Base) {
+        (self.trie, self.base)
+    }
+}
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer, Base: BaseState> TrieTrait
+    for OverlayTrie<Storage, PolyCommit, Base>
+{
+    fn insert(&mut self, kv: impl Iterator<Item = (Key, Value)>) {
+        self.trie.insert(kv)
+    }
+
+    fn get(&self, key: Key) -> Option<Value> {
+        if let Some(value) = self.trie.get(key) {
+            return Some(value);
+        }
+        if self.progress.finished {
+            return None;
+        }
+        self.base.get(key)
+    }
+
+    // The root of the overlay. The base state root is committed to separately.
+    fn root_hash(&self) -> Fr {
+        self.trie.root_hash()
+    }
+
+    fn root_commitment(&self) -> Element {
+        self.trie.root_commitment()
+    }
+
+    fn create_verkle_proof(
+        &self,
+        keys: impl Iterator<Item = Key>,
+    ) -> Result<VerkleProof, ProofCreationError> {
+        self.trie.create_verkle_proof(keys)
+    }
+}
+
+// An in-memory base state, which can stand in for a Merkle-Patricia state
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBaseState {
+    // The header and code leaves, and the storage slots for each account
+    accounts: BTreeMap<AccountId, (AccountLeaves, AccountLeaves)>,
+    values: BTreeMap<Key, Value>,
+}
+
+impl MemoryBaseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_account(
+        &mut self,
+        account: AccountId,
+        account_leaves: Vec<(Key, Value)>,
+        storage_slots: Vec<(Key, Value)>,
+    ) {
+        self.values
+            .extend(account_leaves.iter().chain(&storage_slots).copied());
+        self.accounts
+            .insert(account, (account_leaves, storage_slots));
+    }
+}
+
+impl BaseState for MemoryBaseState {
+    fn get(&self, key: Key) -> Option<Value> {
+        self.values.get(&key).copied()
+    }
+
+    fn next_account(&self, after: Option<AccountId>) -> Option<(AccountId, AccountLeaves)> {
+        let lower_bound = match after {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        self.accounts
+            .range((lower_bound, Bound::Unbounded))
+            .next()
+            .map(|(account, (leaves, _))| (*account, leaves.clone()))
+    }
+
+    fn storage_slots(&self, account: AccountId, start: usize, max: usize) -> Vec<(Key, Value)> {
+        match self.accounts.get(&account) {
+            Some((_, slots)) => slots.iter().skip(start).take(max).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        BaseState, ConversionConfig, ConversionIter, ConversionProgress, MemoryBaseState,
+        OverlayTrie,
+    };
+    use crate::database::memory_db::MemoryDb;
+    use crate::errors::ConversionConfigError;
+    use crate::{trie::Trie, DefaultConfig, TrieTrait};
+
+    // Creates accounts 1..=4, where account `i` has one header leaf and `2 * i` storage slots
+    fn make_base_state() -> MemoryBaseState {
+        let mut base = MemoryBaseState::new();
+        for i in 1..=4u8 {
+            let mut header_key = [i; 32];
+            header_key[31] = 0;
+            let slots = (0..2 * i)
+                .map(|slot| {
+                    let mut key = [i; 32];
+                    key[30] = 100 + slot;
+                    (key, [slot; 32])
+                })
+                .collect();
+            base.insert_account([i; 32], vec![(header_key, [i; 32])], slots);
+        }
+        base
+    }
+
+    fn config() -> ConversionConfig {
+        ConversionConfig::new(1, 3).unwrap()
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        // With no accounts per block, the first account would never be moved, and with
+        // no slots per block, an account with storage would never be finished
+        assert_eq!(
+            ConversionConfig::new(0, 3),
+            Err(ConversionConfigError::ZeroAccountsPerBlock)
+        );
+        assert_eq!(
+            ConversionConfig::new(1, 0),
+            Err(ConversionConfigError::ZeroSlotsPerBlock)
+        );
+
+        // The smallest limits still finish the conversion, one slot at a time
+        let base = make_base_state();
+        let config = ConversionConfig::new(1, 1).unwrap();
+        let batches: Vec<_> =
+            ConversionIter::new(&base, config, ConversionProgress::default()).collect();
+        assert!(batches.iter().all(|batch| !batch.is_empty()));
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 4 + 20);
+    }
+
+    #[test]
+    fn reads_fall_back_to_base_state() {
+        let trie = Trie::new(DefaultConfig::new(MemoryDb::new()));
+        let mut overlay = trie.with_base_state(make_base_state(), config());
+
+        let mut header_key = [2u8; 32];
+        header_key[31] = 0;
+        assert_eq!(overlay.get(header_key), Some([2u8; 32]));
+        assert_eq!(overlay.trie().get(header_key), None);
+
+        // Writes go into the overlay, and shadow the base state
+        overlay.insert_single(header_key, [9u8; 32]);
+        assert_eq!(overlay.get(header_key), Some([9u8; 32]));
+        assert_eq!(overlay.get([7u8; 32]), None);
+    }
+
+    #[test]
+    fn conversion_batches() {
+        let base = make_base_state();
+        let batches: Vec<_> =
+            ConversionIter::new(&base, config(), ConversionProgress::default()).collect();
+
+        // Account 1 has 2 slots, account 2 has 4, account 3 has 6 and account 4 has 8.
+        // Each block moves at most one account's header leaf and three slots.
+        let sizes: Vec<_> = batches.iter().map(|batch| batch.len()).collect();
+        assert_eq!(sizes, vec![3, 4, 4, 3, 4, 3, 3]);
+
+        let num_leaves: usize = sizes.iter().sum();
+        assert_eq!(num_leaves, 4 + 2 + 4 + 6 + 8);
+    }
+
+    #[test]
+    fn conversion_matches_fresh_trie() {
+        let base = make_base_state();
+
+        let mut written_key = [3u8; 32];
+        written_key[30] = 100;
+
+        let mut overlay =
+            Trie::new(DefaultConfig::new(MemoryDb::new())).with_base_state(base, config());
+        overlay.insert_single(written_key, [42u8; 32]);
+
+        // Stop half way, and resume the conversion from the recorded progress
+        for _ in 0..3 {
+            overlay.convert_block();
+        }
+        let progress = overlay.progress().clone();
+        assert_eq!(progress.last_converted_account, Some([2u8; 32]));
+        assert_eq!(progress.accounts_converted, 3);
+        assert_eq!(progress.slots_converted, 8);
+        assert_eq!(progress.next_storage_slot, 2);
+
+        let (trie, base) = overlay.into_parts();
+        let mut overlay = OverlayTrie::with_progress(trie, base, config(), progress);
+        while !overlay.is_converted() {
+            overlay.convert_block();
+        }
+        assert_eq!(overlay.progress().accounts_converted, 4);
+        assert_eq!(overlay.progress().slots_converted, 20);
+
+        // The value that was written to the overlay takes precedence
+        assert_eq!(overlay.get(written_key), Some([42u8; 32]));
+
+        let base = make_base_state();
+        let mut expected = Trie::new(DefaultConfig::new(MemoryDb::new()));
+        let mut account = None;
+        while let Some((id, leaves)) = base.next_account(account) {
+            expected.insert(leaves.into_iter());
+            expected.insert(base.storage_slots(id, 0, usize::MAX).into_iter());
+            account = Some(id);
+        }
+        expected.insert_single(written_key, [42u8; 32]);
+        assert_eq!(overlay.root_commitment(), expected.root_commitment());
+    }
+}
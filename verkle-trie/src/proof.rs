@@ -1,6 +1,6 @@
 use crate::{
-    constants::{CRS, PRECOMPUTED_WEIGHTS},
-    errors::HintError,
+    constants::VERIFIER_KEY,
+    errors::{HintError, ProofVerificationError},
 };
 
 use banderwagon::Element;
@@ -10,11 +10,13 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::io::{Read, Write};
 
 pub mod golang_proof_format;
-mod key_path_finder;
+pub(crate) mod key_path_finder;
 mod opening_data;
 pub(crate) mod prover;
+pub mod range;
 pub mod stateless_updater;
 pub(crate) mod verifier;
+pub mod witness;
 
 // Every stem node has an associated extension node
 // This extension node commits to all of the data in a stem
@@ -93,17 +95,10 @@ impl VerificationHint {
         reader.read_exact(&mut buffer)?;
 
         for byte in buffer {
-            // use a mask to get the last two bits
-            const MASK: u8 = 3;
-            let ext_status = MASK & byte;
-            let ext_status = match ext_status {
-                0 => ExtPresent::None,
-                1 => ExtPresent::DifferentStem,
-                2 => ExtPresent::Present,
-                x => panic!("unexpected ext status number {} ", x),
-            };
-            // shift away the last 3 bits in order to get the depth
-            let depth = byte >> 3;
+            let (ext_status, depth) =
+                witness::unpack_depth_extension_present(byte).map_err(|_| {
+                    HintError::from(std::io::Error::from(std::io::ErrorKind::InvalidData))
+                })?;
             depths.push(depth);
             extension_present.push(ext_status)
         }
@@ -129,36 +124,59 @@ impl VerificationHint {
         // The depths and extension status can be put into a single byte
         // because extension status only needs 3 bits and depth only needs at most 5 bits
         for (depth, ext_status) in self.depths.iter().zip(&self.extension_present) {
-            let mut byte = 0;
-            // Encode extension status into the byte
-            match ext_status {
-                ExtPresent::None => {
-                    // For None, we set the bit to be zero, so do nothing
-                }
-                ExtPresent::DifferentStem => {
-                    // For different stem, we set the first bit to be 1
-                    // This corresponds to the number 1.
-                    byte = 1;
-                }
-                ExtPresent::Present => {
-                    // For present, we set the second bit to be 1
-                    // and the first bit to be zero
-                    // This corresponds to the number 2.
-                    byte = 2;
-                }
-            };
-
-            // Encode depth into the byte, it should only be less
-            // than or equal to 32, and so we only need 5 bits.
-            debug_assert!(*depth <= 32);
-            byte |= depth << 3;
-
+            let byte = witness::pack_depth_extension_present(*depth, *ext_status);
             writer.write_all(&[byte])?;
         }
         Ok(())
     }
 }
 
+// The hint and the proof are serialized using their canonical encodings, see `write`.
+// Human-readable formats use a 0x-prefixed hex string and binary formats use the raw bytes.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{VerificationHint, VerkleProof};
+    use banderwagon::trait_impls::serde::{deserialize_bytes, serialize_bytes};
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for VerificationHint {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut bytes = Vec::new();
+            self.write(&mut bytes).map_err(ser::Error::custom)?;
+            serialize_bytes(&bytes, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for VerificationHint {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bytes = deserialize_bytes(deserializer)?;
+            let mut reader = &bytes[..];
+            let hint = VerificationHint::read(&mut reader).map_err(de::Error::custom)?;
+            if !reader.is_empty() {
+                return Err(de::Error::custom(
+                    "trailing bytes after the verification hint",
+                ));
+            }
+            Ok(hint)
+        }
+    }
+
+    impl Serialize for VerkleProof {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut bytes = Vec::new();
+            self.write(&mut bytes).map_err(ser::Error::custom)?;
+            serialize_bytes(&bytes, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for VerkleProof {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bytes = deserialize_bytes(deserializer)?;
+            VerkleProof::read(&bytes[..]).map_err(de::Error::custom)
+        }
+    }
+}
+
 // Auxillary information that the verifier needs in order to update the root statelessly
 pub struct UpdateHint {
     depths_and_ext_by_stem: BTreeMap<[u8; 31], (ExtPresent, u8)>,
@@ -188,17 +206,15 @@ impl VerkleProof {
         reader.read_exact(&mut num_comms)?;
         let num_comms = u32::from_le_bytes(num_comms);
 
-        let mut comms_sorted = Vec::new();
+        let mut comms_serialized = Vec::new();
         for _ in 0..num_comms {
             let mut comm_serialized = [0u8; 32];
             reader.read_exact(&mut comm_serialized)?;
-
-            let point = Element::from_bytes(&comm_serialized).ok_or(HintError::from(
-                std::io::Error::from(std::io::ErrorKind::InvalidData),
-            ))?;
-
-            comms_sorted.push(point);
+            comms_serialized.push(comm_serialized);
         }
+        let comms_sorted = Element::batch_from_bytes(&comms_serialized).ok_or(HintError::from(
+            std::io::Error::from(std::io::ErrorKind::InvalidData),
+        ))?;
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes)?;
         let proof = MultiPointProof::from_bytes(&bytes, crate::constants::VERKLE_NODE_WIDTH)?;
@@ -217,8 +233,7 @@ impl VerkleProof {
         let num_comms = self.comms_sorted.len() as u32;
         writer.write_all(&num_comms.to_le_bytes())?;
 
-        for comm in &self.comms_sorted {
-            let comm_serialized = comm.to_bytes();
+        for comm_serialized in Element::batch_to_bytes(&self.comms_sorted) {
             writer.write_all(&comm_serialized)?;
         }
 
@@ -229,32 +244,92 @@ impl VerkleProof {
         Ok(())
     }
 
+    // Returns whether the proof is valid, along with the update hint if it is
     pub fn check(
         self,
         keys: Vec<[u8; 32]>,
         values: Vec<Option<[u8; 32]>>,
         root: Element,
     ) -> (bool, Option<UpdateHint>) {
+        match self.check_with_diagnostics(keys, values, root) {
+            Ok(update_hint) => (true, Some(update_hint)),
+            Err(_) => (false, None),
+        }
+    }
+
+    // Same as `check`, however instead of returning `false` when the proof is invalid,
+    // it returns the first inconsistency that was found.
+    //
+    // This is useful for debugging witnesses that were produced by other clients.
+    pub fn check_with_diagnostics(
+        self,
+        keys: Vec<[u8; 32]>,
+        values: Vec<Option<[u8; 32]>>,
+        root: Element,
+    ) -> Result<UpdateHint, ProofVerificationError> {
         // TODO: check the commitments are in the correct subgroup
         // TODO: possibly will be done with Decaf
 
         // TODO: remove need for this Clone, by splitting off the IPA proof object
         // TODO here and sending the rest of the struct to create_verifier_queries
         let proof = self.proof.clone();
-        let queries_update_hint = verifier::create_verifier_queries(self, keys, values, root);
-
-        let (queries, update_hint) = match queries_update_hint {
-            Some((queries, update_hint)) => (queries, update_hint),
-            None => return (false, None),
-        };
+        let (queries, update_hint) = verifier::create_verifier_queries(self, keys, values, root)?;
 
         let mut transcript = Transcript::new(b"vt");
-        let ok = proof.check(&CRS, &PRECOMPUTED_WEIGHTS, &queries, &mut transcript);
+        let ok = proof.check_with_key(&VERIFIER_KEY, &queries, &mut transcript);
+        if !ok {
+            return Err(ProofVerificationError::InvalidMultiPointProof);
+        }
+
+        Ok(update_hint)
+    }
 
-        (ok, Some(update_hint))
+    // Checks many proofs at once, each against its own keys, values and root.
+    //
+    // The multipoint proofs are verified together, see `MultiPointProof::batch_check`.
+    // The result for each proof is returned in the same order as the input.
+    pub fn batch_check(
+        proofs: Vec<ProofToCheck>,
+    ) -> Vec<Result<UpdateHint, ProofVerificationError>> {
+        let mut results = Vec::with_capacity(proofs.len());
+        // The proofs which passed the structural checks, along with their index in `results`
+        let mut pending = Vec::new();
+        for (index, (proof, keys, values, root)) in proofs.into_iter().enumerate() {
+            let multipoint_proof = proof.proof.clone();
+            match verifier::create_verifier_queries(proof, keys, values, root) {
+                Ok((queries, update_hint)) => {
+                    results.push(Ok(update_hint));
+                    pending.push((index, multipoint_proof, queries));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+
+        let batch: Vec<_> = pending
+            .iter()
+            .map(|(_, proof, queries)| (proof, queries.as_slice()))
+            .collect();
+        let transcripts = batch.iter().map(|_| Transcript::new(b"vt")).collect();
+        if let Err(valid) = MultiPointProof::batch_check(
+            &VERIFIER_KEY.crs,
+            &VERIFIER_KEY.precomp,
+            &batch,
+            transcripts,
+        ) {
+            for ((index, _, _), valid) in pending.iter().zip(valid) {
+                if !valid {
+                    results[*index] = Err(ProofVerificationError::InvalidMultiPointProof);
+                }
+            }
+        }
+
+        results
     }
 }
 
+// A proof along with the keys, values and root that it should be checked against
+pub type ProofToCheck = (VerkleProof, Vec<[u8; 32]>, Vec<Option<[u8; 32]>>, Element);
+
 impl std::fmt::Display for VerkleProof {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         writeln!(f, "Verkle proof:")?;
@@ -276,7 +351,8 @@ mod test {
 
     use super::VerkleProof;
     use crate::database::{memory_db::MemoryDb, ReadOnlyHigherDb};
-    use crate::proof::{prover, verifier};
+    use crate::errors::ProofVerificationError;
+    use crate::proof::{prover, verifier, ExtPresent};
     use crate::{trie::Trie, DefaultConfig, TrieTrait};
     use banderwagon::Fr;
 
@@ -368,4 +444,327 @@ mod test {
         let deserialized_proof = VerkleProof::read(&bytes[..]).unwrap();
         assert_eq!(proof, deserialized_proof);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_matches_canonical_bytes() {
+        use super::VerificationHint;
+
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let mut keys = Vec::new();
+        for i in 0..=3 {
+            let mut key_0 = [0u8; 32];
+            key_0[0] = i;
+            keys.push(key_0);
+            trie.insert_single(key_0, key_0);
+        }
+        // An absent key, so that the hint has a stem without a proof
+        keys.push([0xff; 32]);
+        trie.insert_single([0xfe; 32], [1; 32]);
+
+        let proof = prover::create_verkle_proof(&trie.storage, keys).unwrap();
+
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).unwrap();
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json, format!("0x{}", hex::encode(&bytes)));
+        assert_eq!(serde_json::from_value::<VerkleProof>(json).unwrap(), proof);
+
+        let mut hint_bytes = Vec::new();
+        proof.verification_hint.write(&mut hint_bytes).unwrap();
+        let json = serde_json::to_value(&proof.verification_hint).unwrap();
+        assert_eq!(json, format!("0x{}", hex::encode(&hint_bytes)));
+        assert_eq!(
+            serde_json::from_value::<VerificationHint>(json).unwrap(),
+            proof.verification_hint
+        );
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&proof, &mut cbor).unwrap();
+        assert_eq!(
+            ciborium::from_reader::<VerkleProof, _>(&cbor[..]).unwrap(),
+            proof
+        );
+
+        // Malformed proofs are rejected instead of panicking
+        let truncated = format!("\"0x{}\"", hex::encode(&bytes[..bytes.len() - 32]));
+        assert!(serde_json::from_str::<VerkleProof>(&truncated).is_err());
+        let mut bad_ext_status = hint_bytes.clone();
+        *bad_ext_status.last_mut().unwrap() |= 3;
+        let bad_ext_status = format!("\"0x{}\"", hex::encode(bad_ext_status));
+        assert!(serde_json::from_str::<VerificationHint>(&bad_ext_status).is_err());
+    }
+
+    #[test]
+    fn diagnostics_valid_proof() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let mut keys = Vec::new();
+        for i in 0..=3 {
+            let mut key_0 = [0u8; 32];
+            key_0[0] = i;
+            keys.push(key_0);
+            trie.insert_single(key_0, key_0);
+        }
+        let root = trie.root_commitment();
+
+        let proof = prover::create_verkle_proof(&trie.storage, keys.clone()).unwrap();
+        let values: Vec<_> = keys.iter().map(|val| Some(*val)).collect();
+        assert!(proof.check_with_diagnostics(keys, values, root).is_ok());
+    }
+
+    #[test]
+    fn diagnostics_wrong_value() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key = [1u8; 32];
+        trie.insert_single(key, [2u8; 32]);
+        let root = trie.root_commitment();
+
+        let proof = prover::create_verkle_proof(&trie.storage, vec![key]).unwrap();
+
+        let (ok, update_hint) = proof.clone().check(vec![key], vec![Some([3u8; 32])], root);
+        assert!(!ok);
+        assert!(update_hint.is_none());
+
+        let result = proof.check_with_diagnostics(vec![key], vec![Some([3u8; 32])], root);
+        assert_eq!(
+            result.err(),
+            Some(ProofVerificationError::InvalidMultiPointProof)
+        );
+    }
+
+    #[test]
+    fn diagnostics_multiple_stems_at_prefix() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let mut key_a = [0u8; 32];
+        key_a[0..2].copy_from_slice(&[1, 1]);
+        let mut key_b = [0u8; 32];
+        key_b[0..2].copy_from_slice(&[1, 2]);
+        trie.insert_single(key_a, key_a);
+        trie.insert_single(key_b, key_b);
+        let root = trie.root_commitment();
+
+        let mut proof = prover::create_verkle_proof(&trie.storage, vec![key_a, key_b]).unwrap();
+        assert_eq!(proof.verification_hint.depths, vec![2, 2]);
+        // Claim that both stems are directly under the inner node at [1]
+        proof.verification_hint.depths = vec![1, 1];
+        assert_eq!(
+            proof
+                .check_with_diagnostics(vec![key_a, key_b], vec![Some(key_a), Some(key_b)], root)
+                .err(),
+            Some(ProofVerificationError::MultipleStemsAtPrefix {
+                stem: key_a[0..31].try_into().unwrap(),
+                other_stem: key_b[0..31].try_into().unwrap(),
+                depth: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn batch_check_reports_each_proof() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let keys: Vec<_> = (0..4u8).map(|i| [i; 32]).collect();
+        for key in &keys {
+            trie.insert_single(*key, *key);
+        }
+        let root = trie.root_commitment();
+
+        let proof_for =
+            |key: [u8; 32]| prover::create_verkle_proof(&trie.storage, vec![key]).unwrap();
+
+        let proofs = vec![
+            (proof_for(keys[0]), vec![keys[0]], vec![Some(keys[0])], root),
+            (proof_for(keys[1]), vec![keys[1]], vec![Some(keys[1])], root),
+        ];
+        let results = VerkleProof::batch_check(proofs);
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        let proofs = vec![
+            (proof_for(keys[0]), vec![keys[0]], vec![Some(keys[0])], root),
+            (
+                proof_for(keys[1]),
+                vec![keys[1]],
+                vec![Some([9u8; 32])],
+                root,
+            ),
+            (proof_for(keys[2]), vec![keys[2]], vec![], root),
+            (proof_for(keys[3]), vec![keys[3]], vec![Some(keys[3])], root),
+        ];
+        let results = VerkleProof::batch_check(proofs);
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().err(),
+            Some(&ProofVerificationError::InvalidMultiPointProof)
+        );
+        assert_eq!(
+            results[2].as_ref().err(),
+            Some(&ProofVerificationError::MismatchedValuesLength { keys: 1, values: 0 })
+        );
+        assert!(results[3].is_ok());
+    }
+
+    #[test]
+    fn diagnostics_value_for_absent_key() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        trie.insert_single([0u8; 32], [0u8; 32]);
+        let root = trie.root_commitment();
+
+        // This key leads to an empty slot in the root
+        let absent_key = [5u8; 32];
+        let proof = prover::create_verkle_proof(&trie.storage, vec![absent_key]).unwrap();
+        let result = proof.check_with_diagnostics(vec![absent_key], vec![Some([1u8; 32])], root);
+        assert_eq!(
+            result.err(),
+            Some(ProofVerificationError::ValueForAbsentStem {
+                key: absent_key,
+                ext_status: ExtPresent::None,
+            })
+        );
+    }
+
+    #[test]
+    fn diagnostics_conflicting_values() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key = [1u8; 32];
+        trie.insert_single(key, key);
+        let root = trie.root_commitment();
+
+        let proof = prover::create_verkle_proof(&trie.storage, vec![key, key]).unwrap();
+        let result = proof.check_with_diagnostics(vec![key, key], vec![Some(key), None], root);
+        assert_eq!(
+            result.err(),
+            Some(ProofVerificationError::ConflictingValues { key })
+        );
+    }
+
+    #[test]
+    fn diagnostics_hint_mismatch() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key = [1u8; 32];
+        trie.insert_single(key, key);
+        let root = trie.root_commitment();
+
+        let proof = prover::create_verkle_proof(&trie.storage, vec![key]).unwrap();
+
+        let mut bad_depth = proof.clone();
+        bad_depth.verification_hint.depths[0] = 0;
+        assert_eq!(
+            bad_depth
+                .check_with_diagnostics(vec![key], vec![Some(key)], root)
+                .err(),
+            Some(ProofVerificationError::InvalidDepthForExtStatus {
+                stem: [1u8; 31],
+                depth: 0,
+                ext_status: ExtPresent::Present,
+            })
+        );
+
+        let mut missing_depth = proof;
+        missing_depth.verification_hint.depths.clear();
+        assert_eq!(
+            missing_depth
+                .check_with_diagnostics(vec![key], vec![Some(key)], root)
+                .err(),
+            Some(ProofVerificationError::HintLengthMismatch {
+                stems: 1,
+                depths: 0,
+                extension_statuses: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn diagnostics_commitment_count() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key = [1u8; 32];
+        trie.insert_single(key, key);
+        let root = trie.root_commitment();
+
+        let proof = prover::create_verkle_proof(&trie.storage, vec![key]).unwrap();
+        let num_comms = proof.comms_sorted.len();
+
+        let mut extra_comm = proof.clone();
+        extra_comm.comms_sorted.push(root);
+        assert_eq!(
+            extra_comm
+                .check_with_diagnostics(vec![key], vec![Some(key)], root)
+                .err(),
+            Some(ProofVerificationError::UnusedCommitments {
+                expected: num_comms,
+                got: num_comms + 1,
+            })
+        );
+
+        let mut missing_comm = proof;
+        missing_comm.comms_sorted.pop();
+        assert_eq!(
+            missing_comm
+                .check_with_diagnostics(vec![key], vec![Some(key)], root)
+                .err(),
+            Some(ProofVerificationError::MissingCommitments {
+                expected: num_comms,
+                got: num_comms - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn diagnostics_diff_stem_no_proof() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let present_key = [0u8; 32];
+        trie.insert_single(present_key, present_key);
+        let root = trie.root_commitment();
+
+        // This key leads to the stem of `present_key`, which we do not open
+        let mut absent_key = [0u8; 32];
+        absent_key[1] = 1;
+        let proof = prover::create_verkle_proof(&trie.storage, vec![absent_key]).unwrap();
+        assert!(proof
+            .verification_hint
+            .diff_stem_no_proof
+            .contains(&[0u8; 31]));
+
+        let mut missing_stem = proof.clone();
+        missing_stem.verification_hint.diff_stem_no_proof.clear();
+        assert_eq!(
+            missing_stem
+                .check_with_diagnostics(vec![absent_key], vec![None], root)
+                .err(),
+            Some(ProofVerificationError::MissingDiffStemNoProof {
+                stem: absent_key[0..31].try_into().unwrap(),
+                depth: 1,
+            })
+        );
+
+        let mut unused_stem = proof;
+        unused_stem
+            .verification_hint
+            .diff_stem_no_proof
+            .insert([9u8; 31]);
+        assert_eq!(
+            unused_stem
+                .check_with_diagnostics(vec![absent_key], vec![None], root)
+                .err(),
+            Some(ProofVerificationError::UnusedDiffStemNoProof { stem: [9u8; 31] })
+        );
+    }
 }
@@ -1,239 +1,39 @@
 // This is just tech debt. The golang codebase should be reverted to make proofs opaque again
 // and the rest of the code should be handled by clients.
 
-use banderwagon::{CanonicalDeserialize, Element, Fr};
-use ipa_multipoint::{ipa::IPAProof, multiproof::MultiPointProof};
+use banderwagon::Element;
 
-use super::{ExtPresent, VerificationHint, VerkleProof};
-
-struct SuffixDiff {
-    suffix: u8,
-    current_value: Option<[u8; 32]>,
-    new_value: Option<[u8; 32]>,
-}
-
-struct StateDiff {
-    stem: [u8; 31],
-    suffix_diffs: Vec<SuffixDiff>,
-}
-
-impl StateDiff {
-    #[allow(clippy::type_complexity)]
-    /// Returns the keys, their old values and their new values
-    pub fn keys_with_current_values(
-        &self,
-    ) -> (Vec<[u8; 32]>, Vec<Option<[u8; 32]>>, Vec<Option<[u8; 32]>>) {
-        let mut keys = Vec::new();
-        let mut current_values = Vec::new();
-        let mut new_values = Vec::new();
-
-        let stem = self.stem;
-        for suffix_diff in &self.suffix_diffs {
-            let suffix = suffix_diff.suffix;
-            let current_value = suffix_diff.current_value;
-            let new_value = suffix_diff.new_value;
-
-            let mut key = stem.to_vec();
-            key.push(suffix);
-            keys.push(key.try_into().unwrap());
-
-            current_values.push(current_value);
-            new_values.push(new_value);
-        }
-
-        (keys, current_values, new_values)
-    }
-}
+use super::witness::ExecutionWitness;
+pub use super::witness::KeysValues;
+use super::VerkleProof;
+use crate::errors::WitnessError;
 
+// Kept for backwards compatibility, new code should use `ExecutionWitness` directly
 pub struct VerkleProofGo {
-    state_diffs: Vec<StateDiff>,
-    commitments_by_path: Vec<[u8; 32]>,
-    other_stems: Vec<[u8; 31]>,
-    proof: MultiPointProofGo,
-    depths_extension_present: Vec<u8>,
-}
-
-pub struct KeysValues {
-    pub keys: Vec<[u8; 32]>,
-    pub current_values: Vec<Option<[u8; 32]>>,
-    pub new_values: Vec<Option<[u8; 32]>>,
+    witness: ExecutionWitness,
 }
 
 impl VerkleProofGo {
     pub fn from_verkle_proof_go_to_verkle_proof(&self) -> Option<(VerkleProof, KeysValues)> {
-        let mut depths = Vec::new();
-        let mut extension_present = Vec::new();
-        for byte in &self.depths_extension_present {
-            let (ext_status, depth) = byte_to_depth_extension_present(*byte);
-            extension_present.push(ext_status);
-            depths.push(depth);
-        }
-        let mut keys: Vec<[u8; 32]> = Vec::new();
-        let mut current_values = Vec::new();
-        let mut new_values = Vec::new();
-        for state_diff in &self.state_diffs {
-            let (state_keys, state_current_values, state_new_values) =
-                state_diff.keys_with_current_values();
-            keys.extend(state_keys);
-            current_values.extend(state_current_values);
-            new_values.extend(state_new_values);
-        }
-
-        let mut comms_sorted = Vec::with_capacity(self.commitments_by_path.len());
-        for comm_sorted in &self.commitments_by_path {
-            comms_sorted.push(bytes32_to_element(*comm_sorted)?)
-        }
-
-        let mut l_vec = Vec::with_capacity(self.proof.cl.len());
-        for cl in &self.proof.cl {
-            l_vec.push(bytes32_to_element(*cl)?)
-        }
-        let mut r_vec = Vec::with_capacity(self.proof.cr.len());
-        for cr in &self.proof.cr {
-            r_vec.push(bytes32_to_element(*cr)?)
-        }
-
-        let proof = MultiPointProof {
-            open_proof: IPAProof {
-                L_vec: l_vec,
-                R_vec: r_vec,
-                a: bytes32_to_scalar(self.proof.final_evaluation),
-            },
-            g_x_comm: bytes32_to_element(self.proof.d)?,
-        };
-
-        Some((
-            VerkleProof {
-                verification_hint: VerificationHint {
-                    depths,
-                    extension_present,
-                    diff_stem_no_proof: self.other_stems.iter().copied().collect(),
-                },
-                comms_sorted,
-                proof,
-            },
-            KeysValues {
-                keys,
-                current_values,
-                new_values,
-            },
-        ))
+        self.witness.to_verkle_proof().ok()
     }
 
-    pub fn from_json_str(execution_witness: &str) -> Self {
-        let execution_witness: serde_conversions::ExecutionWitness =
-            serde_json::from_str(execution_witness).unwrap();
-
-        let state_diffs = execution_witness
-            .state_diffs
-            .into_iter()
-            .map(|state_diff| StateDiff {
-                stem: hex_to_bytes31(&state_diff.stem),
-                suffix_diffs: state_diff
-                    .suffix_diffs
-                    .into_iter()
-                    .map(|suffix_diff| SuffixDiff {
-                        suffix: suffix_diff.suffix,
-                        current_value: suffix_diff.current_value.map(|cv| hex_to_bytes32(&cv)),
-                        new_value: suffix_diff.new_value.map(|nv| hex_to_bytes32(&nv)),
-                    })
-                    .collect(),
-            })
-            .collect();
-
-        let other_stems = execution_witness
-            .verkle_proof
-            .other_stems
-            .into_iter()
-            .map(|os| hex_to_bytes31(&os))
-            .collect();
-
-        let commitments_by_path = execution_witness
-            .verkle_proof
-            .commitments_by_path
-            .into_iter()
-            .map(|cbp| hex_to_bytes32(&cbp))
-            .collect();
-
-        let proof = MultiPointProofGo {
-            d: hex_to_bytes32(&execution_witness.verkle_proof.d),
-            cl: execution_witness
-                .verkle_proof
-                .ipa_proof
-                .cl
-                .into_iter()
-                .map(|cl| hex_to_bytes32(&cl))
-                .collect(),
-            cr: execution_witness
-                .verkle_proof
-                .ipa_proof
-                .cr
-                .into_iter()
-                .map(|cr| hex_to_bytes32(&cr))
-                .collect(),
-            final_evaluation: hex_to_bytes32(
-                &execution_witness.verkle_proof.ipa_proof.final_evaluation,
-            ),
-        };
-
-        Self {
-            state_diffs,
-            commitments_by_path,
-            other_stems,
-            proof,
-            depths_extension_present: hex_to_bytes(
-                &execution_witness.verkle_proof.depth_extension_present,
-            ),
-        }
+    pub fn from_json_str(execution_witness: &str) -> Result<Self, WitnessError> {
+        let witness = ExecutionWitness::from_json_str(execution_witness)?;
+        Ok(Self { witness })
     }
 }
 
-struct MultiPointProofGo {
-    d: [u8; 32],
-    cl: Vec<[u8; 32]>,
-    cr: Vec<[u8; 32]>,
-    final_evaluation: [u8; 32],
-}
-
 pub fn hex_to_bytes32(hex: &str) -> [u8; 32] {
-    hex_to_fixed_size_array(hex)
-}
-fn hex_to_bytes31(hex: &str) -> [u8; 31] {
-    hex_to_fixed_size_array(hex)
-}
-fn hex_to_fixed_size_array<const N: usize>(hex: &str) -> [u8; N] {
-    let hex = hex.trim_start_matches("0x");
-    let bytes = hex::decode(hex).unwrap();
-    let mut bytes_n = [0u8; N];
-    bytes_n.copy_from_slice(&bytes);
-    bytes_n
-}
-fn hex_to_bytes(hex: &str) -> Vec<u8> {
     let hex = hex.trim_start_matches("0x");
     let bytes = hex::decode(hex).unwrap();
-    let mut bytes_vec = vec![0u8; bytes.len()];
-    bytes_vec.copy_from_slice(&bytes);
-    bytes_vec
+    let mut bytes32 = [0u8; 32];
+    bytes32.copy_from_slice(&bytes);
+    bytes32
 }
 pub fn bytes32_to_element(bytes: [u8; 32]) -> Option<Element> {
     Element::from_bytes(&bytes)
 }
-fn bytes32_to_scalar(mut bytes: [u8; 32]) -> Fr {
-    bytes.reverse();
-    CanonicalDeserialize::deserialize_compressed(&bytes[..]).unwrap()
-}
-
-fn byte_to_depth_extension_present(value: u8) -> (ExtPresent, u8) {
-    let ext_status = value & 3;
-    let ext_status = match ext_status {
-        0 => ExtPresent::None,
-        1 => ExtPresent::DifferentStem,
-        2 => ExtPresent::Present,
-        x => panic!("unexpected ext status number {} ", x),
-    };
-    let depth = value >> 3;
-    (ext_status, depth)
-}
 
 // Taken from https://github.com/ethereumjs/ethereumjs-monorepo/blob/master/packages/statemanager/test/testdata/verkleKaustinenBlock.json#L1-L2626
 // Block number 0x62
@@ -289,63 +89,6 @@ pub const EXECUTION_WITNESS_JSON: &str = r#"
     }
     "#;
 
-// Serde conversion so we can convert from the json execution witness string into the golang proof format
-pub mod serde_conversions {
-
-    use serde::{Deserialize, Serialize};
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub struct ExecutionWitness {
-        #[serde(rename = "stateDiff")]
-        pub(crate) state_diffs: Vec<StateDiff>,
-        #[serde(rename = "verkleProof")]
-        pub(crate) verkle_proof: VerkleProof,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub(crate) struct StateDiff {
-        pub(crate) stem: String,
-        #[serde(rename = "suffixDiffs")]
-        pub(crate) suffix_diffs: Vec<SuffixDiff>,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub(crate) struct SuffixDiff {
-        pub(crate) suffix: u8,
-        #[serde(rename = "currentValue")]
-        pub(crate) current_value: Option<String>,
-        #[serde(rename = "newValue")]
-        pub(crate) new_value: Option<String>,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub(crate) struct VerkleProof {
-        #[serde(rename = "otherStems")]
-        pub(crate) other_stems: Vec<String>,
-        #[serde(rename = "depthExtensionPresent")]
-        pub(crate) depth_extension_present: String,
-        #[serde(rename = "commitmentsByPath")]
-        pub(crate) commitments_by_path: Vec<String>,
-        pub(crate) d: String,
-        #[serde(rename = "ipaProof")]
-        pub(crate) ipa_proof: IpaProof,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub(crate) struct IpaProof {
-        pub(crate) cl: Vec<String>,
-        pub(crate) cr: Vec<String>,
-        #[serde(rename = "finalEvaluation")]
-        pub(crate) final_evaluation: String,
-    }
-
-    #[test]
-    fn test_serde_works() {
-        use super::EXECUTION_WITNESS_JSON;
-        let _: ExecutionWitness = serde_json::from_str(EXECUTION_WITNESS_JSON).unwrap();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::proof::golang_proof_format::{
@@ -355,7 +98,7 @@ mod tests {
 
     #[test]
     fn test_proof_from_json_golang_serde() {
-        let verkle_proof_go = VerkleProofGo::from_json_str(EXECUTION_WITNESS_JSON);
+        let verkle_proof_go = VerkleProofGo::from_json_str(EXECUTION_WITNESS_JSON).unwrap();
         let (got_verkle_proof, keys_values) = verkle_proof_go
             .from_verkle_proof_go_to_verkle_proof()
             .unwrap();
@@ -366,4 +109,9 @@ mod tests {
             got_verkle_proof.check(keys_values.keys, keys_values.current_values, prestate_root);
         assert!(ok);
     }
+
+    #[test]
+    fn test_invalid_json_is_an_error() {
+        assert!(VerkleProofGo::from_json_str("not json").is_err());
+    }
 }
@@ -207,6 +207,7 @@ impl ExtOpeningData {
             Fr::from_le_bytes_mod_order(&stem),
             stem_meta.hash_c1,
             stem_meta.hash_c2,
+            Fr::from(stem_meta.last_epoch),
         ];
 
         // Open(Ext, 0) = 1
@@ -1,12 +1,12 @@
 use super::{VerificationHint, VerkleProof};
 use crate::{
-    constants::{CRS, PRECOMPUTED_WEIGHTS},
+    constants::PROVER_KEY,
     database::ReadOnlyHigherDb,
     errors::ProofCreationError,
     proof::opening_data::{OpeningData, Openings},
 };
 use ipa_multipoint::{
-    multiproof::{MultiPoint, ProverQuery},
+    multiproof::{MultiPoint, ProverQuery, ProverQueryRef},
     transcript::Transcript,
 };
 use itertools::Itertools;
@@ -41,7 +41,8 @@ pub fn create_verkle_proof<Storage: ReadOnlyHigherDb>(
         .collect();
 
     let mut transcript = Transcript::new(b"vt");
-    let proof = MultiPoint::open(CRS.clone(), &PRECOMPUTED_WEIGHTS, &mut transcript, queries);
+    let queries: Vec<_> = queries.iter().map(ProverQueryRef::from).collect();
+    let proof = MultiPoint::open_with_key(&PROVER_KEY, &mut transcript, &queries);
 
     Ok(VerkleProof {
         comms_sorted,
@@ -0,0 +1,537 @@
+use super::{prover, VerkleProof};
+use crate::{
+    database::{BranchChild, ReadOnlyHigherDb},
+    errors::{HintError, ProofCreationError, RangeProofError},
+    proof::ExtPresent,
+    trie::stem_meta_from_leaves,
+    Stem,
+};
+use banderwagon::Element;
+use ipa_multipoint::committer::Committer;
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+
+// The leaves of a stem, as (suffix, value) pairs
+pub type StemLeaves = Vec<(u8, [u8; 32])>;
+
+// A stem in the range, along with everything needed to recompute its extension commitment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeStem {
+    pub stem: Stem,
+    // The epoch that the stem was last written to, this is always zero
+    // when state expiry is not enabled
+    pub last_epoch: u64,
+    // All of the leaves of the stem, sorted by suffix
+    pub leaves: StemLeaves,
+}
+
+// A proof that `stems` are all of the stems in the trie, which lie in an inclusive range of stems.
+//
+// The completeness of the range is shown by covering the whole range with "slots" in the trie.
+// A slot is a child of an inner node, which is either empty or holds a single stem.
+// The `VerkleProof` opens every slot which intersects the range, so the verifier can see
+// that no stems were left out between the range boundaries.
+//
+// Slots that are empty, or that hold a stem outside of the range, are opened using a
+// probe stem. This is a stem inside of the range, which leads to that slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StemRangeProof {
+    // All of the stems in the range, sorted
+    pub stems: Vec<RangeStem>,
+    // Stems which are not in the trie, that we use to open the rest of the slots in the range
+    pub probe_stems: Vec<Stem>,
+    // Opens every slot that intersects the range
+    pub proof: VerkleProof,
+}
+
+impl StemRangeProof {
+    pub fn read<R: Read>(mut reader: R) -> Result<StemRangeProof, HintError> {
+        let num_stems = read_u32(&mut reader)?;
+
+        let mut stems = Vec::new();
+        for _ in 0..num_stems {
+            let mut stem = [0u8; 31];
+            reader.read_exact(&mut stem)?;
+            let mut last_epoch = [0u8; 8];
+            reader.read_exact(&mut last_epoch)?;
+
+            let num_leaves = read_u32(&mut reader)?;
+            let mut leaves = Vec::new();
+            for _ in 0..num_leaves {
+                let mut suffix = [0u8; 1];
+                reader.read_exact(&mut suffix)?;
+                let mut value = [0u8; 32];
+                reader.read_exact(&mut value)?;
+                leaves.push((suffix[0], value));
+            }
+            stems.push(RangeStem {
+                stem,
+                last_epoch: u64::from_le_bytes(last_epoch),
+                leaves,
+            });
+        }
+
+        let num_probe_stems = read_u32(&mut reader)?;
+        let mut probe_stems = Vec::new();
+        for _ in 0..num_probe_stems {
+            let mut stem = [0u8; 31];
+            reader.read_exact(&mut stem)?;
+            probe_stems.push(stem);
+        }
+
+        // The verkle proof reads until the end, so it must come last
+        let proof = VerkleProof::read(reader)?;
+
+        Ok(StemRangeProof {
+            stems,
+            probe_stems,
+            proof,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), HintError> {
+        writer.write_all(&(self.stems.len() as u32).to_le_bytes())?;
+        for range_stem in &self.stems {
+            writer.write_all(&range_stem.stem)?;
+            writer.write_all(&range_stem.last_epoch.to_le_bytes())?;
+            writer.write_all(&(range_stem.leaves.len() as u32).to_le_bytes())?;
+            for (suffix, value) in &range_stem.leaves {
+                writer.write_all(&[*suffix])?;
+                writer.write_all(value)?;
+            }
+        }
+
+        writer.write_all(&(self.probe_stems.len() as u32).to_le_bytes())?;
+        for stem in &self.probe_stems {
+            writer.write_all(stem)?;
+        }
+
+        self.proof.write(writer)
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, HintError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// Returns all of the stems and leaves in the inclusive range [start, end],
+// along with a proof that no stems in that range were left out.
+pub fn create_stem_range_proof<Storage: ReadOnlyHigherDb>(
+    storage: &Storage,
+    start: Stem,
+    end: Stem,
+) -> Result<StemRangeProof, ProofCreationError> {
+    if start > end {
+        return Err(ProofCreationError::InvalidStemRange);
+    }
+
+    let mut stems_in_range = Vec::new();
+    let mut probe_stems = Vec::new();
+    collect_range_slots(
+        storage,
+        Vec::new(),
+        start,
+        end,
+        &mut stems_in_range,
+        &mut probe_stems,
+    );
+
+    // We only need to open a single key per stem, in order to reveal the extension
+    // commitment. The verifier will recompute the extension commitment from the leaves.
+    let keys: Vec<_> = stems_in_range
+        .iter()
+        .chain(&probe_stems)
+        .map(|stem| stem_key(*stem, 0))
+        .collect();
+    let proof = prover::create_verkle_proof(storage, keys)?;
+
+    let stems = stems_in_range
+        .into_iter()
+        .map(|stem| {
+            let last_epoch = storage
+                .get_stem_meta(stem)
+                .map(|stem_meta| stem_meta.last_epoch)
+                .unwrap_or_default();
+            let mut leaves = storage.get_stem_children(stem);
+            leaves.sort_unstable_by_key(|(suffix, _)| *suffix);
+            RangeStem {
+                stem,
+                last_epoch,
+                leaves,
+            }
+        })
+        .collect();
+
+    Ok(StemRangeProof {
+        stems,
+        probe_stems,
+        proof,
+    })
+}
+
+// Walks all of the slots under the inner node at `path` that intersect the range.
+// Stems in the range are added to `stems`, and every other slot is given a probe stem.
+fn collect_range_slots<Storage: ReadOnlyHigherDb>(
+    storage: &Storage,
+    path: Vec<u8>,
+    start: Stem,
+    end: Stem,
+    stems: &mut Vec<Stem>,
+    probe_stems: &mut Vec<Stem>,
+) {
+    let depth = path.len();
+    let first_index = if path[..] == start[0..depth] {
+        start[depth]
+    } else {
+        0
+    };
+    let last_index = if path[..] == end[0..depth] {
+        end[depth]
+    } else {
+        255
+    };
+
+    for index in first_index..=last_index {
+        let mut child_path = path.clone();
+        child_path.push(index);
+
+        match storage.get_branch_child(&path, index) {
+            Some(BranchChild::Branch(_)) => {
+                collect_range_slots(storage, child_path, start, end, stems, probe_stems)
+            }
+            Some(BranchChild::Stem(stem)) if start <= stem && stem <= end => stems.push(stem),
+            // The slot is either empty or holds a stem outside of the range.
+            // The smallest stem in the range under this slot is guaranteed to lead to it.
+            Some(BranchChild::Stem(_)) | None => {
+                probe_stems.push(start.max(slot_bounds(&child_path).0))
+            }
+        }
+    }
+}
+
+// Checks that `range_proof.stems` are all of the stems in the trie with root `root`,
+// that lie in the inclusive range [start, end], and that their leaves are correct.
+pub fn verify_stem_range_proof<C: Committer>(
+    range_proof: StemRangeProof,
+    start: Stem,
+    end: Stem,
+    root: Element,
+    committer: &C,
+) -> Result<(), RangeProofError> {
+    if start > end {
+        return Err(RangeProofError::InvalidRange);
+    }
+
+    let mut stems_in_range = BTreeSet::new();
+    for RangeStem { stem, leaves, .. } in &range_proof.stems {
+        if *stem < start || *stem > end {
+            return Err(RangeProofError::StemOutOfRange { stem: *stem });
+        }
+        if !stems_in_range.insert(*stem) {
+            return Err(RangeProofError::DuplicateStem { stem: *stem });
+        }
+        if leaves.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(RangeProofError::UnsortedLeaves { stem: *stem });
+        }
+    }
+    for stem in &range_proof.probe_stems {
+        if *stem < start || *stem > end {
+            return Err(RangeProofError::StemOutOfRange { stem: *stem });
+        }
+    }
+
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+    for RangeStem { stem, leaves, .. } in &range_proof.stems {
+        keys.push(stem_key(*stem, 0));
+        values.push(
+            leaves
+                .first()
+                .filter(|(suffix, _)| *suffix == 0)
+                .map(|(_, value)| *value),
+        );
+    }
+    for stem in &range_proof.probe_stems {
+        keys.push(stem_key(*stem, 0));
+        values.push(None);
+    }
+
+    let update_hint = range_proof
+        .proof
+        .check_with_diagnostics(keys, values, root)?;
+
+    // Every stem that was opened in the proof, covers the slot that it was found in.
+    // Check that these slots do not hide any stems in the range, and that they cover the whole range.
+    let mut slots = Vec::with_capacity(update_hint.depths_and_ext_by_stem.len());
+    for (stem, (ext_status, depth)) in &update_hint.depths_and_ext_by_stem {
+        let in_range = start <= *stem && *stem <= end;
+        let is_range_stem = stems_in_range.contains(stem);
+        match ext_status {
+            ExtPresent::Present if in_range && !is_range_stem => {
+                return Err(RangeProofError::MissingStem { stem: *stem })
+            }
+            ExtPresent::None | ExtPresent::DifferentStem if is_range_stem => {
+                return Err(RangeProofError::StemNotInTrie { stem: *stem })
+            }
+            _ => {}
+        }
+        slots.push(slot_bounds(&stem[0..*depth as usize]));
+    }
+    for other_stem in update_hint.other_stems_by_prefix.values() {
+        if start <= *other_stem && *other_stem <= end && !stems_in_range.contains(other_stem) {
+            return Err(RangeProofError::MissingStem { stem: *other_stem });
+        }
+    }
+
+    slots.sort_unstable();
+    let mut next_uncovered = Some(start);
+    for (slot_start, slot_end) in slots {
+        match next_uncovered {
+            Some(stem) if stem > end => break,
+            Some(stem) if slot_start <= stem && stem <= slot_end => {
+                next_uncovered = next_stem(slot_end)
+            }
+            _ => {}
+        }
+    }
+    if let Some(stem) = next_uncovered.filter(|stem| *stem <= end) {
+        return Err(RangeProofError::UncoveredStem { stem });
+    }
+
+    // The leaves must match the extension commitment that was opened for their stem
+    for RangeStem {
+        stem,
+        last_epoch,
+        leaves,
+    } in &range_proof.stems
+    {
+        let (_, depth) = update_hint.depths_and_ext_by_stem[stem];
+        let expected = update_hint.commitments_by_path[&stem[0..depth as usize]];
+        let stem_meta = stem_meta_from_leaves(*stem, leaves, *last_epoch, committer);
+        if stem_meta.stem_commitment != expected {
+            return Err(RangeProofError::LeavesMismatch { stem: *stem });
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the smallest and largest stem that lie under the slot at `path`
+fn slot_bounds(path: &[u8]) -> (Stem, Stem) {
+    let mut lowest = [0u8; 31];
+    let mut highest = [255u8; 31];
+    lowest[0..path.len()].copy_from_slice(path);
+    highest[0..path.len()].copy_from_slice(path);
+    (lowest, highest)
+}
+
+// Returns the stem that comes directly after `stem`, or None if `stem` is the largest stem
+fn next_stem(mut stem: Stem) -> Option<Stem> {
+    for byte in stem.iter_mut().rev() {
+        if *byte == 255 {
+            *byte = 0;
+        } else {
+            *byte += 1;
+            return Some(stem);
+        }
+    }
+    None
+}
+
+fn stem_key(stem: Stem, suffix: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[0..31].copy_from_slice(&stem);
+    key[31] = suffix;
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_stem_range_proof, stem_key, verify_stem_range_proof, StemRangeProof};
+    use crate::constants::new_crs;
+    use crate::database::memory_db::MemoryDb;
+    use crate::errors::{ProofVerificationError, RangeProofError};
+    use crate::{trie::Trie, DefaultConfig, TrieTrait};
+    use ipa_multipoint::committer::DefaultCommitter;
+
+    fn make_trie() -> Trie<MemoryDb, DefaultCommitter> {
+        let mut trie = Trie::new(DefaultConfig::new(MemoryDb::new()));
+
+        // Stems at various depths, some of which share a long prefix
+        for (first, second, suffix) in [(0, 0, 0), (0, 1, 5), (3, 0, 200), (3, 0, 7), (7, 9, 0)] {
+            let mut key = [1u8; 32];
+            key[0] = first;
+            key[1] = second;
+            key[31] = suffix;
+            trie.insert_single(key, [suffix; 32]);
+        }
+        let mut key = [3u8; 32];
+        key[30] = 4;
+        trie.insert_single(key, [4u8; 32]);
+        trie
+    }
+
+    fn stem(first: u8, second: u8) -> [u8; 31] {
+        let mut stem = [1u8; 31];
+        stem[0] = first;
+        stem[1] = second;
+        stem
+    }
+
+    #[test]
+    fn full_range() {
+        let trie = make_trie();
+        let committer = DefaultCommitter::new(&new_crs().G);
+
+        let start = [0u8; 31];
+        let end = [255u8; 31];
+        let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+        assert_eq!(range_proof.stems.len(), 5);
+
+        let leaves = &range_proof.stems[2].leaves;
+        assert_eq!(leaves, &vec![(7, [7u8; 32]), (200, [200u8; 32])]);
+
+        verify_stem_range_proof(range_proof, start, end, trie.root_commitment(), &committer)
+            .unwrap();
+    }
+
+    #[test]
+    fn partial_ranges() {
+        let trie = make_trie();
+        let committer = DefaultCommitter::new(&new_crs().G);
+        let root = trie.root_commitment();
+
+        let ranges = [
+            (stem(0, 1), stem(3, 0), 2),
+            (stem(0, 2), stem(2, 255), 0),
+            (stem(3, 0), stem(3, 0), 1),
+            ([3u8; 31], [3u8; 31], 0),
+            (stem(8, 0), [255u8; 31], 0),
+        ];
+        for (start, end, num_stems) in ranges {
+            let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+            assert_eq!(range_proof.stems.len(), num_stems);
+            verify_stem_range_proof(range_proof, start, end, root, &committer).unwrap();
+        }
+    }
+
+    #[test]
+    fn state_expiry() {
+        let mut trie =
+            Trie::new_with_state_expiry(DefaultConfig::new(MemoryDb::new()), 10).unwrap();
+        let committer = DefaultCommitter::new(&new_crs().G);
+
+        trie.insert_single([1u8; 32], [1u8; 32]);
+        trie.set_current_epoch(3).unwrap();
+        trie.insert_single([2u8; 32], [2u8; 32]);
+
+        let start = [0u8; 31];
+        let end = [255u8; 31];
+        let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+        let last_epochs: Vec<_> = range_proof.stems.iter().map(|s| s.last_epoch).collect();
+        assert_eq!(last_epochs, vec![0, 3]);
+
+        verify_stem_range_proof(range_proof, start, end, trie.root_commitment(), &committer)
+            .unwrap();
+    }
+
+    #[test]
+    fn empty_trie() {
+        let trie = Trie::new(DefaultConfig::new(MemoryDb::new()));
+        let committer = DefaultCommitter::new(&new_crs().G);
+
+        let start = [0u8; 31];
+        let end = [255u8; 31];
+        let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+        assert!(range_proof.stems.is_empty());
+        verify_stem_range_proof(range_proof, start, end, trie.root_commitment(), &committer)
+            .unwrap();
+    }
+
+    // Recreates the verkle proof for the stems and probe stems, as a dishonest prover would
+    fn reprove(trie: &Trie<MemoryDb, DefaultCommitter>, range_proof: &mut StemRangeProof) {
+        let keys = range_proof
+            .stems
+            .iter()
+            .map(|range_stem| range_stem.stem)
+            .chain(range_proof.probe_stems.iter().copied())
+            .map(|stem| stem_key(stem, 0));
+        range_proof.proof = trie.create_verkle_proof(keys).unwrap();
+    }
+
+    #[test]
+    fn omitted_stem() {
+        let trie = make_trie();
+        let committer = DefaultCommitter::new(&new_crs().G);
+        let root = trie.root_commitment();
+
+        let start = stem(0, 0);
+        let end = stem(3, 255);
+        let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+
+        // Replacing a stem in the response with a probe stem that leads to its slot
+        let mut missing_stem = range_proof.clone();
+        let removed = missing_stem.stems.remove(1).stem;
+        let mut probe = removed;
+        probe[2] = 0;
+        missing_stem.probe_stems.push(probe);
+        reprove(&trie, &mut missing_stem);
+        assert_eq!(
+            verify_stem_range_proof(missing_stem, start, end, root, &committer),
+            Err(RangeProofError::MissingStem { stem: removed })
+        );
+
+        // Dropping a probe stem leaves a slot in the range uncovered
+        let mut missing_probe = range_proof;
+        let removed_probe = missing_probe.probe_stems.pop().unwrap();
+        reprove(&trie, &mut missing_probe);
+        assert_eq!(
+            verify_stem_range_proof(missing_probe, start, end, root, &committer),
+            Err(RangeProofError::UncoveredStem {
+                stem: removed_probe
+            })
+        );
+    }
+
+    #[test]
+    fn wrong_leaves() {
+        let trie = make_trie();
+        let committer = DefaultCommitter::new(&new_crs().G);
+        let root = trie.root_commitment();
+
+        let start = [0u8; 31];
+        let end = [255u8; 31];
+        let range_proof = create_stem_range_proof(&trie.storage, start, end).unwrap();
+
+        let mut changed_leaf = range_proof.clone();
+        changed_leaf.stems[2].leaves[1].1 = [0u8; 32];
+        assert_eq!(
+            verify_stem_range_proof(changed_leaf, start, end, root, &committer),
+            Err(RangeProofError::LeavesMismatch { stem: stem(3, 0) })
+        );
+
+        // Changing the leaf at suffix zero is caught by the verkle proof itself
+        let mut changed_first_leaf = range_proof;
+        changed_first_leaf.stems[0].leaves[0].1 = [9u8; 32];
+        assert_eq!(
+            verify_stem_range_proof(changed_first_leaf, start, end, root, &committer),
+            Err(RangeProofError::InvalidProof(
+                ProofVerificationError::InvalidMultiPointProof
+            ))
+        );
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let trie = make_trie();
+
+        let range_proof = create_stem_range_proof(&trie.storage, stem(0, 1), stem(7, 0)).unwrap();
+
+        let mut bytes = Vec::new();
+        range_proof.write(&mut bytes).unwrap();
+        let deserialized = StemRangeProof::read(&bytes[..]).unwrap();
+        assert_eq!(range_proof, deserialized);
+    }
+}
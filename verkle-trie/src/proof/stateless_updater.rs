@@ -5,10 +5,6 @@ use ipa_multipoint::committer::Committer;
 use std::collections::{BTreeMap, HashSet};
 
 use super::{UpdateHint, VerkleProof};
-// TODO fix all panics and return Results instead
-//
-// TODO: This needs to be modified more, ie to return a correct error variant
-// and to refactor panics into error variants
 pub fn verify_and_update<C: Committer>(
     proof: VerkleProof,
     root: Element,
@@ -38,13 +34,13 @@ pub(crate) fn update_root<C: Committer>(
     root: Element,
     committer: C,
 ) -> Result<Element, VerificationError> {
-    if !values.len() == updated_values.len() {
+    if values.len() != updated_values.len() {
         return Err(VerificationError::UnexpectedUpdatedLength(
             values.len(),
             updated_values.len(),
         ));
     }
-    if !keys.len() == updated_values.len() {
+    if keys.len() != updated_values.len() {
         return Err(VerificationError::MismatchedKeyLength);
     }
 
@@ -100,7 +96,10 @@ pub(crate) fn update_root<C: Committer>(
     let mut updated_commitents_by_stem: BTreeMap<[u8; 31], (Element, Fr)> = BTreeMap::new();
 
     for (stem, suffix_update) in updated_stems {
-        let (ext_pres, depth) = hint.depths_and_ext_by_stem[&stem];
+        let (ext_pres, depth) = *hint
+            .depths_and_ext_by_stem
+            .get(&stem)
+            .ok_or(VerificationError::MissingStem(stem))?;
         let prefix = stem[0..depth as usize].to_vec();
         updated_stems_by_prefix
             .entry(prefix.clone())
@@ -110,14 +109,15 @@ pub(crate) fn update_root<C: Committer>(
         if ext_pres == ExtPresent::Present {
             let ext_path = stem[0..depth as usize].to_vec(); // It is the prefix
 
-            let mut c_1_delta_update = Element::zero();
-            let mut c_2_delta_update = Element::zero();
+            // The (index, old value, new value) updates for C1 and C2
+            let mut c_1_updates = Vec::new();
+            let mut c_2_updates = Vec::new();
 
             // TODO abstract this into a function, since it's duplicated
             for (suffix, (old_value, new_value)) in suffix_update {
                 // Split values into low_16 and high_16
-                let new_value_low_16 = new_value[0..16].to_vec();
-                let new_value_high_16 = new_value[16..32].to_vec();
+                let new_value_low_16 = Fr::from_le_bytes_mod_order(&new_value[0..16]) + TWO_POW_128;
+                let new_value_high_16 = Fr::from_le_bytes_mod_order(&new_value[16..32]);
 
                 let (old_value_low_16, old_value_high_16) = match old_value {
                     Some(val) => (
@@ -127,12 +127,6 @@ pub(crate) fn update_root<C: Committer>(
                     None => (Fr::zero(), Fr::zero()), // The extension can be present, but it's suffix can be missing
                 };
 
-                // We need to compute two deltas
-                let delta_low =
-                    Fr::from_le_bytes_mod_order(&new_value_low_16) + TWO_POW_128 - old_value_low_16;
-                let delta_high =
-                    Fr::from_le_bytes_mod_order(&new_value_high_16) - old_value_high_16;
-
                 let position = suffix;
                 let is_c1_comm_update = position < 128;
 
@@ -141,56 +135,61 @@ pub(crate) fn update_root<C: Committer>(
                 let low_index = 2 * pos_mod_128 as usize;
                 let high_index = low_index + 1;
 
-                let generator_low = committer.scalar_mul(delta_low, low_index);
-                let generator_high = committer.scalar_mul(delta_high, high_index);
-
-                if is_c1_comm_update {
-                    c_1_delta_update += generator_low + generator_high;
+                let updates = if is_c1_comm_update {
+                    &mut c_1_updates
                 } else {
-                    c_2_delta_update += generator_low + generator_high;
-                }
+                    &mut c_2_updates
+                };
+                updates.push((low_index, old_value_low_16, new_value_low_16));
+                updates.push((high_index, old_value_high_16, new_value_high_16));
             }
+            let c_1_delta_update = committer.commit_delta(&c_1_updates);
+            let c_2_delta_update = committer.commit_delta(&c_2_updates);
             // Compute the delta for C1 and C2, so that we can update the extension commitment
-            let mut hash_c1_delta = Fr::zero();
-            let mut hash_c2_delta = Fr::zero();
+            let mut stem_updates = Vec::new();
             if !c_1_delta_update.is_zero() {
                 let mut c1_path = ext_path.clone();
                 c1_path.push(2);
 
-                let old_c1_comm = hint.commitments_by_path[&c1_path];
+                let old_c1_comm = commitment_at(&hint.commitments_by_path, c1_path)?;
                 let new_c1_commitment = old_c1_comm + c_1_delta_update;
                 let hash_c1_new = group_to_field(&new_c1_commitment);
                 let hash_c1_old = group_to_field(&old_c1_comm);
-                hash_c1_delta = hash_c1_new - hash_c1_old;
+                stem_updates.push((2, hash_c1_old, hash_c1_new));
             }
             if !c_2_delta_update.is_zero() {
                 let mut c2_path = ext_path.clone();
                 c2_path.push(3);
 
-                let old_c2_comm = hint.commitments_by_path[&c2_path];
+                let old_c2_comm = commitment_at(&hint.commitments_by_path, c2_path)?;
                 let new_c2_commitment = old_c2_comm + c_2_delta_update;
                 let hash_c2_new = group_to_field(&new_c2_commitment);
                 let hash_c2_old = group_to_field(&old_c2_comm);
-                hash_c2_delta = hash_c2_new - hash_c2_old;
+                stem_updates.push((3, hash_c2_old, hash_c2_new));
             }
 
-            let mut stem_comm_update = Element::zero();
-            stem_comm_update += committer.scalar_mul(hash_c1_delta, 2);
-            stem_comm_update += committer.scalar_mul(hash_c2_delta, 3);
+            let stem_comm_update = committer.commit_delta(&stem_updates);
 
-            let stem_comm_old = hint.commitments_by_path[&ext_path];
+            let stem_comm_old = commitment_at(&hint.commitments_by_path, ext_path)?;
             let stem_comm_new = stem_comm_old + stem_comm_update;
             let hash_stem_comm_new = group_to_field(&stem_comm_new);
 
             // Note that we have been given a stem to which we know is in the trie (ext_pres) and
             // we have computed all of the updates for that particular stem
             updated_commitents_by_stem.insert(stem, (stem_comm_new, hash_stem_comm_new));
-        } else if ext_pres == ExtPresent::DifferentStem {
-            let other_stem = hint.other_stems_by_prefix[&prefix];
-            updated_stems_by_prefix
-                .entry(prefix)
-                .or_default()
-                .insert(other_stem);
+        } else {
+            // The stem is either in an empty slot, or shares its prefix with another stem which
+            // needs to be moved down into a subtree along with it
+            if ext_pres == ExtPresent::DifferentStem {
+                let other_stem = *hint
+                    .other_stems_by_prefix
+                    .get(&prefix)
+                    .ok_or_else(|| VerificationError::MissingOtherStem(prefix.clone()))?;
+                updated_stems_by_prefix
+                    .entry(prefix)
+                    .or_default()
+                    .insert(other_stem);
+            }
 
             // Since this stem was not present in the trie, we need to make its initial stem commitment
             //
@@ -258,16 +257,17 @@ pub(crate) fn update_root<C: Committer>(
 
         if stems.len() == 1 {
             let stem = stems.iter().next().unwrap();
-            let (_, new_hash_value) = updated_commitents_by_stem[stem];
+            let (_, new_hash_value) = updated_commitents_by_stem
+                .get(stem)
+                .ok_or(VerificationError::MissingStem(*stem))?;
 
             tree.update_prefix(
                 &hint.commitments_by_path,
                 &committer,
                 prefix.clone(),
                 old_hash_value,
-                new_hash_value,
-            )
-            .unwrap();
+                *new_hash_value,
+            )?;
         } else {
             // If we have more than one stem to be processed for a prefix, we need to build a subtree and
             // then update the prefix with the root of the subtree
@@ -281,7 +281,7 @@ pub(crate) fn update_root<C: Committer>(
                 let updated_comm = updated_commitents_by_stem.get(&stem);
                 let stem_comm = match updated_comm {
                     Some((comm, _)) => *comm,
-                    None => hint.commitments_by_path[&prefix],
+                    None => commitment_at(&hint.commitments_by_path, prefix.clone())?,
                 };
                 elements.push((stem, stem_comm))
             }
@@ -294,8 +294,7 @@ pub(crate) fn update_root<C: Committer>(
                 prefix.clone(),
                 old_hash_value,
                 new_hash_value,
-            )
-            .unwrap();
+            )?;
         }
     }
 
@@ -540,7 +539,7 @@ impl SparseVerkleTree {
             let parent_comm = self.updated_commitments_by_path.get(&prefix);
             let old_parent_comm = match parent_comm {
                 Some(comm) => *comm,
-                None => commitments_by_path[&prefix],
+                None => commitment_at(commitments_by_path, prefix.clone())?,
             };
 
             // Update the parent_comm at the child index
@@ -560,6 +559,16 @@ impl SparseVerkleTree {
     }
 }
 
+fn commitment_at(
+    commitments_by_path: &BTreeMap<Vec<u8>, Element>,
+    path: Vec<u8>,
+) -> Result<Element, VerificationError> {
+    match commitments_by_path.get(&path) {
+        Some(comm) => Ok(*comm),
+        None => Err(VerificationError::MissingCommitment(path)),
+    }
+}
+
 // https://stackoverflow.com/a/46767732
 // TODO Check if there is a similar method in itertools
 fn has_unique_elements<T>(iter: T) -> bool
@@ -736,4 +745,105 @@ mod test {
         dbg!(&expected_bytes);
         assert_eq!(got_bytes, expected_bytes)
     }
+
+    #[test]
+    fn update_stem_in_empty_slot() {
+        // The stem of key_b is not in the trie and its slot in the root is empty
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key_a = [0u8; 32];
+        trie.insert_single(key_a, key_a);
+        let key_b = [5u8; 32];
+
+        let keys = vec![key_a, key_b];
+        let values = vec![Some(key_a), None];
+
+        let meta = trie.storage.get_branch_meta(&[]).unwrap();
+        let proof = prover::create_verkle_proof(&trie.storage, keys.clone()).unwrap();
+        let (ok, updated_hint) = proof.check(keys.clone(), values.clone(), meta.commitment);
+        assert!(ok);
+
+        let new_root_comm = update_root(
+            updated_hint.unwrap(),
+            keys,
+            values,
+            vec![None, Some(key_b)],
+            meta.commitment,
+            DefaultCommitter::new(&new_crs().G),
+        )
+        .unwrap();
+
+        trie.insert_single(key_b, key_b);
+        assert_eq!(group_to_field(&new_root_comm), trie.root_hash());
+    }
+
+    #[test]
+    fn update_with_mismatched_lengths() {
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key_a = [0u8; 32];
+        trie.insert_single(key_a, key_a);
+
+        let keys = vec![key_a];
+        let values = vec![Some(key_a)];
+        let meta = trie.storage.get_branch_meta(&[]).unwrap();
+        let proof = prover::create_verkle_proof(&trie.storage, keys.clone()).unwrap();
+        let (ok, updated_hint) = proof.check(keys.clone(), values.clone(), meta.commitment);
+        assert!(ok);
+
+        let result = update_root(
+            updated_hint.unwrap(),
+            keys,
+            values,
+            vec![None, Some(key_a)],
+            meta.commitment,
+            DefaultCommitter::new(&new_crs().G),
+        );
+        assert!(matches!(
+            result,
+            Err(crate::errors::VerificationError::UnexpectedUpdatedLength(
+                1, 2
+            ))
+        ));
+    }
+
+    #[test]
+    fn update_with_missing_other_stem() {
+        // The stem of key_b is not in the trie, and shares its slot in the root with key_a
+        let db = MemoryDb::new();
+        let mut trie = Trie::new(DefaultConfig::new(db));
+
+        let key_a = [0u8; 32];
+        trie.insert_single(key_a, key_a);
+        let mut key_b = [0u8; 32];
+        key_b[1] = 1;
+
+        let keys = vec![key_b];
+        let values = vec![None];
+        let meta = trie.storage.get_branch_meta(&[]).unwrap();
+        let proof = prover::create_verkle_proof(&trie.storage, keys.clone()).unwrap();
+        let (ok, updated_hint) = proof.check(keys.clone(), values.clone(), meta.commitment);
+        assert!(ok);
+
+        let mut updated_hint = updated_hint.unwrap();
+        assert!(updated_hint
+            .other_stems_by_prefix
+            .remove(&vec![0])
+            .is_some());
+
+        let result = update_root(
+            updated_hint,
+            keys,
+            values,
+            vec![Some(key_b)],
+            meta.commitment,
+            DefaultCommitter::new(&new_crs().G),
+        );
+        assert!(matches!(
+            result,
+            Err(crate::errors::VerificationError::MissingOtherStem(prefix)) if prefix == vec![0]
+        ));
+    }
 }
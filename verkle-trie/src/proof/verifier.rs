@@ -1,6 +1,7 @@
 use super::VerkleProof;
 use crate::{
     constants::TWO_POW_128,
+    errors::ProofVerificationError,
     group_to_field,
     proof::{ExtPresent, UpdateHint},
 };
@@ -9,12 +10,23 @@ use ipa_multipoint::multiproof::VerifierQuery;
 use std::collections::{BTreeMap, BTreeSet};
 
 // TODO Document this better and refactor
+//
+// Returns the first inconsistency that was found between the proof and the
+// supplied keys and values, if any.
 pub fn create_verifier_queries(
     proof: VerkleProof,
     keys: Vec<[u8; 32]>,
     values: Vec<Option<[u8; 32]>>,
     root: Element,
-) -> Option<(Vec<VerifierQuery>, UpdateHint)> {
+) -> Result<(Vec<VerifierQuery>, UpdateHint), ProofVerificationError> {
+    if keys.len() != values.len() {
+        return Err(ProofVerificationError::MismatchedValuesLength {
+            keys: keys.len(),
+            values: values.len(),
+        });
+    }
+
+    let num_comms_supplied = proof.comms_sorted.len();
     let commitments_sorted_by_path: Vec<_> =
         std::iter::once(root).chain(proof.comms_sorted).collect();
 
@@ -24,6 +36,25 @@ pub fn create_verifier_queries(
         .map(|key| key[0..31].try_into().unwrap())
         .collect();
 
+    let hint = &proof.verification_hint;
+    if hint.depths.len() != stems.len() || hint.extension_present.len() != stems.len() {
+        return Err(ProofVerificationError::HintLengthMismatch {
+            stems: stems.len(),
+            depths: hint.depths.len(),
+            extension_statuses: hint.extension_present.len(),
+        });
+    }
+
+    // The same key cannot be proven to have two different values
+    let mut value_by_key: BTreeMap<[u8; 32], Option<[u8; 32]>> = BTreeMap::new();
+    for (key, value) in keys.iter().zip(&values) {
+        if let Some(prev_value) = value_by_key.insert(*key, *value) {
+            if prev_value != *value {
+                return Err(ProofVerificationError::ConflictingValues { key: *key });
+            }
+        }
+    }
+
     let mut depths_and_ext_by_stem: BTreeMap<[u8; 31], (ExtPresent, u8)> = BTreeMap::new();
 
     let mut stems_with_extension: BTreeSet<[u8; 31]> = BTreeSet::new();
@@ -43,6 +74,21 @@ pub fn create_verifier_queries(
         .zip(proof.verification_hint.depths)
         .zip(proof.verification_hint.extension_present)
     {
+        // The root is always an inner node, so every stem is at least at depth 1.
+        // A stem can be at most at depth 31, however if the depth is 31 then
+        // the path to it is the full stem, so it cannot lead to a different stem.
+        let valid_depth = match ext_pres {
+            ExtPresent::DifferentStem => (1..31).contains(&depth),
+            ExtPresent::None | ExtPresent::Present => (1..=31).contains(&depth),
+        };
+        if !valid_depth {
+            return Err(ProofVerificationError::InvalidDepthForExtStatus {
+                stem,
+                depth,
+                ext_status: ext_pres,
+            });
+        }
+
         depths_and_ext_by_stem.insert(stem, (ext_pres, depth));
 
         if ext_pres == ExtPresent::Present {
@@ -50,6 +96,26 @@ pub fn create_verifier_queries(
         }
     }
 
+    // Two different stems cannot both have an extension under the same prefix,
+    // otherwise there would be an inner node at that depth.
+    //
+    // The stems are sorted, so all of the stems which share a prefix are next to each other
+    // and it is enough to compare each stem with its neighbour.
+    let sorted_stems: Vec<_> = stems_with_extension.iter().collect();
+    for pair in sorted_stems.windows(2) {
+        let (stem, other_stem) = (pair[0], pair[1]);
+        for (stem, other_stem) in [(stem, other_stem), (other_stem, stem)] {
+            let (_, depth) = depths_and_ext_by_stem[stem];
+            if stem[0..depth as usize] == other_stem[0..depth as usize] {
+                return Err(ProofVerificationError::MultipleStemsAtPrefix {
+                    stem: *stem,
+                    other_stem: *other_stem,
+                    depth,
+                });
+            }
+        }
+    }
+
     for (key, value) in keys.into_iter().zip(values) {
         let stem: [u8; 31] = key[0..31].try_into().unwrap();
         let (extpres, depth) = depths_and_ext_by_stem[&stem];
@@ -104,7 +170,10 @@ pub fn create_verifier_queries(
                 // Since this stem points to a different stem,
                 // the value was never set
                 if value.is_some() {
-                    return None;
+                    return Err(ProofVerificationError::ValueForAbsentStem {
+                        key,
+                        ext_status: extpres,
+                    });
                 }
 
                 // Check if this stem already has an extension proof
@@ -117,30 +186,22 @@ pub fn create_verifier_queries(
                 // depth cannot be 31 because then that would mean that stem[...depth]
                 // is looking for it's tem. This is not possible, because we have already
                 // noted that ExtPresent is DifferentStem
-                assert!(depth != stem.len() as u8);
+                // (This is checked above when validating the depths)
 
-                let mut other_stem = None;
-                let mut found: Vec<_> = stems_with_extension
+                // We have already checked that there is at most one stem with an extension
+                // for each prefix.
+                let mut other_stem = stems_with_extension
                     .iter()
-                    .filter(|x| x[0..depth as usize] == stem[0..depth as usize])
-                    .collect();
-                if found.len() > 1 {
-                    // TODO return error instead when we change the func signature to return Result instead of bool
-                    panic!("found more than one instance of stem_with_extension at depth {}, see: {:?}", depth, found)
-                } else if found.len() == 1 {
-                    other_stem = found.pop();
-                }
+                    .find(|x| x[0..depth as usize] == stem[0..depth as usize]);
+
                 // None means that we need to create the extension proof
-                else if found.is_empty() {
-                    let mut found: Vec<_> = proof
+                if other_stem.is_none() {
+                    let encountered_stem = proof
                         .verification_hint
                         .diff_stem_no_proof
                         .iter()
-                        .filter(|x| x[0..depth as usize] == stem[0..depth as usize])
-                        .collect();
-                    let encountered_stem = found.pop().expect(
-                        "ExtPresent::DifferentStem flag but we cannot find the encountered stem",
-                    );
+                        .rfind(|x| x[0..depth as usize] == stem[0..depth as usize] && **x != stem)
+                        .ok_or(ProofVerificationError::MissingDiffStemNoProof { stem, depth })?;
                     other_stem = Some(encountered_stem);
 
                     other_stems_used.insert(*encountered_stem);
@@ -158,7 +219,10 @@ pub fn create_verifier_queries(
         } else if extpres == ExtPresent::None {
             // If the extension was not present, then the value should be None
             if value.is_some() {
-                return None;
+                return Err(ProofVerificationError::ValueForAbsentStem {
+                    key,
+                    ext_status: extpres,
+                });
             }
 
             //TODO: we may need to rewrite the prover/verifier algorithm to fix this if statement properly.
@@ -178,8 +242,28 @@ pub fn create_verifier_queries(
         }
     }
 
-    assert!(proof.verification_hint.diff_stem_no_proof == other_stems_used);
-    assert!(commitments_sorted_by_path.len() == all_paths.len());
+    if let Some(stem) = proof
+        .verification_hint
+        .diff_stem_no_proof
+        .difference(&other_stems_used)
+        .next()
+    {
+        return Err(ProofVerificationError::UnusedDiffStemNoProof { stem: *stem });
+    }
+
+    // The root is not included in the proof, so we do not expect a commitment for it
+    let num_comms_expected = all_paths.len().saturating_sub(1);
+    if num_comms_supplied < num_comms_expected {
+        return Err(ProofVerificationError::MissingCommitments {
+            expected: num_comms_expected,
+            got: num_comms_supplied,
+        });
+    } else if num_comms_supplied > num_comms_expected {
+        return Err(ProofVerificationError::UnusedCommitments {
+            expected: num_comms_expected,
+            got: num_comms_supplied,
+        });
+    }
 
     let commitments_by_path: BTreeMap<Vec<_>, Element> = all_paths
         .into_iter()
@@ -230,5 +314,5 @@ pub fn create_verifier_queries(
         other_stems_by_prefix,
     };
 
-    Some((queries, update_hint))
+    Ok((queries, update_hint))
 }
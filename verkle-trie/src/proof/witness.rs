@@ -0,0 +1,548 @@
+// The execution witness, as it is included in blocks and exchanged between clients.
+//
+// Unlike `VerkleProof`, every field is kept in its serialized form, so that bindings and
+// codecs (JSON, SSZ, JNI arrays, ...) can fill it in without knowing about the curve.
+// Converting to a `VerkleProof` is where the bytes are validated, and it never panics.
+
+use banderwagon::{CanonicalDeserialize, CanonicalSerialize, Element, Fr};
+use ipa_multipoint::{ipa::IPAProof, multiproof::MultiPointProof};
+use std::collections::BTreeMap;
+
+use super::{ExtPresent, VerificationHint, VerkleProof};
+use crate::errors::WitnessError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuffixDiff {
+    pub suffix: u8,
+    pub current_value: Option<[u8; 32]>,
+    pub new_value: Option<[u8; 32]>,
+}
+
+// The accessed suffixes of a single stem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StemStateDiff {
+    pub stem: [u8; 31],
+    pub suffix_diffs: Vec<SuffixDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpaProofBytes {
+    pub cl: Vec<[u8; 32]>,
+    pub cr: Vec<[u8; 32]>,
+    // Serialized in big endian, unlike the other scalars in this crate
+    pub final_evaluation: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerkleProofBytes {
+    pub other_stems: Vec<[u8; 31]>,
+    // One byte per stem, see `pack_depth_extension_present`
+    pub depth_extension_present: Vec<u8>,
+    pub commitments_by_path: Vec<[u8; 32]>,
+    pub d: [u8; 32],
+    pub ipa_proof: IpaProofBytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionWitness {
+    pub state_diff: Vec<StemStateDiff>,
+    pub verkle_proof: VerkleProofBytes,
+}
+
+// The keys of a witness, along with their values before and after the block
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeysValues {
+    pub keys: Vec<[u8; 32]>,
+    pub current_values: Vec<Option<[u8; 32]>>,
+    pub new_values: Vec<Option<[u8; 32]>>,
+}
+
+// The extension status only needs 2 bits and the depth at most 5 bits,
+// so they are packed into a single byte: depth << 3 | ext_status.
+// A stem is 31 bytes long, so the depth never exceeds 31 and the shift
+// cannot overflow.
+pub fn pack_depth_extension_present(depth: u8, ext_status: ExtPresent) -> u8 {
+    let ext_status = match ext_status {
+        ExtPresent::None => 0,
+        ExtPresent::DifferentStem => 1,
+        ExtPresent::Present => 2,
+    };
+    debug_assert!(depth <= 31);
+    ext_status | (depth << 3)
+}
+
+pub fn unpack_depth_extension_present(byte: u8) -> Result<(ExtPresent, u8), WitnessError> {
+    let ext_status = match byte & 3 {
+        0 => ExtPresent::None,
+        1 => ExtPresent::DifferentStem,
+        2 => ExtPresent::Present,
+        _ => return Err(WitnessError::InvalidExtensionStatus { byte }),
+    };
+    Ok((ext_status, byte >> 3))
+}
+
+impl StemStateDiff {
+    /// Returns the keys, their old values and their new values
+    pub fn keys_with_current_values(&self) -> KeysValues {
+        let mut keys_values = KeysValues::default();
+        for suffix_diff in &self.suffix_diffs {
+            let mut key = [0u8; 32];
+            key[..31].copy_from_slice(&self.stem);
+            key[31] = suffix_diff.suffix;
+
+            keys_values.keys.push(key);
+            keys_values.current_values.push(suffix_diff.current_value);
+            keys_values.new_values.push(suffix_diff.new_value);
+        }
+        keys_values
+    }
+}
+
+impl KeysValues {
+    // Groups the keys by stem, sorting the stems and the suffixes within each stem
+    pub fn group_by_stem(&self) -> Result<Vec<StemStateDiff>, WitnessError> {
+        if self.keys.len() != self.current_values.len() || self.keys.len() != self.new_values.len()
+        {
+            return Err(WitnessError::MismatchedValuesLength {
+                keys: self.keys.len(),
+                current_values: self.current_values.len(),
+                new_values: self.new_values.len(),
+            });
+        }
+
+        let mut suffix_diffs_by_stem: BTreeMap<[u8; 31], Vec<SuffixDiff>> = BTreeMap::new();
+        for ((key, current_value), new_value) in self
+            .keys
+            .iter()
+            .zip(&self.current_values)
+            .zip(&self.new_values)
+        {
+            let stem: [u8; 31] = key[..31].try_into().expect("infallible: key is 32 bytes");
+            suffix_diffs_by_stem
+                .entry(stem)
+                .or_default()
+                .push(SuffixDiff {
+                    suffix: key[31],
+                    current_value: *current_value,
+                    new_value: *new_value,
+                });
+        }
+
+        Ok(suffix_diffs_by_stem
+            .into_iter()
+            .map(|(stem, mut suffix_diffs)| {
+                suffix_diffs.sort_by_key(|suffix_diff| suffix_diff.suffix);
+                StemStateDiff { stem, suffix_diffs }
+            })
+            .collect())
+    }
+}
+
+impl ExecutionWitness {
+    pub fn new(proof: &VerkleProof, keys_values: &KeysValues) -> Result<Self, WitnessError> {
+        Ok(ExecutionWitness {
+            state_diff: keys_values.group_by_stem()?,
+            verkle_proof: VerkleProofBytes::from_verkle_proof(proof),
+        })
+    }
+
+    pub fn keys_values(&self) -> KeysValues {
+        let mut keys_values = KeysValues::default();
+        for stem_state_diff in &self.state_diff {
+            let stem_keys_values = stem_state_diff.keys_with_current_values();
+            keys_values.keys.extend(stem_keys_values.keys);
+            keys_values
+                .current_values
+                .extend(stem_keys_values.current_values);
+            keys_values.new_values.extend(stem_keys_values.new_values);
+        }
+        keys_values
+    }
+
+    pub fn to_verkle_proof(&self) -> Result<(VerkleProof, KeysValues), WitnessError> {
+        Ok((self.verkle_proof.to_verkle_proof()?, self.keys_values()))
+    }
+
+    // Parses the JSON format used by geth and the Kaustinen testnets,
+    // see `golang_proof_format::EXECUTION_WITNESS_JSON` for an example.
+    pub fn from_json_str(json: &str) -> Result<Self, WitnessError> {
+        let witness: json::ExecutionWitness =
+            serde_json::from_str(json).map_err(|err| WitnessError::InvalidJson(err.to_string()))?;
+        witness.try_into()
+    }
+
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(&json::ExecutionWitness::from(self))
+            .expect("infallible: the witness only contains strings and numbers")
+    }
+}
+
+impl VerkleProofBytes {
+    pub fn from_verkle_proof(proof: &VerkleProof) -> Self {
+        let hint = &proof.verification_hint;
+        let open_proof = &proof.proof.open_proof;
+
+        let mut final_evaluation = [0u8; 32];
+        open_proof
+            .a
+            .serialize_compressed(&mut final_evaluation[..])
+            .expect("infallible: a scalar is 32 bytes");
+        final_evaluation.reverse();
+
+        VerkleProofBytes {
+            other_stems: hint.diff_stem_no_proof.iter().copied().collect(),
+            depth_extension_present: hint
+                .depths
+                .iter()
+                .zip(&hint.extension_present)
+                .map(|(depth, ext_status)| pack_depth_extension_present(*depth, *ext_status))
+                .collect(),
+            commitments_by_path: proof.comms_sorted.iter().map(Element::to_bytes).collect(),
+            d: proof.proof.g_x_comm.to_bytes(),
+            ipa_proof: IpaProofBytes {
+                cl: open_proof.L_vec.iter().map(Element::to_bytes).collect(),
+                cr: open_proof.R_vec.iter().map(Element::to_bytes).collect(),
+                final_evaluation,
+            },
+        }
+    }
+
+    pub fn to_verkle_proof(&self) -> Result<VerkleProof, WitnessError> {
+        let (extension_present, depths) = self
+            .depth_extension_present
+            .iter()
+            .map(|byte| unpack_depth_extension_present(*byte))
+            .collect::<Result<(Vec<_>, Vec<_>), _>>()?;
+
+        let comms_sorted = batch_decompress(&self.commitments_by_path, "commitmentsByPath")?;
+        let l_vec = batch_decompress(&self.ipa_proof.cl, "cl")?;
+        let r_vec = batch_decompress(&self.ipa_proof.cr, "cr")?;
+        let g_x_comm =
+            Element::from_bytes(&self.d).ok_or(WitnessError::InvalidCommitment { field: "d" })?;
+
+        let mut final_evaluation = self.ipa_proof.final_evaluation;
+        final_evaluation.reverse();
+        let a = Fr::deserialize_compressed(&final_evaluation[..])
+            .map_err(|_| WitnessError::InvalidFinalEvaluation)?;
+
+        Ok(VerkleProof {
+            verification_hint: VerificationHint {
+                depths,
+                extension_present,
+                diff_stem_no_proof: self.other_stems.iter().copied().collect(),
+            },
+            comms_sorted,
+            proof: MultiPointProof {
+                open_proof: IPAProof {
+                    L_vec: l_vec,
+                    R_vec: r_vec,
+                    a,
+                },
+                g_x_comm,
+            },
+        })
+    }
+}
+
+fn batch_decompress(
+    points: &[[u8; 32]],
+    field: &'static str,
+) -> Result<Vec<Element>, WitnessError> {
+    Element::batch_from_bytes(points).ok_or(WitnessError::InvalidCommitment { field })
+}
+
+fn hex_to_bytes(hex: &str, field: &'static str) -> Result<Vec<u8>, WitnessError> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    hex::decode(hex).map_err(|_| WitnessError::InvalidHex { field })
+}
+
+fn hex_to_array<const N: usize>(hex: &str, field: &'static str) -> Result<[u8; N], WitnessError> {
+    let bytes = hex_to_bytes(hex, field)?;
+    let got = bytes.len();
+    bytes.try_into().map_err(|_| WitnessError::InvalidLength {
+        field,
+        expected: N,
+        got,
+    })
+}
+
+fn hex_to_arrays<const N: usize>(
+    hexes: &[String],
+    field: &'static str,
+) -> Result<Vec<[u8; N]>, WitnessError> {
+    hexes.iter().map(|hex| hex_to_array(hex, field)).collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+// The JSON representation, where every byte string is 0x-prefixed hex
+mod json {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub(super) struct ExecutionWitness {
+        #[serde(rename = "stateDiff")]
+        pub(super) state_diffs: Vec<StateDiff>,
+        #[serde(rename = "verkleProof")]
+        pub(super) verkle_proof: VerkleProof,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub(super) struct StateDiff {
+        pub(super) stem: String,
+        #[serde(rename = "suffixDiffs")]
+        pub(super) suffix_diffs: Vec<SuffixDiff>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub(super) struct SuffixDiff {
+        pub(super) suffix: u8,
+        #[serde(rename = "currentValue")]
+        pub(super) current_value: Option<String>,
+        #[serde(rename = "newValue")]
+        pub(super) new_value: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub(super) struct VerkleProof {
+        #[serde(rename = "otherStems")]
+        pub(super) other_stems: Vec<String>,
+        #[serde(rename = "depthExtensionPresent")]
+        pub(super) depth_extension_present: String,
+        #[serde(rename = "commitmentsByPath")]
+        pub(super) commitments_by_path: Vec<String>,
+        pub(super) d: String,
+        #[serde(rename = "ipaProof")]
+        pub(super) ipa_proof: IpaProof,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub(super) struct IpaProof {
+        pub(super) cl: Vec<String>,
+        pub(super) cr: Vec<String>,
+        #[serde(rename = "finalEvaluation")]
+        pub(super) final_evaluation: String,
+    }
+}
+
+impl TryFrom<json::ExecutionWitness> for ExecutionWitness {
+    type Error = WitnessError;
+
+    fn try_from(witness: json::ExecutionWitness) -> Result<Self, Self::Error> {
+        let state_diff = witness
+            .state_diffs
+            .iter()
+            .map(|state_diff| {
+                let suffix_diffs = state_diff
+                    .suffix_diffs
+                    .iter()
+                    .map(|suffix_diff| {
+                        Ok(SuffixDiff {
+                            suffix: suffix_diff.suffix,
+                            current_value: suffix_diff
+                                .current_value
+                                .as_deref()
+                                .map(|value| hex_to_array(value, "currentValue"))
+                                .transpose()?,
+                            new_value: suffix_diff
+                                .new_value
+                                .as_deref()
+                                .map(|value| hex_to_array(value, "newValue"))
+                                .transpose()?,
+                        })
+                    })
+                    .collect::<Result<_, WitnessError>>()?;
+                Ok(StemStateDiff {
+                    stem: hex_to_array(&state_diff.stem, "stem")?,
+                    suffix_diffs,
+                })
+            })
+            .collect::<Result<_, WitnessError>>()?;
+
+        let proof = witness.verkle_proof;
+        let verkle_proof = VerkleProofBytes {
+            other_stems: hex_to_arrays(&proof.other_stems, "otherStems")?,
+            depth_extension_present: hex_to_bytes(
+                &proof.depth_extension_present,
+                "depthExtensionPresent",
+            )?,
+            commitments_by_path: hex_to_arrays(&proof.commitments_by_path, "commitmentsByPath")?,
+            d: hex_to_array(&proof.d, "d")?,
+            ipa_proof: IpaProofBytes {
+                cl: hex_to_arrays(&proof.ipa_proof.cl, "cl")?,
+                cr: hex_to_arrays(&proof.ipa_proof.cr, "cr")?,
+                final_evaluation: hex_to_array(
+                    &proof.ipa_proof.final_evaluation,
+                    "finalEvaluation",
+                )?,
+            },
+        };
+
+        Ok(ExecutionWitness {
+            state_diff,
+            verkle_proof,
+        })
+    }
+}
+
+impl From<&ExecutionWitness> for json::ExecutionWitness {
+    fn from(witness: &ExecutionWitness) -> Self {
+        let proof = &witness.verkle_proof;
+        json::ExecutionWitness {
+            state_diffs: witness
+                .state_diff
+                .iter()
+                .map(|state_diff| json::StateDiff {
+                    stem: to_hex(&state_diff.stem),
+                    suffix_diffs: state_diff
+                        .suffix_diffs
+                        .iter()
+                        .map(|suffix_diff| json::SuffixDiff {
+                            suffix: suffix_diff.suffix,
+                            current_value: suffix_diff.current_value.map(|value| to_hex(&value)),
+                            new_value: suffix_diff.new_value.map(|value| to_hex(&value)),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            verkle_proof: json::VerkleProof {
+                other_stems: proof.other_stems.iter().map(|stem| to_hex(stem)).collect(),
+                depth_extension_present: to_hex(&proof.depth_extension_present),
+                commitments_by_path: proof
+                    .commitments_by_path
+                    .iter()
+                    .map(|c| to_hex(c))
+                    .collect(),
+                d: to_hex(&proof.d),
+                ipa_proof: json::IpaProof {
+                    cl: proof.ipa_proof.cl.iter().map(|c| to_hex(c)).collect(),
+                    cr: proof.ipa_proof.cr.iter().map(|c| to_hex(c)).collect(),
+                    final_evaluation: to_hex(&proof.ipa_proof.final_evaluation),
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_db::MemoryDb;
+    use crate::proof::golang_proof_format::{EXECUTION_WITNESS_JSON, PREVIOUS_STATE_ROOT};
+    use crate::{DefaultConfig, Trie, TrieTrait};
+
+    fn previous_state_root() -> Element {
+        Element::from_bytes(&hex_to_array::<32>(PREVIOUS_STATE_ROOT, "root").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_witness_verifies() {
+        let witness = ExecutionWitness::from_json_str(EXECUTION_WITNESS_JSON).unwrap();
+        let (proof, keys_values) = witness.to_verkle_proof().unwrap();
+
+        let (ok, _) = proof.check(
+            keys_values.keys,
+            keys_values.current_values,
+            previous_state_root(),
+        );
+        assert!(ok);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let witness = ExecutionWitness::from_json_str(EXECUTION_WITNESS_JSON).unwrap();
+        let json = witness.to_json_string();
+        assert_eq!(ExecutionWitness::from_json_str(&json).unwrap(), witness);
+
+        let (proof, keys_values) = witness.to_verkle_proof().unwrap();
+        assert_eq!(
+            ExecutionWitness::new(&proof, &keys_values).unwrap(),
+            witness
+        );
+    }
+
+    #[test]
+    fn invalid_witnesses_are_errors() {
+        let witness = ExecutionWitness::from_json_str(EXECUTION_WITNESS_JSON).unwrap();
+
+        let mut invalid = witness.clone();
+        invalid.verkle_proof.depth_extension_present[0] |= 3;
+        assert_eq!(
+            invalid.to_verkle_proof().unwrap_err(),
+            WitnessError::InvalidExtensionStatus { byte: 0x13 }
+        );
+
+        let mut invalid = witness.clone();
+        invalid.verkle_proof.ipa_proof.final_evaluation = [0xff; 32];
+        assert_eq!(
+            invalid.to_verkle_proof().unwrap_err(),
+            WitnessError::InvalidFinalEvaluation
+        );
+
+        let mut invalid = witness;
+        invalid.verkle_proof.commitments_by_path[1] = [0xff; 32];
+        assert_eq!(
+            invalid.to_verkle_proof().unwrap_err(),
+            WitnessError::InvalidCommitment {
+                field: "commitmentsByPath"
+            }
+        );
+
+        let json = EXECUTION_WITNESS_JSON.replace("\"0x12\"", "\"0x1\"");
+        assert_eq!(
+            ExecutionWitness::from_json_str(&json).unwrap_err(),
+            WitnessError::InvalidHex {
+                field: "depthExtensionPresent"
+            }
+        );
+        assert!(matches!(
+            ExecutionWitness::from_json_str("{}"),
+            Err(WitnessError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn witness_from_trie_proof() {
+        let mut trie = Trie::new(DefaultConfig::new(MemoryDb::new()));
+        let keys = [[1u8; 32], [2u8; 32], {
+            let mut key = [1u8; 32];
+            key[31] = 0;
+            key
+        }];
+        trie.insert(keys.iter().map(|key| (*key, [9u8; 32])));
+        let proof = trie.create_verkle_proof(keys.into_iter()).unwrap();
+
+        let keys_values = KeysValues {
+            keys: keys.to_vec(),
+            current_values: vec![Some([9u8; 32]); 3],
+            new_values: vec![None; 3],
+        };
+        let witness = ExecutionWitness::new(&proof, &keys_values).unwrap();
+
+        // Keys are grouped by stem, and sorted by suffix
+        assert_eq!(witness.state_diff.len(), 2);
+        let suffixes: Vec<_> = witness.state_diff[0]
+            .suffix_diffs
+            .iter()
+            .map(|suffix_diff| suffix_diff.suffix)
+            .collect();
+        assert_eq!(suffixes, vec![0, 1]);
+
+        let (got_proof, got_keys_values) = witness.to_verkle_proof().unwrap();
+        assert_eq!(got_proof, proof);
+        let (ok, _) = got_proof.check(
+            got_keys_values.keys,
+            got_keys_values.current_values,
+            trie.root_commitment(),
+        );
+        assert!(ok);
+
+        let mismatched = KeysValues {
+            new_values: vec![],
+            ..keys_values
+        };
+        assert!(ExecutionWitness::new(&proof, &mismatched).is_err());
+    }
+}
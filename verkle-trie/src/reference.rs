@@ -0,0 +1,145 @@
+// A naive verkle trie, which is used as a reference to test `Trie` against.
+//
+// It only stores the leaves, and recomputes every commitment from scratch whenever
+// the root or a proof is requested. This makes it far too slow for real use,
+// however it is simple enough to be checked by hand.
+use crate::{
+    database::{memory_db::MemoryDb, BranchMeta, ReadOnlyHigherDb, WriteOnlyHigherDb},
+    errors::ProofCreationError,
+    group_to_field,
+    proof::{prover, VerkleProof},
+    trie::stem_meta_from_leaves,
+    Key, Stem, TrieTrait, Value,
+};
+use banderwagon::{trait_defs::*, Element, Fr};
+use ipa_multipoint::committer::Committer;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct ReferenceTrie<C: Committer> {
+    leaves: BTreeMap<Key, Value>,
+    committer: C,
+}
+
+impl<C: Committer> ReferenceTrie<C> {
+    pub fn new(committer: C) -> Self {
+        ReferenceTrie {
+            leaves: BTreeMap::new(),
+            committer,
+        }
+    }
+
+    // Removes a leaf, returning its value if it was in the trie
+    pub fn remove(&mut self, key: Key) -> Option<Value> {
+        self.leaves.remove(&key)
+    }
+
+    // Returns all of the leaves, sorted by key
+    pub fn leaves(&self) -> impl Iterator<Item = (Key, Value)> + '_ {
+        self.leaves.iter().map(|(key, value)| (*key, *value))
+    }
+
+    // Computes every node in the trie from scratch, and stores them in a fresh database
+    pub fn to_memory_db(&self) -> MemoryDb {
+        let mut stems: BTreeMap<Stem, Vec<(u8, Value)>> = BTreeMap::new();
+        for (key, value) in &self.leaves {
+            let stem: Stem = key[0..31].try_into().unwrap();
+            stems.entry(stem).or_default().push((key[31], *value));
+        }
+        let stems: Vec<_> = stems.into_iter().collect();
+
+        let mut db = MemoryDb::new();
+        // An empty trie still has a root node, which commits to nothing
+        if stems.is_empty() {
+            db.insert_branch(Vec::new(), BranchMeta::zero(), 0);
+        } else {
+            self.build_branch(Vec::new(), &stems, &mut db);
+        }
+        db
+    }
+
+    fn root_meta(&self) -> BranchMeta {
+        self.to_memory_db()
+            .get_branch_meta(&[])
+            .expect("the root is always materialized")
+    }
+
+    // Builds the branch node at `path` whose subtree holds `stems`
+    fn build_branch(
+        &self,
+        path: Vec<u8>,
+        stems: &[(Stem, Vec<(u8, Value)>)],
+        db: &mut MemoryDb,
+    ) -> BranchMeta {
+        let depth = path.len();
+        let mut child_hashes = vec![Fr::zero(); 256];
+
+        // `stems` is sorted, so the stems under each child are next to each other
+        let mut remaining = stems;
+        while let Some((first_stem, _)) = remaining.first() {
+            let index = first_stem[depth];
+            let end = remaining
+                .iter()
+                .position(|(stem, _)| stem[depth] != index)
+                .unwrap_or(remaining.len());
+            let (child_stems, rest) = remaining.split_at(end);
+            remaining = rest;
+
+            let mut child_path = path.clone();
+            child_path.push(index);
+
+            // A single stem is placed directly in the slot, otherwise we need another branch node
+            child_hashes[index as usize] = match child_stems {
+                [(stem, stem_leaves)] => {
+                    let stem_meta = stem_meta_from_leaves(*stem, stem_leaves, 0, &self.committer);
+                    for (suffix, value) in stem_leaves {
+                        let mut key = [0u8; 32];
+                        key[0..31].copy_from_slice(stem);
+                        key[31] = *suffix;
+                        db.insert_leaf(key, *value, child_path.len() as u8);
+                    }
+                    db.insert_stem(*stem, stem_meta, child_path.len() as u8);
+                    db.add_stem_as_branch_child(child_path, *stem, depth as u8);
+                    stem_meta.hash_stem_commitment
+                }
+                _ => {
+                    self.build_branch(child_path, child_stems, db)
+                        .hash_commitment
+                }
+            };
+        }
+
+        let commitment = self.committer.commit_lagrange(&child_hashes);
+        let branch_meta = BranchMeta {
+            commitment,
+            hash_commitment: group_to_field(&commitment),
+        };
+        db.insert_branch(path, branch_meta, depth as u8);
+        branch_meta
+    }
+}
+
+impl<C: Committer> TrieTrait for ReferenceTrie<C> {
+    fn insert(&mut self, kv: impl Iterator<Item = (Key, Value)>) {
+        self.leaves.extend(kv)
+    }
+
+    fn get(&self, key: Key) -> Option<Value> {
+        self.leaves.get(&key).copied()
+    }
+
+    fn root_hash(&self) -> Fr {
+        self.root_meta().hash_commitment
+    }
+
+    fn root_commitment(&self) -> Element {
+        self.root_meta().commitment
+    }
+
+    fn create_verkle_proof(
+        &self,
+        keys: impl Iterator<Item = Key>,
+    ) -> Result<VerkleProof, ProofCreationError> {
+        prover::create_verkle_proof(&self.to_memory_db(), keys.collect())
+    }
+}
@@ -11,19 +11,32 @@ use banderwagon::{trait_defs::*, Element, Fr};
 // The trie implements the logic to insert values, fetch values, and create paths to said values
 pub struct Trie<Storage, PolyCommit: Committer> {
     pub(crate) storage: Storage,
-    committer: PolyCommit,
+    pub(crate) committer: PolyCommit,
+    // This is only set when the trie was created with state expiry enabled
+    pub(crate) expiry: Option<crate::expiry::StateExpiry>,
 }
 
 // Implementation of the trie trait that should be considered the public API for the trie
 impl<S: ReadWriteHigherDb, P: Committer> TrieTrait for Trie<S, P> {
     fn insert(&mut self, kv: impl Iterator<Item = (crate::Key, crate::Value)>) {
+        if self.expiry.is_some() {
+            // Dropping the write would lose data without the caller noticing
+            if let Err(err) = self.try_insert(kv) {
+                panic!("{err}, use `try_insert` to handle writes to expired stems")
+            }
+            return;
+        }
         for (key_bytes, value_bytes) in kv {
-            let ins = self.create_insert_instructions(key_bytes, value_bytes);
-            self.process_instructions(ins);
+            self.insert_unchecked(key_bytes, value_bytes);
         }
     }
 
     fn get(&self, key: crate::Key) -> Option<crate::Value> {
+        if self.expiry.is_some() {
+            // Expired stems cannot be read from, until they are resurrected.
+            // `try_get` can be used to tell an expired stem apart from a missing key.
+            return self.try_get(key).ok().flatten();
+        }
         self.storage.get_leaf(key)
     }
 
@@ -145,9 +158,16 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
         Trie {
             storage: db,
             committer: pc,
+            expiry: None,
         }
     }
 
+    // Inserts a single value, without checking whether its stem has expired
+    pub(crate) fn insert_unchecked(&mut self, key_bytes: [u8; 32], value_bytes: [u8; 32]) {
+        let ins = self.create_insert_instructions(key_bytes, value_bytes);
+        self.process_instructions(ins);
+    }
+
     // Inserting a leaf in the trie is done in two steps
     // First we need to modify the corresponding parts of the
     // tree to account for the new leaf
@@ -313,10 +333,11 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                         None => Fr::zero(),
                     };
 
-                    let delta = new_hash_comm - old_hash_comm;
-                    let delta_comm = self
-                        .committer
-                        .scalar_mul(delta, branch_child_index as usize);
+                    let delta_comm = self.committer.commit_delta(&[(
+                        branch_child_index as usize,
+                        old_hash_comm,
+                        new_hash_comm,
+                    )]);
 
                     let old_parent_branch_metadata =
                         self.storage.get_branch_meta(&branch_id).unwrap();
@@ -445,9 +466,12 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                     {
                         let depth = parent_branch_node.len() as u8;
 
-                        let delta = inner_node_below_val; // Remember the old value will be zero, since we just created it.
-
-                        let updated_comm = self.committer.scalar_mul(delta, *child_path as usize);
+                        // Remember the old value will be zero, since we just created it.
+                        let updated_comm = self.committer.commit_delta(&[(
+                            *child_path as usize,
+                            Fr::zero(),
+                            inner_node_below_val,
+                        )]);
                         let branch_root = group_to_field(&updated_comm);
 
                         self.storage.insert_branch(
@@ -467,12 +491,15 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
 
                     let old_stem_value = stem_meta_data.hash_stem_commitment;
                     let new_inner_node_value = inner_node_below_val;
-                    let delta = new_inner_node_value - old_stem_value;
 
                     let top_parent = self.storage.get_branch_meta(&parent_branch_node).unwrap();
 
                     let updated_top_comm = top_parent.commitment
-                        + self.committer.scalar_mul(delta, child_index as usize);
+                        + self.committer.commit_delta(&[(
+                            child_index as usize,
+                            old_stem_value,
+                            new_inner_node_value,
+                        )]);
                     let top_parent_root = group_to_field(&updated_top_comm);
 
                     self.storage.insert_branch(
@@ -546,8 +573,9 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
         //
 
         // Split values into low_16 and high_16
-        let new_value_low_16 = update_leaf.new_value[0..16].to_vec();
-        let new_value_high_16 = update_leaf.new_value[16..32].to_vec();
+        let new_value_low_16 =
+            Fr::from_le_bytes_mod_order(&update_leaf.new_value[0..16]) + TWO_POW_128;
+        let new_value_high_16 = Fr::from_le_bytes_mod_order(&update_leaf.new_value[16..32]);
 
         let (old_value_low_16, old_value_high_16) = match update_leaf.old_val {
             Some(val) => (
@@ -557,11 +585,6 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
             None => (Fr::zero(), Fr::zero()),
         };
 
-        // We need to compute two deltas
-        let delta_low =
-            Fr::from_le_bytes_mod_order(&new_value_low_16) + TWO_POW_128 - old_value_low_16;
-        let delta_high = Fr::from_le_bytes_mod_order(&new_value_high_16) - old_value_high_16;
-
         // We need to compute which group elements in the srs are being used
         // We know that the first 128 values are mapped to the first 256 group elements
         // and the last 128 values are mapped to the second 256 group elements
@@ -581,12 +604,14 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
         let low_index = 2 * pos_mod_128 as usize;
         let high_index = low_index + 1;
 
-        let generator_low = self.committer.scalar_mul(delta_low, low_index);
-        let generator_high = self.committer.scalar_mul(delta_high, high_index);
+        let leaf_delta = self.committer.commit_delta(&[
+            (low_index, old_value_low_16, new_value_low_16),
+            (high_index, old_value_high_16, new_value_high_16),
+        ]);
 
         let stem: [u8; 31] = update_leaf.key[0..31].try_into().unwrap();
 
-        let (c_1, old_hash_c1, c_2, old_hash_c2, stem_comm, old_hash_stem_comm) =
+        let (c_1, old_hash_c1, c_2, old_hash_c2, stem_comm, old_hash_stem_comm, old_epoch) =
             match self.storage.get_stem_meta(stem) {
                 Some(comm_val) => (
                     comm_val.c_1,
@@ -595,6 +620,7 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                     comm_val.hash_c2,
                     comm_val.stem_commitment,
                     Some(comm_val.hash_stem_commitment),
+                    comm_val.last_epoch,
                 ),
                 None => {
                     // This is the first leaf for the stem, so the C1, C2 commitments will be zero
@@ -611,6 +637,7 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                         group_to_field(&Element::zero()),
                         stem_comm,
                         None,
+                        0,
                     )
                 }
             };
@@ -619,11 +646,12 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
         let (updated_c_1, new_hash_c1, updated_c_2, new_hash_c2, updated_stem_comm) =
             if position < 128 {
                 // update c_1
-                let updated_c_1 = c_1 + generator_low + generator_high;
+                let updated_c_1 = c_1 + leaf_delta;
                 let new_hash_c1 = group_to_field(&updated_c_1);
 
-                let c_1_delta = new_hash_c1 - old_hash_c1;
-                let c_1_point = self.committer.scalar_mul(c_1_delta, 2);
+                let c_1_point = self
+                    .committer
+                    .commit_delta(&[(2, old_hash_c1, new_hash_c1)]);
 
                 let updated_stem_comm = stem_comm + c_1_point;
 
@@ -636,11 +664,12 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                 )
             } else {
                 // update c_2
-                let updated_c_2 = c_2 + generator_low + generator_high;
+                let updated_c_2 = c_2 + leaf_delta;
                 let new_hash_c2 = group_to_field(&updated_c_2);
 
-                let c_2_delta = new_hash_c2 - old_hash_c2;
-                let c_2_point = self.committer.scalar_mul(c_2_delta, 3);
+                let c_2_point = self
+                    .committer
+                    .commit_delta(&[(3, old_hash_c2, new_hash_c2)]);
 
                 let updated_stem_comm = stem_comm + c_2_point;
                 (
@@ -652,6 +681,15 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                 )
             };
 
+        // With state expiry, writing to a stem also marks it as touched in the current epoch
+        let (updated_stem_comm, last_epoch) = match &self.expiry {
+            Some(expiry) => (
+                updated_stem_comm + epoch_delta(&self.committer, old_epoch, expiry.current_epoch),
+                expiry.current_epoch,
+            ),
+            None => (updated_stem_comm, old_epoch),
+        };
+
         let updated_hash_stem_comm = group_to_field(&updated_stem_comm);
 
         self.storage.insert_stem(
@@ -663,6 +701,7 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
                 hash_c2: new_hash_c2,
                 stem_commitment: updated_stem_comm,
                 hash_stem_commitment: updated_hash_stem_comm,
+                last_epoch,
             },
             depth,
         );
@@ -690,10 +729,11 @@ impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit
         // does not commit to it.
         let old_stem_hash = stem_update.old_val.unwrap_or_else(Fr::zero);
         let new_stem_hash = stem_update.new_val;
-        let delta = new_stem_hash - old_stem_hash;
 
         let old_branch_comm = self.storage.get_branch_meta(&branch_id).unwrap().commitment;
-        let delta_comm = self.committer.scalar_mul(delta, branch_index as usize);
+        let delta_comm =
+            self.committer
+                .commit_delta(&[(branch_index as usize, old_stem_hash, new_stem_hash)]);
         let updated_branch_comm = old_branch_comm + delta_comm;
         let hash_updated_branch_comm = group_to_field(&updated_branch_comm);
 
@@ -725,6 +765,68 @@ impl<Storage: ReadWriteHigherDb + Flush, PolyCommit: Committer> Trie<Storage, Po
         self.storage.flush()
     }
 }
+
+impl<Storage: ReadWriteHigherDb, PolyCommit: Committer> Trie<Storage, PolyCommit> {
+    // Returns all of the stems and leaves in the inclusive range [start, end],
+    // along with a proof that the range is complete
+    pub fn create_stem_range_proof(
+        &self,
+        start: crate::Stem,
+        end: crate::Stem,
+    ) -> Result<crate::proof::range::StemRangeProof, crate::errors::ProofCreationError> {
+        crate::proof::range::create_stem_range_proof(&self.storage, start, end)
+    }
+}
+
+// Returns the change in a stem commitment, when its last epoch at index 4 is updated
+pub(crate) fn epoch_delta<C: Committer>(committer: &C, old_epoch: u64, new_epoch: u64) -> Element {
+    committer.commit_delta(&[(4, Fr::from(old_epoch), Fr::from(new_epoch))])
+}
+
+// Computes the metadata for a stem from scratch, given all of its leaves
+pub(crate) fn stem_meta_from_leaves<C: Committer>(
+    stem: crate::Stem,
+    leaves: &[(u8, [u8; 32])],
+    last_epoch: u64,
+    committer: &C,
+) -> StemMeta {
+    let mut c1_values = vec![Fr::zero(); 256];
+    let mut c2_values = vec![Fr::zero(); 256];
+    for (suffix, value) in leaves {
+        let values = if *suffix < 128 {
+            &mut c1_values
+        } else {
+            &mut c2_values
+        };
+        let value_lower_index = 2 * (*suffix % 128) as usize;
+        values[value_lower_index] = Fr::from_le_bytes_mod_order(&value[0..16]) + TWO_POW_128;
+        values[value_lower_index + 1] = Fr::from_le_bytes_mod_order(&value[16..32]);
+    }
+
+    let c_1 = committer.commit_lagrange(&c1_values);
+    let c_2 = committer.commit_lagrange(&c2_values);
+    let hash_c1 = group_to_field(&c_1);
+    let hash_c2 = group_to_field(&c_2);
+
+    let stem_commitment = committer.commit_lagrange(&[
+        Fr::one(),
+        Fr::from_le_bytes_mod_order(&stem),
+        hash_c1,
+        hash_c2,
+        Fr::from(last_epoch),
+    ]);
+
+    StemMeta {
+        c_1,
+        hash_c1,
+        c_2,
+        hash_c2,
+        stem_commitment,
+        hash_stem_commitment: group_to_field(&stem_commitment),
+        last_epoch,
+    }
+}
+
 // Returns a list of all of the path indices where the two stems
 // are the same and the next path index where they both differ for each
 // stem.
@@ -734,7 +836,7 @@ fn path_difference(key_a: [u8; 31], key_b: [u8; 31]) -> (Vec<u8>, Option<u8>, Op
 
     let mut same_path_indices = Vec::with_capacity(AVERAGE_NUMBER_OF_SHARED_INDICES);
 
-    for (p_a, p_b) in key_a.into_iter().zip(key_b.into_iter()) {
+    for (p_a, p_b) in key_a.into_iter().zip(key_b) {
         if p_a != p_b {
             return (same_path_indices, Some(p_a), Some(p_b));
         }
@@ -0,0 +1,186 @@
+// Differential tests which run the same operations through `Trie` and the naive
+// `ReferenceTrie`, and check that both agree on the root, the values and the proofs.
+//
+// `Trie` cannot delete leaves, so deletes are only applied to the reference model.
+// After a delete, the `Trie` is rebuilt from the leaves that the reference model has left,
+// which checks that the reference model agrees with `Trie`, but not `Trie`'s own deletion.
+use ipa_multipoint::committer::DefaultCommitter;
+use once_cell::sync::Lazy;
+use verkle_trie::{
+    database::memory_db::MemoryDb, reference::ReferenceTrie, Config, Key, Trie, TrieTrait, Value,
+    VerkleConfig,
+};
+
+// The committer tables are expensive to build, so all of the tries share them
+pub static COMMITTER: Lazy<DefaultCommitter> =
+    Lazy::new(|| VerkleConfig::new(MemoryDb::new()).committer);
+
+type TestTrie = Trie<MemoryDb, &'static DefaultCommitter>;
+type TestReference = ReferenceTrie<&'static DefaultCommitter>;
+
+fn new_trie() -> TestTrie {
+    Trie::new(Config {
+        db: MemoryDb::new(),
+        committer: &*COMMITTER,
+    })
+}
+
+#[derive(Debug)]
+enum Op {
+    Insert(Vec<(Key, Value)>),
+    Delete(Key),
+}
+
+#[test]
+fn differential_random_inserts() {
+    run_differential([1u8; 32], 25, false);
+}
+
+// Deletes are only applied to the reference model, see the top of this file
+#[test]
+fn differential_random_inserts_and_deletes() {
+    run_differential([2u8; 32], 25, true);
+}
+
+#[test]
+fn differential_empty_trie() {
+    let trie = new_trie();
+    let reference = ReferenceTrie::new(&*COMMITTER);
+
+    assert_eq!(trie.root_hash(), reference.root_hash());
+    assert_eq!(trie.root_commitment(), reference.root_commitment());
+    compare_tries(&trie, &reference, &[[0u8; 32], [255u8; 32]]);
+}
+
+fn run_differential(seed: [u8; 32], num_ops: usize, with_deletes: bool) {
+    let mut prng = BasicPRNG::new(seed);
+
+    let mut trie = new_trie();
+    let mut reference = ReferenceTrie::new(&*COMMITTER);
+    // Every key we have touched, including the ones which have since been deleted
+    let mut seen_keys: Vec<Key> = Vec::new();
+
+    for _ in 0..num_ops {
+        let op = random_op(&mut prng, &seen_keys, with_deletes);
+        match &op {
+            Op::Insert(kvs) => {
+                trie.insert(kvs.iter().copied());
+                reference.insert(kvs.iter().copied());
+                seen_keys.extend(kvs.iter().map(|(key, _)| *key));
+            }
+            Op::Delete(key) => {
+                // `Trie` cannot delete, so we compare against a trie which never saw the key.
+                // This only covers the deletion in the reference model, see the top of this file.
+                reference.remove(*key);
+                trie = new_trie();
+                trie.insert(reference.leaves());
+            }
+        }
+
+        assert_eq!(
+            trie.root_commitment(),
+            reference.root_commitment(),
+            "roots differ after {:?}",
+            op
+        );
+        assert_eq!(trie.root_hash(), reference.root_hash());
+        compare_tries(&trie, &reference, &seen_keys);
+    }
+}
+
+// Checks that both tries return the same values and proofs for `seen_keys`,
+// along with a couple of keys which were never inserted
+fn compare_tries(trie: &TestTrie, reference: &TestReference, seen_keys: &[Key]) {
+    let mut keys = seen_keys.to_vec();
+    let mut absent_key = [0xffu8; 32];
+    absent_key[31] = 0x11;
+    keys.push(absent_key);
+    if let Some(key) = seen_keys.first() {
+        // Same stem as an existing key, but a suffix which is unlikely to be set
+        let mut same_stem = *key;
+        same_stem[31] = same_stem[31].wrapping_add(128);
+        keys.push(same_stem);
+    }
+    keys.sort();
+    keys.dedup();
+
+    let values: Vec<_> = keys.iter().map(|key| reference.get(*key)).collect();
+    for (key, value) in keys.iter().zip(&values) {
+        assert_eq!(
+            trie.get(*key),
+            *value,
+            "values differ for {}",
+            hex::encode(key)
+        );
+    }
+
+    // Proofs are deterministic, so both tries must produce the same bytes
+    let proof = trie.create_verkle_proof(keys.clone().into_iter()).unwrap();
+    let reference_proof = reference
+        .create_verkle_proof(keys.clone().into_iter())
+        .unwrap();
+    let mut proof_bytes = Vec::new();
+    proof.write(&mut proof_bytes).unwrap();
+    let mut reference_proof_bytes = Vec::new();
+    reference_proof.write(&mut reference_proof_bytes).unwrap();
+    assert_eq!(proof_bytes, reference_proof_bytes, "proofs differ");
+
+    let (ok, _) = reference_proof.check(keys, values, reference.root_commitment());
+    assert!(ok, "reference proof does not verify");
+}
+
+// Keys are derived from previously seen keys most of the time, so that
+// stems share long prefixes and the trie ends up more than a couple of levels deep
+fn random_op(prng: &mut BasicPRNG, seen_keys: &[Key], with_deletes: bool) -> Op {
+    if with_deletes && !seen_keys.is_empty() && prng.rand_index(4) == 0 {
+        let index = prng.rand_index(seen_keys.len());
+        return Op::Delete(seen_keys[index]);
+    }
+
+    let batch_size = 1 + prng.rand_index(5);
+    let kvs = (0..batch_size)
+        .map(|_| {
+            let fresh = prng.rand_bytes();
+            let key = if seen_keys.is_empty() || prng.rand_index(4) == 0 {
+                fresh
+            } else {
+                let mut key = seen_keys[prng.rand_index(seen_keys.len())];
+                // Keep a prefix of the existing key, which may be the whole stem
+                let shared = prng.rand_index(32);
+                key[shared..].copy_from_slice(&fresh[shared..]);
+                key
+            };
+            (key, prng.rand_bytes())
+        })
+        .collect();
+    Op::Insert(kvs)
+}
+
+struct BasicPRNG {
+    seed: [u8; 32],
+    counter: u64,
+}
+
+impl BasicPRNG {
+    pub fn new(seed: [u8; 32]) -> BasicPRNG {
+        BasicPRNG { counter: 0, seed }
+    }
+
+    pub fn rand_bytes(&mut self) -> [u8; 32] {
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(&self.counter.to_le_bytes()[..]);
+        hasher.update(&self.seed[..]);
+        let res: [u8; 32] = hasher.finalize().into();
+
+        self.counter += 1;
+
+        res
+    }
+
+    pub fn rand_index(&mut self, len: usize) -> usize {
+        let bytes = self.rand_bytes();
+        u64::from_le_bytes(bytes[0..8].try_into().unwrap()) as usize % len
+    }
+}